@@ -7,6 +7,22 @@ use sdl2::audio::AudioFormatNum;
 use sdl2::mixer::{Channel, Chunk};
 use std::sync::Arc;
 
+/// Multi-byte sample formats in the native byte order and in the opposite one. Mixer could be
+/// configured for either of them.
+#[cfg(target_endian = "little")]
+mod order {
+  pub use sdl2::mixer::{AUDIO_F32LSB as NATIVE_F32, AUDIO_S16LSB as NATIVE_S16, AUDIO_S32LSB as NATIVE_S32};
+  pub use sdl2::mixer::{AUDIO_F32MSB as SWAPPED_F32, AUDIO_S16MSB as SWAPPED_S16, AUDIO_S32MSB as SWAPPED_S32};
+  pub use sdl2::mixer::{AUDIO_U16LSB as NATIVE_U16, AUDIO_U16MSB as SWAPPED_U16};
+}
+
+#[cfg(target_endian = "big")]
+mod order {
+  pub use sdl2::mixer::{AUDIO_F32LSB as SWAPPED_F32, AUDIO_S16LSB as SWAPPED_S16, AUDIO_S32LSB as SWAPPED_S32};
+  pub use sdl2::mixer::{AUDIO_F32MSB as NATIVE_F32, AUDIO_S16MSB as NATIVE_S16, AUDIO_S32MSB as NATIVE_S32};
+  pub use sdl2::mixer::{AUDIO_U16LSB as SWAPPED_U16, AUDIO_U16MSB as NATIVE_U16};
+}
+
 const BUF_LEN: usize = 4096;
 static mut BUF: [u8; BUF_LEN] = [0; BUF_LEN];
 
@@ -21,6 +37,30 @@ static mut PLACEHOLDER: sdl2_sys::mixer::Mix_Chunk = sdl2_sys::mixer::Mix_Chunk
 
 /// Play sound effect on a given channel with a given playback frequency located at `position`.
 pub fn play_sound_sample(channel: Channel, frequency: i32, chunk: Arc<[u8]>, position: f32) -> Result<(), String> {
+  start_sample(channel, frequency, chunk, position, 1.0, false)?;
+  Ok(())
+}
+
+/// Play sound effect in a loop at the given `volume` (from 0.0 to 1.0). Returns the channel effect
+/// is playing on (so it could be halted later) or `None` if there are no free channels.
+pub fn play_looped_sample(
+  channel: Channel,
+  frequency: i32,
+  chunk: Arc<[u8]>,
+  position: f32,
+  volume: f32,
+) -> Result<Option<Channel>, String> {
+  start_sample(channel, frequency, chunk, position, volume, true)
+}
+
+fn start_sample(
+  channel: Channel,
+  frequency: i32,
+  chunk: Arc<[u8]>,
+  position: f32,
+  volume: f32,
+  looped: bool,
+) -> Result<Option<Channel>, String> {
   let placeholder = Chunk {
     raw: unsafe { &mut PLACEHOLDER as *mut _ },
     owned: false,
@@ -28,7 +68,7 @@ pub fn play_sound_sample(channel: Channel, frequency: i32, chunk: Arc<[u8]>, pos
   // FIXME: maybe, stop other channel?
   let channel = match channel.play(&placeholder, -1) {
     Ok(channel) => channel,
-    Err(_) => return Ok(()),
+    Err(_) => return Ok(None),
   };
   let (mixer_frequency, format, channels) = sdl2::mixer::query_spec()?;
   let effect = Box::new(SampleCallback {
@@ -38,6 +78,8 @@ pub fn play_sound_sample(channel: Channel, frequency: i32, chunk: Arc<[u8]>, pos
     mixer_frequency,
     target_sample_offset: 0,
     position,
+    volume,
+    looped,
   });
   let user_ptr = Box::into_raw(effect);
 
@@ -57,7 +99,7 @@ pub fn play_sound_sample(channel: Channel, frequency: i32, chunk: Arc<[u8]>, pos
     }
     Err(sdl2::get_error())
   } else {
-    Ok(())
+    Ok(Some(channel))
   }
 }
 
@@ -65,15 +107,14 @@ fn gen_pitch_callback(format: sdl2::mixer::AudioFormat) -> sdl2_sys::mixer::Mix_
   let func = match format {
     sdl2::mixer::AUDIO_U8 => pitch_effect_cb_template::<u8>,
     sdl2::mixer::AUDIO_S8 => pitch_effect_cb_template::<i8>,
-    sdl2::mixer::AUDIO_U16LSB => pitch_effect_cb_template::<u16>,
-    sdl2::mixer::AUDIO_S16LSB => pitch_effect_cb_template::<i16>,
-    sdl2::mixer::AUDIO_S32LSB => pitch_effect_cb_template::<i32>,
-    sdl2::mixer::AUDIO_F32LSB => pitch_effect_cb_template::<f32>,
-
-    // Need some types that will do conversion from MSB=>LBS or what?
-    sdl2::mixer::AUDIO_U16MSB | sdl2::mixer::AUDIO_S16MSB | sdl2::mixer::AUDIO_S32MSB | sdl2::mixer::AUDIO_F32MSB => {
-      unimplemented!()
-    }
+    order::NATIVE_U16 => pitch_effect_cb_template::<u16>,
+    order::NATIVE_S16 => pitch_effect_cb_template::<i16>,
+    order::NATIVE_S32 => pitch_effect_cb_template::<i32>,
+    order::NATIVE_F32 => pitch_effect_cb_template::<f32>,
+    order::SWAPPED_U16 => pitch_effect_cb_template::<Swapped<u16>>,
+    order::SWAPPED_S16 => pitch_effect_cb_template::<Swapped<i16>>,
+    order::SWAPPED_S32 => pitch_effect_cb_template::<Swapped<i32>>,
+    order::SWAPPED_F32 => pitch_effect_cb_template::<Swapped<f32>>,
     _other => unreachable!(),
   };
   Some(func)
@@ -92,16 +133,23 @@ struct SampleCallback {
   mixer_frequency: i32,
   /// Sample index (in the output format; basically, amount of samples we have generated so far).
   target_sample_offset: usize,
+  /// Volume multiplier, from 0.0 to 1.0
+  volume: f32,
+  /// Start over once the end of the sample is reached (effect plays until channel is halted)
+  looped: bool,
 }
 
 impl SampleCallback {
-  fn generate_samples<T: AudioFormatNum + IntoSample>(&mut self, _chan: c_int, stream: &mut [T]) -> bool {
+  fn generate_samples<T: IntoSample>(&mut self, _chan: c_int, stream: &mut [T]) -> bool {
     let samples = stream.len() / self.channels;
     for sample in 0..samples {
       let output = &mut stream[(sample * self.channels)..][..self.channels];
 
       let target_sample = self.target_sample_offset + sample;
-      let source_pos = (target_sample as f32) * (self.play_frequency as f32) / (self.mixer_frequency as f32);
+      let mut source_pos = (target_sample as f32) * (self.play_frequency as f32) / (self.mixer_frequency as f32);
+      if self.looped {
+        source_pos %= self.chunk.len() as f32;
+      }
       // round to floor
       let index = source_pos as usize;
 
@@ -113,7 +161,7 @@ impl SampleCallback {
         let fract = source_pos.fract();
         let first = f32::from(first.wrapping_sub(u8::SILENCE) as i8) / 256.0;
         let second = f32::from(second.wrapping_sub(u8::SILENCE) as i8) / 256.0;
-        let sample = first * fract + second * (1.0 - fract);
+        let sample = (first * fract + second * (1.0 - fract)) * self.volume;
         // Clamp the output
         let sample = if sample < -0.5 {
           -0.5
@@ -131,7 +179,7 @@ impl SampleCallback {
       } else {
         // We are done playing! Fill the rest with the silence and return termination flag.
         for item in &mut stream[sample * self.channels..] {
-          *item = T::SILENCE;
+          *item = T::from_f32(0.0);
         }
         return true;
       }
@@ -141,7 +189,7 @@ impl SampleCallback {
   }
 }
 
-extern "C" fn pitch_effect_cb_template<T: AudioFormatNum + IntoSample>(
+extern "C" fn pitch_effect_cb_template<T: IntoSample>(
   chan: c_int,
   stream: *mut c_void,
   len: c_int,
@@ -220,3 +268,42 @@ impl IntoSample for f32 {
     sample
   }
 }
+
+/// Sample in the byte order opposite to the native one
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+struct Swapped<T>(T);
+
+impl<T: IntoSample + SwapBytes> IntoSample for Swapped<T> {
+  fn from_f32(sample: f32) -> Self {
+    Swapped(T::from_f32(sample).swap_bytes())
+  }
+}
+
+trait SwapBytes {
+  fn swap_bytes(self) -> Self;
+}
+
+impl SwapBytes for u16 {
+  fn swap_bytes(self) -> Self {
+    u16::swap_bytes(self)
+  }
+}
+
+impl SwapBytes for i16 {
+  fn swap_bytes(self) -> Self {
+    i16::swap_bytes(self)
+  }
+}
+
+impl SwapBytes for i32 {
+  fn swap_bytes(self) -> Self {
+    i32::swap_bytes(self)
+  }
+}
+
+impl SwapBytes for f32 {
+  fn swap_bytes(self) -> Self {
+    f32::from_bits(self.to_bits().swap_bytes())
+  }
+}
@@ -0,0 +1,171 @@
+//! Minimal ZIP archive reader, so game data and asset packs could be distributed as a single file.
+//! Only stored and deflated entries are supported (which is what every archiver produces by
+//! default); ZIP64 archives are not supported. Archive is read into memory as a whole.
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+use flate2::read::DeflateDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+/// End of central directory record is 22 bytes, followed by up to 64K of comment
+const MAX_END_OF_DIRECTORY_LEN: usize = 22 + 0xffff;
+/// Largest file we are willing to unpack; game files are much smaller than that
+const MAX_ENTRY_SIZE: usize = 64 * 1024 * 1024;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+#[derive(Debug, Error)]
+#[error("Provided file is not a valid ZIP archive")]
+pub struct InvalidArchive;
+
+#[derive(Debug, Error)]
+#[error("Entry is compressed with unsupported method {0}")]
+pub struct UnsupportedCompression(u16);
+
+#[derive(Debug, Error)]
+#[error("Entry is too large ({0} bytes)")]
+pub struct EntryTooLarge(usize);
+
+#[derive(Debug, Error)]
+#[error("Failed to read archive '{path}'")]
+pub struct ArchiveReadError {
+  path: PathBuf,
+  source: anyhow::Error,
+}
+
+struct ZipEntry {
+  method: u16,
+  /// Offset of the local header of the entry
+  offset: usize,
+  compressed_size: usize,
+  size: usize,
+}
+
+pub struct ZipArchive {
+  path: PathBuf,
+  data: Vec<u8>,
+  /// Entries by their upper case names. Directories are not included.
+  entries: HashMap<String, ZipEntry>,
+}
+
+impl ZipArchive {
+  pub fn open(path: &Path) -> Result<ZipArchive, ArchiveReadError> {
+    Self::open_internal(path).map_err(|source| ArchiveReadError {
+      path: path.to_owned(),
+      source,
+    })
+  }
+
+  fn open_internal(path: &Path) -> Result<ZipArchive, anyhow::Error> {
+    let data = std::fs::read(path)?;
+    let entries = read_central_directory(&data).ok_or(InvalidArchive)?;
+    Ok(ZipArchive {
+      path: path.to_owned(),
+      data,
+      entries,
+    })
+  }
+
+  /// Path to the archive file
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Names of all files in the archive, in upper case
+  pub fn names(&self) -> impl Iterator<Item = &str> {
+    self.entries.keys().map(String::as_str)
+  }
+
+  /// Read file with the given name (ignoring the case); `None` if there is no such file
+  pub fn read(&self, name: &str) -> Option<Result<Vec<u8>, anyhow::Error>> {
+    let entry = self.entries.get(&name.to_ascii_uppercase())?;
+    Some(self.read_entry(entry))
+  }
+
+  fn read_entry(&self, entry: &ZipEntry) -> Result<Vec<u8>, anyhow::Error> {
+    let header = self.data.get(entry.offset..entry.offset + 30).ok_or(InvalidArchive)?;
+    if LittleEndian::read_u32(header) != LOCAL_HEADER_SIGNATURE {
+      return Err(InvalidArchive.into());
+    }
+    // Local header has its own name and extra field lengths, these could differ from the central ones
+    let name_len = usize::from(LittleEndian::read_u16(&header[26..]));
+    let extra_len = usize::from(LittleEndian::read_u16(&header[28..]));
+    let start = entry.offset + 30 + name_len + extra_len;
+    let compressed = self
+      .data
+      .get(start..start + entry.compressed_size)
+      .ok_or(InvalidArchive)?;
+
+    if entry.size > MAX_ENTRY_SIZE {
+      return Err(EntryTooLarge(entry.size).into());
+    }
+    let mut data = Vec::with_capacity(entry.size);
+    match entry.method {
+      METHOD_STORED => data.extend_from_slice(compressed),
+      METHOD_DEFLATED => {
+        // Never unpack more than the entry claims to have, so broken (or malicious) archive can't
+        // make us run out of memory
+        DeflateDecoder::new(compressed)
+          .take(entry.size as u64 + 1)
+          .read_to_end(&mut data)?;
+      }
+      method => return Err(UnsupportedCompression(method).into()),
+    }
+    if data.len() != entry.size {
+      return Err(InvalidArchive.into());
+    }
+    Ok(data)
+  }
+}
+
+/// Parse central directory of the archive; `None` if archive is broken
+fn read_central_directory(data: &[u8]) -> Option<HashMap<String, ZipEntry>> {
+  // End of central directory record is at the very end, unless archive has a comment
+  let search_from = data.len().saturating_sub(MAX_END_OF_DIRECTORY_LEN);
+  let end = (search_from..data.len().checked_sub(22)?)
+    .rev()
+    .find(|pos| data[*pos..].starts_with(&END_OF_DIRECTORY_SIGNATURE.to_le_bytes()))?;
+  let mut it = &data[end + 10..];
+  let count = it.read_u16::<LittleEndian>().ok()?;
+  let _size = it.read_u32::<LittleEndian>().ok()?;
+  let offset = it.read_u32::<LittleEndian>().ok()? as usize;
+
+  let mut entries = HashMap::new();
+  let mut it = data.get(offset..)?;
+  for _ in 0..count {
+    if it.read_u32::<LittleEndian>().ok()? != CENTRAL_HEADER_SIGNATURE {
+      return None;
+    }
+    // Skip versions and flags
+    it = it.get(6..)?;
+    let method = it.read_u16::<LittleEndian>().ok()?;
+    // Skip modification time and CRC
+    it = it.get(8..)?;
+    let compressed_size = it.read_u32::<LittleEndian>().ok()? as usize;
+    let size = it.read_u32::<LittleEndian>().ok()? as usize;
+    let name_len = usize::from(it.read_u16::<LittleEndian>().ok()?);
+    let extra_len = usize::from(it.read_u16::<LittleEndian>().ok()?);
+    let comment_len = usize::from(it.read_u16::<LittleEndian>().ok()?);
+    // Skip disk number and attributes
+    it = it.get(8..)?;
+    let offset = it.read_u32::<LittleEndian>().ok()? as usize;
+    let name = String::from_utf8_lossy(it.get(..name_len)?).to_ascii_uppercase();
+    it = it.get(name_len + extra_len + comment_len..)?;
+
+    if !name.ends_with('/') {
+      let entry = ZipEntry {
+        method,
+        offset,
+        compressed_size,
+        size,
+      };
+      entries.insert(name, entry);
+    }
+  }
+  Some(entries)
+}
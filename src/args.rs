@@ -3,22 +3,108 @@ use std::path::PathBuf;
 pub struct Args {
   pub path: PathBuf,
   pub campaign_mode: bool,
+  /// File to write current game status to (for external tools)
+  pub status_file: Option<PathBuf>,
+  /// Skip the title screen
+  pub skip_intro: bool,
+  /// Start a game with the last used settings and players right away
+  pub quickstart: bool,
+  /// Level to play right away, skipping all menus
+  pub play: Option<String>,
+  /// Override amount of players
+  pub players: Option<u8>,
+  /// Override amount of rounds
+  pub rounds: Option<u16>,
+  /// Practice mode: tuning file with game constants, re-read every round
+  pub practice: Option<PathBuf>,
+  /// Reload images, fonts and sounds when they change on disk
+  pub watch_assets: bool,
+  /// Stream the game to observers on the given address
+  pub broadcast: Option<String>,
+  /// Watch the game streamed from the given address
+  pub observe: Option<String>,
+  /// Host a network game lobby on the given address
+  pub host: Option<String>,
+  /// Join a network game lobby on the given address
+  pub join: Option<String>,
+  /// Host a network game lobby on the given address without window and audio
+  pub host_only: Option<String>,
 }
 
 pub fn parse_args() -> Args {
   let mut args = Args {
     path: Default::default(),
     campaign_mode: false,
+    status_file: None,
+    skip_intro: false,
+    quickstart: false,
+    play: None,
+    players: None,
+    rounds: None,
+    practice: None,
+    watch_assets: false,
+    broadcast: None,
+    observe: None,
+    host: None,
+    join: None,
+    host_only: None,
   };
-  for arg in std::env::args().skip(1) {
+  let mut it = std::env::args().skip(1);
+  while let Some(arg) = it.next() {
     match arg.as_str() {
       "--campaign" => {
         args.campaign_mode = true;
       }
+      "--skip-intro" => {
+        args.skip_intro = true;
+      }
+      "--quickstart" => {
+        args.quickstart = true;
+      }
+      "--watch-assets" => {
+        args.watch_assets = true;
+      }
+      "--play" => args.play = Some(arg_value(&mut it, "--play")),
+      "--players" => match arg_value(&mut it, "--players").parse() {
+        Ok(players @ 2..=4) => args.players = Some(players),
+        _ => {
+          eprintln!("'--players' must be a number between 2 and 4");
+          std::process::exit(1);
+        }
+      },
+      "--rounds" => match arg_value(&mut it, "--rounds").parse() {
+        Ok(rounds @ 1..=55) => args.rounds = Some(rounds),
+        _ => {
+          eprintln!("'--rounds' must be a number between 1 and 55");
+          std::process::exit(1);
+        }
+      },
+      "--broadcast" => args.broadcast = Some(arg_value(&mut it, "--broadcast")),
+      "--observe" => args.observe = Some(arg_value(&mut it, "--observe")),
+      "--host" => args.host = Some(arg_value(&mut it, "--host")),
+      "--join" => args.join = Some(arg_value(&mut it, "--join")),
+      "--host-only" => args.host_only = Some(arg_value(&mut it, "--host-only")),
+      "--practice" => args.practice = Some(PathBuf::from(arg_value(&mut it, "--practice"))),
+      "--status-file" => args.status_file = Some(PathBuf::from(arg_value(&mut it, "--status-file"))),
       "--help" => {
         eprintln!("MineBombers 3.11\n");
         eprintln!("USAGE:");
-        eprintln!("    mb-reloaded [--campaign] [game-path]");
+        eprintln!("    mb-reloaded [--campaign] [--skip-intro] [--quickstart] [--status-file <path>] [game-path]");
+        eprintln!("    mb-reloaded --play <level> [--players <count>] [--rounds <count>] [game-path]");
+        eprintln!("    mb-reloaded --observe <host:port> [game-path]");
+        eprintln!("    mb-reloaded --host <address> | --join <host:port> [game-path]\n");
+        eprintln!("With '--play', game starts right away and exit code is the number of the winner");
+        eprintln!("(1 to 4) or 0 if there is no single winner. Use 'random' for a random level.");
+        eprintln!("With '--practice <file>', game constants are read from the tuning file every round.");
+        eprintln!("In practice mode, F3 cycles debug overlay of cell timers and hits, F4 shows time spent in");
+        eprintln!("world subsystems (also reported at the end of each round).");
+        eprintln!("With '--broadcast <address>', game is streamed to observers connecting to the address");
+        eprintln!("(for example, '0.0.0.0:7374').");
+        eprintln!("With '--host <address>', network lobby is opened for players to join with '--join'.");
+        eprintln!("With '--host-only <address>', network lobby is hosted without window or audio (dedicated");
+        eprintln!("host); players join it with '--join' and the match is streamed to '--broadcast' address.");
+        eprintln!("With '--watch-assets', images, fonts and sounds are reloaded once changed on disk.");
+        eprintln!("Game path could be a directory or a ZIP archive with the game files.");
         std::process::exit(0);
       }
       arg => {
@@ -36,12 +122,24 @@ pub fn parse_args() -> Args {
     }
   }
 
-  if !args.path.is_dir() || !args.path.join("TITLEBE.SPY").is_file() {
+  let is_game_dir = args.path.is_dir() && args.path.join("TITLEBE.SPY").is_file();
+  if !is_game_dir && !crate::vfs::is_archive(&args.path) {
     eprintln!(
-      "'{}' is not a valid game directory (must be a directory with 'TITLEBE.SPY' file).",
+      "'{}' is not a valid game directory (must be a directory with 'TITLEBE.SPY' file or a ZIP archive).",
       args.path.display()
     );
     std::process::exit(1);
   }
   args
 }
+
+/// Get value of the argument which requires one
+fn arg_value(it: &mut impl Iterator<Item = String>, name: &str) -> String {
+  match it.next() {
+    Some(value) => value,
+    None => {
+      eprintln!("Missing value for '{}'", name);
+      std::process::exit(1);
+    }
+  }
+}
@@ -0,0 +1,55 @@
+//! Asset watcher for development: tracks modification times of the game assets (images, fonts and
+//! sound samples) in the game directory and enabled asset packs, so modders can see their changes
+//! without restarting the game. Enabled with `--watch-assets`.
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Extensions of the files which could be reloaded
+pub const ASSET_EXTENSIONS: [&str; 4] = ["SPY", "PPM", "FON", "VOC"];
+
+pub struct AssetWatcher {
+  dirs: Vec<PathBuf>,
+  /// Last seen modification time of each asset, by file path
+  modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl AssetWatcher {
+  pub fn new(dirs: Vec<PathBuf>) -> Self {
+    let mut watcher = AssetWatcher {
+      dirs,
+      modified: HashMap::new(),
+    };
+    // Remember current state, so only files changed from now on are reported
+    watcher.changed();
+    watcher
+  }
+
+  /// Names (in upper case) of the asset files changed since the last call
+  pub fn changed(&mut self) -> Vec<String> {
+    let mut changed = Vec::new();
+    let entries = self
+      .dirs
+      .iter()
+      .flat_map(|dir| dir.read_dir().into_iter().flatten().flatten());
+    for entry in entries {
+      let name = entry.file_name().to_string_lossy().to_ascii_uppercase();
+      let is_asset = name
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| ASSET_EXTENSIONS.contains(&ext));
+      if !is_asset {
+        continue;
+      }
+      let modified = match entry.metadata().and_then(|meta| meta.modified()) {
+        Ok(modified) => modified,
+        Err(_) => continue,
+      };
+      if let Some(previous) = self.modified.insert(entry.path(), modified) {
+        if previous != modified && !changed.contains(&name) {
+          changed.push(name);
+        }
+      }
+    }
+    changed
+  }
+}
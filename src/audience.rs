@@ -0,0 +1,111 @@
+//! Party mode: local HTTP endpoint which allows audience (for example, stream viewers via a chat
+//! bot) to trigger events in the game. Requests are as simple as `GET /medikit`.
+use crate::world::map::MapValue;
+use crate::world::World;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+/// Default address to listen on
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7373";
+
+/// Minimum time between two accepted events
+const COOLDOWN: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug)]
+pub enum AudienceEvent {
+  /// Put a medikit somewhere on the map
+  Medikit,
+  /// Shake the level
+  Earthquake,
+  /// Put a weapons crate somewhere on the map
+  Crate,
+}
+
+impl AudienceEvent {
+  fn from_path(path: &str) -> Option<AudienceEvent> {
+    match path {
+      "/medikit" => Some(AudienceEvent::Medikit),
+      "/earthquake" => Some(AudienceEvent::Earthquake),
+      "/crate" => Some(AudienceEvent::Crate),
+      _ => None,
+    }
+  }
+
+  /// Apply event to the world
+  pub fn apply(self, world: &mut World) {
+    match self {
+      AudienceEvent::Medikit => {
+        world.place_at_random(MapValue::Medikit);
+      }
+      AudienceEvent::Earthquake => world.earthquake(),
+      AudienceEvent::Crate => {
+        world.place_at_random(MapValue::WeaponsCrate);
+      }
+    }
+  }
+}
+
+/// Receiving end of the audience events
+pub struct Audience {
+  events: Receiver<AudienceEvent>,
+}
+
+impl Audience {
+  /// Start listening for the audience commands in a background thread
+  pub fn start(address: &str) -> Result<Audience, std::io::Error> {
+    let listener = TcpListener::bind(address)?;
+    let (sender, events) = channel();
+    std::thread::spawn(move || {
+      let mut last_event: Option<Instant> = None;
+      for stream in listener.incoming().flatten() {
+        // Ignore misbehaving clients
+        let _ = handle_request(stream, &sender, &mut last_event);
+      }
+    });
+    Ok(Audience { events })
+  }
+
+  /// Apply all pending audience events to the world
+  pub fn apply_events(&self, world: &mut World) {
+    for event in self.events.try_iter() {
+      event.apply(world);
+    }
+  }
+
+  /// Drop all pending events (for example, events received between rounds)
+  pub fn discard_events(&self) {
+    self.events.try_iter().for_each(drop);
+  }
+}
+
+fn handle_request(
+  mut stream: TcpStream,
+  sender: &Sender<AudienceEvent>,
+  last_event: &mut Option<Instant>,
+) -> Result<(), std::io::Error> {
+  stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+  let mut line = String::new();
+  BufReader::new(&stream).read_line(&mut line)?;
+
+  // Request line looks like `GET /medikit HTTP/1.1`
+  let path = line.split_whitespace().nth(1).unwrap_or("");
+  let status = match AudienceEvent::from_path(path) {
+    None => "404 Not Found",
+    Some(_) if last_event.is_some_and(|last| last.elapsed() < COOLDOWN) => "429 Too Many Requests",
+    Some(event) => {
+      *last_event = Some(Instant::now());
+      let _ = sender.send(event);
+      "200 OK"
+    }
+  };
+  write!(
+    stream,
+    "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}\n",
+    status,
+    status.len() + 1,
+    status
+  )?;
+  Ok(())
+}
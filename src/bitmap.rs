@@ -1,28 +1,115 @@
 use crate::world::map::MapValue;
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
+use std::iter::FromIterator;
 
+/// Build a set from the raw bitmap (32 bytes, least significant bit of the first byte is the value
+/// 0). Used for the tables taken from the original game.
 #[macro_export]
 macro_rules! bitmap {
   ($bits:expr) => {
-    $crate::bitmap::MapValueSet { bits: $bits }
+    $crate::bitmap::MapValueSet::from_bits($bits)
   };
 }
 
-/// Bitmap enables indexing
+/// Set of map values, stored as a bitmap. Sets could be built at compile time (`bitmap!` or
+/// `from_values`) as well as at runtime (`insert`, `remove`, collecting from an iterator).
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
 pub struct MapValueSet {
-  #[doc(hidden)]
-  pub bits: [u8; 32],
+  bits: [u8; 32],
+}
+
+impl MapValueSet {
+  pub const fn empty() -> Self {
+    MapValueSet { bits: [0; 32] }
+  }
+
+  pub const fn from_bits(bits: [u8; 32]) -> Self {
+    MapValueSet { bits }
+  }
+
+  pub const fn from_values(values: &[MapValue]) -> Self {
+    let mut bits = [0; 32];
+    let mut idx = 0;
+    while idx < values.len() {
+      let value = values[idx] as usize;
+      bits[value / 8] |= 1 << (value & 7);
+      idx += 1;
+    }
+    MapValueSet { bits }
+  }
+
+  pub fn contains(&self, value: MapValue) -> bool {
+    let value = value as usize;
+    (self.bits[value / 8] & (1 << (value & 7))) != 0
+  }
+
+  /// Add value to the set. Returns `true` if value was not in the set.
+  pub fn insert(&mut self, value: MapValue) -> bool {
+    let added = !self.contains(value);
+    let value = value as usize;
+    self.bits[value / 8] |= 1 << (value & 7);
+    added
+  }
+
+  /// Remove value from the set. Returns `true` if value was in the set.
+  pub fn remove(&mut self, value: MapValue) -> bool {
+    let removed = self.contains(value);
+    let value = value as usize;
+    self.bits[value / 8] &= !(1 << (value & 7));
+    removed
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.bits.iter().all(|byte| *byte == 0)
+  }
+
+  pub fn len(&self) -> usize {
+    self.bits.iter().map(|byte| byte.count_ones() as usize).sum()
+  }
+
+  /// Iterate through all values in the set, in the order of their codes
+  pub fn iter(&self) -> impl Iterator<Item = MapValue> + '_ {
+    (0..=255u8)
+      .filter_map(|idx| MapValue::try_from(idx).ok())
+      .filter(move |value| self.contains(*value))
+  }
+
+  pub fn union(&self, other: &MapValueSet) -> MapValueSet {
+    let mut bits = self.bits;
+    for (bits, other) in bits.iter_mut().zip(other.bits.iter()) {
+      *bits |= *other;
+    }
+    MapValueSet { bits }
+  }
+
+  pub fn intersection(&self, other: &MapValueSet) -> MapValueSet {
+    let mut bits = self.bits;
+    for (bits, other) in bits.iter_mut().zip(other.bits.iter()) {
+      *bits &= *other;
+    }
+    MapValueSet { bits }
+  }
+}
+
+impl FromIterator<MapValue> for MapValueSet {
+  fn from_iter<I: IntoIterator<Item = MapValue>>(iter: I) -> Self {
+    let mut set = MapValueSet::empty();
+    set.extend(iter);
+    set
+  }
+}
+
+impl Extend<MapValue> for MapValueSet {
+  fn extend<I: IntoIterator<Item = MapValue>>(&mut self, iter: I) {
+    for value in iter {
+      self.insert(value);
+    }
+  }
 }
 
 impl std::fmt::Debug for MapValueSet {
   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-    let mut list: Vec<MapValue> = Vec::new();
-    for idx in 0..=255u8 {
-      if self[MapValue::try_from(idx).unwrap()] {
-        list.push(idx.try_into().unwrap());
-      }
-    }
-    std::fmt::Debug::fmt(&list, f)
+    f.debug_set().entries(self.iter()).finish()
   }
 }
 
@@ -30,8 +117,7 @@ impl std::ops::Index<MapValue> for MapValueSet {
   type Output = bool;
 
   fn index(&self, index: MapValue) -> &Self::Output {
-    let index = index as usize;
-    if (self.bits.as_ref()[index / 8] & (1 << (index & 7))) != 0 {
+    if self.contains(index) {
       &true
     } else {
       &false
@@ -0,0 +1,273 @@
+//! Game broadcast for observers: host streams world snapshots and per-tick deltas over TCP, so
+//! the game could be watched (and cast) from another machine. Observers can also chat with each
+//! other; host relays chat messages to everyone.
+use crate::world::snapshot::{WorldDelta, WorldSnapshot};
+use crate::world::World;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::cell::RefCell;
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Observer too slow to receive the stream is disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+const FRAME_SNAPSHOT: u8 = 0;
+const FRAME_DELTA: u8 = 1;
+const FRAME_CHAT: u8 = 2;
+const FRAME_RESYNC: u8 = 3;
+
+/// How often (in ticks) state checksum is sent along with the delta
+const CHECKSUM_INTERVAL: usize = 50;
+
+/// Amount of frames waiting to be written to observers. If observers can't keep up, frames are
+/// dropped and the stream starts over from the full snapshot.
+const QUEUE_LEN: usize = 100;
+
+/// Single message of the stream
+pub enum Frame {
+  /// Full state of the world; sent to new observers and at the start of each round
+  Snapshot(WorldSnapshot),
+  Delta(WorldDelta),
+  /// Chat message
+  Chat(String),
+  /// Observer detected desync and requests the full snapshot
+  Resync,
+}
+
+/// Encoded frames for the writer thread
+enum Outgoing {
+  /// Frame for all observers
+  Frame(Vec<u8>),
+  /// Observers who have just joined, with the snapshot they start from
+  Join(Vec<TcpStream>, Vec<u8>),
+}
+
+/// Host side of the broadcast
+pub struct Broadcast {
+  /// Observers which have just connected and need a full snapshot
+  connected: Receiver<TcpStream>,
+  /// Connected observers waiting for the room in the queue
+  joining: RefCell<Vec<TcpStream>>,
+  /// Frames written to observers by the writer thread, so game never waits for the network
+  outgoing: SyncSender<Outgoing>,
+  /// Some observer requested the full snapshot
+  resync: Arc<AtomicBool>,
+  /// Last state sent to the observers
+  last: RefCell<Option<WorldSnapshot>>,
+}
+
+impl Broadcast {
+  /// Start accepting observers in a background thread
+  pub fn start(address: &str) -> Result<Broadcast, std::io::Error> {
+    let listener = TcpListener::bind(address)?;
+    let (sender, connected) = channel();
+    let (outgoing, queue) = sync_channel(QUEUE_LEN);
+    let relay = outgoing.clone();
+    let resync = Arc::new(AtomicBool::new(false));
+    let requested = resync.clone();
+    std::thread::spawn(move || {
+      for stream in listener.incoming().flatten() {
+        if stream.set_nodelay(true).is_err() || stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err() {
+          continue;
+        }
+        if let Ok(reader) = stream.try_clone() {
+          let relay = relay.clone();
+          let requested = requested.clone();
+          std::thread::spawn(move || relay_chat(reader, &relay, &requested));
+        }
+        if sender.send(stream).is_err() {
+          break;
+        }
+      }
+    });
+    std::thread::spawn(move || write_frames(queue));
+    Ok(Broadcast {
+      connected,
+      joining: RefCell::new(Vec::new()),
+      outgoing,
+      resync,
+      last: RefCell::new(None),
+    })
+  }
+
+  /// Send world changes since the last call to all observers. New observers (and everyone on the
+  /// start of a new round) get the full snapshot.
+  pub fn publish(&self, world: &World) {
+    let mut last = self.last.borrow_mut();
+    let resync = self.resync.swap(false, Ordering::Relaxed);
+    let frame = match last.as_mut() {
+      Some(last) if last.tick <= world.round_counter && !resync => {
+        let mut delta = last.advance(world);
+        if delta.tick % CHECKSUM_INTERVAL == 0 {
+          delta.checksum = Some(last.checksum());
+        }
+        Frame::Delta(delta)
+      }
+      _ => {
+        let snapshot = world.snapshot();
+        *last = Some(snapshot.clone());
+        Frame::Snapshot(snapshot)
+      }
+    };
+    if self.outgoing.try_send(Outgoing::Frame(encode(&frame))).is_err() {
+      // Observers missed the frame, so the next one has to be the full snapshot
+      *last = None;
+      return;
+    }
+
+    let mut joining = self.joining.borrow_mut();
+    joining.extend(self.connected.try_iter());
+    if let (false, Some(last)) = (joining.is_empty(), last.as_ref()) {
+      let frame = encode(&Frame::Snapshot(last.clone()));
+      if let Err(TrySendError::Full(Outgoing::Join(streams, _))) = self
+        .outgoing
+        .try_send(Outgoing::Join(std::mem::take(&mut joining), frame))
+      {
+        *joining = streams;
+      }
+    }
+  }
+
+  /// Forget the last sent state, so the next round starts with a full snapshot. That is also how
+  /// observers learn about the restart of the round: snapshot of the earlier tick than the one they
+  /// have starts the round over, so there is no separate restart message.
+  pub fn reset(&self) {
+    *self.last.borrow_mut() = None;
+  }
+}
+
+/// Read messages sent by the observer: relay chat messages to all observers and remember resync
+/// requests. Chat goes through the writer queue, so it is never written in the middle of a frame.
+fn relay_chat(stream: TcpStream, outgoing: &SyncSender<Outgoing>, resync: &AtomicBool) {
+  let mut reader = BufReader::new(stream);
+  loop {
+    match decode(&mut reader) {
+      Ok(Frame::Chat(text)) => {
+        if outgoing.send(Outgoing::Frame(encode(&Frame::Chat(text)))).is_err() {
+          break;
+        }
+      }
+      Ok(Frame::Resync) => resync.store(true, Ordering::Relaxed),
+      // Observers don't send anything else; disconnect misbehaving clients
+      _ => break,
+    }
+  }
+}
+
+/// Write queued frames to observers until the host is gone
+fn write_frames(queue: Receiver<Outgoing>) {
+  let mut observers = Vec::new();
+  for message in queue {
+    match message {
+      Outgoing::Frame(frame) => send(&mut observers, &frame),
+      Outgoing::Join(mut joined, frame) => {
+        send(&mut joined, &frame);
+        observers.extend(joined);
+      }
+    }
+  }
+}
+
+/// Write frame to every observer, dropping the ones which fail to receive it
+fn send(observers: &mut Vec<TcpStream>, frame: &[u8]) {
+  observers.retain(|mut stream| stream.write_all(frame).is_ok());
+}
+
+fn encode(frame: &Frame) -> Vec<u8> {
+  // Length of the frame is patched once it is encoded
+  let mut buf = vec![0; 4];
+  let result = match frame {
+    Frame::Snapshot(snapshot) => {
+      buf.push(FRAME_SNAPSHOT);
+      snapshot.write_to(&mut buf)
+    }
+    Frame::Delta(delta) => {
+      buf.push(FRAME_DELTA);
+      delta.write_to(&mut buf)
+    }
+    Frame::Chat(text) => {
+      buf.push(FRAME_CHAT);
+      let text = &text.as_bytes()[..text.len().min(255)];
+      buf.write_u8(text.len() as u8).and_then(|()| buf.write_all(text))
+    }
+    Frame::Resync => {
+      buf.push(FRAME_RESYNC);
+      Ok(())
+    }
+  };
+  // Writing to a vector never fails
+  result.unwrap();
+  let len = (buf.len() - 4) as u32;
+  buf[..4].copy_from_slice(&len.to_le_bytes());
+  buf
+}
+
+fn decode(r: &mut impl Read) -> Result<Frame, anyhow::Error> {
+  let len = r.read_u32::<LittleEndian>()?;
+  let mut frame = r.take(u64::from(len));
+  let result = match frame.read_u8()? {
+    FRAME_SNAPSHOT => Frame::Snapshot(WorldSnapshot::read_from(&mut frame)?),
+    FRAME_DELTA => Frame::Delta(WorldDelta::read_from(&mut frame)?),
+    FRAME_CHAT => {
+      let mut text = vec![0; usize::from(frame.read_u8()?)];
+      frame.read_exact(&mut text)?;
+      Frame::Chat(String::from_utf8_lossy(&text).into_owned())
+    }
+    FRAME_RESYNC => Frame::Resync,
+    _ => return Err(crate::world::snapshot::InvalidSnapshot.into()),
+  };
+  // Skip whatever is left of the frame
+  std::io::copy(&mut frame, &mut std::io::sink())?;
+  Ok(result)
+}
+
+/// Observer side of the broadcast
+pub struct BroadcastReceiver {
+  frames: Receiver<Result<Frame, String>>,
+  /// Stream to send chat messages to the host
+  stream: TcpStream,
+}
+
+impl BroadcastReceiver {
+  /// Connect to the host and start receiving frames in a background thread
+  pub fn connect(address: &str) -> Result<BroadcastReceiver, std::io::Error> {
+    let stream = TcpStream::connect(address)?;
+    let reader = stream.try_clone()?;
+    let (sender, frames) = channel();
+    std::thread::spawn(move || {
+      let mut reader = BufReader::new(reader);
+      loop {
+        let frame = decode(&mut reader).map_err(|err| format!("{:#}", err));
+        let failed = frame.is_err();
+        if sender.send(frame).is_err() || failed {
+          break;
+        }
+      }
+    });
+    Ok(BroadcastReceiver { frames, stream })
+  }
+
+  /// Next received frame, if any. Error is returned once the stream is over.
+  pub fn next_frame(&self) -> Result<Option<Frame>, anyhow::Error> {
+    match self.frames.try_recv() {
+      Ok(Ok(frame)) => Ok(Some(frame)),
+      Ok(Err(err)) => Err(anyhow::anyhow!("broadcast stream failed: {}", err)),
+      Err(TryRecvError::Empty) => Ok(None),
+      Err(TryRecvError::Disconnected) => Err(anyhow::anyhow!("broadcast stream is closed")),
+    }
+  }
+
+  /// Send chat message to everyone watching the game
+  pub fn send_chat(&self, text: &str) -> Result<(), std::io::Error> {
+    (&self.stream).write_all(&encode(&Frame::Chat(text.to_owned())))
+  }
+
+  /// Ask host for the full snapshot
+  pub fn request_resync(&self) -> Result<(), std::io::Error> {
+    (&self.stream).write_all(&encode(&Frame::Resync))
+  }
+}
@@ -0,0 +1,336 @@
+//! Campaign progress: best completion time of each campaign level and medals awarded for them. Par
+//! times come from the campaign manifest, `campaign.txt` in the game data (or asset pack), given as
+//! `LEVEL<n>=<seconds>` lines (levels are numbered from 0, same as `LEVEL<n>.MNL` files). Manifest
+//! can also make campaign branch: `LEVEL<n>.EXIT<id>=<level>` line tells which level exit tagged
+//! with `id` leads to (by default, every exit leads to the next level).
+//!
+//! Bonus levels (`BONUS<m>.MNL` files) are played between campaign levels: `BONUS<m>=<level>` plays
+//! bonus level after the given campaign level is completed and `LEVEL<n>.EXIT<id>=B<m>` makes exit a
+//! secret entrance to the bonus level. Time limit of the bonus level is given as
+//! `BONUS<m>.TIME=<seconds>`.
+//!
+//! Campaign level can be given a time limit as `LEVEL<n>.TIME=<seconds>`. Running out of time does
+//! not fail the level: monsters get enraged instead, and the player has to hurry to the exit.
+//! Shop prices before the level are scaled by `LEVEL<n>.PRICES=<percent>`, so later levels could be
+//! made more expensive.
+use crate::vfs::Vfs;
+use byteorder::{LittleEndian, ReadBytesExt};
+use sdl2::pixels::Color;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Amount of levels in the campaign
+pub const CAMPAIGN_LEVELS: usize = 15;
+
+const MANIFEST_FILE: &str = "campaign.txt";
+
+/// Best times are stored in our own file, original game has no such thing
+const PROGRESS_FILE: &str = "camprel.dat";
+
+/// Par time for levels not listed in the manifest
+const DEFAULT_PAR: Duration = Duration::from_secs(180);
+
+/// Allowed range of the shop price multiplier, in percents
+const PRICE_PERCENT_RANGE: std::ops::RangeInclusive<u32> = 10..=1000;
+
+/// Time limit (in seconds) of bonus levels not listed in the manifest
+const DEFAULT_BONUS_TIME: u32 = 60;
+
+/// After that many failures of the same level, player is offered an assist
+pub const ASSIST_FAILURES: u8 = 3;
+
+/// Extra cash given by the cash assist
+pub const ASSIST_CASH: u32 = 500;
+
+#[derive(Debug, Error)]
+#[error("Failed to save campaign progress to '{path}'")]
+pub struct ProgressSaveError {
+  #[source]
+  source: std::io::Error,
+  path: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Medal {
+  Bronze,
+  Silver,
+  Gold,
+}
+
+impl Medal {
+  /// Gold is for beating the par time, silver is for being within half of it on top; every completed
+  /// level gets at least bronze.
+  pub fn award(time: Duration, par: Duration) -> Medal {
+    if time <= par {
+      Medal::Gold
+    } else if time <= par + par / 2 {
+      Medal::Silver
+    } else {
+      Medal::Bronze
+    }
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Medal::Bronze => "Bronze",
+      Medal::Silver => "Silver",
+      Medal::Gold => "Gold",
+    }
+  }
+
+  pub fn color(self) -> Color {
+    match self {
+      Medal::Bronze => Color::RGB(205, 127, 50),
+      Medal::Silver => Color::RGB(192, 192, 192),
+      Medal::Gold => Color::RGB(255, 215, 0),
+    }
+  }
+}
+
+/// Help offered to the player stuck on a level, only applies to a single attempt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assist {
+  /// Extra starting cash
+  Cash,
+  /// Monsters are less aggressive
+  CalmMonsters,
+}
+
+pub struct CampaignManifest {
+  pub par: [Duration; CAMPAIGN_LEVELS],
+  /// Level each tagged exit leads to, as `(level, exit id, next level)`
+  branches: Vec<(usize, u8, usize)>,
+  /// Bonus levels behind secret exits, as `(level, exit id, bonus level)`
+  secrets: Vec<(usize, u8, usize)>,
+  /// Bonus levels played after completing campaign levels, as `(level, bonus level)`
+  bonuses: Vec<(usize, usize)>,
+  /// Time limits of bonus levels, in seconds
+  bonus_times: Vec<(usize, u32)>,
+  /// Time limits of campaign levels, in seconds
+  time_limits: Vec<(usize, u32)>,
+  /// Shop price multiplier before each level, in percents
+  prices: [u32; CAMPAIGN_LEVELS],
+}
+
+impl CampaignManifest {
+  /// Load par times from the manifest. Manifest is optional; missing or invalid entries use the
+  /// default par time.
+  pub fn load(vfs: &Vfs) -> Self {
+    let mut manifest = CampaignManifest {
+      par: [DEFAULT_PAR; CAMPAIGN_LEVELS],
+      branches: Vec::new(),
+      secrets: Vec::new(),
+      bonuses: Vec::new(),
+      bonus_times: Vec::new(),
+      time_limits: Vec::new(),
+      prices: [100; CAMPAIGN_LEVELS],
+    };
+    let text = match vfs.read(MANIFEST_FILE) {
+      Ok(file) => String::from_utf8_lossy(&file.data).into_owned(),
+      Err(_) => return manifest,
+    };
+    for line in text.lines() {
+      let (key, value) = match line.split_once('=') {
+        Some((key, value)) => (key.trim().to_ascii_uppercase(), value.trim()),
+        None => continue,
+      };
+      if let Some((level, exit)) = key.split_once(".EXIT") {
+        manifest.parse_branch(level, exit, value);
+        continue;
+      }
+      if let Some(bonus) = key.strip_prefix("BONUS") {
+        manifest.parse_bonus(bonus, value);
+        continue;
+      }
+      if let Some(level) = key.strip_suffix(".TIME") {
+        manifest.parse_time_limit(level, value);
+        continue;
+      }
+      if let Some(level) = key.strip_suffix(".PRICES") {
+        manifest.parse_prices(level, value);
+        continue;
+      }
+      let level = key.strip_prefix("LEVEL").and_then(|level| level.parse::<usize>().ok());
+      if let (Some(level), Ok(secs)) = (level, value.parse::<u64>()) {
+        if level < CAMPAIGN_LEVELS && secs > 0 {
+          manifest.par[level] = Duration::from_secs(secs);
+        }
+      }
+    }
+    manifest
+  }
+
+  fn parse_branch(&mut self, level: &str, exit: &str, value: &str) {
+    let level = level
+      .strip_prefix("LEVEL")
+      .and_then(|level| level.parse::<usize>().ok());
+    if let Some(bonus) = value.strip_prefix('B') {
+      if let (Some(level), Ok(exit), Ok(bonus)) = (level, exit.parse::<u8>(), bonus.parse::<usize>()) {
+        self.secrets.push((level, exit, bonus));
+      }
+      return;
+    }
+    if let (Some(level), Ok(exit), Ok(next)) = (level, exit.parse::<u8>(), value.parse::<usize>()) {
+      // Leading past the last level finishes the campaign
+      if level < CAMPAIGN_LEVELS {
+        self.branches.push((level, exit, next.min(CAMPAIGN_LEVELS)));
+      }
+    }
+  }
+
+  fn parse_time_limit(&mut self, level: &str, value: &str) {
+    let level = level
+      .strip_prefix("LEVEL")
+      .and_then(|level| level.parse::<usize>().ok());
+    if let (Some(level), Ok(secs)) = (level, value.parse::<u32>()) {
+      if level < CAMPAIGN_LEVELS && secs > 0 {
+        self.time_limits.push((level, secs));
+      }
+    }
+  }
+
+  fn parse_prices(&mut self, level: &str, value: &str) {
+    let level = level
+      .strip_prefix("LEVEL")
+      .and_then(|level| level.parse::<usize>().ok());
+    if let (Some(level), Ok(percent)) = (level, value.trim_end_matches('%').parse::<u32>()) {
+      if level < CAMPAIGN_LEVELS {
+        self.prices[level] = percent.clamp(*PRICE_PERCENT_RANGE.start(), *PRICE_PERCENT_RANGE.end());
+      }
+    }
+  }
+
+  fn parse_bonus(&mut self, bonus: &str, value: &str) {
+    if let Some(bonus) = bonus.strip_suffix(".TIME") {
+      if let (Ok(bonus), Ok(secs)) = (bonus.parse::<usize>(), value.parse::<u32>()) {
+        self.bonus_times.push((bonus, secs.max(1)));
+      }
+    } else if let (Ok(bonus), Ok(level)) = (bonus.parse::<usize>(), value.parse::<usize>()) {
+      self.bonuses.push((level, bonus));
+    }
+  }
+
+  /// Bonus level to play after leaving `level` through the given exit, if any
+  pub fn bonus_level(&self, level: usize, exit: u8) -> Option<usize> {
+    let secret = self
+      .secrets
+      .iter()
+      .find(|(from, id, _)| *from == level && *id == exit)
+      .map(|(_, _, bonus)| *bonus);
+    secret.or_else(|| {
+      self
+        .bonuses
+        .iter()
+        .find(|(after, _)| *after == level)
+        .map(|(_, bonus)| *bonus)
+    })
+  }
+
+  /// Time limit of the bonus level, in seconds
+  pub fn bonus_time(&self, bonus: usize) -> u32 {
+    self
+      .bonus_times
+      .iter()
+      .find(|(id, _)| *id == bonus)
+      .map_or(DEFAULT_BONUS_TIME, |(_, secs)| *secs)
+  }
+
+  /// Time limit of the campaign level, in seconds; levels not listed in the manifest have no limit
+  pub fn time_limit(&self, level: usize) -> Option<u32> {
+    self
+      .time_limits
+      .iter()
+      .find(|(id, _)| *id == level)
+      .map(|(_, secs)| *secs)
+  }
+
+  /// Shop price multiplier before the campaign level, in percents
+  pub fn price_percent(&self, level: usize) -> u32 {
+    self.prices.get(level).copied().unwrap_or(100)
+  }
+
+  /// Level to play after leaving `level` through the given exit
+  pub fn next_level(&self, level: usize, exit: u8) -> usize {
+    self
+      .branches
+      .iter()
+      .find(|(from, id, _)| *from == level && *id == exit)
+      .map_or(level + 1, |(_, _, next)| *next)
+  }
+}
+
+#[derive(Default)]
+pub struct CampaignProgress {
+  /// Best completion time of each level, if it was ever completed
+  best: [Option<Duration>; CAMPAIGN_LEVELS],
+  /// Failures of each level since it was completed last time
+  failures: [u8; CAMPAIGN_LEVELS],
+}
+
+impl CampaignProgress {
+  /// Load best times and failure counters. Best time of each level is stored as amount of
+  /// milliseconds (`u32`), zero means level was never completed. Best times are followed by failure
+  /// counters (`u8`). Missing or broken file is treated as no progress. Progress is saved into the
+  /// game directory; until it is, progress shipped with the game data (if any) is used.
+  pub fn load(game_dir: &Path, vfs: &Vfs) -> Self {
+    let mut progress = CampaignProgress::default();
+    let data = std::fs::read(game_dir.join(PROGRESS_FILE)).or_else(|_| vfs.read(PROGRESS_FILE).map(|file| file.data));
+    if let Ok(data) = data {
+      let mut it = data.as_slice();
+      for best in progress.best.iter_mut() {
+        let millis = it.read_u32::<LittleEndian>().unwrap_or(0);
+        if millis != 0 {
+          *best = Some(Duration::from_millis(u64::from(millis)));
+        }
+      }
+      for failures in progress.failures.iter_mut() {
+        *failures = it.read_u8().unwrap_or(0);
+      }
+    }
+    progress
+  }
+
+  pub fn save(&self, game_dir: &Path) -> Result<(), ProgressSaveError> {
+    let mut out = Vec::with_capacity(CAMPAIGN_LEVELS * 5);
+    for best in &self.best {
+      let millis = best.map_or(0, |best| best.as_millis().clamp(1, u128::from(u32::MAX)) as u32);
+      out.extend_from_slice(&millis.to_le_bytes());
+    }
+    out.extend_from_slice(&self.failures);
+    let path = game_dir.join(PROGRESS_FILE);
+    std::fs::write(&path, out).map_err(|source| ProgressSaveError { source, path })
+  }
+
+  pub fn best(&self, level: usize) -> Option<Duration> {
+    self.best[level]
+  }
+
+  pub fn failures(&self, level: usize) -> u8 {
+    self.failures[level]
+  }
+
+  pub fn record_failure(&mut self, level: usize) {
+    self.failures[level] = self.failures[level].saturating_add(1);
+  }
+
+  /// Record level completion time and reset its failure counter. Assisted completions don't count
+  /// towards best times. Returns `true` if progress was changed.
+  pub fn record(&mut self, level: usize, time: Duration, assisted: bool) -> bool {
+    let failed = std::mem::take(&mut self.failures[level]) != 0;
+    if assisted {
+      return failed;
+    }
+    if self.best[level].is_some_and(|best| best <= time) {
+      return failed;
+    }
+    self.best[level] = Some(time);
+    true
+  }
+}
+
+/// Format time as `M:SS`
+pub fn format_time(time: Duration) -> String {
+  let secs = time.as_secs();
+  format!("{}:{:02}", secs / 60, secs % 60)
+}
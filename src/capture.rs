@@ -0,0 +1,135 @@
+//! Capture of the round into an animated PNG, for sharing the highlights. Frames are read back from
+//! the screen buffer every few ticks and queued to the encoder thread, so the game is not stalled by
+//! the compression. APNG needs the amount of frames upfront, so frames are kept compressed in memory
+//! until the capture is stopped.
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Frames are captured every that many ticks (10 frames per second)
+const CAPTURE_INTERVAL: usize = 5;
+/// Delay between frames, in seconds (numerator and denominator)
+const FRAME_DELAY: (u16, u16) = (1, 10);
+/// Frames waiting for the encoder; new frames are dropped while the encoder is lagging behind
+const MAX_QUEUED: usize = 8;
+/// Captures are limited to 10 minutes, further frames are ignored
+const MAX_FRAMES: usize = 6000;
+
+#[derive(Debug, Error)]
+#[error("Failed to write round capture to '{path}'")]
+pub struct CaptureSaveError {
+  #[source]
+  source: anyhow::Error,
+  path: PathBuf,
+}
+
+/// Frame read back from the screen buffer, 3 bytes (RGB) per pixel
+pub struct Frame {
+  pub width: u32,
+  pub height: u32,
+  pub data: Vec<u8>,
+}
+
+pub struct RoundCapture {
+  /// Frames for the encoder thread. Thread writes the capture once the sender is dropped; nobody
+  /// waits for it, so the game is never stalled by writing.
+  sender: SyncSender<Frame>,
+  /// Last captured tick, so stalled game doesn't produce duplicate frames
+  last_tick: Option<usize>,
+}
+
+impl RoundCapture {
+  /// Start capturing the round into `captures` directory of the game directory. Capture is written
+  /// once it is dropped or finished; failures are reported, but not propagated to the game.
+  pub fn start(game_dir: &Path, round: u16, level: &str) -> Self {
+    let timestamp = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |duration| duration.as_secs());
+    let path = game_dir
+      .join("captures")
+      .join(format!("{}-round{:02}-{}.png", timestamp, round + 1, level));
+    let (sender, receiver) = sync_channel::<Frame>(MAX_QUEUED);
+    std::thread::spawn(move || {
+      let mut frames = Vec::new();
+      let mut size = (0, 0);
+      let mut result = Ok(());
+      for frame in receiver {
+        if frames.is_empty() {
+          size = (frame.width, frame.height);
+        }
+        if result.is_err() || frames.len() >= MAX_FRAMES || size != (frame.width, frame.height) {
+          continue;
+        }
+        match compress(&frame.data) {
+          Ok(frame) => frames.push(frame),
+          Err(err) => result = Err(err.into()),
+        }
+      }
+      let result = result.and_then(|()| write_capture(&path, size, &frames));
+      if let Err(source) = result {
+        let err = CaptureSaveError { source, path };
+        eprintln!("Failed to save round capture: {:#}", anyhow::Error::from(err));
+      }
+    });
+    RoundCapture {
+      sender,
+      last_tick: None,
+    }
+  }
+
+  /// Check if frame of the given tick should be captured
+  pub fn wants(&mut self, tick: usize) -> bool {
+    if tick % CAPTURE_INTERVAL != 0 || self.last_tick == Some(tick) {
+      return false;
+    }
+    self.last_tick = Some(tick);
+    true
+  }
+
+  /// Queue frame for encoding. Frame is dropped if encoder is busy.
+  pub fn push(&self, frame: Frame) {
+    let _ = self.sender.try_send(frame);
+  }
+
+  /// Stop capturing; capture is written in the background
+  pub fn finish(self) {
+    drop(self.sender);
+  }
+}
+
+fn compress(data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+  let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+  encoder.write_all(data)?;
+  encoder.finish()
+}
+
+fn write_capture(path: &Path, (width, height): (u32, u32), frames: &[Vec<u8>]) -> Result<(), anyhow::Error> {
+  if frames.is_empty() {
+    return Ok(());
+  }
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+
+  let file = File::create(path)?;
+  let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+  encoder.set_color(png::ColorType::Rgb);
+  encoder.set_depth(png::BitDepth::Eight);
+  encoder.set_animated(frames.len() as u32, 0)?;
+  encoder.set_frame_delay(FRAME_DELAY.0, FRAME_DELAY.1)?;
+  let mut writer = encoder.write_header()?;
+  let mut data = Vec::new();
+  for frame in frames {
+    data.clear();
+    DeflateDecoder::new(&frame[..]).read_to_end(&mut data)?;
+    writer.write_image_data(&data)?;
+  }
+  writer.finish()?;
+  Ok(())
+}
@@ -0,0 +1,123 @@
+//! Text chat overlay: `T` opens the input line, Enter sends the message, Escape cancels. Only
+//! used on screens without player input, so chat keys never conflict with player controls.
+use crate::error::ApplicationError::SdlError;
+use crate::fonts::Font;
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Amount of the recent messages shown
+const MAX_LINES: usize = 4;
+
+/// Maximum length of a single message
+const MAX_MESSAGE_LEN: usize = 60;
+
+/// For how long messages are shown
+const LINE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Top coordinate of the chat overlay
+const CHAT_Y: i32 = 416;
+
+/// Result of handling an input event
+pub enum ChatInput {
+  /// Event was not for the chat
+  Ignored,
+  /// Event was consumed by the chat
+  Handled,
+  /// Message was entered
+  Send(String),
+}
+
+#[derive(Default)]
+pub struct Chat {
+  /// Message being typed, if input line is open
+  input: Option<String>,
+  /// Skip text input event of the key which opened the chat
+  skip_text: bool,
+  lines: VecDeque<(Instant, String)>,
+}
+
+impl Chat {
+  /// Add received message
+  pub fn push_line(&mut self, line: String) {
+    if self.lines.len() == MAX_LINES {
+      self.lines.pop_front();
+    }
+    self.lines.push_back((Instant::now(), line));
+  }
+
+  /// Drop old messages. Returns `true` if overlay needs to be re-rendered.
+  pub fn expire(&mut self) -> bool {
+    let len = self.lines.len();
+    self.lines.retain(|(at, _)| at.elapsed() < LINE_TIMEOUT);
+    self.lines.len() != len
+  }
+
+  pub fn handle_event(&mut self, event: &Event) -> ChatInput {
+    let input = match &mut self.input {
+      Some(input) => input,
+      None => {
+        if let Event::KeyDown {
+          scancode: Some(Scancode::T),
+          ..
+        } = event
+        {
+          self.input = Some(String::new());
+          self.skip_text = true;
+          return ChatInput::Handled;
+        }
+        return ChatInput::Ignored;
+      }
+    };
+    match event {
+      Event::TextInput { text, .. } if std::mem::take(&mut self.skip_text) => {
+        // Remainder of the text typed along with the opening key
+        input.extend(text.chars().skip(1).filter(|ch| ch.is_ascii_graphic() || *ch == ' '));
+      }
+      Event::TextInput { text, .. } => {
+        for ch in text.chars() {
+          if input.len() < MAX_MESSAGE_LEN && (ch.is_ascii_graphic() || ch == ' ') {
+            input.push(ch);
+          }
+        }
+      }
+      Event::KeyDown {
+        scancode: Some(scancode),
+        ..
+      } => match scancode {
+        Scancode::Return | Scancode::KpEnter => {
+          let message = self.input.take().unwrap_or_default();
+          if !message.trim().is_empty() {
+            return ChatInput::Send(message);
+          }
+        }
+        Scancode::Escape => self.input = None,
+        Scancode::Backspace | Scancode::Delete => {
+          input.pop();
+        }
+        _ => {}
+      },
+      _ => return ChatInput::Ignored,
+    }
+    ChatInput::Handled
+  }
+
+  /// Render recent messages and the input line at the bottom of the playfield
+  pub fn render(&self, canvas: &mut WindowCanvas, font: &Font, color: Color) -> Result<(), anyhow::Error> {
+    let mut y = CHAT_Y + 10 * (MAX_LINES - self.lines.len()) as i32;
+    let input = self.input.as_ref().map(|input| format!("> {}_", input));
+    for line in self.lines.iter().map(|(_, line)| line).chain(input.as_ref()) {
+      canvas.set_draw_color(Color::BLACK);
+      canvas
+        .fill_rect(Rect::new(12, y - 1, 8 * line.len() as u32 + 4, 10))
+        .map_err(SdlError)?;
+      font.render(canvas, 14, y, color, line)?;
+      y += 10;
+    }
+    Ok(())
+  }
+}
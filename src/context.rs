@@ -1,7 +1,19 @@
+use crate::assets::{AssetWatcher, ASSET_EXTENSIONS};
+use crate::capture::Frame;
+use crate::error::ApplicationError;
 use crate::error::ApplicationError::SdlError;
 use crate::fonts::Font;
+use crate::glyphs::{GlyphFallbacks, GlyphSheet, Glyphs};
 use crate::images::{TextureFormat, TexturePalette};
-use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::keys::{KeysConfig, MenuAction, MenuBindings};
+use crate::mods::ModList;
+use crate::options::{Options, TransitionSpeed};
+use crate::palette::{PaletteRoles, OVERRIDES_FILE};
+use crate::status::{GameStatus, StatusReporter};
+use crate::textures::{TextureCache, TextureHandle};
+use crate::vfs::Vfs;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH, WIDESCREEN_WIDTH};
+use sdl2::controller::{Button, GameController};
 use sdl2::event::Event;
 use sdl2::keyboard::{Keycode, Scancode};
 use sdl2::mixer::{Music, AUDIO_S16LSB};
@@ -10,18 +22,46 @@ use sdl2::rect::Rect;
 use sdl2::render::{BlendMode, Texture, TextureCreator, WindowCanvas};
 use sdl2::surface::Surface;
 use sdl2::video::WindowContext;
-use sdl2::EventPump;
+use sdl2::{EventPump, GameControllerSubsystem};
+use std::collections::VecDeque;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Brightness of the screen under the modal dialogs
+const MODAL_DIM: u8 = 96;
+
+/// Duration of a single frame
+const FRAME_DURATION: Duration = Duration::new(0, 1_000_000_000u32 / 60);
 
 /// Application environment resources packaged into one structs. Provides helper functions used
 /// across the whole application.
 pub struct ApplicationContext<'canvas, 'textures> {
   game_dir: PathBuf,
+  /// Game files, with asset packs applied
+  vfs: Vfs,
   events: EventPump,
   canvas: &'canvas mut WindowCanvas,
   buffer: Texture<'textures>,
   texture_creator: &'textures TextureCreator<WindowContext>,
+  status: StatusReporter,
+  transitions: TransitionSpeed,
+  effect_limits: PostEffectLimits,
+  /// Opacity of the flash presented with the last frame
+  flash_level: u8,
+  /// Changes of the flash opacity presented during the last second
+  flash_changes: VecDeque<(Instant, u32)>,
+  /// Buffer is wider than the playfield, with extra space on both sides
+  widescreen: bool,
+  menu_bindings: MenuBindings,
+  controller_subsystem: GameControllerSubsystem,
+  /// Open gamepads; these are only used for menu navigation
+  controllers: Vec<GameController>,
+  /// Watches asset files for changes (development mode)
+  assets: Option<AssetWatcher>,
+  /// Asset packs were changed, so all assets need to be reloaded
+  assets_replaced: bool,
+  /// Textures created at runtime
+  textures: TextureCache<'textures>,
 }
 
 pub enum Animation {
@@ -29,6 +69,117 @@ pub enum Animation {
   FadeDown,
 }
 
+/// Post-processing effects applied to the frame when it is presented. Effects can be combined, for
+/// example, screen can shake while being tinted.
+#[derive(Clone, Copy, Default)]
+pub struct PostEffects {
+  /// Shift the screen up; each point is 10 pixels
+  pub shake: u16,
+  /// Cover the screen with white color of a given opacity (255 is the full flash)
+  pub flash: u8,
+  /// Modulate colors of the screen with a given color
+  pub tint: Option<Color>,
+  /// Show only the given part of the screen, scaled up to the whole window
+  pub view: Option<Rect>,
+}
+
+impl PostEffects {
+  pub fn shake(shake: u16) -> Self {
+    PostEffects {
+      shake,
+      ..Default::default()
+    }
+  }
+
+  pub fn flash() -> Self {
+    PostEffects {
+      flash: 255,
+      ..Default::default()
+    }
+  }
+
+  pub fn tint(tint: Color) -> Self {
+    PostEffects {
+      tint: Some(tint),
+      ..Default::default()
+    }
+  }
+
+  /// Stack two sets of effects together
+  pub fn combine(self, other: PostEffects) -> Self {
+    let tint = match (self.tint, other.tint) {
+      (Some(first), Some(second)) => Some(Color::RGB(
+        (u16::from(first.r) * u16::from(second.r) / 255) as u8,
+        (u16::from(first.g) * u16::from(second.g) / 255) as u8,
+        (u16::from(first.b) * u16::from(second.b) / 255) as u8,
+      )),
+      (tint, None) | (None, tint) => tint,
+    };
+    PostEffects {
+      shake: self.shake.max(other.shake),
+      flash: self.flash.max(other.flash),
+      tint,
+      view: other.view.or(self.view),
+    }
+  }
+}
+
+/// Limits on the post effects, for players who are sensitive to flashes or screen movements
+#[derive(Clone, Copy)]
+pub struct PostEffectLimits {
+  /// Allow screen to shake
+  pub shake: bool,
+  /// Maximum opacity of the screen flash
+  pub max_flash: u8,
+  /// Maximum change of the flash opacity between two presented frames
+  pub max_flash_step: u8,
+  /// Maximum total change of the flash opacity during a second (both brightening and dimming)
+  pub max_flash_change: u32,
+  /// Minimum amount of steps of the fade transitions between screens
+  pub min_fade_steps: usize,
+  /// Maximum amount of blood and slime splatters drawn each frame
+  pub max_splatters: usize,
+}
+
+impl Default for PostEffectLimits {
+  fn default() -> Self {
+    PostEffectLimits {
+      shake: true,
+      max_flash: 255,
+      max_flash_step: 255,
+      max_flash_change: u32::MAX,
+      min_fade_steps: 0,
+      max_splatters: usize::MAX,
+    }
+  }
+}
+
+impl PostEffectLimits {
+  /// Limits for the photosensitive mode: no screen shake, flashes are dim, ramp up and down over
+  /// few frames and only few of them could happen each second; screens never change abruptly and
+  /// only few splatters are drawn at once.
+  pub fn photosensitive() -> Self {
+    PostEffectLimits {
+      shake: false,
+      max_flash: 96,
+      max_flash_step: 24,
+      // Up to three full flashes per second
+      max_flash_change: 3 * 2 * 96,
+      min_fade_steps: 4,
+      // Bursts of splatters flicker all over the screen
+      max_splatters: 2,
+    }
+  }
+
+  pub fn from_options(options: &Options) -> Self {
+    if options.photosensitive {
+      PostEffectLimits::photosensitive()
+    } else {
+      PostEffectLimits::default()
+    }
+  }
+}
+
 /// Our representation of an input event. Most of the time, we only care about scancodes. However,
 /// when we allow entering a text (player creation screen), we need to be able to represent input
 /// text as well.
@@ -38,8 +189,9 @@ pub enum InputEvent {
 }
 
 impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
+  /// Run the application; game data could be either a directory or a ZIP archive
   pub fn with_context(
-    game_dir: PathBuf,
+    data: PathBuf,
     cb: impl FnOnce(ApplicationContext) -> Result<(), anyhow::Error>,
   ) -> Result<(), anyhow::Error> {
     let sdl_context = sdl2::init().map_err(SdlError)?;
@@ -51,9 +203,12 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
       .resizable()
       .build()?;
 
+    let game_dir = crate::vfs::game_dir(&data);
+    let vfs = Vfs::new(&data, &ModList::load(&game_dir))?;
+
     // Set application icon, we chop it off one of the game images
-    let data = std::fs::read(game_dir.join("TITLEBE.SPY"))?;
-    let mut spy = crate::images::decode_spy(SCREEN_WIDTH, SCREEN_HEIGHT, &data)?;
+    let title = vfs.read("TITLEBE.SPY")?;
+    let mut spy = crate::images::decode_spy(SCREEN_WIDTH, SCREEN_HEIGHT, &title.data)?;
     let from = ((SCREEN_WIDTH * 305 + 265) * 3) as usize;
     let surface =
       Surface::from_data(&mut spy.image[from..], 96, 96, 3 * SCREEN_WIDTH, PixelFormatEnum::RGB24).map_err(SdlError)?;
@@ -63,6 +218,7 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
     let mut canvas = window.into_canvas().build()?;
     sdl_context.mouse().show_cursor(false);
     let events = sdl_context.event_pump().map_err(SdlError)?;
+    let controller_subsystem = sdl_context.game_controller().map_err(SdlError)?;
     let texture_creator = canvas.texture_creator();
 
     // Create texture we use as a permanent buffer for rendering, to make it easier to
@@ -73,13 +229,26 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
       texture_creator.create_texture_target(PixelFormatEnum::RGB24, SCREEN_WIDTH, SCREEN_HEIGHT)?;
 
     // Initialize audio
-    sdl2::mixer::open_audio(44100, AUDIO_S16LSB, 2, 1024).map_err(SdlError)?;
+    sdl2::mixer::open_audio(44100, AUDIO_S16LSB, 2, 1024).map_err(ApplicationError::AudioInit)?;
     let ctx = ApplicationContext {
       game_dir,
+      vfs,
       canvas: &mut canvas,
       events,
       buffer,
       texture_creator: &texture_creator,
+      status: StatusReporter::default(),
+      transitions: TransitionSpeed::Classic,
+      effect_limits: PostEffectLimits::default(),
+      flash_level: 0,
+      flash_changes: VecDeque::new(),
+      widescreen: false,
+      menu_bindings: MenuBindings::default(),
+      controller_subsystem,
+      controllers: Vec::new(),
+      assets: None,
+      assets_replaced: false,
+      textures: TextureCache::new(&texture_creator),
     };
     cb(ctx)?;
     Ok(())
@@ -87,9 +256,25 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
 
   /// Invoke callback in a "rendering" context. Makes canvas to render in a separate buffer
   /// texture so we can apply post-processing to it (for example, emulate palette animation).
+  /// Rendering is done into the 640x480 playfield, which is centered in the widescreen mode.
   pub fn with_render_context<R>(
     &mut self,
     callback: impl FnOnce(&mut WindowCanvas) -> Result<R, anyhow::Error>,
+  ) -> Result<R, anyhow::Error> {
+    let playfield = Rect::new(self.playfield_offset(), 0, SCREEN_WIDTH, SCREEN_HEIGHT);
+    let mut result = None;
+    self.canvas.with_texture_canvas(&mut self.buffer, |canvas| {
+      canvas.set_viewport(playfield);
+      result = Some(callback(canvas));
+    })?;
+    result.unwrap()
+  }
+
+  /// Same as `with_render_context`, but rendering is done to the whole screen, including the side
+  /// panels of the widescreen mode.
+  pub fn with_screen_context<R>(
+    &mut self,
+    callback: impl FnOnce(&mut WindowCanvas) -> Result<R, anyhow::Error>,
   ) -> Result<R, anyhow::Error> {
     let mut result = None;
     self.canvas.with_texture_canvas(&mut self.buffer, |canvas| {
@@ -98,6 +283,34 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
     result.unwrap()
   }
 
+  /// Switch between regular (4:3) and widescreen (16:9) modes
+  pub fn set_widescreen(&mut self, widescreen: bool) -> Result<(), anyhow::Error> {
+    if self.widescreen == widescreen {
+      return Ok(());
+    }
+    let width = if widescreen { WIDESCREEN_WIDTH } else { SCREEN_WIDTH };
+    self.buffer = self
+      .texture_creator
+      .create_texture_target(PixelFormatEnum::RGB24, width, SCREEN_HEIGHT)?;
+    self.widescreen = widescreen;
+    self.with_screen_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      Ok(())
+    })?;
+    self.canvas.window_mut().set_size(width, SCREEN_HEIGHT)?;
+    Ok(())
+  }
+
+  /// Width of the space to the left of the playfield (side panel in the widescreen mode)
+  pub fn playfield_offset(&self) -> i32 {
+    if self.widescreen {
+      ((WIDESCREEN_WIDTH - SCREEN_WIDTH) / 2) as i32
+    } else {
+      0
+    }
+  }
+
   pub fn render_texture(&mut self, texture: &Texture) -> Result<(), anyhow::Error> {
     self.with_render_context(|canvas| {
       canvas.copy(texture, None, None).map_err(SdlError)?;
@@ -108,37 +321,97 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
 
   /// Load SPY texture from a given path
   pub fn load_spy(&self, file_name: &str) -> Result<TexturePalette<'textures>, anyhow::Error> {
-    let path = self.game_dir.join(file_name);
-    Ok(crate::images::load_texture(
-      self.texture_creator,
-      &path,
-      TextureFormat::SPY,
-    )?)
+    let file = self.vfs.read(file_name)?;
+    let mut texture = crate::images::load_texture(self.texture_creator, &file.path, &file.data, TextureFormat::SPY)?;
+    self.override_roles(&mut texture.roles);
+    Ok(texture)
+  }
+
+  /// Apply palette role overrides of the game data and asset packs, if there are any
+  fn override_roles(&self, roles: &mut PaletteRoles) {
+    if let Ok(file) = self.vfs.read(OVERRIDES_FILE) {
+      roles.apply_overrides(&String::from_utf8_lossy(&file.data));
+    }
+  }
+
+  /// Load glyphs texture from the given SPY file
+  pub fn load_glyphs(&self, file_name: &str) -> Result<Glyphs<'textures>, anyhow::Error> {
+    Ok(Glyphs::new(self.load_glyph_sheet(file_name, &[])?))
+  }
+
+  /// Load glyph sheet from the given SPY file, replacing given colors of its palette
+  pub fn load_glyph_sheet(
+    &self,
+    file_name: &str,
+    palette: &[(u8, Color)],
+  ) -> Result<GlyphSheet<'textures>, anyhow::Error> {
+    let file = self.vfs.read(file_name)?;
+    let mut image = crate::images::load_image(&file.path, &file.data, TextureFormat::SPY)?;
+    image.remap(palette);
+    let texture = crate::images::create_texture(self.texture_creator, &image)?;
+    let fallbacks = match self.vfs.read("GLYPHS.CFG") {
+      Ok(file) => GlyphFallbacks::parse(&String::from_utf8_lossy(&file.data)),
+      Err(_) => GlyphFallbacks::default(),
+    };
+    Ok(GlyphSheet::from_image(texture.texture, &image, fallbacks))
   }
 
   /// Load PPM texture from a given path
   pub fn load_ppm(&self, file_name: &str) -> Result<TexturePalette<'textures>, anyhow::Error> {
-    let path = self.game_dir.join(file_name);
-    Ok(crate::images::load_texture(
-      self.texture_creator,
-      &path,
-      TextureFormat::PPM,
-    )?)
+    let file = self.vfs.read(file_name)?;
+    let mut texture = crate::images::load_texture(self.texture_creator, &file.path, &file.data, TextureFormat::PPM)?;
+    self.override_roles(&mut texture.roles);
+    Ok(texture)
   }
 
   /// Load fonts from a given path
   pub fn load_font(&self, file_name: &str) -> Result<Font<'textures>, anyhow::Error> {
-    let path = self.game_dir.join(file_name);
-    Ok(crate::fonts::load_font(self.texture_creator, &path)?)
+    let file = self.vfs.read(file_name)?;
+    Ok(crate::fonts::load_font(self.texture_creator, &file.path, &file.data)?)
   }
 
+  /// Load music from the given file. Mixer reads music data while playing it, so music packed into
+  /// an archive is unpacked into a temporary file first (unique to this process). The file is removed
+  /// as soon as mixer has opened it.
   pub fn load_music(&self, file_name: &str) -> Result<Music<'static>, anyhow::Error> {
-    let path = self.game_dir.join(file_name);
-    let music = Music::from_file(path).map_err(SdlError)?;
-    Ok(music)
+    let file = self.vfs.read(file_name)?;
+    if file.path.is_file() {
+      return Ok(Music::from_file(&file.path).map_err(SdlError)?);
+    }
+    let path = std::env::temp_dir().join(format!("mb-reloaded-{}-{}", std::process::id(), file_name));
+    std::fs::write(&path, &file.data)?;
+    let music = Music::from_file(&path).map_err(SdlError);
+    if let Err(err) = std::fs::remove_file(&path) {
+      eprintln!("Failed to remove temporary music file '{}': {:#}", path.display(), err);
+    }
+    Ok(music?)
+  }
+
+  /// Set speed of the transitions between screens
+  pub fn set_transition_speed(&mut self, speed: TransitionSpeed) {
+    self.transitions = speed;
+  }
+
+  /// Fade screen in or out, with the speed configured by the player
+  pub fn transition(&mut self, animation: Animation) -> Result<(), anyhow::Error> {
+    let steps = match self.transitions {
+      TransitionSpeed::Classic => 7,
+      TransitionSpeed::Fast => 2,
+      TransitionSpeed::Instant => 0,
+    };
+    match steps.max(self.effect_limits.min_fade_steps) {
+      0 if matches!(animation, Animation::FadeUp) => self.present(),
+      0 => {
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        self.canvas.present();
+        Ok(())
+      }
+      steps => self.animate(animation, steps),
+    }
   }
 
-  pub fn animate(&mut self, animation: Animation, steps: usize) -> Result<(), anyhow::Error> {
+  fn animate(&mut self, animation: Animation, steps: usize) -> Result<(), anyhow::Error> {
     // Note that we actually do steps + 1 iteration, as per original behavior
     // Roughly, we do it for half a second for 8 steps. For 60 FPS, which means ~4 frames per step.
     let total_frames = (steps + 1) * 4;
@@ -162,18 +435,24 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
   }
 
   pub fn present(&mut self) -> Result<(), anyhow::Error> {
-    self.present_shake(0)
+    self.present_with(PostEffects::default())
   }
 
-  pub fn present_shake(&mut self, shake: u16) -> Result<(), anyhow::Error> {
+  /// Present the frame with given post effects
+  pub fn present_with(&mut self, effects: PostEffects) -> Result<(), anyhow::Error> {
+    let shake = if self.effect_limits.shake { effects.shake } else { 0 };
+    let flash = self.limit_flash(effects.flash);
+
     self.buffer.set_blend_mode(BlendMode::None);
     self.buffer.set_alpha_mod(255);
+    let tint = effects.tint.unwrap_or(Color::WHITE);
+    self.buffer.set_color_mod(tint.r, tint.g, tint.b);
     let (w, h) = self.canvas.output_size().map_err(SdlError)?;
     let mut target = Rect::new(0, 0, w, h);
 
     // Render "shaking" screen effect
     if shake != 0 {
-      let top = u32::from(shake) * 10 * h / SCREEN_HEIGHT;
+      let top = (u32::from(shake) * 10 * h / SCREEN_HEIGHT).min(h);
       target.set_y(-(top as i32));
       self.canvas.set_draw_color(Color::BLACK);
       self
@@ -181,21 +460,136 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
         .fill_rect(Rect::new(0, (h - top) as i32, w, top))
         .map_err(SdlError)?;
     }
-    self.canvas.copy(&self.buffer, None, Some(target)).map_err(SdlError)?;
+    let result = self
+      .canvas
+      .copy(&self.buffer, effects.view, Some(target))
+      .map_err(SdlError);
+    self.buffer.set_color_mod(255, 255, 255);
+    result?;
+
+    if flash != 0 {
+      self.canvas.set_blend_mode(BlendMode::Blend);
+      self.canvas.set_draw_color(Color::RGBA(255, 255, 255, flash));
+      let result = self.canvas.fill_rect(None).map_err(SdlError);
+      self.canvas.set_blend_mode(BlendMode::None);
+      result?;
+    }
     self.canvas.present();
     Ok(())
   }
 
-  pub fn present_flash(&mut self) -> Result<(), anyhow::Error> {
-    self.canvas.set_draw_color(Color::WHITE);
-    self.canvas.clear();
+  /// Opacity of the flash to present, given the requested one. Flash is brought towards the
+  /// requested opacity no faster than the limits allow; dimming is always allowed, so the flash
+  /// never gets stuck once the budget of the last second is spent.
+  fn limit_flash(&mut self, flash: u8) -> u8 {
+    let limits = self.effect_limits;
+    let now = Instant::now();
+    while let Some((at, _)) = self.flash_changes.front() {
+      if now.duration_since(*at) < Duration::from_secs(1) {
+        break;
+      }
+      self.flash_changes.pop_front();
+    }
+    let spent: u32 = self.flash_changes.iter().map(|(_, change)| *change).sum();
+
+    let target = flash.min(limits.max_flash);
+    let level = if target > self.flash_level {
+      let budget = limits.max_flash_change.saturating_sub(spent);
+      let step = u32::from(limits.max_flash_step).min(budget) as u8;
+      self.flash_level + (target - self.flash_level).min(step)
+    } else {
+      self.flash_level - (self.flash_level - target).min(limits.max_flash_step)
+    };
+    let change = u32::from(level.abs_diff(self.flash_level));
+    if change != 0 {
+      self.flash_changes.push_back((now, change));
+    }
+    self.flash_level = level;
+    level
+  }
+
+  /// Read back contents of the buffer (whole screen, including side panels)
+  pub fn read_buffer(&mut self) -> Result<Frame, anyhow::Error> {
+    let query = self.buffer.query();
+    let mut result = None;
+    self.canvas.with_texture_canvas(&mut self.buffer, |canvas| {
+      result = Some(canvas.read_pixels(None, PixelFormatEnum::RGB24).map_err(SdlError));
+    })?;
+    Ok(Frame {
+      width: query.width,
+      height: query.height,
+      data: result.unwrap()?,
+    })
+  }
+
+  /// Cache of the textures created at runtime
+  pub fn textures(&mut self) -> &mut TextureCache<'textures> {
+    &mut self.textures
+  }
+
+  /// Render cached texture into the given rectangle of the playfield. Returns `false` if texture
+  /// was evicted from the cache and nothing was rendered.
+  pub fn render_cached(&mut self, handle: TextureHandle, target: Rect) -> Result<bool, anyhow::Error> {
+    let texture = match self.textures.get(handle) {
+      Some(texture) => texture,
+      None => return Ok(false),
+    };
+    let playfield = Rect::new(self.playfield_offset(), 0, SCREEN_WIDTH, SCREEN_HEIGHT);
+    let mut result = Ok(());
+    self.canvas.with_texture_canvas(&mut self.buffer, |canvas| {
+      canvas.set_viewport(playfield);
+      result = canvas.copy(texture, None, target).map_err(SdlError);
+    })?;
+    result?;
+    Ok(true)
+  }
+
+  /// Present the frame dimmed, with a modal (for example, a confirmation dialog) rendered over it.
+  /// Modal is rendered into a separate texture, so the buffer is left intact and the screen under
+  /// the modal is restored by presenting the frame again.
+  pub fn present_modal(
+    &mut self,
+    callback: impl FnOnce(&mut WindowCanvas) -> Result<(), anyhow::Error>,
+  ) -> Result<(), anyhow::Error> {
+    let query = self.buffer.query();
+    let mut overlay =
+      self
+        .texture_creator
+        .create_texture_target(PixelFormatEnum::RGBA8888, query.width, query.height)?;
+    overlay.set_blend_mode(BlendMode::Blend);
+    let playfield = Rect::new(self.playfield_offset(), 0, SCREEN_WIDTH, SCREEN_HEIGHT);
+    let mut result = Ok(());
+    self.canvas.with_texture_canvas(&mut overlay, |canvas| {
+      canvas.set_draw_color(Color::RGBA(0, 0, 0, 0));
+      canvas.clear();
+      canvas.set_viewport(playfield);
+      result = callback(canvas);
+    })?;
+    result?;
+
+    self.buffer.set_blend_mode(BlendMode::None);
+    self.buffer.set_alpha_mod(255);
+    self.buffer.set_color_mod(MODAL_DIM, MODAL_DIM, MODAL_DIM);
+    let result = self.canvas.copy(&self.buffer, None, None).map_err(SdlError);
+    self.buffer.set_color_mod(255, 255, 255);
+    result?;
+    self.canvas.copy(&overlay, None, None).map_err(SdlError)?;
     self.canvas.present();
     Ok(())
   }
 
+  /// Limit post effects (for example, to disable screen shake)
+  pub fn set_post_effect_limits(&mut self, limits: PostEffectLimits) {
+    self.effect_limits = limits;
+  }
+
+  pub fn post_effect_limits(&self) -> PostEffectLimits {
+    self.effect_limits
+  }
+
   pub fn wait_frame(&self) {
     // We should wait for the remaining time; for now just do a fixed delay.
-    ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
+    ::std::thread::sleep(FRAME_DURATION);
   }
 
   /// Wait until some key is pressed
@@ -212,6 +606,12 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
           ..
         } => return InputEvent::KeyPress(code, key),
         Event::TextInput { text, .. } => return InputEvent::TextInput(text),
+        Event::ControllerDeviceAdded { which, .. } => self.open_controller(which),
+        Event::ControllerButtonDown { button, .. } => {
+          if let Some((code, key)) = controller_key(button) {
+            return InputEvent::KeyPress(code, key);
+          }
+        }
         _ => {}
       }
     }
@@ -227,15 +627,145 @@ impl<'canvas, 'textures> ApplicationContext<'canvas, 'textures> {
     }
   }
 
+  /// Wait until some key is pressed, but no longer than the given timeout. Returns `None` if no key
+  /// was pressed in time.
+  pub fn wait_key_pressed_timeout(&mut self, timeout: Duration) -> Option<(Scancode, Keycode)> {
+    let deadline = Instant::now() + timeout;
+    loop {
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      let event = self.events.wait_event_timeout(remaining.as_millis() as u32)?;
+      match event {
+        Event::Quit { .. } => return Some((Scancode::Escape, Keycode::Escape)),
+        Event::KeyDown {
+          scancode: Some(code),
+          keycode: Some(key),
+          repeat: false,
+          ..
+        } => return Some((code, key)),
+        Event::ControllerDeviceAdded { which, .. } => self.open_controller(which),
+        Event::ControllerButtonDown { button, .. } => {
+          if let Some(key) = controller_key(button) {
+            return Some(key);
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  /// Set keys used for menu navigation: built-in keys plus movement keys of the first player
+  pub fn set_menu_keys(&mut self, keys: &KeysConfig) {
+    self.menu_bindings = MenuBindings::from_keys(keys);
+  }
+
+  /// Translate pressed key into the menu navigation action
+  pub fn menu_action(&self, scancode: Scancode) -> MenuAction {
+    self.menu_bindings.action(scancode)
+  }
+
+  /// Same as `menu_action`, but only built-in keys are used. Should be used in menus which accept
+  /// text input, as player could use letters for movement.
+  pub fn text_menu_action(&self, scancode: Scancode) -> MenuAction {
+    self.menu_bindings.builtin_action(scancode)
+  }
+
+  fn open_controller(&mut self, which: u32) {
+    // If we cannot open gamepad, it won't be usable, but that's not fatal
+    if let Ok(controller) = self.controller_subsystem.open(which) {
+      self.controllers.push(controller);
+    }
+  }
+
+  /// Wait until some key is pressed, invoking `idle` callback every frame while waiting, so screen
+  /// could be animated. Callback receives the amount of frames passed since we started waiting.
+  /// Returns `None` if no key was pressed before the timeout (if any) expired.
+  pub fn wait_key_pressed_idle(
+    &mut self,
+    timeout: Option<Duration>,
+    mut idle: impl FnMut(&mut Self, usize) -> Result<(), anyhow::Error>,
+  ) -> Result<Option<(Scancode, Keycode)>, anyhow::Error> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut frame = 0;
+    loop {
+      let mut wait = FRAME_DURATION;
+      if let Some(deadline) = deadline {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+          return Ok(None);
+        }
+        wait = wait.min(remaining);
+      }
+      if let Some(key) = self.wait_key_pressed_timeout(wait) {
+        return Ok(Some(key));
+      }
+      idle(self, frame)?;
+      frame += 1;
+    }
+  }
+
+  /// Check if key with a given scancode is currently held down
+  pub fn is_key_pressed(&self, scancode: Scancode) -> bool {
+    self.events.keyboard_state().is_scancode_pressed(scancode)
+  }
+
   pub fn poll_iter(&mut self) -> impl Iterator<Item = Event> + '_ {
     self.events.poll_iter()
   }
 
+  /// Enable writing game status into the given file
+  pub fn set_status_file(&mut self, path: Option<PathBuf>) {
+    self.status = StatusReporter::new(path);
+  }
+
+  /// Report current game status to external tools (if enabled)
+  pub fn report_status(&mut self, status: GameStatus) {
+    self.status.report(status);
+  }
+
+  /// Start watching asset files for changes
+  pub fn watch_assets(&mut self) {
+    self.assets = Some(AssetWatcher::new(self.vfs.dirs()));
+  }
+
+  /// Names of the asset files changed on disk since the last call. Always empty unless watching
+  /// assets was enabled or asset packs were changed (in which case all assets are reported).
+  pub fn changed_assets(&mut self) -> Vec<String> {
+    let changed = self.assets.as_mut().map_or_else(Vec::new, AssetWatcher::changed);
+    if std::mem::take(&mut self.assets_replaced) {
+      return self.vfs.list(&ASSET_EXTENSIONS);
+    }
+    changed
+  }
+
+  /// Apply new set of asset packs. Assets are reloaded next time changed assets are checked.
+  pub fn set_mods(&mut self, mods: &ModList) {
+    self.vfs = self.vfs.with_mods(&self.game_dir, mods);
+    if self.assets.is_some() {
+      self.watch_assets();
+    }
+    self.assets_replaced = true;
+  }
+
+  /// Game files, with asset packs applied
+  pub fn vfs(&self) -> &Vfs {
+    &self.vfs
+  }
+
   pub fn game_dir(&self) -> &Path {
     &self.game_dir
   }
+}
 
-  pub fn texture_creator(&self) -> &'textures TextureCreator<WindowContext> {
-    self.texture_creator
-  }
+/// Gamepad buttons are translated into the keys they correspond to in menus
+fn controller_key(button: Button) -> Option<(Scancode, Keycode)> {
+  let key = match button {
+    Button::DPadUp => (Scancode::Up, Keycode::Up),
+    Button::DPadDown => (Scancode::Down, Keycode::Down),
+    Button::DPadLeft => (Scancode::Left, Keycode::Left),
+    Button::DPadRight => (Scancode::Right, Keycode::Right),
+    Button::A | Button::Start => (Scancode::Return, Keycode::Return),
+    Button::B | Button::Back => (Scancode::Escape, Keycode::Escape),
+    _ => return None,
+  };
+  Some(key)
 }
@@ -0,0 +1,95 @@
+//! Layout of the game screen: where map cells, actors and parts of the HUD are drawn. World
+//! positions are pixel coordinates of the cell (or actor) centers on the 640x480 playfield, while
+//! glyphs are drawn from their top left corner, so renderers should go through these conversions
+//! instead of offsetting positions themselves.
+use crate::world::position::{Cursor, Direction, Position};
+use sdl2::rect::{Point, Rect};
+
+/// Size of the map cell on the screen, in pixels
+pub const CELL_SIZE: u32 = 10;
+
+/// Distance from the center of the cell (or actor) to its top left corner
+const HALF_CELL: i32 = CELL_SIZE as i32 / 2;
+
+/// Width of the player info area on the top of the screen
+const PLAYER_AREA_WIDTH: i32 = 160;
+
+/// Left coordinate of the each player information on the top of the game screen
+const PLAYER_INFO_X: [i32; 4] = [12, 174, 337, 500];
+
+/// Left coordinate of the each player health bar
+pub const HEALTH_BAR_LEFT: [i32; 4] = [142, 304, 467, 630];
+
+/// Fields of the player info bar, relative to the player info position
+pub const INFO_SELECTION: (i32, i32) = (0, 0);
+pub const INFO_NAME: (i32, i32) = (36, 1);
+pub const INFO_DRILLING: (i32, i32) = (50, 11);
+pub const INFO_CASH: (i32, i32) = (50, 21);
+pub const INFO_RADIOS: (i32, i32) = (30, 21);
+pub const INFO_CLONES: (i32, i32) = (92, 11);
+/// Time left in the bonus round or gold needed to unlock the exit
+pub const INFO_STATUS: (i32, i32) = (92, 21);
+
+/// Top left corner of the map cell
+pub fn cell_origin(cursor: Cursor) -> (i32, i32) {
+  actor_origin(cursor.position())
+}
+
+/// Center of the map cell
+pub fn cell_center(cursor: Cursor) -> Point {
+  let pos = cursor.position();
+  Point::new(i32::from(pos.x), i32::from(pos.y))
+}
+
+/// Area covered by the map cell
+pub fn cell_rect(cursor: Cursor) -> Rect {
+  let (x, y) = cell_origin(cursor);
+  Rect::new(x, y, CELL_SIZE, CELL_SIZE)
+}
+
+/// Top left corner of the glyph of the actor at the given position
+pub fn actor_origin(pos: Position) -> (i32, i32) {
+  (i32::from(pos.x) - HALF_CELL, i32::from(pos.y) - HALF_CELL)
+}
+
+/// Top left corner of the border glyph drawn on the given side of the cell
+pub fn border_origin(cursor: Cursor, dir: Direction) -> (i32, i32) {
+  let center = cell_center(cursor);
+  let (dx, dy) = match dir {
+    Direction::Left => (-9, -5),
+    Direction::Right => (5, -5),
+    Direction::Up => (-5, -8),
+    Direction::Down => (-5, 5),
+  };
+  (center.x() + dx, center.y() + dy)
+}
+
+/// Screen position of the field of the player info bar
+pub fn player_info(player: usize, field: (i32, i32)) -> (i32, i32) {
+  (PLAYER_INFO_X[player] + field.0, field.1)
+}
+
+/// Left coordinate of the info area of the given player; areas of the missing players are used for
+/// the campaign lives and time limit
+pub fn player_area_x(player: usize) -> i32 {
+  PLAYER_AREA_WIDTH * player as i32
+}
+
+/// Width of the time bar at the bottom of the screen
+pub const TIME_BAR_WIDTH: u32 = 635;
+
+/// Time bar at the bottom of the screen
+pub fn time_bar() -> Rect {
+  Rect::new(2, 473, TIME_BAR_WIDTH, 5)
+}
+
+/// Part of the time bar showing time already passed; it grows from the right end of the bar
+pub fn time_bar_elapsed(width: u32) -> Rect {
+  let bar = time_bar();
+  Rect::new(bar.right() - 1 - width as i32, bar.y(), width, bar.height())
+}
+
+/// Position of the cell on the minimap drawn at the given origin, one pixel per cell
+pub fn minimap_point(origin: (i32, i32), cursor: Cursor) -> Point {
+  Point::new(origin.0 + i32::from(cursor.col), origin.1 + i32::from(cursor.row))
+}
@@ -1,17 +1,17 @@
-use crate::error::ApplicationError::SdlError;
+use crate::error::ApplicationError;
+use crate::vfs::Vfs;
 use crate::world::map::MAP_COLS;
 use crate::world::position::Cursor;
+use rand::Rng;
 use sdl2::mixer::Channel;
-use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
+use std::thread::JoinHandle;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
-#[error("Failed to load sound sample from '{path}'")]
-pub struct SampleLoadingFailed {
-  path: PathBuf,
-  source: anyhow::Error,
-}
+#[error("Audio thread has stopped")]
+pub struct AudioThreadStopped;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SoundEffect {
@@ -29,13 +29,22 @@ pub enum SoundEffect {
   Applause,
 }
 
+/// Playback frequency of the fuse hiss
+const FUSE_FREQUENCY: i32 = 11000;
+
+/// Volume of the fuse hiss (it's just a background noise)
+const FUSE_VOLUME: f32 = 0.3;
+
+/// Length of the generated fuse hiss sample
+const FUSE_SAMPLE_LEN: usize = 4096;
+
 /// VOC files are unsigned, eight bits, 1 channel, frequency defined at the playback time (typically 11000).
 /// We use `Arc` here so we can give references to these samples to sound effects without worrying
 /// about ownership.
 #[derive(Clone)]
 struct RawSample(Arc<[u8]>);
 
-pub struct SoundEffects {
+struct Samples {
   kili: RawSample,
   picaxe: RawSample,
   explos1: RawSample,
@@ -50,29 +59,153 @@ pub struct SoundEffects {
   applause: RawSample,
 }
 
+/// Command sent to the audio thread
+enum AudioCommand {
+  Play(Playback),
+  /// Replace samples with the freshly loaded ones
+  Reload(Samples),
+  /// Set stereo positions of the lit bomb fuses to hiss at (stops hissing fuses which are not listed)
+  Fuses(Vec<f32>),
+}
+
+/// Request to start playing a sound effect
+struct Playback {
+  effect: SoundEffect,
+  frequency: i32,
+  /// Stereo position, from 0.0 (left) to 1.0 (right)
+  position: f32,
+}
+
+/// Sound effects player. Mixer calls could block for a while (for example, when lots of effects are
+/// started at once after an atomic bomb goes off), so effects are dispatched on a dedicated audio
+/// thread and playing an effect never stalls the game loop.
+pub struct SoundEffects {
+  sender: Option<Sender<AudioCommand>>,
+  thread: Option<JoinHandle<()>>,
+}
+
 impl SoundEffects {
-  /// Initialize game sound effects given the game directory
-  pub fn new(path: &Path) -> Result<Self, anyhow::Error> {
+  /// Initialize game sound effects given the game files
+  pub fn new(vfs: &Vfs) -> Result<Self, anyhow::Error> {
+    let samples = Samples::load(vfs)?;
+    let (sender, receiver) = mpsc::channel();
+    let thread = std::thread::Builder::new()
+      .name("audio".to_owned())
+      .spawn(move || samples.dispatch(receiver))?;
     Ok(SoundEffects {
-      kili: load_sample(path.join("KILI.VOC"))?,
-      picaxe: load_sample(path.join("PICAXE.VOC"))?,
-      explos1: load_sample(path.join("EXPLOS1.VOC"))?,
-      explos2: load_sample(path.join("EXPLOS2.VOC"))?,
-      explos3: load_sample(path.join("EXPLOS3.VOC"))?,
-      explos4: load_sample(path.join("EXPLOS4.VOC"))?,
-      explos5: load_sample(path.join("EXPLOS5.VOC"))?,
-      aargh: load_sample(path.join("AARGH.VOC"))?,
-      karjaisu: load_sample(path.join("KARJAISU.VOC"))?,
-      pikkupom: load_sample(path.join("PIKKUPOM.VOC"))?,
-      urethan: load_sample(path.join("URETHAN.VOC"))?,
-      applause: load_sample(path.join("APPLAUSE.VOC"))?,
+      sender: Some(sender),
+      thread: Some(thread),
     })
   }
 
-  /// Play sound effec
+  /// Reload sound samples from the game files
+  pub fn reload(&self, vfs: &Vfs) -> Result<(), anyhow::Error> {
+    let samples = Samples::load(vfs)?;
+    self.send(AudioCommand::Reload(samples))
+  }
+
+  /// Play sound effect. Effect is started asynchronously, on the audio thread.
   pub fn play(&self, effect: SoundEffect, frequency: i32, location: Cursor) -> Result<(), anyhow::Error> {
-    let position = f32::from(location.col) / f32::from(MAP_COLS - 1);
-    let effect = match effect {
+    let position = stereo_position(location);
+    self.send(AudioCommand::Play(Playback {
+      effect,
+      frequency,
+      position,
+    }))
+  }
+
+  /// Keep fuses of the given lit bombs hissing (quiet looping sound), silence all other fuses
+  pub fn play_fuses(&self, fuses: &[Cursor]) -> Result<(), anyhow::Error> {
+    self.send(AudioCommand::Fuses(
+      fuses.iter().copied().map(stereo_position).collect(),
+    ))
+  }
+
+  fn send(&self, command: AudioCommand) -> Result<(), anyhow::Error> {
+    if let Some(sender) = &self.sender {
+      sender.send(command).map_err(|_| AudioThreadStopped)?;
+    }
+    Ok(())
+  }
+}
+
+impl Samples {
+  fn load(vfs: &Vfs) -> Result<Self, ApplicationError> {
+    Ok(Samples {
+      kili: load_sample(vfs, "KILI.VOC")?,
+      picaxe: load_sample(vfs, "PICAXE.VOC")?,
+      explos1: load_sample(vfs, "EXPLOS1.VOC")?,
+      explos2: load_sample(vfs, "EXPLOS2.VOC")?,
+      explos3: load_sample(vfs, "EXPLOS3.VOC")?,
+      explos4: load_sample(vfs, "EXPLOS4.VOC")?,
+      explos5: load_sample(vfs, "EXPLOS5.VOC")?,
+      aargh: load_sample(vfs, "AARGH.VOC")?,
+      karjaisu: load_sample(vfs, "KARJAISU.VOC")?,
+      pikkupom: load_sample(vfs, "PIKKUPOM.VOC")?,
+      urethan: load_sample(vfs, "URETHAN.VOC")?,
+      applause: load_sample(vfs, "APPLAUSE.VOC")?,
+    })
+  }
+}
+
+impl Drop for SoundEffects {
+  fn drop(&mut self) {
+    // Closing the channel stops the audio thread; wait for it, so it doesn't outlive the mixer
+    self.sender.take();
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+impl Samples {
+  /// Audio thread main loop: play effects until channel is closed
+  fn dispatch(mut self, receiver: Receiver<AudioCommand>) {
+    let hiss = hiss_sample();
+    // Stereo positions of the fuses currently hissing, with channels they are played on
+    let mut fuses: Vec<(f32, Option<Channel>)> = Vec::new();
+    for command in receiver {
+      let playback = match command {
+        AudioCommand::Play(playback) => playback,
+        AudioCommand::Reload(samples) => {
+          self = samples;
+          continue;
+        }
+        AudioCommand::Fuses(positions) => {
+          if fuses
+            .iter()
+            .map(|(position, _)| *position)
+            .ne(positions.iter().copied())
+          {
+            for channel in fuses.drain(..).filter_map(|(_, channel)| channel) {
+              channel.halt();
+            }
+            for position in positions {
+              let channel = Channel::all();
+              let channel =
+                mb_sdl2_effects::play_looped_sample(channel, FUSE_FREQUENCY, hiss.0.clone(), position, FUSE_VOLUME)
+                  .unwrap_or_else(|err| {
+                    eprintln!("Failed to play bomb fuse: {}", err);
+                    None
+                  });
+              fuses.push((position, channel));
+            }
+          }
+          continue;
+        }
+      };
+      // FIXME: reuse channels if all cannels are busy
+      let channel = Channel::all();
+      let sample = self.sample(playback.effect).0.clone();
+      // Failing to play an effect is not fatal, game continues without it
+      if let Err(err) = mb_sdl2_effects::play_sound_sample(channel, playback.frequency, sample, playback.position) {
+        eprintln!("Failed to play sound effect {:?}: {}", playback.effect, err);
+      }
+    }
+  }
+
+  fn sample(&self, effect: SoundEffect) -> &RawSample {
+    match effect {
       SoundEffect::Kili => &self.kili,
       SoundEffect::Picaxe => &self.picaxe,
       SoundEffect::Explos1 => &self.explos1,
@@ -85,18 +218,23 @@ impl SoundEffects {
       SoundEffect::Pikkupom => &self.pikkupom,
       SoundEffect::Urethan => &self.urethan,
       SoundEffect::Applause => &self.applause,
-    };
-    // FIXME: reuse channels if all cannels are busy
-    let channel = Channel::all();
-    mb_sdl2_effects::play_sound_sample(channel, frequency, effect.0.clone(), position).map_err(SdlError)?;
-    Ok(())
+    }
   }
 }
 
-fn load_sample(path: PathBuf) -> Result<RawSample, SampleLoadingFailed> {
-  let data = std::fs::read(&path).map_err(|source| SampleLoadingFailed {
-    path,
-    source: source.into(),
-  })?;
-  Ok(RawSample(data.into()))
+/// Stereo position of the map cell, from 0.0 (left) to 1.0 (right)
+fn stereo_position(location: Cursor) -> f32 {
+  f32::from(location.col) / f32::from(MAP_COLS - 1)
+}
+
+/// Fuse hiss is not among the original game samples, so we generate it (quiet white noise)
+fn hiss_sample() -> RawSample {
+  let mut rng = rand::thread_rng();
+  let data: Vec<u8> = (0..FUSE_SAMPLE_LEN).map(|_| rng.gen_range(112..=144)).collect();
+  RawSample(data.into())
+}
+
+fn load_sample(vfs: &Vfs, name: &str) -> Result<RawSample, ApplicationError> {
+  let file = vfs.read(name)?;
+  Ok(RawSample(file.data.into()))
 }
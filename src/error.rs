@@ -1,11 +1,36 @@
 use sdl2::render::TargetRenderError;
+use std::path::PathBuf;
 use thiserror::Error;
 
+/// Errors of the application. Messages are shown to the player as is, so they explain what to do
+/// rather than just what went wrong.
 #[derive(Debug, Error)]
 pub enum ApplicationError {
   #[error("SDL error: {0}")]
   SdlError(String),
 
-  #[error("Target render error")]
-  TargetRenderError(#[from] TargetRenderError),
+  #[error(
+    "Game file '{}' is missing; make sure game directory contains files of the original MineBombers 3.11",
+    path.display()
+  )]
+  AssetMissing { path: PathBuf },
+
+  #[error("Failed to read game file '{}'; check that the file is readable", path.display())]
+  AssetRead {
+    path: PathBuf,
+    #[source]
+    source: anyhow::Error,
+  },
+
+  #[error(
+    "Map file '{}' is corrupted at byte {offset}; fix the map in the level editor or remove it",
+    path.display()
+  )]
+  MapParse { path: PathBuf, offset: usize },
+
+  #[error("Failed to initialize audio ({0}); check that sound device is available and not used exclusively")]
+  AudioInit(String),
+
+  #[error("Failed to render to texture; make sure graphics driver supports render targets")]
+  RenderTarget(#[from] TargetRenderError),
 }
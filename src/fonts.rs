@@ -47,12 +47,13 @@ impl Font<'_> {
   }
 }
 
-/// Load font texture
+/// Load font texture from the file data; path is only used for error reporting
 pub fn load_font<'t>(
   texture_creator: &'t TextureCreator<WindowContext>,
   path: &Path,
+  data: &[u8],
 ) -> Result<Font<'t>, FontLoadingFailed> {
-  let texture = load_font_internal(texture_creator, path).map_err(|source| FontLoadingFailed {
+  let texture = load_font_internal(texture_creator, data).map_err(|source| FontLoadingFailed {
     path: path.to_owned(),
     source,
   })?;
@@ -63,10 +64,9 @@ pub fn load_font<'t>(
 
 fn load_font_internal<'t>(
   texture_creator: &'t TextureCreator<WindowContext>,
-  path: &Path,
+  data: &[u8],
 ) -> Result<Texture<'t>, anyhow::Error> {
-  let data = std::fs::read(path)?;
-  let data = decode_font(&data)?;
+  let data = decode_font(data)?;
   let mut texture = texture_creator.create_texture_static(PixelFormatEnum::RGBA32, 16 * 8, 16 * 8)?;
   texture.update(None, &data, 16 * 8 * 4)?;
   texture.set_blend_mode(BlendMode::Blend);
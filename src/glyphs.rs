@@ -1,15 +1,163 @@
 use crate::error::ApplicationError::SdlError;
-use crate::images::TexturePalette;
+use crate::images::DecodedImage;
 use crate::world::actor::{ActorKind, Player};
 use crate::world::equipment::Equipment;
 use crate::world::map::MapValue;
 use crate::world::position::Direction;
+use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::{Texture, WindowCanvas};
+use std::cell::RefCell;
+use std::collections::HashSet;
 
 /// Glyphs is one single texture with all game icons on it.
 pub struct Glyphs<'t> {
+  sheet: RefCell<GlyphSheet<'t>>,
+  /// Level specific glyph sheet used instead of the regular one while the level is played
+  skin: RefCell<Option<GlyphSheet<'t>>>,
+}
+
+/// Texture with all game icons on it
+pub struct GlyphSheet<'t> {
   texture: Texture<'t>,
+  /// Positions of directional glyphs which are blank in the texture. Modded glyph sheets might not
+  /// have all four directions drawn; these are synthesized from other directions when rendering.
+  missing: HashSet<(i32, i32)>,
+  fallbacks: GlyphFallbacks,
+}
+
+/// How missing directional glyphs of each class are synthesized. Could be configured by the
+/// `GLYPHS.CFG` file next to the glyph sheet, which consists of `<class>=<fallback>` lines, where
+/// class is `PLAYER` or `MONSTER` and fallback is `FLIP`, `ROTATE` or `NONE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphFallbacks {
+  player: Fallback,
+  monster: Fallback,
+}
+
+impl Default for GlyphFallbacks {
+  /// Players hold their tools in a particular hand, so rotated images look off; monsters are
+  /// symmetric enough to be rotated.
+  fn default() -> Self {
+    GlyphFallbacks {
+      player: Fallback::Flip,
+      monster: Fallback::FlipOrRotate,
+    }
+  }
+}
+
+impl GlyphFallbacks {
+  /// Parse fallbacks configuration. Missing or invalid values keep their defaults.
+  pub fn parse(text: &str) -> Self {
+    let mut fallbacks = GlyphFallbacks::default();
+    for line in text.lines() {
+      let (class, value) = match line.split_once('=') {
+        Some((class, value)) => (class.trim().to_ascii_uppercase(), value.trim().to_ascii_uppercase()),
+        None => continue,
+      };
+      let fallback = match value.as_str() {
+        "FLIP" => Fallback::Flip,
+        "ROTATE" => Fallback::FlipOrRotate,
+        "NONE" => Fallback::None,
+        _ => continue,
+      };
+      match class.as_str() {
+        "PLAYER" => fallbacks.player = fallback,
+        "MONSTER" => fallbacks.monster = fallback,
+        _ => {}
+      }
+    }
+    fallbacks
+  }
+
+  fn of(self, class: GlyphClass) -> Fallback {
+    match class {
+      GlyphClass::Player => self.player,
+      GlyphClass::Monster => self.monster,
+    }
+  }
+}
+
+/// Class of directional glyphs, defines how missing directions are synthesized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GlyphClass {
+  Player,
+  Monster,
+}
+
+/// How missing directional glyphs are synthesized from the existing ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fallback {
+  /// Missing glyph is mirrored from the opposite direction
+  Flip,
+  /// Missing glyph is mirrored from the opposite direction or, if that one is missing, too, rotated
+  /// from the perpendicular one
+  FlipOrRotate,
+  /// Missing glyph is left blank
+  None,
+}
+
+impl GlyphClass {
+  fn of(kind: ActorKind) -> GlyphClass {
+    match kind {
+      ActorKind::Player(_) | ActorKind::Clone(_) => GlyphClass::Player,
+      ActorKind::Furry | ActorKind::Grenadier | ActorKind::Slime | ActorKind::Alien | ActorKind::Trader => {
+        GlyphClass::Monster
+      }
+    }
+  }
+}
+
+/// Source of the synthesized glyph: direction to take the image from and how to transform it
+struct Substitute {
+  direction: Direction,
+  flip_horizontal: bool,
+  flip_vertical: bool,
+  /// Clockwise rotation, in degrees
+  angle: f64,
+}
+
+impl Fallback {
+  /// Candidate substitutes for the given direction, in the order of preference
+  fn substitutes(self, dir: Direction) -> Vec<Substitute> {
+    let flip = |direction: Direction| Substitute {
+      direction,
+      flip_horizontal: matches!(dir, Direction::Left | Direction::Right),
+      flip_vertical: matches!(dir, Direction::Up | Direction::Down),
+      angle: 0.0,
+    };
+    let rotate = |direction: Direction, angle: f64| Substitute {
+      direction,
+      flip_horizontal: false,
+      flip_vertical: false,
+      angle,
+    };
+    match self {
+      Fallback::Flip => vec![flip(dir.reverse())],
+      Fallback::FlipOrRotate => {
+        let (clockwise, counter_clockwise) = match dir {
+          Direction::Right => (Direction::Up, Direction::Down),
+          Direction::Down => (Direction::Right, Direction::Left),
+          Direction::Left => (Direction::Down, Direction::Up),
+          Direction::Up => (Direction::Left, Direction::Right),
+        };
+        vec![
+          flip(dir.reverse()),
+          rotate(clockwise, 90.0),
+          rotate(counter_clockwise, -90.0),
+        ]
+      }
+      Fallback::None => Vec::new(),
+    }
+  }
+}
+
+/// Source rectangle and transformation to render the glyph with
+struct ResolvedGlyph {
+  rect: Rect,
+  flip_horizontal: bool,
+  flip_vertical: bool,
+  angle: f64,
 }
 
 #[repr(u8)]
@@ -63,12 +211,28 @@ impl Glyph {
       Glyph::ShopSlot(false) => (64, 92, 127, 139),
       Glyph::ShopSlot(true) => (128, 92, 191, 139),
       Glyph::Ready => (120, 140, 149, 169),
+      Glyph::Selection(Equipment::Bandage) => {
+        // There is no dedicated image for bandage, so we use medikit
+        let (x, y) = MAP_GLYPHS[(MapValue::Medikit as usize) - (MapValue::Passage as usize)];
+        (x, y, x + 9, y + 9)
+      }
       Glyph::Selection(equpment) => {
         let (x, y) = EQUIPMENT_GLYPHS[equpment as usize];
         (x, y, x + 29, y + 29)
       }
       Glyph::Map(value) => {
-        let (x, y) = if value >= MapValue::Passage && value <= MapValue::Item182 {
+        let (x, y) = if value.nest().is_some() || value == MapValue::BiomassHeart {
+          // There is no dedicated image for nests and biomass hearts, so we use biomass
+          MAP_GLYPHS[(MapValue::Biomass as usize) - (MapValue::Passage as usize)]
+        } else if value == MapValue::Fire {
+          // Fire re-uses explosion image
+          MAP_GLYPHS[(MapValue::Explosion as usize) - (MapValue::Passage as usize)]
+        } else if value.key_color().is_some() {
+          // There is no image for keys in the texture, so they are drawn on top of the passage
+          MAP_GLYPHS[0]
+        } else if value.locked_door_color().is_some() {
+          MAP_GLYPHS[(MapValue::Door as usize) - (MapValue::Passage as usize)]
+        } else if value >= MapValue::Passage && value <= MapValue::Item182 {
           MAP_GLYPHS[(value as usize) - (MapValue::Passage as usize)]
         } else {
           UNMAPPED
@@ -101,7 +265,7 @@ impl Glyph {
           ActorKind::Furry => (160, 50),
           ActorKind::Grenadier => (160, 60),
           ActorKind::Slime => (160, 70),
-          ActorKind::Alien => (0, 80),
+          ActorKind::Alien | ActorKind::Trader => (0, 80),
           ActorKind::Player(Player::Player1) | ActorKind::Clone(Player::Player1) if digging == Digging::Pickaxe => {
             (160, 200)
           }
@@ -140,23 +304,158 @@ impl Glyph {
   }
 }
 
+impl<'t> GlyphSheet<'t> {
+  /// Create glyph sheet from the texture. Decoded image is used to find directional glyphs missing
+  /// from the texture.
+  pub fn from_image(texture: Texture<'t>, image: &DecodedImage, fallbacks: GlyphFallbacks) -> GlyphSheet<'t> {
+    let missing = directional_glyphs()
+      .map(Glyph::rect)
+      .filter(|rect| image.is_blank(*rect))
+      .map(|rect| (rect.x(), rect.y()))
+      .collect();
+    GlyphSheet {
+      texture,
+      missing,
+      fallbacks,
+    }
+  }
+
+  /// Find the source of the glyph image, synthesizing it from other directions if it is missing
+  fn resolve(&self, glyph: Glyph) -> ResolvedGlyph {
+    let rect = glyph.rect();
+    let is_missing = |rect: Rect| self.missing.contains(&(rect.x(), rect.y()));
+    if let Glyph::Monster(kind, dir, digging, anim) = glyph {
+      if is_missing(rect) {
+        let substitute = self
+          .fallbacks
+          .of(GlyphClass::of(kind))
+          .substitutes(dir)
+          .into_iter()
+          .map(|substitute| {
+            (
+              Glyph::Monster(kind, substitute.direction, digging, anim).rect(),
+              substitute,
+            )
+          })
+          .find(|(rect, _)| !is_missing(*rect));
+        if let Some((rect, substitute)) = substitute {
+          return ResolvedGlyph {
+            rect,
+            flip_horizontal: substitute.flip_horizontal,
+            flip_vertical: substitute.flip_vertical,
+            angle: substitute.angle,
+          };
+        }
+      }
+    }
+    ResolvedGlyph {
+      rect,
+      flip_horizontal: false,
+      flip_vertical: false,
+      angle: 0.0,
+    }
+  }
+
+  fn copy(&self, canvas: &mut WindowCanvas, glyph: Glyph, target: Rect) -> Result<(), anyhow::Error> {
+    let resolved = self.resolve(glyph);
+    canvas
+      .copy_ex(
+        &self.texture,
+        resolved.rect,
+        target,
+        resolved.angle,
+        None,
+        resolved.flip_horizontal,
+        resolved.flip_vertical,
+      )
+      .map_err(SdlError)?;
+    Ok(())
+  }
+}
+
 impl<'t> Glyphs<'t> {
-  /// Load glyph texture
-  pub fn from_texture(texture: TexturePalette<'t>) -> Glyphs<'t> {
+  pub fn new(sheet: GlyphSheet<'t>) -> Glyphs<'t> {
     Self {
-      texture: texture.texture,
+      sheet: RefCell::new(sheet),
+      skin: RefCell::new(None),
+    }
+  }
+
+  /// Replace glyph sheet with the level skin (or go back to the regular sheet if `None`)
+  pub fn set_skin(&self, skin: Option<GlyphSheet<'t>>) {
+    *self.skin.borrow_mut() = skin;
+  }
+
+  /// Invoke callback with the glyph sheet currently in use
+  fn with_sheet<R>(&self, callback: impl FnOnce(&mut GlyphSheet<'t>) -> R) -> R {
+    if let Some(skin) = self.skin.borrow_mut().as_mut() {
+      return callback(skin);
     }
+    callback(&mut self.sheet.borrow_mut())
   }
 
   /// Render given glyph at position
   pub fn render(&self, canvas: &mut WindowCanvas, x: i32, y: i32, glyph: Glyph) -> Result<(), anyhow::Error> {
-    let src_rect = glyph.rect();
-    let tgt_rect = Rect::new(x, y, src_rect.width(), src_rect.height());
-    canvas.copy(&self.texture, src_rect, tgt_rect).map_err(SdlError)?;
-    Ok(())
+    let (width, height) = glyph.dimensions();
+    let tgt_rect = Rect::new(x, y, width, height);
+    self.with_sheet(|sheet| sheet.copy(canvas, glyph, tgt_rect))
+  }
+
+  /// Render given glyph scaled into the given rectangle
+  pub fn render_scaled(&self, canvas: &mut WindowCanvas, target: Rect, glyph: Glyph) -> Result<(), anyhow::Error> {
+    self.with_sheet(|sheet| sheet.copy(canvas, glyph, target))
+  }
+
+  /// Render given glyph at position, with its colors modulated by the given tint
+  pub fn render_tinted(
+    &self,
+    canvas: &mut WindowCanvas,
+    x: i32,
+    y: i32,
+    glyph: Glyph,
+    tint: Color,
+  ) -> Result<(), anyhow::Error> {
+    let (width, height) = glyph.dimensions();
+    let tgt_rect = Rect::new(x, y, width, height);
+    self.with_sheet(|sheet| {
+      sheet.texture.set_color_mod(tint.r, tint.g, tint.b);
+      let result = sheet.copy(canvas, glyph, tgt_rect);
+      sheet.texture.set_color_mod(255, 255, 255);
+      result
+    })
   }
 }
 
+/// All directional glyphs that could be synthesized from other directions
+fn directional_glyphs() -> impl Iterator<Item = Glyph> {
+  const KINDS: [ActorKind; 9] = [
+    ActorKind::Furry,
+    ActorKind::Grenadier,
+    ActorKind::Slime,
+    ActorKind::Alien,
+    ActorKind::Trader,
+    ActorKind::Player(Player::Player1),
+    ActorKind::Player(Player::Player2),
+    ActorKind::Player(Player::Player3),
+    ActorKind::Player(Player::Player4),
+  ];
+  const PHASES: [AnimationPhase; 4] = [
+    AnimationPhase::Phase1,
+    AnimationPhase::Phase2,
+    AnimationPhase::Phase3,
+    AnimationPhase::Phase4,
+  ];
+  KINDS.iter().flat_map(|kind| {
+    Direction::all().flat_map(move |dir| {
+      [Digging::Hands, Digging::Pickaxe].iter().flat_map(move |digging| {
+        PHASES
+          .iter()
+          .map(move |anim| Glyph::Monster(*kind, dir, *digging, *anim))
+      })
+    })
+  })
+}
+
 /// Table for mapping equipment type to texture coordinates. Note that this list must be consistent
 /// with the `Equipment` enum.
 const EQUIPMENT_GLYPHS: [(i16, i16); Equipment::TOTAL] = [
@@ -187,6 +486,8 @@ const EQUIPMENT_GLYPHS: [(i16, i16); Equipment::TOTAL] = [
   (105, 40),
   (60, 40),
   (0, 90),
+  // Bandage has no dedicated image, see `Glyph::rect`
+  (0, 0),
 ];
 
 /// FIXME: we perhaps can map monsters, too, even though we actually never render them as map cells
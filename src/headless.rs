@@ -0,0 +1,161 @@
+//! Dedicated host: runs the network lobby and the match without any window or audio, suitable for
+//! running on a small server everyone can connect to. Players join with `--join` and play the match
+//! in lockstep (see `netplay`); host relays their inputs and simulates the match along with them,
+//! so it could stream the match to observers (see `broadcast`). Match results are written to the
+//! match log in the game directory.
+use crate::broadcast::Broadcast;
+use crate::lockstep::Lockstep;
+use crate::matchlog::{compute_ranks, MatchLog, RoundResult, Standing};
+use crate::menu::lobby::{match_options, report_violations, HostLobby, Member};
+use crate::netplay::MIN_INPUT_DELAY;
+use crate::options::Options;
+use crate::world::map::{LevelMap, LevelMeta};
+use crate::world::player::PlayerComponent;
+use crate::world::World;
+use std::path::Path;
+use std::time::Duration;
+
+/// Duration of a single simulation tick
+const TICK_DURATION: Duration = Duration::from_millis(20);
+
+/// Maximum amount of players of the match
+const MAX_PLAYERS: usize = 4;
+
+/// Host the lobby on the given address and play the match once enough players joined and all of
+/// them are ready, using options from the game directory. If `observers` address is given, match
+/// is streamed to the observers connecting to it.
+pub fn host_only(game_dir: &Path, address: &str, observers: Option<&str>) -> Result<(), anyhow::Error> {
+  let mut options = Options::load(game_dir);
+  match_options(&mut options);
+  let broadcast = observers.map(Broadcast::start).transpose()?;
+  let count = usize::from(options.players).clamp(2, MAX_PLAYERS);
+  let mut lobby = HostLobby::bind(address, MAX_PLAYERS)?;
+  println!("Hosting on {}, waiting for {} players", address, count);
+  loop {
+    let changed = lobby.poll()?;
+    for line in lobby.take_chat() {
+      println!("{}", line);
+    }
+    if changed {
+      let members: Vec<Member> = lobby.members().cloned().collect();
+      lobby.send_state(&options, &members)?;
+      if members.len() >= count && members.iter().all(|member| member.ready) {
+        break;
+      }
+    }
+    std::thread::sleep(TICK_DURATION);
+  }
+
+  let netplay = lobby.start(None, &mut options)?;
+  let mut players: Vec<PlayerComponent> = netplay
+    .names()
+    .enumerate()
+    .map(|(idx, name)| PlayerComponent::new(name.to_owned(), netplay.remote_keys(idx), &options))
+    .collect();
+  let names = players.iter().map(|player| player.stats.name.clone()).collect();
+  let mut match_log = MatchLog::new(&options, names);
+  let input_delay = options.input_delay.max(MIN_INPUT_DELAY);
+
+  // Every step must be the same as the one players take in their game, so the simulation here
+  // doesn't diverge from theirs
+  'rounds: for round in 0..options.rounds {
+    if let Some(items) = options.loadout_items() {
+      for player in &mut players {
+        player.buy_loadout(items);
+      }
+    }
+    let mut rng = netplay.next_epoch();
+    let mut level = LevelMap::random_map(options.treasures, &mut rng);
+    level.generate_entrances(options.players, &mut rng);
+    let saved = players.clone();
+    let mut world = World::create(
+      level.clone(),
+      &mut players,
+      options.darkness,
+      &options,
+      false,
+      LevelMeta::default(),
+      rng,
+    );
+    if let Some(ref broadcast) = broadcast {
+      broadcast.reset();
+    }
+    let mut lockstep = Lockstep::new(world.players.len(), input_delay, &options);
+    loop {
+      if netplay.is_abandoned() || netplay.is_lost() {
+        println!("Everyone has left the match");
+        break 'rounds;
+      }
+      if !lockstep.poll(&world, Some(&netplay)) {
+        std::thread::sleep(Duration::from_millis(1));
+        continue;
+      }
+      world.tick();
+      if let Some(ref broadcast) = broadcast {
+        broadcast.publish(&world);
+      }
+      world.update.clear();
+      world.effects.clear();
+      world.events.clear();
+
+      lockstep.apply_inputs(&mut world, Some(&netplay));
+      if let Some((player, tick)) = netplay.take_desync() {
+        println!(
+          "Game of {} went out of sync at tick {}",
+          world.players[player].stats.name, tick
+        );
+      }
+      if lockstep.restart_voted() {
+        let players = world.players;
+        players.clone_from_slice(&saved);
+        world = World::create(
+          level.clone(),
+          players,
+          options.darkness,
+          &options,
+          false,
+          LevelMeta::default(),
+          netplay.next_epoch(),
+        );
+        if let Some(ref broadcast) = broadcast {
+          broadcast.reset();
+        }
+        lockstep.restart();
+        continue;
+      }
+
+      if TICK_DURATION * world.round_counter as u32 >= options.round_time || world.is_end_of_round() {
+        break;
+      }
+    }
+    let settlement = world.end_of_round();
+
+    let result = RoundResult {
+      level: "Random".to_owned(),
+      settlement,
+    };
+    println!(
+      "Round {} of {}: cash {:?}",
+      round + 1,
+      options.rounds,
+      result.settlement.cash()
+    );
+    match_log.record_round(result);
+  }
+  netplay.leave();
+  report_violations(&netplay);
+  let ranks = compute_ranks(&players, options.win);
+  let standings: Vec<Standing> = players
+    .iter()
+    .zip(ranks)
+    .map(|(player, rank)| Standing {
+      name: player.stats.name.clone(),
+      rank,
+      rounds_win: player.rounds_win,
+      cash: player.cash,
+      kills: player.kills,
+    })
+    .collect();
+  match_log.save(game_dir, &standings)?;
+  Ok(())
+}
@@ -0,0 +1,83 @@
+//! Per-cell statistics of the round (explosions, deaths, digs), exported as a PNG heatmap next to
+//! the match log. Helps map authors to see which parts of their levels are actually played.
+use crate::world::map::{Map, MAP_COLS, MAP_ROWS};
+use crate::world::position::Cursor;
+use crate::world::{GameEvent, World};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Size of the map cell in the exported image, in pixels
+const CELL_SIZE: u32 = 8;
+
+#[derive(Debug, Error)]
+#[error("Failed to write heatmap to '{path}'")]
+pub struct HeatmapSaveError {
+  #[source]
+  source: anyhow::Error,
+  path: PathBuf,
+}
+
+#[derive(Default)]
+pub struct RoundHeatmap {
+  explosions: Map<u16>,
+  deaths: Map<u16>,
+  digs: Map<u16>,
+}
+
+impl RoundHeatmap {
+  /// Count gameplay event in the cell it happened in
+  pub fn record(&mut self, event: &GameEvent, world: &World) {
+    let (map, cursor) = match *event {
+      GameEvent::Exploded { cursor } => (&mut self.explosions, cursor),
+      GameEvent::Dug { cursor } => (&mut self.digs, cursor),
+      GameEvent::PlayerKilled { victim, .. } => (&mut self.deaths, world.actors[victim].pos.cursor()),
+      GameEvent::TreasureFound { .. } => return,
+    };
+    map[cursor] = map[cursor].saturating_add(1);
+  }
+
+  /// Save heatmap of the round into the game directory (next to the match log), as a PNG image. Each
+  /// statistic has its own color channel: explosions are red, digs are green and deaths are blue.
+  /// Each channel is scaled to its maximum value.
+  pub fn save(&self, game_dir: &Path, round: u16, level: &str) -> Result<(), HeatmapSaveError> {
+    let timestamp = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |duration| duration.as_secs());
+    let path = game_dir.join(format!("heatmap-{}-round{:02}-{}.png", timestamp, round + 1, level));
+    self
+      .write_image(&path)
+      .map_err(|source| HeatmapSaveError { source, path })
+  }
+
+  fn write_image(&self, path: &Path) -> Result<(), anyhow::Error> {
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+
+    let channels = [&self.explosions, &self.digs, &self.deaths];
+    let max = channels.map(|map| Cursor::all().map(|cursor| map[cursor]).max().unwrap_or(0).max(1));
+    let (width, height) = (u32::from(MAP_COLS) * CELL_SIZE, u32::from(MAP_ROWS) * CELL_SIZE);
+    let mut image = Vec::with_capacity((width * height * 3) as usize);
+    for y in 0..height {
+      for x in 0..width {
+        let cursor = Cursor::new((y / CELL_SIZE) as u16, (x / CELL_SIZE) as u16);
+        for (map, max) in channels.iter().zip(max) {
+          // Square root makes rare events still visible next to the hot spots
+          let heat = (f64::from(map[cursor]) / f64::from(max)).sqrt();
+          image.push((255.0 * heat) as u8);
+        }
+      }
+    }
+
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image)?;
+    Ok(())
+  }
+}
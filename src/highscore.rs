@@ -1,5 +1,6 @@
 //! Player statistics
 use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
@@ -19,27 +20,135 @@ pub struct ScoresSaveError {
   path: PathBuf,
 }
 
+/// File with high scores in our own format
+const EXTENDED_FILE: &str = "highrel.dat";
+
+/// Legacy high scores file, written by the original game
+const LEGACY_FILE: &str = "HIGHSCOR.DAT";
+
+/// Signature and version of the extended high scores file. Version 2 adds flags to each score,
+/// version 3 adds ironman flag to each board.
+const MAGIC: &[u8; 4] = b"MBHS";
+const VERSION: u8 = 3;
+
+/// Score flag: some of the levels were skipped using skip tokens
+const FLAG_SKIPPED: u8 = 1;
+
+/// Score flag: player took an assist on some of the levels
+const FLAG_ASSISTED: u8 = 2;
+
+/// Maximum amount of scores in each board
+pub const MAX_SCORES: usize = 50;
+
 #[derive(Clone, Debug)]
 pub struct Score {
   pub name: String,
   pub level: u8,
   pub cash: u32,
+  /// Player skipped some levels, so the score is not quite legit
+  pub skipped: bool,
+  /// Player took an assist on some of the levels
+  pub assisted: bool,
+}
+
+/// Games with more players or played in darkness have different difficulty, so each combination
+/// gets its own board. Ironman games (no continues, darkness is forced) have their own boards, too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardKind {
+  pub players: u8,
+  pub darkness: bool,
+  pub ironman: bool,
+}
+
+impl BoardKind {
+  /// Original game only had single player games. Imported scores go to the board shown by default,
+  /// so players could find them.
+  const LEGACY: BoardKind = BoardKind {
+    players: 1,
+    darkness: false,
+    ironman: false,
+  };
 }
 
 #[derive(Default, Debug)]
 pub struct Highscores {
-  pub scores: Box<[Option<Score>; 10]>,
+  boards: Vec<(BoardKind, Vec<Score>)>,
 }
 
 impl Highscores {
-  /// Load high scoress from `HIGHSCOR.DAT`.
+  /// Load high scores. Scores from `HIGHSCOR.DAT` which are not in our format yet are merged in.
   pub fn load(game_dir: &Path) -> Result<Highscores, ScoresLoadError> {
-    let path = game_dir.join("HIGHSCOR.DAT");
-    if path.is_file() {
-      Highscores::load_scores_internal(&path).map_err(|source| ScoresLoadError { path, source })
+    let path = game_dir.join(EXTENDED_FILE);
+    let mut scores = if path.is_file() {
+      Highscores::load_scores_extended(&path).map_err(|source| ScoresLoadError { path, source })?
     } else {
-      Ok(Highscores::default())
+      Highscores::default()
+    };
+    let path = game_dir.join(LEGACY_FILE);
+    if path.is_file() {
+      let legacy = Highscores::load_scores_internal(&path).map_err(|source| ScoresLoadError { path, source })?;
+      scores.merge(legacy);
     }
+    Ok(scores)
+  }
+
+  /// Add scores which are not on any board yet, so importing the same scores again does nothing (even
+  /// if they were imported to another board by an older version)
+  fn merge(&mut self, other: Highscores) {
+    for (kind, board) in other.boards {
+      for score in board {
+        let known = self.boards.iter().flat_map(|(_, board)| board).any(|known| {
+          (known.name.as_str(), known.level, known.cash) == (score.name.as_str(), score.level, score.cash)
+        });
+        if !known {
+          self.insert(kind, score);
+        }
+      }
+    }
+  }
+
+  fn load_scores_extended(path: &Path) -> Result<Highscores, std::io::Error> {
+    let data = std::fs::read(path)?;
+    let mut scores = Highscores::default();
+    let mut it = match data.strip_prefix(MAGIC.as_ref()) {
+      Some(it) => it,
+      // Invalid format, just ignore
+      None => return Ok(scores),
+    };
+    let version = it.read_u8()?;
+    if version == 0 || version > VERSION {
+      return Ok(scores);
+    }
+
+    let boards = it.read_u8()?;
+    for _ in 0..boards {
+      let kind = BoardKind {
+        players: it.read_u8()?,
+        darkness: it.read_u8()? != 0,
+        ironman: version >= 3 && it.read_u8()? != 0,
+      };
+      let count = it.read_u8()?;
+      let mut board = Vec::with_capacity(usize::from(count));
+      for _ in 0..count {
+        let len = usize::from(it.read_u8()?);
+        let mut name = vec![0; len];
+        it.read_exact(&mut name)?;
+        let name = String::from_utf8_lossy(&name).into_owned();
+        let level = it.read_u8()?;
+        let cash = it.read_u32::<LittleEndian>()?;
+        let flags = if version >= 2 { it.read_u8()? } else { 0 };
+        board.push(Score {
+          name,
+          level,
+          cash,
+          skipped: flags & FLAG_SKIPPED != 0,
+          assisted: flags & FLAG_ASSISTED != 0,
+        });
+      }
+      board.truncate(MAX_SCORES);
+      scores.boards.push((kind, board));
+    }
+    Ok(scores)
   }
 
   fn load_scores_internal(path: &Path) -> Result<Highscores, std::io::Error> {
@@ -50,6 +159,7 @@ impl Highscores {
       return Ok(players);
     }
 
+    let mut board = Vec::new();
     for player in 0..10 {
       // Each record is 26 byte long
       let data = &data[player * 26..][..26];
@@ -61,34 +171,80 @@ impl Highscores {
         let mut it = &data[21..26];
         let level = it.read_u8().unwrap();
         let cash = it.read_u32::<LittleEndian>().unwrap();
-        players.scores[player] = Some(Score { name, level, cash });
+        board.push(Score {
+          name,
+          level,
+          cash,
+          skipped: false,
+          assisted: false,
+        });
       }
     }
+    players.boards.push((BoardKind::LEGACY, board));
 
     Ok(players)
   }
 
+  /// Scores of the given board, best first
+  pub fn board(&self, kind: BoardKind) -> &[Score] {
+    self
+      .boards
+      .iter()
+      .find(|(board, _)| *board == kind)
+      .map_or(&[], |(_, scores)| scores.as_slice())
+  }
+
+  /// Add new score to the board. Returns position of the score if it made it to the board.
+  pub fn insert(&mut self, kind: BoardKind, score: Score) -> Option<usize> {
+    let idx = match self.boards.iter().position(|(board, _)| *board == kind) {
+      Some(idx) => idx,
+      None => {
+        self.boards.push((kind, Vec::new()));
+        self.boards.len() - 1
+      }
+    };
+    let board = &mut self.boards[idx].1;
+    // Higher level goes first, then more money; new score goes after the equal ones
+    let pos = board
+      .iter()
+      .position(|other| (score.level, score.cash) > (other.level, other.cash))
+      .unwrap_or(board.len());
+    if pos >= MAX_SCORES {
+      return None;
+    }
+    board.insert(pos, score);
+    board.truncate(MAX_SCORES);
+    Some(pos)
+  }
+
   pub fn save(&self, game_dir: &Path) -> Result<(), ScoresSaveError> {
-    let mut out: Vec<u8> = Vec::with_capacity(32 * 101);
-    for score in self.scores.iter() {
-      if let Some(score) = score {
-        let name_len = score.name.len().min(18);
-        // See `load`, we always add "1 " in the front.
-        out.push((name_len + 2) as u8);
-        out.push(b'1');
-        out.push(b' ');
-        out.extend_from_slice(&score.name.as_bytes()[..name_len]);
-        out.resize(out.len() + (18 - name_len), 0);
+    let mut out: Vec<u8> = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.push(self.boards.len() as u8);
+    for (kind, scores) in &self.boards {
+      out.push(kind.players);
+      out.push(kind.darkness as u8);
+      out.push(kind.ironman as u8);
+      out.push(scores.len() as u8);
+      for score in scores {
+        let name = &score.name.as_bytes()[..score.name.len().min(255)];
+        out.push(name.len() as u8);
+        out.extend_from_slice(name);
         out.push(score.level);
         out.extend_from_slice(&score.cash.to_le_bytes());
-      } else {
-        // Just pad zeroes
-        out.resize(out.len() + 26, 0);
+        let mut flags = 0;
+        if score.skipped {
+          flags |= FLAG_SKIPPED;
+        }
+        if score.assisted {
+          flags |= FLAG_ASSISTED;
+        }
+        out.push(flags);
       }
     }
-    assert_eq!(26 * 10, out.len());
 
-    let path = game_dir.join("HIGHSCOR.DAT");
+    let path = game_dir.join(EXTENDED_FILE);
     std::fs::write(&path, &out).map_err(|source| ScoresSaveError { path, source })?;
     Ok(())
   }
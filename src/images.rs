@@ -1,6 +1,8 @@
 //! Tools to work with SPY files
+use crate::palette::PaletteRoles;
 use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
 use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
 use sdl2::render::{Texture, TextureCreator};
 use sdl2::video::WindowContext;
 use std::path::{Path, PathBuf};
@@ -11,6 +13,8 @@ pub struct TexturePalette<'t> {
   pub texture: Texture<'t>,
   /// We only load 16 first colors as other part of palette is not used
   pub palette: [Color; 16],
+  /// Palette colors by their role
+  pub roles: PaletteRoles,
 }
 
 #[derive(Debug, Error)]
@@ -30,6 +34,38 @@ pub struct DecodedImage {
   pub image: Vec<u8>,
 }
 
+impl DecodedImage {
+  /// Check if all pixels of the given rectangle have the same color (nothing is drawn there)
+  pub fn is_blank(&self, rect: Rect) -> bool {
+    let pixel = |x: i32, y: i32| {
+      let offset = ((y as usize) * (self.width as usize) + (x as usize)) * 3;
+      self.image.get(offset..offset + 3)
+    };
+    let first = pixel(rect.x(), rect.y());
+    (rect.top()..rect.bottom()).all(|y| (rect.left()..rect.right()).all(|x| pixel(x, y) == first))
+  }
+
+  /// Replace colors of the palette (only first 16 colors could be replaced). Pixels of the image
+  /// are re-colored, too.
+  pub fn remap(&mut self, overrides: &[(u8, Color)]) {
+    let mut mapping = Vec::new();
+    for &(index, color) in overrides {
+      if let Some(entry) = self.palette.get_mut(usize::from(index)) {
+        mapping.push(([entry.r, entry.g, entry.b], [color.r, color.g, color.b]));
+        *entry = color;
+      }
+    }
+    if mapping.is_empty() {
+      return;
+    }
+    for pixel in self.image.chunks_exact_mut(3) {
+      if let Some((_, to)) = mapping.iter().find(|(from, _)| pixel == from) {
+        pixel.copy_from_slice(to);
+      }
+    }
+  }
+}
+
 #[derive(Debug, Error)]
 #[error("Failed to load texture from '{path}'")]
 pub struct TextureLoadingFailed {
@@ -45,13 +81,23 @@ pub enum TextureFormat {
   PPM,
 }
 
-/// Load texture at the given path
+/// Load texture from the file data; path is only used for error reporting
 pub fn load_texture<'t>(
   texture_creator: &'t TextureCreator<WindowContext>,
   path: &Path,
+  data: &[u8],
   format: TextureFormat,
 ) -> Result<TexturePalette<'t>, TextureLoadingFailed> {
-  load_texture_internal(texture_creator, path, format).map_err(|source| TextureLoadingFailed {
+  load_texture_internal(texture_creator, data, format).map_err(|source| TextureLoadingFailed {
+    path: path.to_owned(),
+    source,
+  })
+}
+
+/// Decode image from the file data without creating a texture. Used when image pixels need to be
+/// inspected or changed (for example, to detect missing glyphs or to apply level palette).
+pub fn load_image(path: &Path, data: &[u8], format: TextureFormat) -> Result<DecodedImage, TextureLoadingFailed> {
+  decode_image(data, format).map_err(|source| TextureLoadingFailed {
     path: path.to_owned(),
     source,
   })
@@ -59,21 +105,31 @@ pub fn load_texture<'t>(
 
 fn load_texture_internal<'t>(
   texture_creator: &'t TextureCreator<WindowContext>,
-  path: &Path,
+  data: &[u8],
   format: TextureFormat,
 ) -> Result<TexturePalette<'t>, anyhow::Error> {
-  let data = std::fs::read(path)?;
+  let decoded = decode_image(data, format)?;
+  create_texture(texture_creator, &decoded)
+}
 
-  let decoded = match format {
-    TextureFormat::SPY => decode_spy(SCREEN_WIDTH, SCREEN_HEIGHT, &data)?,
-    TextureFormat::PPM => decode_ppm(&data)?,
-  };
-  let mut texture =
-    texture_creator.create_texture_static(PixelFormatEnum::RGB24, decoded.width as u32, decoded.height as u32)?;
+fn decode_image(data: &[u8], format: TextureFormat) -> Result<DecodedImage, anyhow::Error> {
+  Ok(match format {
+    TextureFormat::SPY => decode_spy(SCREEN_WIDTH, SCREEN_HEIGHT, data)?,
+    TextureFormat::PPM => decode_ppm(data)?,
+  })
+}
+
+/// Create texture from the decoded image
+pub fn create_texture<'t>(
+  texture_creator: &'t TextureCreator<WindowContext>,
+  decoded: &DecodedImage,
+) -> Result<TexturePalette<'t>, anyhow::Error> {
+  let mut texture = texture_creator.create_texture_static(PixelFormatEnum::RGB24, decoded.width, decoded.height)?;
 
   texture.update(None, &decoded.image, (decoded.width as usize) * 3)?;
   Ok(TexturePalette {
     palette: decoded.palette,
+    roles: PaletteRoles::resolve(&decoded.palette),
     texture,
   })
 }
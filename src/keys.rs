@@ -8,6 +8,35 @@ use std::path::Path;
 pub struct KeyBindings {
   /// Keys, indexed by `Key` enum.
   keys: [Option<Scancode>; 8],
+  /// How direction keys control the player
+  pub style: ControlStyle,
+}
+
+/// How direction keys control the player
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, TryFromPrimitive)]
+pub enum ControlStyle {
+  /// Pressing direction key turns the player and starts moving, as in the original game
+  #[default]
+  Classic,
+  /// Tapping direction key only turns the player; player moves while the key is held down
+  TurnThenMove,
+}
+
+impl ControlStyle {
+  pub fn toggle(self) -> ControlStyle {
+    match self {
+      ControlStyle::Classic => ControlStyle::TurnThenMove,
+      ControlStyle::TurnThenMove => ControlStyle::Classic,
+    }
+  }
+
+  pub fn label(self) -> &'static str {
+    match self {
+      ControlStyle::Classic => "CLASSIC",
+      ControlStyle::TurnThenMove => "TAP TO TURN",
+    }
+  }
 }
 
 pub struct KeysConfig {
@@ -35,6 +64,122 @@ impl Key {
   pub fn all_keys() -> impl Iterator<Item = Key> {
     (0..8).map(|v| v.try_into().unwrap())
   }
+
+  /// Check if key changes the direction of the player
+  pub fn is_direction(self) -> bool {
+    matches!(self, Key::Left | Key::Right | Key::Up | Key::Down)
+  }
+
+  /// Bitmask (indexed by `Key`) of the keys the predicate holds for
+  pub fn mask(predicate: impl Fn(Key) -> bool) -> u8 {
+    Key::all_keys()
+      .filter(|key| predicate(*key))
+      .fold(0, |mask, key| mask | (1 << key as u8))
+  }
+
+  /// Check if key is set in the bitmask
+  pub fn in_mask(self, mask: u8) -> bool {
+    mask & (1 << self as u8) != 0
+  }
+}
+
+/// Menu navigation actions. Menus work with actions rather than with keys, so navigation works the
+/// same way across all menus and can be done with the keys chosen by the player or with a gamepad.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuAction {
+  Up,
+  Down,
+  Left,
+  Right,
+  /// Confirm the selection
+  Select,
+  /// Leave the menu
+  Back,
+  /// Key which is not used for navigation
+  Other(Scancode),
+}
+
+/// Keys menus use for their own purposes; these are never mapped to navigation actions
+const MENU_RESERVED: [Scancode; 6] = [
+  Scancode::Tab,
+  Scancode::F1,
+  Scancode::F10,
+  Scancode::Backspace,
+  Scancode::Delete,
+  Scancode::Escape,
+];
+
+/// Mapping between keys and menu navigation actions
+#[derive(Clone)]
+pub struct MenuBindings {
+  /// Built-in bindings: arrows, keypad, enter and escape
+  builtin: Vec<(Scancode, MenuAction)>,
+  /// Bindings derived from the keys of the first player
+  custom: Vec<(Scancode, MenuAction)>,
+}
+
+impl Default for MenuBindings {
+  fn default() -> Self {
+    let builtin = vec![
+      (Scancode::Up, MenuAction::Up),
+      (Scancode::Kp8, MenuAction::Up),
+      (Scancode::Down, MenuAction::Down),
+      (Scancode::Kp2, MenuAction::Down),
+      (Scancode::Left, MenuAction::Left),
+      (Scancode::Kp4, MenuAction::Left),
+      (Scancode::Right, MenuAction::Right),
+      (Scancode::Kp6, MenuAction::Right),
+      (Scancode::Return, MenuAction::Select),
+      (Scancode::Return2, MenuAction::Select),
+      (Scancode::KpEnter, MenuAction::Select),
+      (Scancode::Escape, MenuAction::Back),
+    ];
+    MenuBindings {
+      builtin,
+      custom: Vec::new(),
+    }
+  }
+}
+
+impl MenuBindings {
+  /// Built-in bindings plus movement and bomb keys of the first player
+  pub fn from_keys(keys: &KeysConfig) -> Self {
+    let mut bindings = MenuBindings::default();
+    let player = &keys.keys[0];
+    let actions = [
+      (Key::Up, MenuAction::Up),
+      (Key::Down, MenuAction::Down),
+      (Key::Left, MenuAction::Left),
+      (Key::Right, MenuAction::Right),
+      (Key::Bomb, MenuAction::Select),
+    ];
+    for (key, action) in actions {
+      if let Some(scancode) = player[key].filter(|scancode| !MENU_RESERVED.contains(scancode)) {
+        bindings.custom.push((scancode, action));
+      }
+    }
+    bindings
+  }
+
+  /// Menu action for the given key
+  pub fn action(&self, scancode: Scancode) -> MenuAction {
+    self
+      .builtin
+      .iter()
+      .chain(self.custom.iter())
+      .find(|(code, _)| *code == scancode)
+      .map_or(MenuAction::Other(scancode), |(_, action)| *action)
+  }
+
+  /// Menu action for the given key, only using built-in bindings. Used in menus which accept text
+  /// input, so keys player uses for movement could be typed as text.
+  pub fn builtin_action(&self, scancode: Scancode) -> MenuAction {
+    self
+      .builtin
+      .iter()
+      .find(|(code, _)| *code == scancode)
+      .map_or(MenuAction::Other(scancode), |(_, action)| *action)
+  }
 }
 
 impl std::ops::Index<Key> for KeyBindings {
@@ -79,9 +224,10 @@ impl KeysConfig {
     KeysConfig { keys }
   }
 
-  /// Save key bindings; note that we always save in our new format, using SDL keycodes.
+  /// Save key bindings; note that we always save in our new format, using SDL keycodes. Control
+  /// styles of all players follow the keys.
   pub fn save(&self, game_dir: &Path) -> Result<(), anyhow::Error> {
-    let mut buf = Vec::with_capacity(32);
+    let mut buf = Vec::with_capacity(CONFIG_LEN);
     for keys in self.keys.iter() {
       // Note that in our format, we use different order (same as `Key` enum).
       for key in Key::all_keys() {
@@ -89,6 +235,9 @@ impl KeysConfig {
         buf.write_i32::<LittleEndian>(value)?;
       }
     }
+    for keys in self.keys.iter() {
+      buf.write_u8(keys.style as u8)?;
+    }
     let file = game_dir.join("keysrel.cfg");
     std::fs::write(file, &buf)?;
     Ok(())
@@ -108,6 +257,7 @@ fn default_keys() -> [KeyBindings; 4] {
         Some(Scancode::LCtrl),
         Some(Scancode::LShift),
       ],
+      style: ControlStyle::Classic,
     },
     KeyBindings {
       keys: [
@@ -120,18 +270,26 @@ fn default_keys() -> [KeyBindings; 4] {
         Some(Scancode::Num8),
         Some(Scancode::Num9),
       ],
+      style: ControlStyle::Classic,
     },
     KeyBindings::default(),
     KeyBindings::default(),
   ]
 }
 
+/// Length of the key bindings of all players in our configuration file
+const KEYS_LEN: usize = 128;
+
+/// Length of our configuration file: key bindings followed by control style of each player (older
+/// versions only saved key bindings)
+const CONFIG_LEN: usize = KEYS_LEN + 4;
+
 /// Load key assignments from a new configuration file
 fn load_keys_internal(path: &Path) -> Option<[KeyBindings; 4]> {
   let file = path.join("keysrel.cfg");
   let data = std::fs::read(file).ok()?;
 
-  if data.len() != 128 {
+  if data.len() != KEYS_LEN && data.len() != CONFIG_LEN {
     return None;
   }
 
@@ -146,6 +304,13 @@ fn load_keys_internal(path: &Path) -> Option<[KeyBindings; 4]> {
       keys[key] = value;
     }
   }
+  for keys in keys.iter_mut() {
+    keys.style = it
+      .read_u8()
+      .ok()
+      .and_then(|style| style.try_into().ok())
+      .unwrap_or_default();
+  }
   Some(keys)
 }
 
@@ -4,27 +4,49 @@ use crate::fonts::Font;
 use crate::glyphs::Glyphs;
 use crate::images::TexturePalette;
 use sdl2::mixer::Music;
-use std::path::Path;
+use std::cell::RefCell;
 
+mod archive;
 mod args;
+mod assets;
+#[cfg(feature = "audience")]
+mod audience;
 pub mod bitmap;
+mod broadcast;
+mod campaign;
+mod capture;
+mod chat;
 mod context;
+mod coords;
 pub mod effects;
 mod error;
 pub mod fonts;
 mod glyphs;
+mod headless;
+mod heatmap;
 mod highscore;
 mod identities;
 pub mod images;
 mod keys;
+mod lockstep;
+mod matchlog;
 mod menu;
+mod mods;
+mod netplay;
 mod options;
+pub mod palette;
+mod register;
 mod roster;
 mod settings;
+mod status;
+mod textures;
+mod vfs;
 pub mod world;
 
 const SCREEN_WIDTH: u32 = 640;
 const SCREEN_HEIGHT: u32 = 480;
+/// Screen width in the widescreen (16:9) mode; 640x480 playfield is centered on the screen
+const WIDESCREEN_WIDTH: u32 = 854;
 
 pub struct Avatars<'t> {
   win: TexturePalette<'t>,
@@ -34,12 +56,35 @@ pub struct Avatars<'t> {
 
 pub fn main() -> Result<(), anyhow::Error> {
   let args = args::parse_args();
-  let campaign_mode = args.campaign_mode;
-  ApplicationContext::with_context(args.path, |mut ctx| {
-    let app = Application::init(&ctx)?;
-    app.main_menu(&mut ctx, campaign_mode)?;
+  if let Some(ref address) = args.host_only {
+    return headless::host_only(&vfs::game_dir(&args.path), address, args.broadcast.as_deref());
+  }
+  let mut exit_code = 0;
+  ApplicationContext::with_context(args.path.clone(), |mut ctx| {
+    ctx.set_status_file(args.status_file.clone());
+    if args.watch_assets {
+      ctx.watch_assets();
+    }
+    let mut app = Application::init(&ctx)?;
+    if let Some(ref address) = args.broadcast {
+      app.broadcast = Some(broadcast::Broadcast::start(address)?);
+    }
+    if let Some(ref address) = args.host {
+      app.host_game(&mut ctx, address)?;
+    } else if let Some(ref address) = args.join {
+      app.join_game(&mut ctx, address)?;
+    } else if let Some(ref address) = args.observe {
+      app.observe(&mut ctx, address)?;
+    } else if args.play.is_some() {
+      exit_code = app.play_from_command_line(&mut ctx, &args)?;
+    } else {
+      app.main_menu(&mut ctx, &args)?;
+    }
     Ok(())
   })?;
+  if exit_code != 0 {
+    std::process::exit(exit_code);
+  }
   Ok(())
 }
 
@@ -64,8 +109,14 @@ struct Application<'t> {
   music1: Music<'static>,
   // Position 465 is position of shop music.
   music2: Music<'static>,
-  registered: String,
+  registered: RefCell<String>,
   effects: SoundEffects,
+  #[cfg(feature = "audience")]
+  audience: Option<audience::Audience>,
+  /// Stream of the game for observers
+  broadcast: Option<broadcast::Broadcast>,
+  /// Players of the network match (local and controlled from other machines)
+  netplay: Option<netplay::RemotePlayers>,
 }
 
 impl<'textures> Application<'textures> {
@@ -77,7 +128,7 @@ impl<'textures> Application<'textures> {
       levels_menu: ctx.load_spy("LEVSELEC.SPY")?,
       keys: ctx.load_spy("KEYS.SPY")?,
       shop: ctx.load_spy("SHOPPIC.SPY")?,
-      glyphs: Glyphs::from_texture(ctx.load_spy("SIKA.SPY")?),
+      glyphs: ctx.load_glyphs("SIKA.SPY")?,
       font: ctx.load_font("FONTTI.FON")?,
       info: [
         ctx.load_spy("INFO1.SPY")?,
@@ -94,8 +145,18 @@ impl<'textures> Application<'textures> {
       halloffa: ctx.load_spy("HALLOFFA.SPY")?,
       music1: ctx.load_music("HUIPPE.S3M")?,
       music2: ctx.load_music("OEKU.S3M")?,
-      effects: SoundEffects::new(ctx.game_dir())?,
-      registered: load_registered(ctx.game_dir()).unwrap_or_default(),
+      effects: SoundEffects::new(ctx.vfs())?,
+      registered: RefCell::new(register::load_registered(ctx.game_dir()).unwrap_or_default()),
+      broadcast: None,
+      netplay: None,
+      #[cfg(feature = "audience")]
+      audience: match audience::Audience::start(audience::DEFAULT_ADDRESS) {
+        Ok(audience) => Some(audience),
+        Err(err) => {
+          eprintln!("Failed to start audience endpoint: {}", err);
+          None
+        }
+      },
       avatars: [
         Avatars {
           win: ctx.load_ppm("SINVOIT.PPM")?,
@@ -120,17 +181,65 @@ impl<'textures> Application<'textures> {
       ],
     })
   }
-}
 
-fn load_registered(path: &Path) -> Option<String> {
-  let register = std::fs::read(path.join("register.dat")).ok()?;
-  if register.is_empty() {
-    return None;
+  /// Reload assets changed on disk (only when watching assets is enabled). Textures are re-created
+  /// from the updated files; sound samples are replaced on the audio thread. Files which fail to
+  /// load are reported and old assets are kept.
+  fn reload_assets(&mut self, ctx: &mut ApplicationContext<'_, 'textures>) {
+    let mut sounds = false;
+    for name in ctx.changed_assets() {
+      if name.ends_with(".VOC") {
+        sounds = true;
+      } else if let Err(err) = self.reload_asset(ctx, &name) {
+        eprintln!("Failed to reload '{}': {:#}", name, err);
+      }
+    }
+    if sounds {
+      if let Err(err) = self.effects.reload(ctx.vfs()) {
+        eprintln!("Failed to reload sound effects: {:#}", err);
+      }
+    }
   }
-  let len = register[0] as usize;
-  if len < 26 && len < register.len() + 1 {
-    Some(String::from_utf8_lossy(&register[1..1 + len]).into_owned())
-  } else {
-    None
+
+  fn reload_asset(&mut self, ctx: &ApplicationContext<'_, 'textures>, name: &str) -> Result<(), anyhow::Error> {
+    const AVATARS: [[&str; 3]; 4] = [
+      ["SINVOIT.PPM", "SINDRAW.PPM", "SINLOSE.PPM"],
+      ["PUNVOIT.PPM", "PUNDRAW.PPM", "PUNLOSE.PPM"],
+      ["VIHVOIT.PPM", "VIHDRAW.PPM", "VIHLOSE.PPM"],
+      ["KELVOIT.PPM", "KELDRAW.PPM", "KELLOSE.PPM"],
+    ];
+    match name {
+      "TITLEBE.SPY" => self.title = ctx.load_spy(name)?,
+      "MAIN3.SPY" => self.main_menu = ctx.load_spy(name)?,
+      "OPTIONS5.SPY" => self.options_menu = ctx.load_spy(name)?,
+      "LEVSELEC.SPY" => self.levels_menu = ctx.load_spy(name)?,
+      "KEYS.SPY" => self.keys = ctx.load_spy(name)?,
+      "SHOPPIC.SPY" => self.shop = ctx.load_spy(name)?,
+      "SIKA.SPY" => self.glyphs = ctx.load_glyphs(name)?,
+      "FONTTI.FON" => self.font = ctx.load_font(name)?,
+      "INFO1.SPY" => self.info[0] = ctx.load_spy(name)?,
+      "INFO3.SPY" => self.info[1] = ctx.load_spy(name)?,
+      "SHAPET.SPY" => self.info[2] = ctx.load_spy(name)?,
+      "INFO2.SPY" => self.info[3] = ctx.load_spy(name)?,
+      "CODES.SPY" => self.codes = ctx.load_spy(name)?,
+      "IDENTIFW.SPY" => self.select_players = ctx.load_spy(name)?,
+      "PLAYERS.SPY" => self.players = ctx.load_spy(name)?,
+      "GAMEOVER.SPY" => self.game_over = ctx.load_spy(name)?,
+      "CONGRATU.SPY" => self.game_win = ctx.load_spy(name)?,
+      "FINAL.SPY" => self.r#final = ctx.load_spy(name)?,
+      "HALLOFFA.SPY" => self.halloffa = ctx.load_spy(name)?,
+      _ => {
+        for (avatars, names) in self.avatars.iter_mut().zip(AVATARS.iter()) {
+          if name == names[0] {
+            avatars.win = ctx.load_ppm(name)?;
+          } else if name == names[1] {
+            avatars.draw = ctx.load_ppm(name)?;
+          } else if name == names[2] {
+            avatars.lose = ctx.load_ppm(name)?;
+          }
+        }
+      }
+    }
+    Ok(())
   }
 }
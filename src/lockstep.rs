@@ -0,0 +1,268 @@
+//! Input scheduling for the lockstep simulation. Every command is scheduled for a specific tick
+//! (current tick plus the input delay), and simulation only advances to the tick once inputs of all
+//! players for that tick are known. Local players confirm their inputs immediately; remote peers
+//! confirm them once their messages arrive. Keys held down are scheduled the same way, so every peer
+//! sees the same keys held at every tick.
+use crate::keys::Key;
+use crate::netplay::{RemotePlayers, CHECKSUM_INTERVAL};
+use crate::options::{Options, StallPolicy};
+use crate::world::World;
+use thiserror::Error;
+
+/// For how many ticks simulation waits for the late player before skipping their inputs (with
+/// the `Skip` policy); 2 seconds
+const STALL_TIMEOUT: usize = 100;
+
+/// Maximum amount of commands a player can issue for a single tick
+const MAX_INPUTS_PER_TICK: usize = 8;
+
+/// For how many input ticks (2 seconds) every player needs to hold the stop key to restart the round
+pub const RESTART_VOTE_TICKS: usize = 50;
+
+/// Input which could not come from a legitimate client
+#[derive(Error, Debug)]
+pub enum InputViolation {
+  #[error("input for tick {0} arrived after the tick was simulated")]
+  Late(usize),
+  #[error("too many inputs for tick {0}")]
+  Flood(usize),
+  #[error("key pressed twice without being released at tick {0}")]
+  Repeat(usize),
+}
+
+pub struct InputSchedule {
+  /// Delay between the key press and the tick the command is applied at
+  delay: usize,
+  policy: StallPolicy,
+  /// Scheduled commands: tick, player and key
+  commands: Vec<(usize, usize, Key)>,
+  /// Keys held down by each player (as a bitmask indexed by `Key`) and the tick they are held from
+  held: Vec<Vec<(usize, u8)>>,
+  /// Last tick inputs of each player are known for
+  confirmed: Vec<usize>,
+  /// For how many ticks simulation is stalled
+  stalled: usize,
+  /// Last tick commands were taken for
+  applied: Option<usize>,
+}
+
+impl InputSchedule {
+  pub fn new(players: usize, delay: u8, policy: StallPolicy) -> Self {
+    let delay = usize::from(delay);
+    InputSchedule {
+      delay,
+      policy,
+      commands: Vec::new(),
+      held: vec![Vec::new(); players],
+      // Nothing could be pressed during the first `delay` ticks
+      confirmed: vec![delay; players],
+      stalled: 0,
+      applied: None,
+    }
+  }
+
+  /// Schedule command pressed at the given tick. Commands which arrive too late or too often are
+  /// rejected, as well as the key pressed again at the same tick (it has to be released first, which
+  /// takes longer than a tick).
+  pub fn push(&mut self, tick: usize, player: usize, key: Key) -> Result<(), InputViolation> {
+    let at = tick + self.delay;
+    if self.applied.is_some_and(|applied| at <= applied) {
+      return Err(InputViolation::Late(at));
+    }
+    let pressed = self
+      .commands
+      .iter()
+      .filter(|&&(other, other_player, _)| other == at && other_player == player);
+    if pressed.clone().any(|&(_, _, other)| other == key) {
+      return Err(InputViolation::Repeat(at));
+    }
+    if pressed.count() >= MAX_INPUTS_PER_TICK {
+      return Err(InputViolation::Flood(at));
+    }
+    self.commands.push((at, player, key));
+    Ok(())
+  }
+
+  /// All inputs of the player pressed up to the given tick are scheduled
+  pub fn confirm(&mut self, player: usize, tick: usize) {
+    let confirmed = &mut self.confirmed[player];
+    *confirmed = (*confirmed).max(tick + self.delay);
+  }
+
+  /// Player left the game: simulation never waits for their inputs again
+  pub fn leave(&mut self, player: usize) {
+    self.confirmed[player] = usize::MAX;
+  }
+
+  /// Schedule keys held down at the given tick. Keys sampled again at the same tick (while the
+  /// simulation is stalled) replace the previous ones.
+  pub fn hold(&mut self, tick: usize, player: usize, mask: u8) {
+    let at = tick + self.delay;
+    let held = &mut self.held[player];
+    held.retain(|&(other, _)| other != at);
+    held.push((at, mask));
+  }
+
+  /// Keys the player holds down at the given tick, as a bitmask indexed by `Key`
+  pub fn held(&mut self, tick: usize, player: usize) -> u8 {
+    let held = &mut self.held[player];
+    // Only the last of the keys which are due matters
+    let due = held.iter().take_while(|&&(at, _)| at <= tick).count();
+    held.drain(..due.saturating_sub(1));
+    match held.first() {
+      Some(&(at, mask)) if at <= tick => mask,
+      _ => 0,
+    }
+  }
+
+  /// Check if simulation can advance to the given tick. Tracks how long the simulation is stalled
+  /// and, depending on the policy, gives up on late players after a while.
+  pub fn ready(&mut self, tick: usize) -> bool {
+    if self.confirmed.iter().all(|&confirmed| confirmed >= tick) {
+      self.stalled = 0;
+      return true;
+    }
+    self.stalled += 1;
+    if self.policy == StallPolicy::Skip && self.stalled >= STALL_TIMEOUT {
+      for confirmed in &mut self.confirmed {
+        *confirmed = (*confirmed).max(tick);
+      }
+      self.stalled = 0;
+      return true;
+    }
+    false
+  }
+
+  /// Players simulation is waiting for
+  pub fn late_players(&self, tick: usize) -> impl Iterator<Item = usize> + '_ {
+    (0..self.confirmed.len()).filter(move |&player| self.confirmed[player] < tick)
+  }
+
+  /// Take commands which are due at the given tick (including the ones which were due earlier), in
+  /// the order they were pressed. Direction changes are always taken at the next tick, other
+  /// commands only if `all` is set (classic input handles them every other tick only).
+  ///
+  /// Player changes direction at most once per tick, so facing set by a quick successive press is
+  /// not lost: the press is kept for the next tick, together with all the commands pressed after it.
+  pub fn take_due(&mut self, tick: usize, all: bool) -> Vec<(usize, Key)> {
+    self.applied = Some(tick);
+    let mut due = Vec::new();
+    let mut turned = vec![false; self.confirmed.len()];
+    let mut deferred = vec![false; self.confirmed.len()];
+    self.commands.retain(|&(at, player, key)| {
+      if at > tick {
+        return true;
+      }
+      let take = !deferred[player]
+        && if key.is_direction() {
+          !std::mem::replace(&mut turned[player], true)
+        } else {
+          all
+        };
+      if take {
+        due.push((player, key));
+      } else {
+        deferred[player] = true;
+      }
+      !take
+    });
+    due
+  }
+}
+
+/// Lockstep simulation of the round, as seen by one peer: input schedule and restart votes. Both the
+/// game screen and the dedicated host advance the world through it, so every peer applies the same
+/// inputs at the same ticks.
+pub struct Lockstep {
+  pub schedule: InputSchedule,
+  /// How long (in input ticks) each player is holding the stop key to vote for the restart
+  pub restart_votes: Vec<usize>,
+  delay: u8,
+  policy: StallPolicy,
+  /// Commands other than direction changes are only handled every other tick
+  classic_input: bool,
+}
+
+/// Inputs applied at the tick the world has advanced to
+pub struct AppliedInputs {
+  /// Players whose commands were applied
+  pub players: Vec<usize>,
+  /// Held keys and restart votes were sampled at this tick
+  pub input_tick: bool,
+}
+
+impl Lockstep {
+  pub fn new(players: usize, delay: u8, options: &Options) -> Self {
+    Lockstep {
+      schedule: InputSchedule::new(players, delay, options.stall_policy),
+      restart_votes: vec![0; players],
+      delay,
+      policy: options.stall_policy,
+      classic_input: options.classic_input,
+    }
+  }
+
+  /// Start the round over: nothing is scheduled and nobody is voting
+  pub fn restart(&mut self) {
+    let players = self.restart_votes.len();
+    self.schedule = InputSchedule::new(players, self.delay, self.policy);
+    self.restart_votes.iter_mut().for_each(|votes| *votes = 0);
+  }
+
+  /// Collect inputs for the next tick of the world. Returns `true` if simulation can advance to it;
+  /// local players are never late.
+  pub fn poll(&mut self, world: &World, netplay: Option<&RemotePlayers>) -> bool {
+    let next = world.round_counter + 1;
+    for player in 0..world.players.len() {
+      if !netplay.is_some_and(|netplay| netplay.is_remote(player)) {
+        self.schedule.confirm(player, next);
+      }
+    }
+    if let Some(netplay) = netplay {
+      netplay.receive(&mut self.schedule);
+    }
+    self.schedule.ready(next)
+  }
+
+  /// Apply inputs due at the tick the world has just advanced to: commands, held keys and restart
+  /// votes. Checksum of the network game is taken once all inputs of the tick are applied, same as on
+  /// every other peer.
+  pub fn apply_inputs(&mut self, world: &mut World, netplay: Option<&RemotePlayers>) -> AppliedInputs {
+    let tick = world.round_counter;
+    // Original game only handles commands every other tick; direction changes are applied right
+    // away, so turning doesn't feel sluggish
+    let input_tick = tick % 2 == 0;
+    let mut players = Vec::new();
+    for (player, key) in self.schedule.take_due(tick, input_tick || !self.classic_input) {
+      players.push(player);
+      world.player_action(player, key);
+    }
+    // Hold durations are counted in input ticks, so held keys are always sampled every other tick
+    if input_tick {
+      for player in 0..world.players.len() {
+        let held = self.schedule.held(tick, player);
+        for key in Key::all_keys() {
+          world.player_key_held(player, key, key.in_mask(held));
+        }
+        let votes = &mut self.restart_votes[player];
+        *votes = if Key::Stop.in_mask(held) { *votes + 1 } else { 0 };
+      }
+    }
+    if let Some(netplay) = netplay {
+      if tick % CHECKSUM_INTERVAL == 0 {
+        netplay.record_checksum(tick, world.checksum());
+      }
+    }
+    AppliedInputs { players, input_tick }
+  }
+
+  /// Some player is holding the stop key
+  pub fn voting(&self) -> bool {
+    self.restart_votes.iter().any(|votes| *votes > 0)
+  }
+
+  /// Every player held the stop key long enough to restart the round
+  pub fn restart_voted(&self) -> bool {
+    self.restart_votes.iter().all(|votes| *votes >= RESTART_VOTE_TICKS)
+  }
+}
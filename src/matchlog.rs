@@ -0,0 +1,233 @@
+//! Log of played multiplayer tournaments
+use crate::options::{Options, WinCondition};
+use crate::world::player::PlayerComponent;
+use crate::world::settlement::{PlayerSettlement, RoundSettlement};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("Failed to write match log to '{path}'")]
+pub struct MatchLogSaveError {
+  #[source]
+  source: std::io::Error,
+  path: PathBuf,
+}
+
+/// Result of a single round
+pub struct RoundResult {
+  pub level: String,
+  /// Cash changes of each player; also tells who has won the round
+  pub settlement: RoundSettlement,
+}
+
+/// Final standing of a player
+pub struct Standing {
+  pub name: String,
+  /// Place, 0 is the first place
+  pub rank: usize,
+  pub rounds_win: u32,
+  pub cash: u32,
+  pub kills: u32,
+}
+
+/// Compute final rank of each player (0 is the first place). Players are compared by the win
+/// condition first; ties are broken by amount of rounds won, then money, then kills. Players
+/// with identical results share the same rank.
+pub fn compute_ranks(players: &[PlayerComponent], win: WinCondition) -> Vec<usize> {
+  let scorefn = |player: &PlayerComponent| {
+    let primary = match win {
+      WinCondition::ByWins => player.rounds_win,
+      WinCondition::ByMoney => player.cash,
+    };
+    (primary, player.rounds_win, player.cash, player.kills)
+  };
+  players
+    .iter()
+    .map(|player| {
+      let score = scorefn(player);
+      players.iter().filter(|other| scorefn(other) > score).count()
+    })
+    .collect()
+}
+
+/// Tournament record, appended to `matches.log` (human-readable) and `matches.json` (one JSON
+/// object per line) once tournament is over.
+pub struct MatchLog {
+  /// Seconds since UNIX epoch when tournament has started
+  started: u64,
+  options: String,
+  players: Vec<String>,
+  rounds: Vec<RoundResult>,
+}
+
+impl MatchLog {
+  pub fn new(options: &Options, players: Vec<String>) -> Self {
+    let win = match options.win {
+      WinCondition::ByWins => "wins",
+      WinCondition::ByMoney => "money",
+    };
+    MatchLog {
+      started: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs()),
+      options: format!(
+        "rounds={} cash={} treasures={} round_time={} darkness={} win={}",
+        options.rounds,
+        options.cash,
+        options.treasures,
+        options.round_time.as_secs(),
+        options.darkness,
+        win
+      ),
+      players,
+      rounds: Vec::new(),
+    }
+  }
+
+  pub fn record_round(&mut self, result: RoundResult) {
+    self.rounds.push(result);
+  }
+
+  /// Append tournament record to the log files in the game directory
+  pub fn save(&self, game_dir: &Path, standings: &[Standing]) -> Result<(), MatchLogSaveError> {
+    append(&game_dir.join("matches.log"), &self.to_text(standings))?;
+    append(&game_dir.join("matches.json"), &self.to_json(standings))?;
+    Ok(())
+  }
+
+  fn to_text(&self, standings: &[Standing]) -> String {
+    let mut out = String::new();
+    writeln!(out, "=== {} ===", format_date(self.started)).unwrap();
+    writeln!(out, "Options: {}", self.options).unwrap();
+    writeln!(out, "Players: {}", self.players.join(", ")).unwrap();
+    for (idx, round) in self.rounds.iter().enumerate() {
+      let winners = round.settlement.winners();
+      let winners: Vec<&str> = winners.iter().map(|w| self.players[*w].as_str()).collect();
+      let winners = if winners.is_empty() {
+        "-".to_owned()
+      } else {
+        winners.join(", ")
+      };
+      writeln!(out, "Round {:<2} {:<12} won by {}", idx + 1, round.level, winners).unwrap();
+    }
+    writeln!(out, "Final standings:").unwrap();
+    let mut sorted: Vec<&Standing> = standings.iter().collect();
+    sorted.sort_by_key(|standing| standing.rank);
+    for standing in sorted {
+      writeln!(
+        out,
+        "  {}. {:<24} wins {:<3} money {:<7} kills {}",
+        standing.rank + 1,
+        standing.name,
+        standing.rounds_win,
+        standing.cash,
+        standing.kills
+      )
+      .unwrap();
+    }
+    writeln!(out).unwrap();
+    out
+  }
+
+  fn to_json(&self, standings: &[Standing]) -> String {
+    let mut out = String::new();
+    write!(
+      out,
+      "{{\"date\":{},\"started\":{},\"options\":{},\"players\":[",
+      json_string(&format_date(self.started)),
+      self.started,
+      json_string(&self.options)
+    )
+    .unwrap();
+    out.push_str(&join(self.players.iter().map(|name| json_string(name))));
+    out.push_str("],\"rounds\":[");
+    out.push_str(&join(self.rounds.iter().map(|round| {
+      let players = &round.settlement.players;
+      let amounts = |amount: fn(&PlayerSettlement) -> u32| join(players.iter().map(|player| amount(player).to_string()));
+      format!(
+        "{{\"level\":{},\"winners\":[{}],\"cash\":[{}],\"collected\":[{}],\"interest\":[{}],\"inherited\":[{}],\"bonus\":[{}]}}",
+        json_string(&round.level),
+        join(round.settlement.winners().iter().map(ToString::to_string)),
+        amounts(PlayerSettlement::after),
+        amounts(|player| if player.lost { 0 } else { player.collected }),
+        amounts(|player| player.interest),
+        amounts(|player| player.inherited),
+        amounts(|player| player.bonus)
+      )
+    })));
+    out.push_str("],\"standings\":[");
+    out.push_str(&join(standings.iter().map(|standing| {
+      format!(
+        "{{\"name\":{},\"rank\":{},\"wins\":{},\"cash\":{},\"kills\":{}}}",
+        json_string(&standing.name),
+        standing.rank + 1,
+        standing.rounds_win,
+        standing.cash,
+        standing.kills
+      )
+    })));
+    out.push_str("]}\n");
+    out
+  }
+}
+
+fn append(path: &Path, text: &str) -> Result<(), MatchLogSaveError> {
+  std::fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)
+    .and_then(|mut file| file.write_all(text.as_bytes()))
+    .map_err(|source| MatchLogSaveError {
+      path: path.to_owned(),
+      source,
+    })
+}
+
+fn join(items: impl Iterator<Item = String>) -> String {
+  items.collect::<Vec<_>>().join(",")
+}
+
+/// Quote the string as JSON string literal
+pub fn json_string(value: &str) -> String {
+  let mut out = String::with_capacity(value.len() + 2);
+  out.push('"');
+  for ch in value.chars() {
+    match ch {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+      ch => out.push(ch),
+    }
+  }
+  out.push('"');
+  out
+}
+
+/// Format UNIX timestamp as a UTC date
+fn format_date(timestamp: u64) -> String {
+  let days = (timestamp / 86400) as i64;
+  let secs = timestamp % 86400;
+
+  // Convert days since epoch to the civil date
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097);
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + i64::from(month <= 2);
+  format!(
+    "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+    year,
+    month,
+    day,
+    secs / 3600,
+    secs / 60 % 60,
+    secs % 60
+  )
+}
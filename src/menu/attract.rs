@@ -0,0 +1,171 @@
+//! Attract mode: when nobody touches the keyboard on the title screen, we show credits and a demo
+//! game played by bots.
+use crate::context::{Animation, ApplicationContext, PostEffects};
+use crate::error::ApplicationError::SdlError;
+use crate::keys::{Key, KeyBindings};
+use crate::menu::hud::Hud;
+use crate::options::Options;
+use crate::world::equipment::Equipment;
+use crate::world::map::{LevelMap, LevelMeta};
+use crate::world::player::PlayerComponent;
+use crate::world::rng::WorldRng;
+use crate::world::{Viewer, World};
+use crate::Application;
+use rand::prelude::*;
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::pixels::Color;
+use std::time::Duration;
+
+const CREDITS: [&str; 9] = [
+  "MINEBOMBERS RELOADED",
+  "",
+  "Original MineBombers 3.11",
+  "by Skitso Productions",
+  "",
+  "Rust re-implementation",
+  "by Ivan Dubrov",
+  "",
+  "Thanks for playing!",
+];
+
+const CREDITS_COLOR: Color = Color::RGB(200, 200, 200);
+
+/// Distance between lines of credits
+const CREDITS_LINE_HEIGHT: i32 = 16;
+
+/// Amount of bots playing the demo
+const DEMO_PLAYERS: usize = 4;
+
+/// Maximum duration of the demo, in ticks
+const DEMO_TICKS: usize = 1500;
+
+/// Bot playing the demo game. Bot simply walks in a random direction, changing it from time to time,
+/// and occasionally drops bombs.
+struct Bot {
+  /// Amount of input ticks before the next direction change
+  remaining: u16,
+}
+
+impl Bot {
+  fn act(&mut self, world: &mut World, player: usize, rng: &mut impl Rng) {
+    if self.remaining == 0 {
+      let key = *[Key::Left, Key::Right, Key::Up, Key::Down].choose(rng).unwrap();
+      world.player_action(player, key);
+      self.remaining = rng.gen_range(10..40);
+    }
+    self.remaining -= 1;
+    if rng.gen_range(0..40) == 0 {
+      world.player_action(player, Key::Bomb);
+    }
+  }
+}
+
+impl Application<'_> {
+  /// Scroll credits over the title screen. Returns the key if any was pressed.
+  pub(super) fn credits_scroll(
+    &self,
+    ctx: &mut ApplicationContext,
+  ) -> Result<Option<(Scancode, Keycode)>, anyhow::Error> {
+    let render = |ctx: &mut ApplicationContext, frame: usize| {
+      ctx.with_render_context(|canvas| {
+        canvas.copy(&self.title.texture, None, None).map_err(SdlError)?;
+        let top = 480 - frame as i32;
+        for (idx, line) in CREDITS.iter().enumerate() {
+          let x = 320 - 4 * line.len() as i32;
+          let y = top + CREDITS_LINE_HEIGHT * idx as i32;
+          self.font.render(canvas, x, y, CREDITS_COLOR, line)?;
+        }
+        Ok(())
+      })
+    };
+
+    render(ctx, 0)?;
+    ctx.transition(Animation::FadeUp)?;
+    // Scroll one pixel per frame, until the last line is gone
+    let frames = 480 + CREDITS_LINE_HEIGHT as u64 * CREDITS.len() as u64;
+    let key = ctx.wait_key_pressed_idle(Some(Duration::from_millis(frames * 1000 / 60)), |ctx, frame| {
+      render(ctx, frame + 1)?;
+      ctx.present()
+    })?;
+    ctx.transition(Animation::FadeDown)?;
+    Ok(key)
+  }
+
+  /// Play a demo game between bots on a random level. Returns the key if any was pressed.
+  pub(super) fn attract_demo(
+    &self,
+    ctx: &mut ApplicationContext,
+  ) -> Result<Option<(Scancode, Keycode)>, anyhow::Error> {
+    let options = Options::default();
+    let mut players: Vec<PlayerComponent> = (0..DEMO_PLAYERS)
+      .map(|idx| {
+        let mut player = PlayerComponent::new(format!("Bot {}", idx + 1), KeyBindings::default(), &options);
+        player.inventory[Equipment::SmallBomb] = 50;
+        player.selection = Equipment::SmallBomb;
+        player
+      })
+      .collect();
+    let mut world_rng = WorldRng::random();
+    let mut level = LevelMap::random_map(options.treasures, &mut world_rng);
+    level.generate_entrances(DEMO_PLAYERS as u8, &mut world_rng);
+    let mut world = World::create(
+      level,
+      &mut players,
+      false,
+      &options,
+      false,
+      LevelMeta::default(),
+      world_rng,
+    );
+    let mut bots: Vec<Bot> = (0..DEMO_PLAYERS).map(|_| Bot { remaining: 0 }).collect();
+    let mut rng = thread_rng();
+
+    ctx.with_render_context(|canvas| self.render_game_screen(canvas, &world))?;
+    ctx.transition(Animation::FadeUp)?;
+    // Demo is shown without the side panels
+    let mut hud = Hud::new(0);
+
+    let mut pressed = None;
+    for _ in 0..DEMO_TICKS {
+      world.tick();
+      if world.round_counter % 2 == 0 {
+        for (player, bot) in bots.iter_mut().enumerate() {
+          bot.act(&mut world, player, &mut rng);
+        }
+      }
+      hud.track(&mut world.update, world.round_counter);
+      let max_splatters = ctx.post_effect_limits().max_splatters;
+      ctx.with_render_context(|canvas| {
+        self.render_updates(canvas, &mut world, Viewer::Shared, max_splatters)?;
+        self.render_hud(canvas, &mut hud, &world)
+      })?;
+      // Demo is silent and nobody watches the events
+      world.effects.clear();
+      world.events.clear();
+
+      for event in ctx.poll_iter() {
+        if let Event::KeyDown {
+          scancode: Some(scancode),
+          keycode: Some(keycode),
+          ..
+        } = event
+        {
+          pressed = Some((scancode, keycode));
+        }
+      }
+      if pressed.is_some() || world.is_end_of_round() {
+        break;
+      }
+
+      let mut effects = PostEffects::shake(world.shake_offset());
+      if world.flash {
+        effects = effects.combine(PostEffects::flash());
+      }
+      ctx.present_with(effects)?;
+      std::thread::sleep(Duration::from_millis(20));
+    }
+    ctx.transition(Animation::FadeDown)?;
+    Ok(pressed)
+  }
+}
@@ -0,0 +1,79 @@
+//! Level progress screen shown at the end of a campaign game: completion times of the last game
+//! along with best times and medals for each level.
+use crate::campaign::{format_time, CampaignManifest, CampaignProgress, Medal, CAMPAIGN_LEVELS};
+use crate::context::{Animation, ApplicationContext};
+use crate::palette::PaletteRole;
+use crate::Application;
+use sdl2::pixels::Color;
+use std::time::Duration;
+
+/// Vertical position of the first level row
+const PROGRESS_TOP: i32 = 100;
+
+/// Distance between level rows
+const PROGRESS_LINE_HEIGHT: i32 = 16;
+
+impl Application<'_> {
+  /// Show completion times and medals for every campaign level. `times` are completion times of the
+  /// game just played.
+  pub(super) fn campaign_progress(
+    &self,
+    ctx: &mut ApplicationContext,
+    manifest: &CampaignManifest,
+    progress: &CampaignProgress,
+    times: &[Option<Duration>; CAMPAIGN_LEVELS],
+    assists: &[bool; CAMPAIGN_LEVELS],
+  ) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let roles = &self.main_menu.roles;
+      self
+        .font
+        .render(canvas, 220, 60, roles[PaletteRole::TextPrimary], "Campaign progress")?;
+      self.font.render(
+        canvas,
+        140,
+        PROGRESS_TOP - PROGRESS_LINE_HEIGHT,
+        roles[PaletteRole::TextSecondary],
+        "Level  Time   Par    Best   Medal",
+      )?;
+      for (level, time) in times.iter().enumerate() {
+        let y = PROGRESS_TOP + PROGRESS_LINE_HEIGHT * level as i32;
+        let par = manifest.par[level];
+        let mut time = time.map_or_else(|| "-".to_owned(), format_time);
+        if assists[level] {
+          time.push('*');
+        }
+        let best = progress.best(level);
+        let text = format!(
+          "{:<7}{:<7}{:<7}{:<7}",
+          level + 1,
+          time,
+          format_time(par),
+          best.map_or_else(|| "-".to_owned(), format_time)
+        );
+        self
+          .font
+          .render(canvas, 140, y, roles[PaletteRole::TextPrimary], &text)?;
+        if let Some(best) = best {
+          let medal = Medal::award(best, par);
+          self.font.render(canvas, 140 + 8 * 28, y, medal.color(), medal.name())?;
+        }
+      }
+      let legend_y = PROGRESS_TOP + PROGRESS_LINE_HEIGHT * CAMPAIGN_LEVELS as i32 + 8;
+      self.font.render(
+        canvas,
+        140,
+        legend_y,
+        roles[PaletteRole::TextSecondary],
+        "* completed with an assist",
+      )?;
+      Ok(())
+    })?;
+    ctx.transition(Animation::FadeUp)?;
+    ctx.wait_key_pressed();
+    ctx.transition(Animation::FadeDown)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,61 @@
+//! Modal confirmation dialog for the destructive actions (deleting players, quitting the game). The
+//! dialog is shown over the dimmed current screen, which is restored once the dialog is answered.
+use crate::context::ApplicationContext;
+use crate::error::ApplicationError::SdlError;
+use crate::keys::MenuAction;
+use crate::palette::PaletteRole;
+use crate::{Application, SCREEN_HEIGHT, SCREEN_WIDTH};
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+const ANSWER_HINT: &str = "Y - yes, N - no";
+
+impl Application<'_> {
+  /// Ask the player to confirm the action (Y or Enter to confirm, N or Escape to cancel)
+  pub(super) fn confirm(&self, ctx: &mut ApplicationContext, question: &str) -> Result<bool, anyhow::Error> {
+    let roles = &self.main_menu.roles;
+    let width = (question.len().max(ANSWER_HINT.len()) * 8) as u32 + 32;
+    let height = 48;
+    let left = (SCREEN_WIDTH - width) as i32 / 2;
+    let top = (SCREEN_HEIGHT - height) as i32 / 2;
+    ctx.present_modal(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas
+        .fill_rect(Rect::new(left, top, width, height))
+        .map_err(SdlError)?;
+      canvas.set_draw_color(roles[PaletteRole::Muted]);
+      canvas
+        .draw_rect(Rect::new(left, top, width, height))
+        .map_err(SdlError)?;
+      let x = |text: &str| left + (width as i32 - 8 * text.len() as i32) / 2;
+      self
+        .font
+        .render(canvas, x(question), top + 12, roles[PaletteRole::TextPrimary], question)?;
+      self
+        .font
+        .render(canvas, x(ANSWER_HINT), top + 28, roles[PaletteRole::Muted], ANSWER_HINT)?;
+      Ok(())
+    })?;
+    let answer = wait_yes_no(ctx);
+    ctx.present()?;
+    Ok(answer)
+  }
+}
+
+/// Wait for the answer to the yes / no question
+pub(super) fn wait_yes_no(ctx: &mut ApplicationContext) -> bool {
+  loop {
+    let (scancode, _) = ctx.wait_key_pressed();
+    match scancode {
+      Scancode::Y => return true,
+      Scancode::N => return false,
+      _ => {}
+    }
+    match ctx.menu_action(scancode) {
+      MenuAction::Select => return true,
+      MenuAction::Back => return false,
+      _ => {}
+    }
+  }
+}
@@ -0,0 +1,592 @@
+use crate::context::{Animation, ApplicationContext};
+use crate::error::ApplicationError::SdlError;
+use crate::glyphs::Glyph;
+use crate::keys::MenuAction;
+use crate::menu::options::Autosave;
+use crate::options::{
+  ArmorModel, LevelRotation, Options, StallPolicy, StartingLoadout, TransitionSpeed, IDLE_TIMEOUT_STEP, MAX_CLONE_CAP,
+  MAX_CONTINUES, MAX_IDLE_TIMEOUT, MAX_INPUT_DELAY, MAX_LIVES, MAX_MONSTER_CAP, MIN_LIVES, MONSTER_CAP_STEP,
+};
+use crate::palette::PaletteRole;
+use crate::{Application, SCREEN_WIDTH};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use std::convert::TryInto;
+
+/// Options which are not present in the original game. Since options menu texture has a fixed set
+/// of items, these are rendered on a separate, text-only, page.
+#[derive(Debug, Clone, Copy, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[repr(usize)]
+enum ExtendedOption {
+  CloneCap,
+  CloneCarryGold,
+  MonsterLoot,
+  MonsterCap,
+  Traders,
+  FastForward,
+  Autosave,
+  Transitions,
+  Photosensitive,
+  SkipIntro,
+  RealisticDamage,
+  ArmorModel,
+  Regeneration,
+  Widescreen,
+  KillFeed,
+  SatellitePreview,
+  ClassicInput,
+  PushChains,
+  Rockfall,
+  BiomassHearts,
+  RadioPing,
+  InputDelay,
+  StallPolicy,
+  MuffledFogSounds,
+  LevelRotation,
+  Lives,
+  Continues,
+  Heatmaps,
+  RecordRounds,
+  Loadout,
+  IdleTimeout,
+  SmoothMovement,
+  /// Not an option, but a link to the asset pack manager
+  AssetPacks,
+  /// Not an option, but a link to the registration screen
+  Registration,
+}
+
+impl ExtendedOption {
+  const FIRST: ExtendedOption = ExtendedOption::CloneCap;
+  const LAST: ExtendedOption = ExtendedOption::Registration;
+
+  fn next(self) -> ExtendedOption {
+    let pos: usize = self.into();
+    (pos + 1).try_into().unwrap_or(Self::FIRST)
+  }
+
+  fn prev(self) -> ExtendedOption {
+    let pos: usize = self.into();
+    pos.checked_sub(1).map_or(Self::LAST, |pos| pos.try_into().unwrap())
+  }
+
+  fn all_options() -> impl Iterator<Item = ExtendedOption> {
+    (0..=usize::from(Self::LAST)).map(|v| v.try_into().unwrap())
+  }
+
+  fn label(self) -> &'static str {
+    match self {
+      ExtendedOption::CloneCap => "Clones per player",
+      ExtendedOption::CloneCarryGold => "Clones carry gold",
+      ExtendedOption::MonsterLoot => "Monster loot",
+      ExtendedOption::MonsterCap => "Live monsters limit",
+      ExtendedOption::Traders => "Wandering trader",
+      ExtendedOption::FastForward => "Single player fast-forward",
+      ExtendedOption::Autosave => "Save options on change",
+      ExtendedOption::Transitions => "Screen transitions",
+      ExtendedOption::Photosensitive => "Photosensitive mode",
+      ExtendedOption::SkipIntro => "Skip title screen",
+      ExtendedOption::RealisticDamage => "Realistic damage",
+      ExtendedOption::ArmorModel => "Armor",
+      ExtendedOption::Regeneration => "Health regeneration",
+      ExtendedOption::Widescreen => "Widescreen",
+      ExtendedOption::KillFeed => "Kill feed",
+      ExtendedOption::SatellitePreview => "Satellite preview",
+      ExtendedOption::ClassicInput => "Classic input timing",
+      ExtendedOption::PushChains => "Push chains",
+      ExtendedOption::Rockfall => "Rockfall",
+      ExtendedOption::BiomassHearts => "Biomass hearts",
+      ExtendedOption::RadioPing => "Radio bomb ping",
+      ExtendedOption::InputDelay => "Network input delay",
+      ExtendedOption::StallPolicy => "Late network player",
+      ExtendedOption::MuffledFogSounds => "Muffle sounds in fog",
+      ExtendedOption::LevelRotation => "Level rotation",
+      ExtendedOption::Lives => "Single player lives",
+      ExtendedOption::Continues => "Single player continues",
+      ExtendedOption::Heatmaps => "Export round heatmaps",
+      ExtendedOption::RecordRounds => "Record rounds",
+      ExtendedOption::Loadout => "Starting loadout",
+      ExtendedOption::IdleTimeout => "Pause for idle player",
+      ExtendedOption::SmoothMovement => "Smooth movement",
+      ExtendedOption::AssetPacks => "Asset packs",
+      ExtendedOption::Registration => "Registration",
+    }
+  }
+
+  fn value(self, options: &Options) -> String {
+    match self {
+      ExtendedOption::CloneCap if options.clone_cap == 0 => "Unlimited".to_owned(),
+      ExtendedOption::CloneCap => options.clone_cap.to_string(),
+      ExtendedOption::CloneCarryGold => on_off(options.clone_carry_gold),
+      ExtendedOption::MonsterLoot => on_off(options.monster_loot),
+      ExtendedOption::MonsterCap if options.monster_cap == 0 => "Unlimited".to_owned(),
+      ExtendedOption::MonsterCap => options.monster_cap.to_string(),
+      ExtendedOption::Traders => on_off(options.traders),
+      ExtendedOption::FastForward => on_off(options.fast_forward),
+      ExtendedOption::Autosave => on_off(options.autosave),
+      ExtendedOption::Transitions => match options.transitions {
+        TransitionSpeed::Classic => "Classic",
+        TransitionSpeed::Fast => "Fast",
+        TransitionSpeed::Instant => "Instant",
+      }
+      .to_owned(),
+      ExtendedOption::Photosensitive => on_off(options.photosensitive),
+      ExtendedOption::SkipIntro => on_off(options.skip_intro),
+      ExtendedOption::RealisticDamage => on_off(options.realistic_damage),
+      ExtendedOption::ArmorModel => match options.armor_model {
+        ArmorModel::Health => "Extra health",
+        ArmorModel::Reduction => "Damage reduction",
+      }
+      .to_owned(),
+      ExtendedOption::Regeneration => on_off(options.regeneration),
+      ExtendedOption::Widescreen => on_off(options.widescreen),
+      ExtendedOption::KillFeed => on_off(options.kill_feed),
+      ExtendedOption::SatellitePreview => on_off(options.satellite_preview),
+      ExtendedOption::ClassicInput => on_off(options.classic_input),
+      ExtendedOption::PushChains => on_off(options.push_chains),
+      ExtendedOption::Rockfall => on_off(options.rockfall),
+      ExtendedOption::BiomassHearts => on_off(options.biomass_hearts),
+      ExtendedOption::RadioPing => on_off(options.radio_ping),
+      ExtendedOption::InputDelay if options.input_delay == 0 => "None".to_owned(),
+      ExtendedOption::InputDelay => format!("{} ticks", options.input_delay),
+      ExtendedOption::StallPolicy => match options.stall_policy {
+        StallPolicy::Wait => "Wait",
+        StallPolicy::Skip => "Skip after a while",
+      }
+      .to_owned(),
+      ExtendedOption::MuffledFogSounds => on_off(options.muffled_fog_sounds),
+      ExtendedOption::LevelRotation => match options.level_rotation {
+        LevelRotation::Off => "Off (random maps)",
+        LevelRotation::Alphabetical => "All levels, A-Z",
+        LevelRotation::Shuffled => "All levels, shuffled",
+      }
+      .to_owned(),
+      ExtendedOption::Lives => options.lives.to_string(),
+      ExtendedOption::Continues if options.continues == 0 => "None".to_owned(),
+      ExtendedOption::Continues => options.continues.to_string(),
+      ExtendedOption::Heatmaps => on_off(options.heatmaps),
+      ExtendedOption::RecordRounds => on_off(options.capture_rounds),
+      ExtendedOption::Loadout => match options.loadout {
+        StartingLoadout::Shop => "None (visit shop)",
+        StartingLoadout::Basic => "Basic",
+        StartingLoadout::Demolition => "Demolition",
+        StartingLoadout::Tunneler => "Tunneler",
+        StartingLoadout::Custom => "Custom (loadout.cfg)",
+      }
+      .to_owned(),
+      ExtendedOption::IdleTimeout if options.idle_timeout == 0 => "Never".to_owned(),
+      ExtendedOption::IdleTimeout => format!("After {} sec", options.idle_timeout),
+      ExtendedOption::SmoothMovement => on_off(options.smooth_movement),
+      ExtendedOption::AssetPacks | ExtendedOption::Registration => "Press Enter".to_owned(),
+    }
+  }
+
+  fn value_minus(self, options: &mut Options) {
+    match self {
+      ExtendedOption::CloneCap if options.clone_cap > 0 => {
+        options.clone_cap -= 1;
+      }
+      ExtendedOption::CloneCarryGold => {
+        options.clone_carry_gold = !options.clone_carry_gold;
+      }
+      ExtendedOption::MonsterLoot => {
+        options.monster_loot = !options.monster_loot;
+      }
+      ExtendedOption::MonsterCap if options.monster_cap > 0 => {
+        options.monster_cap -= MONSTER_CAP_STEP;
+      }
+      ExtendedOption::Traders => {
+        options.traders = !options.traders;
+      }
+      ExtendedOption::FastForward => {
+        options.fast_forward = !options.fast_forward;
+      }
+      ExtendedOption::Autosave => {
+        options.autosave = !options.autosave;
+      }
+      ExtendedOption::Transitions => {
+        options.transitions = match options.transitions {
+          TransitionSpeed::Classic | TransitionSpeed::Fast => TransitionSpeed::Classic,
+          TransitionSpeed::Instant => TransitionSpeed::Fast,
+        };
+      }
+      ExtendedOption::SkipIntro => {
+        options.skip_intro = !options.skip_intro;
+      }
+      ExtendedOption::RealisticDamage => {
+        options.realistic_damage = !options.realistic_damage;
+      }
+      ExtendedOption::ArmorModel => {
+        options.armor_model = match options.armor_model {
+          ArmorModel::Health => ArmorModel::Reduction,
+          ArmorModel::Reduction => ArmorModel::Health,
+        };
+      }
+      ExtendedOption::Regeneration => {
+        options.regeneration = !options.regeneration;
+      }
+      ExtendedOption::Widescreen => {
+        options.widescreen = !options.widescreen;
+      }
+      ExtendedOption::KillFeed => {
+        options.kill_feed = !options.kill_feed;
+      }
+      ExtendedOption::SatellitePreview => {
+        options.satellite_preview = !options.satellite_preview;
+      }
+      ExtendedOption::ClassicInput => {
+        options.classic_input = !options.classic_input;
+      }
+      ExtendedOption::PushChains => {
+        options.push_chains = !options.push_chains;
+      }
+      ExtendedOption::Rockfall => {
+        options.rockfall = !options.rockfall;
+      }
+      ExtendedOption::BiomassHearts => {
+        options.biomass_hearts = !options.biomass_hearts;
+      }
+      ExtendedOption::RadioPing => {
+        options.radio_ping = !options.radio_ping;
+      }
+      ExtendedOption::InputDelay if options.input_delay > 0 => {
+        options.input_delay -= 1;
+      }
+      ExtendedOption::Lives if options.lives > MIN_LIVES => {
+        options.lives -= 1;
+      }
+      ExtendedOption::Continues if options.continues > 0 => {
+        options.continues -= 1;
+      }
+      ExtendedOption::IdleTimeout if options.idle_timeout > 0 => {
+        options.idle_timeout -= IDLE_TIMEOUT_STEP;
+      }
+      ExtendedOption::StallPolicy => {
+        options.stall_policy = match options.stall_policy {
+          StallPolicy::Wait => StallPolicy::Skip,
+          StallPolicy::Skip => StallPolicy::Wait,
+        };
+      }
+      ExtendedOption::MuffledFogSounds => {
+        options.muffled_fog_sounds = !options.muffled_fog_sounds;
+      }
+      ExtendedOption::LevelRotation => {
+        options.level_rotation = match options.level_rotation {
+          LevelRotation::Off => LevelRotation::Shuffled,
+          LevelRotation::Alphabetical => LevelRotation::Off,
+          LevelRotation::Shuffled => LevelRotation::Alphabetical,
+        };
+      }
+      ExtendedOption::Heatmaps => {
+        options.heatmaps = !options.heatmaps;
+      }
+      ExtendedOption::Loadout => {
+        options.loadout = match options.loadout {
+          StartingLoadout::Shop => StartingLoadout::Custom,
+          StartingLoadout::Basic => StartingLoadout::Shop,
+          StartingLoadout::Demolition => StartingLoadout::Basic,
+          StartingLoadout::Tunneler => StartingLoadout::Demolition,
+          StartingLoadout::Custom => StartingLoadout::Tunneler,
+        };
+      }
+      ExtendedOption::SmoothMovement => {
+        options.smooth_movement = !options.smooth_movement;
+      }
+      ExtendedOption::RecordRounds => {
+        options.capture_rounds = !options.capture_rounds;
+      }
+      ExtendedOption::Photosensitive => {
+        options.photosensitive = !options.photosensitive;
+      }
+      _ => {}
+    }
+  }
+
+  fn value_plus(self, options: &mut Options) {
+    match self {
+      ExtendedOption::CloneCap if options.clone_cap < MAX_CLONE_CAP => {
+        options.clone_cap += 1;
+      }
+      ExtendedOption::CloneCarryGold => {
+        options.clone_carry_gold = !options.clone_carry_gold;
+      }
+      ExtendedOption::MonsterLoot => {
+        options.monster_loot = !options.monster_loot;
+      }
+      ExtendedOption::MonsterCap if options.monster_cap < MAX_MONSTER_CAP => {
+        options.monster_cap += MONSTER_CAP_STEP;
+      }
+      ExtendedOption::Traders => {
+        options.traders = !options.traders;
+      }
+      ExtendedOption::FastForward => {
+        options.fast_forward = !options.fast_forward;
+      }
+      ExtendedOption::Autosave => {
+        options.autosave = !options.autosave;
+      }
+      ExtendedOption::Transitions => {
+        options.transitions = match options.transitions {
+          TransitionSpeed::Classic => TransitionSpeed::Fast,
+          TransitionSpeed::Fast | TransitionSpeed::Instant => TransitionSpeed::Instant,
+        };
+      }
+      ExtendedOption::SkipIntro => {
+        options.skip_intro = !options.skip_intro;
+      }
+      ExtendedOption::RealisticDamage => {
+        options.realistic_damage = !options.realistic_damage;
+      }
+      ExtendedOption::ArmorModel => {
+        options.armor_model = match options.armor_model {
+          ArmorModel::Health => ArmorModel::Reduction,
+          ArmorModel::Reduction => ArmorModel::Health,
+        };
+      }
+      ExtendedOption::Regeneration => {
+        options.regeneration = !options.regeneration;
+      }
+      ExtendedOption::Widescreen => {
+        options.widescreen = !options.widescreen;
+      }
+      ExtendedOption::KillFeed => {
+        options.kill_feed = !options.kill_feed;
+      }
+      ExtendedOption::SatellitePreview => {
+        options.satellite_preview = !options.satellite_preview;
+      }
+      ExtendedOption::ClassicInput => {
+        options.classic_input = !options.classic_input;
+      }
+      ExtendedOption::PushChains => {
+        options.push_chains = !options.push_chains;
+      }
+      ExtendedOption::Rockfall => {
+        options.rockfall = !options.rockfall;
+      }
+      ExtendedOption::BiomassHearts => {
+        options.biomass_hearts = !options.biomass_hearts;
+      }
+      ExtendedOption::RadioPing => {
+        options.radio_ping = !options.radio_ping;
+      }
+      ExtendedOption::InputDelay if options.input_delay < MAX_INPUT_DELAY => {
+        options.input_delay += 1;
+      }
+      ExtendedOption::Lives if options.lives < MAX_LIVES => {
+        options.lives += 1;
+      }
+      ExtendedOption::Continues if options.continues < MAX_CONTINUES => {
+        options.continues += 1;
+      }
+      ExtendedOption::IdleTimeout if options.idle_timeout < MAX_IDLE_TIMEOUT => {
+        options.idle_timeout += IDLE_TIMEOUT_STEP;
+      }
+      ExtendedOption::StallPolicy => {
+        options.stall_policy = match options.stall_policy {
+          StallPolicy::Wait => StallPolicy::Skip,
+          StallPolicy::Skip => StallPolicy::Wait,
+        };
+      }
+      ExtendedOption::MuffledFogSounds => {
+        options.muffled_fog_sounds = !options.muffled_fog_sounds;
+      }
+      ExtendedOption::LevelRotation => {
+        options.level_rotation = match options.level_rotation {
+          LevelRotation::Off => LevelRotation::Alphabetical,
+          LevelRotation::Alphabetical => LevelRotation::Shuffled,
+          LevelRotation::Shuffled => LevelRotation::Off,
+        };
+      }
+      ExtendedOption::Heatmaps => {
+        options.heatmaps = !options.heatmaps;
+      }
+      ExtendedOption::Loadout => {
+        options.loadout = match options.loadout {
+          StartingLoadout::Shop => StartingLoadout::Basic,
+          StartingLoadout::Basic => StartingLoadout::Demolition,
+          StartingLoadout::Demolition => StartingLoadout::Tunneler,
+          StartingLoadout::Tunneler => StartingLoadout::Custom,
+          StartingLoadout::Custom => StartingLoadout::Shop,
+        };
+      }
+      ExtendedOption::SmoothMovement => {
+        options.smooth_movement = !options.smooth_movement;
+      }
+      ExtendedOption::RecordRounds => {
+        options.capture_rounds = !options.capture_rounds;
+      }
+      ExtendedOption::Photosensitive => {
+        options.photosensitive = !options.photosensitive;
+      }
+      _ => {}
+    }
+  }
+
+  /// Left coordinate of the option label
+  const LABEL_X: i32 = 140;
+
+  /// Left coordinate of the option value
+  const VALUE_X: i32 = 400;
+
+  /// Top coordinate of the first visible option
+  const ITEM_Y: i32 = 120;
+
+  /// Option item height
+  const ITEM_HEIGHT: i32 = 14;
+
+  /// Amount of options shown at once; list is scrolled to keep the selected option visible
+  const VISIBLE_ITEMS: usize = 24;
+
+  /// First visible option once this option is selected, given the currently first visible one
+  fn scroll(self, top: usize) -> usize {
+    let pos = usize::from(self);
+    if pos < top {
+      pos
+    } else if pos >= top + Self::VISIBLE_ITEMS {
+      pos + 1 - Self::VISIBLE_ITEMS
+    } else {
+      top
+    }
+  }
+
+  /// Top coordinate of the option, `None` if it is scrolled out of the view
+  fn item_y(self, top: usize) -> Option<i32> {
+    let pos = usize::from(self)
+      .checked_sub(top)
+      .filter(|pos| *pos < Self::VISIBLE_ITEMS)?;
+    Some(Self::ITEM_Y + (pos as i32) * Self::ITEM_HEIGHT)
+  }
+}
+
+fn on_off(value: bool) -> String {
+  if value { "On" } else { "Off" }.to_owned()
+}
+
+impl Application<'_> {
+  /// Menu for the options which are not supported by the original game
+  pub(super) fn extended_options_menu(
+    &self,
+    ctx: &mut ApplicationContext,
+    options: &mut Options,
+    autosave: &mut Autosave,
+  ) -> Result<(), anyhow::Error> {
+    let mut selected = ExtendedOption::FIRST;
+    let mut top = 0;
+    self.render_extended_options(ctx, options, selected, top)?;
+    ctx.transition(Animation::FadeUp)?;
+
+    loop {
+      let previous = selected;
+      let (scancode, _) = autosave.wait_key_pressed(ctx, options, |ctx| self.show_saved_indicator(ctx, true))?;
+      match ctx.menu_action(scancode) {
+        MenuAction::Down => selected = selected.next(),
+        MenuAction::Up => selected = selected.prev(),
+        MenuAction::Left => {
+          selected.value_minus(options);
+          autosave.changed(options);
+        }
+        MenuAction::Right => {
+          selected.value_plus(options);
+          autosave.changed(options);
+        }
+        MenuAction::Select if selected == ExtendedOption::AssetPacks => {
+          ctx.transition(Animation::FadeDown)?;
+          self.mods_menu(ctx)?;
+          self.render_extended_options(ctx, options, selected, top)?;
+          ctx.transition(Animation::FadeUp)?;
+          continue;
+        }
+        MenuAction::Select if selected == ExtendedOption::Registration => {
+          ctx.transition(Animation::FadeDown)?;
+          self.registration_menu(ctx)?;
+          self.render_extended_options(ctx, options, selected, top)?;
+          ctx.transition(Animation::FadeUp)?;
+          continue;
+        }
+        MenuAction::Back | MenuAction::Select | MenuAction::Other(Scancode::Tab) => break,
+        _ => continue,
+      }
+      let scrolled = selected.scroll(top);
+      if scrolled != top {
+        top = scrolled;
+        self.render_extended_options(ctx, options, selected, top)?;
+      } else {
+        ctx.with_render_context(|canvas| {
+          self.render_extended_option(canvas, options, previous, false, top)?;
+          self.render_extended_option(canvas, options, selected, true, top)?;
+          Ok(())
+        })?;
+      }
+      self.show_saved_indicator(ctx, false)?;
+    }
+    ctx.transition(Animation::FadeDown)?;
+    Ok(())
+  }
+
+  fn render_extended_options(
+    &self,
+    ctx: &mut ApplicationContext,
+    options: &Options,
+    selected: ExtendedOption,
+    top: usize,
+  ) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let color = self.options_menu.roles[PaletteRole::TextPrimary];
+      self.font.render(canvas, 272, 80, color, "MORE OPTIONS")?;
+      for option in ExtendedOption::all_options() {
+        self.render_extended_option(canvas, options, option, option == selected, top)?;
+      }
+
+      // Hint that there are more options above or below
+      let color = self.options_menu.roles[PaletteRole::Muted];
+      let bottom = ExtendedOption::ITEM_Y + (ExtendedOption::VISIBLE_ITEMS as i32) * ExtendedOption::ITEM_HEIGHT;
+      if top > 0 {
+        let y = ExtendedOption::ITEM_Y - ExtendedOption::ITEM_HEIGHT;
+        self.font.render(canvas, ExtendedOption::LABEL_X, y, color, "...")?;
+      }
+      if top + ExtendedOption::VISIBLE_ITEMS <= usize::from(ExtendedOption::LAST) {
+        self
+          .font
+          .render(canvas, ExtendedOption::LABEL_X, bottom, color, "...")?;
+      }
+      Ok(())
+    })
+  }
+
+  fn render_extended_option(
+    &self,
+    canvas: &mut WindowCanvas,
+    options: &Options,
+    option: ExtendedOption,
+    selected: bool,
+    top: usize,
+  ) -> Result<(), anyhow::Error> {
+    let y = match option.item_y(top) {
+      Some(y) => y,
+      None => return Ok(()),
+    };
+    canvas.set_draw_color(Color::BLACK);
+    canvas
+      .fill_rect(Rect::new(0, y - 2, SCREEN_WIDTH, ExtendedOption::ITEM_HEIGHT as u32))
+      .map_err(SdlError)?;
+    if selected {
+      self
+        .glyphs
+        .render(canvas, ExtendedOption::LABEL_X - 32, y - 2, Glyph::ArrowPointer)?;
+    }
+    let color = self.options_menu.roles[PaletteRole::Muted];
+    self
+      .font
+      .render(canvas, ExtendedOption::LABEL_X, y, color, option.label())?;
+    self
+      .font
+      .render(canvas, ExtendedOption::VALUE_X, y, color, &option.value(options))?;
+    Ok(())
+  }
+}
@@ -0,0 +1,122 @@
+//! Kill feed: a small ticker in the corner of the game screen showing recent kills and notable finds.
+use crate::palette::{PaletteRole, PaletteRoles};
+use crate::world::map::MapValue;
+use crate::world::{EntityIndex, GameEvent, World};
+use sdl2::pixels::Color;
+use std::collections::VecDeque;
+
+/// Maximum amount of lines shown at once
+pub const FEED_LINES: usize = 4;
+
+/// Maximum length of the line, in characters
+pub const FEED_LINE_LENGTH: usize = 38;
+
+/// For how many ticks line is shown
+const LINE_DURATION: usize = 250;
+
+/// For how many ticks line fades out before disappearing
+const LINE_FADE: usize = 100;
+
+/// Piece of the feed line, rendered with the given color
+pub enum Segment {
+  /// Name of the player
+  Player(EntityIndex),
+  Text(&'static str),
+}
+
+impl Segment {
+  /// Text of the segment and its color; `None` if player is not in the game anymore
+  pub fn text<'a>(&self, world: &'a World, roles: &PaletteRoles) -> Option<(&'a str, Color)> {
+    match *self {
+      Segment::Player(player) => world
+        .players
+        .get(player)
+        .map(|info| (info.stats.name.as_str(), roles[PaletteRole::player(player)])),
+      Segment::Text(text) => Some((text, roles[PaletteRole::TextPrimary])),
+    }
+  }
+}
+
+struct FeedLine {
+  segments: Vec<Segment>,
+  /// Tick when line was added
+  added: usize,
+}
+
+#[derive(Default)]
+pub struct KillFeed {
+  lines: VecDeque<FeedLine>,
+  /// Feed was changed since last render
+  dirty: bool,
+}
+
+impl KillFeed {
+  /// Add line for the gameplay event, if it is notable enough
+  pub fn push(&mut self, event: GameEvent, tick: usize) {
+    let segments = match event {
+      GameEvent::PlayerKilled {
+        victim,
+        killer: Some(killer),
+      } if killer != victim => vec![
+        Segment::Player(killer),
+        Segment::Text(" blew up "),
+        Segment::Player(victim),
+      ],
+      GameEvent::PlayerKilled {
+        victim,
+        killer: Some(_),
+      } => {
+        vec![Segment::Player(victim), Segment::Text(" blew themselves up")]
+      }
+      GameEvent::PlayerKilled { victim, killer: None } => vec![Segment::Player(victim), Segment::Text(" died")],
+      GameEvent::TreasureFound {
+        player,
+        value: MapValue::Diamond,
+      } => vec![Segment::Player(player), Segment::Text(" found a diamond")],
+      GameEvent::TreasureFound {
+        player,
+        value: MapValue::GoldCrown,
+      } => vec![Segment::Player(player), Segment::Text(" found a crown")],
+      GameEvent::TreasureFound { .. } | GameEvent::Exploded { .. } | GameEvent::Dug { .. } => return,
+    };
+    if self.lines.len() == FEED_LINES {
+      self.lines.pop_front();
+    }
+    self.lines.push_back(FeedLine { segments, added: tick });
+    self.dirty = true;
+  }
+
+  /// Remove all lines (feed is rendered once more, so they are erased from the screen)
+  pub fn clear(&mut self) {
+    self.lines.clear();
+    self.dirty = true;
+  }
+
+  /// Remove expired lines. Returns `true` if feed needs to be rendered: it is not empty (feed is
+  /// rendered on top of the map, so it needs to be refreshed as map changes) or it just got empty.
+  pub fn tick(&mut self, tick: usize) -> bool {
+    while self
+      .lines
+      .front()
+      .is_some_and(|line| tick >= line.added + LINE_DURATION)
+    {
+      self.lines.pop_front();
+      self.dirty = true;
+    }
+    std::mem::take(&mut self.dirty) || !self.lines.is_empty()
+  }
+
+  /// Iterate through lines, oldest first, along with their brightness (0..=255)
+  pub fn lines(&self, tick: usize) -> impl Iterator<Item = (&[Segment], u8)> {
+    self.lines.iter().map(move |line| {
+      let remaining = (line.added + LINE_DURATION).saturating_sub(tick).min(LINE_FADE);
+      (line.segments.as_slice(), (remaining * 255 / LINE_FADE) as u8)
+    })
+  }
+}
+
+/// Dim the color according to the line brightness
+pub fn fade(color: Color, brightness: u8) -> Color {
+  let fade = |c: u8| (u16::from(c) * u16::from(brightness) / 255) as u8;
+  Color::RGB(fade(color.r), fade(color.g), fade(color.b))
+}
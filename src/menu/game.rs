@@ -1,18 +1,38 @@
-use crate::context::{Animation, ApplicationContext};
+use crate::campaign::{Assist, CampaignManifest, CampaignProgress, ASSIST_CASH, ASSIST_FAILURES, CAMPAIGN_LEVELS};
+use crate::capture::RoundCapture;
+use crate::context::{Animation, ApplicationContext, PostEffects};
+use crate::coords::{actor_origin, border_origin, cell_center, cell_origin, cell_rect};
 use crate::effects::SoundEffect;
 use crate::error::ApplicationError::SdlError;
-use crate::glyphs::{AnimationPhase, Border, Digging, Glyph};
-use crate::highscore::{Highscores, Score};
-use crate::keys::Key;
+use crate::glyphs::{AnimationPhase, Border, Digging, Glyph, Glyphs};
+use crate::heatmap::RoundHeatmap;
+use crate::highscore::{BoardKind, Highscores, Score};
+use crate::keys::{Key, MenuAction};
+use crate::lockstep::Lockstep;
+use crate::matchlog::{compute_ranks, MatchLog, RoundResult, Standing};
+use crate::menu::confirm::wait_yes_no;
+use crate::menu::feed::{fade, KillFeed, FEED_LINES, FEED_LINE_LENGTH};
+use crate::menu::hud::{Hud, JUMP_MARKER_COLORS};
+use crate::menu::load_levels::LevelSchedule;
+use crate::menu::overlay::{render_heat_overlay, render_profile_overlay, HeatOverlay};
+use crate::menu::players::SelectedPlayer;
+use crate::menu::preview::Preview;
 use crate::menu::shop::ShopResult;
+use crate::netplay::{RemotePlayers, MIN_INPUT_DELAY};
 use crate::options::WinCondition;
+use crate::palette::PaletteRole;
 use crate::roster::PlayersRoster;
 use crate::settings::GameSettings;
-use crate::world::actor::{ActorComponent, ActorKind};
-use crate::world::map::{LevelInfo, LevelMap, MapValue, DIRT_BORDER_BITMAP, MAP_COLS, MAP_ROWS};
+use crate::status::GameStatus;
+use crate::world::actor::{ActorComponent, ActorKind, PathRecorder};
+use crate::world::locks::KeyColor;
+use crate::world::map::{LevelInfo, LevelMap, LevelMeta, MapValue, DIRT_BORDER_BITMAP, MAP_COLS, MAP_ROWS};
 use crate::world::player::{GlyphCheat, PlayerComponent};
-use crate::world::position::{Cursor, Direction};
-use crate::world::{Maps, SplatterKind, Update, World};
+use crate::world::position::{Cursor, Direction, Position};
+use crate::world::rng::WorldRng;
+use crate::world::settlement::RoundSettlement;
+use crate::world::tuning::Tuning;
+use crate::world::{Maps, SplatterKind, Update, Viewer, Visibility, World, TICKS_PER_SECOND};
 use crate::Application;
 use rand::prelude::*;
 use sdl2::event::Event;
@@ -21,10 +41,39 @@ use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::WindowCanvas;
 use std::path::Path;
-use std::rc::Rc;
 use std::time::{Duration, Instant};
 
-const CAMPAIGN_ROUNDS: u16 = 15;
+const CAMPAIGN_ROUNDS: u16 = CAMPAIGN_LEVELS as u16;
+
+/// Percentage of cash player keeps after continuing the single player game
+const CONTINUE_CASH_PERCENT: u32 = 50;
+
+/// Amount of levels to complete without losing a life to earn a skip token
+const FLAWLESS_PER_SKIP_TOKEN: u32 = 3;
+
+/// Duration of a single game tick
+const TICK_DURATION: Duration = Duration::from_millis(1000 / TICKS_PER_SECOND as u64);
+
+/// Actors moving further than that many pixels in a single tick are not interpolated
+const MAX_INTERPOLATED_STEP: i32 = 4;
+
+/// For how many frames post-round review is offered before the round ends
+const REVIEW_WAIT_FRAMES: usize = 100;
+
+/// Amount of lit bomb fuses heard at the same time
+const MAX_FUSE_SOUNDS: usize = 3;
+
+/// Screen is dimmed while the game is paused
+const PAUSE_TINT: Color = Color::RGB(128, 128, 128);
+
+/// Map columns covered by the kill feed (starting from the column 1)
+const FEED_COLS: u16 = 31;
+
+/// How long (in frames) the new score in the hall of fame stays highlighted or not
+const HALL_OF_FAME_BLINK_FRAMES: usize = 20;
+
+/// Amount of scores shown on a single page of the hall of fame
+const HALL_OF_FAME_PAGE: usize = 10;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RoundEnd {
@@ -36,85 +85,317 @@ pub enum RoundEnd {
   Failed,
 }
 
-impl Application<'_> {
+/// Level skin applied to the glyphs; regular glyphs are restored once it is dropped
+struct LevelSkin<'a, 't>(&'a Glyphs<'t>);
+
+impl Drop for LevelSkin<'_, '_> {
+  fn drop(&mut self) {
+    self.0.set_skin(None);
+  }
+}
+
+impl<'t> Application<'t> {
   /// Play game, starting from player selection
-  pub fn play_game(&self, ctx: &mut ApplicationContext, settings: &GameSettings) -> Result<(), anyhow::Error> {
+  /// If `quickstart` is set, players selected in the previous game are used (if any).
+  /// Returns the winner of the game, if there is a single one.
+  pub fn play_game(
+    &mut self,
+    ctx: &mut ApplicationContext<'_, 't>,
+    settings: &GameSettings,
+    quickstart: bool,
+  ) -> Result<Option<usize>, anyhow::Error> {
     sdl2::mixer::Music::halt();
     let campaign_mode = settings.options.players == 1 || settings.options.campaign_mode;
-    let selected = self.players_select_menu(ctx, settings.options.players)?;
+    let last_players = if quickstart || settings.scripted {
+      self.last_selected_players(ctx, settings.options.players)?
+    } else {
+      None
+    };
+    let network_players = self.netplay.as_ref().map(|netplay| {
+      netplay
+        .names()
+        .map(|name| SelectedPlayer {
+          name: name.to_owned(),
+          roster_index: 0,
+        })
+        .collect()
+    });
+    let selected = match network_players.or(last_players) {
+      Some(selected) => selected,
+      None if settings.scripted => (0..settings.options.players)
+        .map(|idx| SelectedPlayer {
+          name: format!("Player {}", idx + 1),
+          roster_index: idx,
+        })
+        .collect(),
+      None => self.players_select_menu(ctx, settings.options.players)?,
+    };
     if selected.is_empty() {
-      return Ok(());
+      return Ok(None);
     }
 
     let mut players = Vec::with_capacity(selected.len());
     let mut players_to_roster = Vec::with_capacity(selected.len());
     for (idx, selected) in selected.into_iter().enumerate() {
-      players.push(PlayerComponent::new(
-        selected.name,
-        settings.keys.keys[idx],
-        &settings.options,
-      ));
+      // Remote players are controlled through their inputs sent over the network; local player of
+      // the network game uses keys of the first player
+      let keys = match self.netplay {
+        Some(ref netplay) if netplay.is_remote(idx) => netplay.remote_keys(idx),
+        Some(_) => settings.keys.keys[0],
+        None => settings.keys.keys[idx],
+      };
+      players.push(PlayerComponent::new(selected.name, keys, &settings.options));
       players_to_roster.push(selected.roster_index);
     }
 
+    // Ironman: single life budget (no continues), progress is not saved and darkness is forced
+    let ironman = campaign_mode && !settings.scripted && self.ironman_prompt(ctx)?;
     if campaign_mode {
       // In single player, we start with 250 for each player
       players[0].cash = 250 * u32::from(settings.options.players);
-      players[0].lives = 3;
+      players[0].lives = u16::from(settings.options.lives);
     }
 
+    let schedule = LevelSchedule::new(ctx.vfs(), &settings.levels, settings.options.level_rotation);
+
+    // In single player, skip transitions between levels if requested; scripted game never shows them
+    let fast_forward = settings.scripted || (campaign_mode && settings.options.fast_forward);
+    let mut results: Vec<String> = Vec::new();
+    let names = players.iter().map(|player| player.stats.name.clone()).collect();
+    let mut match_log = MatchLog::new(&settings.options, names);
+    // Completion time of each campaign level and if it was completed with an assist
+    let mut times = [None; CAMPAIGN_LEVELS];
+    let mut assists = [false; CAMPAIGN_LEVELS];
+    let mut progress = CampaignProgress::load(ctx.game_dir(), ctx.vfs());
+    let manifest = CampaignManifest::load(ctx.vfs());
+    // Levels completed without losing a life; every few of them earn a token to skip a level
+    let mut flawless = 0;
+    let mut skip_tokens = 0;
+    let mut skipped = false;
+    let mut continues = if ironman { 0 } else { settings.options.continues };
     let mut round = 0;
     while (!campaign_mode && round < settings.options.rounds)
       || (campaign_mode && players[0].lives > 0 && round < CAMPAIGN_ROUNDS)
     {
+      // Asset changes are applied between the rounds
+      self.reload_assets(ctx);
       ctx.with_render_context(|canvas| {
         canvas.set_draw_color(Color::BLACK);
         canvas.clear();
-        let color = self.main_menu.palette[1];
+        let color = self.main_menu.roles[PaletteRole::TextPrimary];
         self
           .font
           .render(canvas, 220, 200, color, "Creating level...please wait")?;
+        for (idx, result) in results.iter().enumerate() {
+          self.font.render(canvas, 220, 230 + 10 * (idx as i32), color, result)?;
+        }
         Ok(())
       })?;
 
       // Select a level to play
-      ctx.animate(Animation::FadeUp, 7)?;
+      if fast_forward {
+        ctx.present()?;
+      } else {
+        ctx.transition(Animation::FadeUp)?;
+      }
       let slot;
       let level = if campaign_mode {
-        slot = LevelMap::prepare_campaign_level(ctx.game_dir(), round)?;
+        slot = LevelMap::prepare_campaign_level(ctx.vfs(), round)?;
         &slot
       } else {
-        settings
-          .levels
-          .get(usize::from(round))
-          .map(Rc::as_ref)
-          .unwrap_or(&LevelInfo::Random)
+        schedule.level(round)
       };
-      ctx.animate(Animation::FadeDown, 7)?;
-      let result = self.play_round(ctx, &mut players, round, level, settings, campaign_mode)?;
+      if !fast_forward {
+        ctx.transition(Animation::FadeDown)?;
+      }
+      let level_idx = usize::from(round);
+      let mut assist = None;
+      if campaign_mode && !settings.scripted && progress.failures(level_idx) >= ASSIST_FAILURES {
+        assist = self.assist_prompt(ctx)?;
+      }
+      if assist == Some(Assist::Cash) {
+        players[0].cash += ASSIST_CASH;
+      }
+      if (round == 0 || self.netplay.is_some()) && !campaign_mode {
+        if let Some(items) = settings.options.loadout_items() {
+          // Starting loadout replaces the first visit to the shop (every visit in network games, as
+          // shop is not available to remote players)
+          for player in &mut players {
+            player.buy_loadout(items);
+          }
+        }
+      }
+
+      let lives_before = players[0].lives;
+      let (result, round_time, exit, settlement) = self.play_round(
+        ctx,
+        &mut players,
+        round,
+        level,
+        settings,
+        campaign_mode,
+        ironman,
+        assist,
+        None,
+        manifest.time_limit(level_idx).filter(|_| campaign_mode),
+        if campaign_mode {
+          manifest.price_percent(level_idx)
+        } else {
+          100
+        },
+      )?;
+      if !campaign_mode && result != RoundEnd::Game {
+        match_log.record_round(RoundResult {
+          level: level.name().to_owned(),
+          settlement,
+        });
+      }
+      let mut earned_token = false;
+      if campaign_mode && result != RoundEnd::Game {
+        let changed = if result == RoundEnd::Round {
+          progress.record(level_idx, round_time, assist.is_some())
+        } else {
+          progress.record_failure(level_idx);
+          true
+        };
+        if changed && !settings.scripted && !ironman {
+          if let Err(err) = progress.save(ctx.game_dir()) {
+            eprintln!("Failed to save campaign progress: {:#}", err);
+          }
+        }
+      }
+      if campaign_mode && result == RoundEnd::Round {
+        times[level_idx] = Some(round_time);
+        assists[level_idx] = assist.is_some();
+        if players[0].lives == lives_before {
+          flawless += 1;
+          if flawless % FLAWLESS_PER_SKIP_TOKEN == 0 {
+            skip_tokens += 1;
+            earned_token = true;
+          }
+        }
+      }
+      let mut skip = false;
+      if campaign_mode && result == RoundEnd::Failed && players[0].lives > 0 && skip_tokens > 0 && !settings.scripted {
+        skip = self.skip_level_prompt(ctx, skip_tokens)?;
+        if skip {
+          skip_tokens -= 1;
+          skipped = true;
+        }
+      }
+      if campaign_mode {
+        // Results ticker, shown while next level is being created
+        let outcome = match result {
+          RoundEnd::Round => "done",
+          _ if skip => "skipped",
+          _ => "failed",
+        };
+        let token = if earned_token { "  +skip token" } else { "" };
+        results.push(format!(
+          "Level {:<2} {:<7} Money {}{}",
+          round + 1,
+          outcome,
+          players[0].cash,
+          token
+        ));
+      }
+      if campaign_mode && players[0].lives == 0 && continues > 0 && !settings.scripted {
+        // Continue: start the same level over with a fresh set of lives, but less cash
+        if self.continue_prompt(ctx, continues)? {
+          continues -= 1;
+          players[0].lives = u16::from(settings.options.lives);
+          players[0].cash = players[0].cash * CONTINUE_CASH_PERCENT / 100;
+          results.push(format!("Continue      Money {}", players[0].cash));
+        }
+      }
       if campaign_mode && players[0].lives == 0 {
         // End of game: out of lives!
         break;
       }
       match result {
         RoundEnd::Game => break,
+        RoundEnd::Failed if skip => {
+          // Skipped level is left through its default exit
+          round = manifest.next_level(level_idx, 0) as u16;
+        }
         RoundEnd::Failed => {
           // Keep playing the same round!
         }
+        RoundEnd::Round if campaign_mode => {
+          let exit = exit.unwrap_or(0);
+          if let Some(bonus) = manifest.bonus_level(level_idx, exit) {
+            let cash = players[0].cash;
+            let (result, _, _, _) =
+              self.play_bonus_round(ctx, &mut players, round, bonus, &manifest, settings, ironman)?;
+            if result == RoundEnd::Game {
+              break;
+            }
+            results.push(format!(
+              "Bonus {:<2} +{:<5} Money {}",
+              bonus + 1,
+              players[0].cash - cash,
+              players[0].cash
+            ));
+          }
+          round = manifest.next_level(level_idx, exit) as u16;
+        }
         RoundEnd::Round => {
           round += 1;
         }
       }
     }
 
+    ctx.report_status(GameStatus::Results);
+    if settings.scripted {
+      let winner = if campaign_mode {
+        Some(0).filter(|_| round == CAMPAIGN_ROUNDS)
+      } else {
+        let ranks = compute_ranks(&players, settings.options.win);
+        let mut winners = (0..players.len()).filter(|idx| ranks[*idx] == 0);
+        winners.next().filter(|_| winners.next().is_none())
+      };
+      return Ok(winner);
+    }
+
     if campaign_mode {
       self.campaign_end(ctx, round == CAMPAIGN_ROUNDS)?;
-      self.hall_of_fame(ctx, round as u8, &players[0])?;
+      self.campaign_progress(ctx, &manifest, &progress, &times, &assists)?;
+      let kind = BoardKind {
+        players: players.len() as u8,
+        darkness: settings.options.darkness || ironman,
+        ironman,
+      };
+      let score = Score {
+        name: players[0].stats.name.to_owned(),
+        level: round as u8,
+        cash: players[0].cash,
+        skipped,
+        assisted: assists.iter().any(|assisted| *assisted),
+      };
+      self.hall_of_fame(ctx, kind, score)?;
     } else {
       self.multi_player_end(ctx, &players, settings.options.win)?;
-      update_player_stats(ctx.game_dir(), &mut players, &players_to_roster, settings.options.win)?;
+      let ranks = compute_ranks(&players, settings.options.win);
+      let standings: Vec<Standing> = players
+        .iter()
+        .zip(ranks)
+        .map(|(player, rank)| Standing {
+          name: player.stats.name.clone(),
+          rank,
+          rounds_win: player.rounds_win,
+          cash: player.cash,
+          kills: player.kills,
+        })
+        .collect();
+      // Network players are not from the local roster
+      if self.netplay.is_none() {
+        update_player_stats(ctx.game_dir(), &mut players, &players_to_roster, settings.options.win)?;
+      }
+      if let Err(err) = match_log.save(ctx.game_dir(), &standings) {
+        eprintln!("Failed to save match log: {:#}", err);
+      }
     }
-    Ok(())
+    Ok(None)
   }
 
   /// Show ending screen of a campaign game
@@ -128,65 +409,236 @@ impl Application<'_> {
       canvas.copy(texture, None, None).map_err(SdlError)?;
       Ok(())
     })?;
-    ctx.animate(Animation::FadeUp, 7)?;
+    ctx.transition(Animation::FadeUp)?;
     if win {
       self
         .effects
         .play(SoundEffect::Applause, 11000, Cursor::new(0, MAP_COLS / 2))?;
     }
     ctx.wait_key_pressed();
-    ctx.animate(Animation::FadeDown, 7)?;
+    ctx.transition(Animation::FadeDown)?;
     Ok(())
   }
 
+  /// Offer to spend a skip token on the level player failed. Returns `true` if level should be
+  /// skipped.
+  fn skip_level_prompt(&self, ctx: &mut ApplicationContext, tokens: u32) -> Result<bool, anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let roles = &self.main_menu.roles;
+      let text = format!("You have {} skip token(s)", tokens);
+      self
+        .font
+        .render(canvas, 180, 200, roles[PaletteRole::TextPrimary], &text)?;
+      self.font.render(
+        canvas,
+        180,
+        215,
+        roles[PaletteRole::TextPrimary],
+        "Skip this level? (Y/N)",
+      )?;
+      self.font.render(
+        canvas,
+        180,
+        240,
+        roles[PaletteRole::Danger],
+        "Skipped games are marked in the hall of fame",
+      )?;
+      Ok(())
+    })?;
+    ctx.transition(Animation::FadeUp)?;
+    let skip = wait_yes_no(ctx);
+    ctx.transition(Animation::FadeDown)?;
+    Ok(skip)
+  }
+
+  /// Ask if single player game should be played in the ironman mode
+  fn ironman_prompt(&self, ctx: &mut ApplicationContext) -> Result<bool, anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let roles = &self.main_menu.roles;
+      self.font.render(
+        canvas,
+        180,
+        200,
+        roles[PaletteRole::TextPrimary],
+        "Play in ironman mode? (Y/N)",
+      )?;
+      self.font.render(
+        canvas,
+        180,
+        225,
+        roles[PaletteRole::Danger],
+        "Darkness, no continues, progress is not saved",
+      )?;
+      self.font.render(
+        canvas,
+        180,
+        240,
+        roles[PaletteRole::Danger],
+        "Scores go to a separate hall of fame",
+      )?;
+      Ok(())
+    })?;
+    ctx.transition(Animation::FadeUp)?;
+    let answer = wait_yes_no(ctx);
+    ctx.transition(Animation::FadeDown)?;
+    Ok(answer)
+  }
+
+  /// Offer to continue the single player game after losing all lives
+  fn continue_prompt(&self, ctx: &mut ApplicationContext, continues: u8) -> Result<bool, anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let roles = &self.main_menu.roles;
+      let text = format!("Game over! You have {} continue(s)", continues);
+      self
+        .font
+        .render(canvas, 180, 200, roles[PaletteRole::TextPrimary], &text)?;
+      self.font.render(
+        canvas,
+        180,
+        215,
+        roles[PaletteRole::TextPrimary],
+        "Continue from this level? (Y/N)",
+      )?;
+      let text = format!("You keep {}% of your money", CONTINUE_CASH_PERCENT);
+      self.font.render(canvas, 180, 240, roles[PaletteRole::Danger], &text)?;
+      Ok(())
+    })?;
+    ctx.transition(Animation::FadeUp)?;
+    let answer = wait_yes_no(ctx);
+    ctx.transition(Animation::FadeDown)?;
+    Ok(answer)
+  }
+
+  /// Offer an assist to the player who keeps failing the level
+  fn assist_prompt(&self, ctx: &mut ApplicationContext) -> Result<Option<Assist>, anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let roles = &self.main_menu.roles;
+      self.font.render(
+        canvas,
+        180,
+        185,
+        roles[PaletteRole::TextPrimary],
+        "Having trouble with this level?",
+      )?;
+      let cash = format!("1  Extra {} cash", ASSIST_CASH);
+      self
+        .font
+        .render(canvas, 180, 205, roles[PaletteRole::TextPrimary], &cash)?;
+      self
+        .font
+        .render(canvas, 180, 220, roles[PaletteRole::TextPrimary], "2  Calmer monsters")?;
+      self
+        .font
+        .render(canvas, 180, 235, roles[PaletteRole::TextPrimary], "ESC  No, thanks")?;
+      self.font.render(
+        canvas,
+        180,
+        260,
+        roles[PaletteRole::Danger],
+        "Assisted games are marked in the hall of fame",
+      )?;
+      Ok(())
+    })?;
+    ctx.transition(Animation::FadeUp)?;
+    let assist = loop {
+      let (scancode, _) = ctx.wait_key_pressed();
+      match scancode {
+        Scancode::Num1 | Scancode::Kp1 => break Some(Assist::Cash),
+        Scancode::Num2 | Scancode::Kp2 => break Some(Assist::CalmMonsters),
+        _ if ctx.menu_action(scancode) == MenuAction::Back => break None,
+        _ => {}
+      }
+    };
+    ctx.transition(Animation::FadeDown)?;
+    Ok(assist)
+  }
+
   /// Show hall of fame for a single player game
-  fn hall_of_fame(
-    &self,
-    ctx: &mut ApplicationContext,
-    rounds: u8,
-    player: &PlayerComponent,
-  ) -> Result<(), anyhow::Error> {
+  fn hall_of_fame(&self, ctx: &mut ApplicationContext, kind: BoardKind, score: Score) -> Result<(), anyhow::Error> {
     let mut scores = Highscores::load(ctx.game_dir())?;
-    let pos = scores
-      .scores
-      .binary_search_by(|score| {
-        rounds
-          .cmp(score.as_ref().map_or(&0, |s| &s.level))
-          .then_with(|| player.cash.cmp(score.as_ref().map_or(&0, |s| &s.cash)))
-      })
-      .unwrap_or_else(|pos| pos);
-    if pos < scores.scores.len() {
-      // Drop the last element, replace it with the new score
-      scores.scores[pos..].rotate_right(1);
-      scores.scores[pos] = Some(Score {
-        name: player.stats.name.to_owned(),
-        level: rounds,
-        cash: player.cash,
-      });
+    let pos = scores.insert(kind, score);
+    if pos.is_some() {
       scores.save(ctx.game_dir())?;
     }
+    let board = scores.board(kind);
+    let pages = board.len().div_ceil(HALL_OF_FAME_PAGE).max(1);
+    // Start from the page with the new score
+    let mut page = pos.map_or(0, |pos| pos / HALL_OF_FAME_PAGE);
 
-    // FIXME: implement rendering!
-    ctx.with_render_context(|canvas| {
-      canvas.copy(&self.halloffa.texture, None, None).map_err(SdlError)?;
-      let color = self.halloffa.palette[1];
-      for (idx, score) in scores.scores.iter().enumerate() {
-        if let Some(score) = score {
+    let render = |ctx: &mut ApplicationContext, page: usize, highlight: bool| {
+      ctx.with_render_context(|canvas| {
+        canvas.copy(&self.halloffa.texture, None, None).map_err(SdlError)?;
+        if kind.ironman {
+          self
+            .font
+            .render(canvas, 127, 165, self.halloffa.roles[PaletteRole::Highlight], "Ironman")?;
+        }
+        let first = page * HALL_OF_FAME_PAGE;
+        for (idx, score) in board.iter().enumerate().skip(first).take(HALL_OF_FAME_PAGE) {
           let text = format!(
-            "{:<2}    {:<20}Level {:<2} Money {}",
+            "{:<2}    {:<20}Level {:<2} Money {:<8}{}{}",
             idx + 1,
             score.name,
             score.level,
-            score.cash
+            score.cash,
+            if score.skipped { "(skip)" } else { "" },
+            if score.assisted { "(assist)" } else { "" }
           );
-          self.font.render(canvas, 127, 10 * (idx as i32) + 179, color, &text)?;
+          let color = if highlight && Some(idx) == pos {
+            self.halloffa.roles[PaletteRole::Highlight]
+          } else {
+            self.halloffa.roles[PaletteRole::TextPrimary]
+          };
+          self
+            .font
+            .render(canvas, 127, 10 * ((idx - first) as i32) + 179, color, &text)?;
+        }
+        if pages > 1 {
+          let text = format!("Page {}/{}", page + 1, pages);
+          self.font.render(
+            canvas,
+            127,
+            10 * HALL_OF_FAME_PAGE as i32 + 189,
+            self.halloffa.roles[PaletteRole::TextPrimary],
+            &text,
+          )?;
+        }
+        Ok(())
+      })
+    };
+    render(ctx, page, false)?;
+    ctx.transition(Animation::FadeUp)?;
+    loop {
+      // Blink the new score, if it made it to the table
+      let key = ctx.wait_key_pressed_idle(None, |ctx, frame| {
+        if pos.is_some() && frame % HALL_OF_FAME_BLINK_FRAMES == 0 {
+          render(ctx, page, (frame / HALL_OF_FAME_BLINK_FRAMES) % 2 == 0)?;
+          ctx.present()?;
         }
+        Ok(())
+      })?;
+      let (scancode, _) = match key {
+        Some(key) => key,
+        None => break,
+      };
+      match ctx.menu_action(scancode) {
+        MenuAction::Left | MenuAction::Up if page > 0 => page -= 1,
+        MenuAction::Right | MenuAction::Down if page + 1 < pages => page += 1,
+        MenuAction::Left | MenuAction::Up | MenuAction::Right | MenuAction::Down => continue,
+        _ => break,
       }
-      Ok(())
-    })?;
-    ctx.animate(Animation::FadeUp, 7)?;
-    ctx.wait_key_pressed();
-    ctx.animate(Animation::FadeDown, 7)?;
+      render(ctx, page, false)?;
+      ctx.present()?;
+    }
+    ctx.transition(Animation::FadeDown)?;
     Ok(())
   }
 
@@ -197,68 +649,118 @@ impl Application<'_> {
     players: &[PlayerComponent],
     win: WinCondition,
   ) -> Result<(), anyhow::Error> {
+    const RANK_LABELS: [&str; 4] = ["1st", "2nd", "3rd", "4th"];
+    let ranks = compute_ranks(players, win);
+    // Podium: columns go from the first place to the last one
+    let mut order: Vec<usize> = (0..players.len()).collect();
+    order.sort_by_key(|idx| ranks[*idx]);
     ctx.with_render_context(|canvas| {
       canvas.copy(&self.r#final.texture, None, None).map_err(SdlError)?;
-      for idx in 0..players.len() {
-        let score = compute_score(players, idx, win);
+      for (column, &idx) in order.iter().enumerate() {
+        let score = compute_score(&ranks, idx);
         let avatars = &self.avatars[idx];
-        let dest = Rect::new(32 + 150 * (idx as i32), 95, 132, 218);
+        let left = 32 + 150 * (column as i32);
+        // Lower places stand lower on the podium
+        let drop = 8 * (ranks[idx] as i32);
+        let dest = Rect::new(left, 95 + drop, 132, 218 - drop as u32);
         let texture = match score {
           PlayerWin::Win => &avatars.win.texture,
           PlayerWin::Lose => &avatars.lose.texture,
           PlayerWin::Draw => &avatars.draw.texture,
         };
         canvas.copy(texture, None, dest).map_err(SdlError)?;
-        let color = self.r#final.palette[1];
+        let color = self.r#final.roles[PaletteRole::TextPrimary];
         self
           .font
-          .render(canvas, 36 + 150 * (idx as i32), 330, color, &players[idx].stats.name)?;
-        self.font.render(
-          canvas,
-          36 + 150 * (idx as i32),
-          362,
-          color,
-          &players[idx].rounds_win.to_string(),
-        )?;
-        self.font.render(
-          canvas,
-          36 + 150 * (idx as i32),
-          346,
-          color,
-          &players[idx].cash.to_string(),
-        )?;
+          .render(canvas, left + 4, 330, color, &players[idx].stats.name)?;
+        self
+          .font
+          .render(canvas, left + 4, 362, color, &players[idx].rounds_win.to_string())?;
+        self
+          .font
+          .render(canvas, left + 4, 346, color, &players[idx].cash.to_string())?;
+        self
+          .font
+          .render(canvas, left + 4, 378, color, RANK_LABELS[ranks[idx]])?;
       }
       Ok(())
     })?;
-    ctx.animate(Animation::FadeUp, 7)?;
+    ctx.transition(Animation::FadeUp)?;
     self
       .effects
       .play(SoundEffect::Applause, 11000, Cursor::new(0, MAP_COLS / 2))?;
     ctx.wait_key_pressed();
-    ctx.animate(Animation::FadeDown, 7)?;
+    ctx.transition(Animation::FadeDown)?;
 
     // FIXME: save stats back!
     Ok(())
   }
 
-  /// Play a single game round
+  /// Play campaign bonus level after the given round. Cash collected in the bonus round is kept
+  /// even if player dies, and dying doesn't cost a life.
+  #[allow(clippy::too_many_arguments)]
+  fn play_bonus_round(
+    &self,
+    ctx: &mut ApplicationContext<'_, 't>,
+    players: &mut [PlayerComponent],
+    round: u16,
+    bonus: usize,
+    manifest: &CampaignManifest,
+    settings: &GameSettings,
+    ironman: bool,
+  ) -> Result<(RoundEnd, Duration, Option<u8>, RoundSettlement), anyhow::Error> {
+    let level = LevelMap::prepare_bonus_level(ctx.vfs(), bonus)?;
+    let time_limit = manifest.bonus_time(bonus);
+    self.play_round(
+      ctx,
+      players,
+      round,
+      &level,
+      settings,
+      true,
+      ironman,
+      None,
+      Some(time_limit),
+      None,
+      100,
+    )
+  }
+
+  /// Play a single game round. Returns how the round ended, time it took and the exit player left
+  /// the level through (campaign mode). If `bonus` is given, round is a campaign bonus round with
+  /// the given time limit, in seconds. Campaign level could also have a `time_limit`, in seconds,
+  /// after which monsters get enraged, and shop prices scaled by `price_percent`. Ironman rounds are
+  /// always played in darkness.
+  #[allow(clippy::too_many_arguments)]
   fn play_round(
     &self,
-    ctx: &mut ApplicationContext,
+    ctx: &mut ApplicationContext<'_, 't>,
     players: &mut [PlayerComponent],
     round: u16,
     level: &LevelInfo,
     settings: &GameSettings,
     campaign_mode: bool,
-  ) -> Result<RoundEnd, anyhow::Error> {
+    ironman: bool,
+    assist: Option<Assist>,
+    bonus: Option<u32>,
+    time_limit: Option<u32>,
+    price_percent: u32,
+  ) -> Result<(RoundEnd, Duration, Option<u8>, RoundSettlement), anyhow::Error> {
     // Note: in original game, single player is always played dark. However, in this
     // re-implementation I'm relaxing this as I never had patience to play through all 15 levels
-    // with darkness 😅
-    let darkness = settings.options.darkness; // || players.len() == 1;
+    // with darkness 😅 (unless playing ironman)
+    let darkness = settings.options.darkness || ironman;
+    let level_name = level.name();
+    let meta = level.meta();
+    // Peers of the network game generate the same level and simulate the same world
+    let mut rng = match self.netplay {
+      Some(ref netplay) => netplay.next_epoch(),
+      None => WorldRng::random(),
+    };
     let level = match level {
       LevelInfo::Random => {
-        let mut level = LevelMap::random_map(settings.options.treasures);
-        level.generate_entrances(settings.options.players);
+        let mut level = LevelMap::random_map(settings.options.treasures, &mut rng);
+        level.generate_entrances(settings.options.players, &mut rng);
         level
       }
       LevelInfo::File { map, .. } => map.clone(),
@@ -270,30 +772,65 @@ impl Application<'_> {
 
     let mut shared_cash = if campaign_mode { Some(players[0].cash) } else { None };
     let mut it = players.iter_mut();
-    while let Some(right) = it.next() {
+    // No shopping before the bonus round or if players have got starting loadout instead
+    let loadout =
+      (round == 0 || self.netplay.is_some()) && !campaign_mode && settings.options.loadout_items().is_some();
+    let shopping = !settings.scripted && self.netplay.is_none() && bonus.is_none() && !loadout;
+    while let Some(right) = it.next().filter(|_| shopping) {
       let left = it.next();
       let total_rounds = if campaign_mode { 15 } else { settings.options.rounds };
       let remaining = total_rounds - round;
-      let preview_map = if darkness { None } else { Some(&level) };
+      let preview = if !darkness {
+        Preview::Full(&level)
+      } else if settings.options.satellite_preview {
+        Preview::Satellite(&level)
+      } else {
+        Preview::Hidden
+      };
+      ctx.report_status(GameStatus::Shop { round, total_rounds });
       if self.shop(
         ctx,
         remaining,
         &settings.options,
-        preview_map,
+        price_percent,
+        preview,
         &mut shared_cash,
         left,
         right,
       )? == ShopResult::ExitGame
       {
         sdl2::mixer::Music::halt();
-        return Ok(RoundEnd::Game);
+        return Ok((RoundEnd::Game, Duration::from_secs(0), None, RoundSettlement::default()));
       }
     }
 
     if let Some(cash) = shared_cash {
       players[0].cash = cash;
     }
-    let mut world = World::create(level, players, darkness, settings.options.bomb_damage, campaign_mode);
+    // Level could have its own look, which is used until the end of the round
+    let _skin = self.apply_level_skin(ctx, &meta);
+    // Players can vote to restart multiplayer round: it is played again on the same map and with
+    // the same inventories
+    let restart = if campaign_mode {
+      None
+    } else {
+      Some((level.clone(), meta.clone(), players.to_vec()))
+    };
+    let mut world = World::create(level, players, darkness, &settings.options, campaign_mode, meta, rng);
+    world.calm_monsters = assist == Some(Assist::CalmMonsters);
+    if let Some(seconds) = bonus {
+      world.start_bonus_round(seconds);
+    }
+    if let Some(seconds) = time_limit {
+      world.set_time_limit(seconds);
+    }
+    if let Some(ref path) = settings.tuning {
+      world.apply_tuning(Tuning::load(ctx.vfs(), path));
+      world.profile.enable();
+    }
+    if let Some(ref broadcast) = self.broadcast {
+      broadcast.reset();
+    }
 
     sdl2::mixer::Music::halt();
     // FIXME: start playing random music from the level music; also, don't play shop music?
@@ -304,116 +841,362 @@ impl Application<'_> {
       self.render_game_screen(canvas, &world)?;
       Ok(())
     })?;
-    ctx.animate(Animation::FadeUp, 7)?;
+    let panel_width = ctx.playfield_offset();
+    let mut hud = Hud::new(panel_width);
+    if hud.panels_outdated() {
+      ctx.with_screen_context(|canvas| self.render_hud_panels(canvas, &mut hud, &world))?;
+    }
+    let total_rounds = if campaign_mode {
+      CAMPAIGN_ROUNDS
+    } else {
+      settings.options.rounds
+    };
+    // Closing the window during the intro asks whether to quit as soon as the round starts
+    let mut window_closed = if settings.scripted {
+      // Scripted game starts right away
+      ctx.present()?;
+      false
+    } else {
+      ctx.transition(Animation::FadeUp)?;
+      self.round_intro(ctx, &world, round, total_rounds, level_name, settings)?
+    };
 
-    let start = Instant::now();
+    #[cfg(feature = "audience")]
+    if let Some(ref audience) = self.audience {
+      audience.discard_events();
+    }
+
+    let kill_feed = settings.options.kill_feed && !world.campaign_mode;
+    let mut feed = KillFeed::default();
+    let mut heatmap = if settings.options.heatmaps && !world.campaign_mode {
+      Some(RoundHeatmap::default())
+    } else {
+      None
+    };
+
+    let mut start = Instant::now();
     let mut paused_time = Duration::from_secs(0);
+    let mut round_time = Duration::from_secs(0);
+    let options = &settings.options;
+    // Round capture, started by the option or toggled with F9
+    let game_dir = ctx.game_dir().to_owned();
+    let mut capture = if options.capture_rounds {
+      Some(RoundCapture::start(&game_dir, round, level_name))
+    } else {
+      None
+    };
+    // Local players don't need to exchange inputs, so they are never delayed
+    let input_delay = if self.netplay.is_some() {
+      options.input_delay.max(MIN_INPUT_DELAY)
+    } else {
+      0
+    };
+    let mut lockstep = Lockstep::new(world.players.len(), input_delay, options);
+    let mut stalled_since: Option<Instant> = None;
+    // Debug overlay, only available in practice mode
+    let mut heat: Option<HeatOverlay> = None;
+    let mut heat_changed = false;
+    let mut show_profile = false;
+    // Positions of actors before the last tick and last rendered digging animation of each actor,
+    // for smooth movement
+    let mut previous: Vec<Position> = Vec::new();
+    let mut digging: Vec<Digging> = Vec::new();
+    // Fuses heard by the players, reused between ticks
+    let mut fuses: Vec<Cursor> = Vec::new();
+    // Tick of the last input of each player, to detect players who stepped away
+    let mut last_input = vec![0; world.players.len()];
+    // Network game is not paused for a single player
+    let idle_ticks = if campaign_mode || self.netplay.is_some() {
+      0
+    } else {
+      usize::from(options.idle_timeout) * TICKS_PER_SECOND
+    };
+    // Players controlled over the network
+    let remote = |player: usize| self.netplay.as_ref().is_some_and(|netplay| netplay.is_remote(player));
+    // Keys held down by the local players at the current tick, as bitmasks indexed by `Key`
+    let mut held_keys = vec![0; world.players.len()];
     let exit_reason = 'round: loop {
-      world.tick();
-
-      // Handle player commands
-      if world.round_counter % 2 == 0 {
-        // FIXME: in original game, command has slight delay on facing direction
-        //  However, facing seems to be only used when holding still, so doesn't really matter much.
-
-        let mut paused = false;
-        for event in ctx.poll_iter() {
-          if let Event::KeyDown {
-            scancode: Some(scancode),
-            ..
-          } = event
-          {
-            match scancode {
-              Scancode::Escape if world.campaign_mode => {
-                // Artificial death
-                world.players[0].lives -= 1;
-                break 'round RoundEnd::Failed;
-              }
-              Scancode::Escape => break 'round RoundEnd::Round,
-              Scancode::F10 => break 'round RoundEnd::Game,
-              // FIXME: some better scancode?
-              Scancode::Pause => {
-                paused = true;
-              }
-              Scancode::F5 => {
-                if music_on {
-                  sdl2::mixer::Music::pause();
-                } else {
-                  sdl2::mixer::Music::resume();
-                }
-                music_on = !music_on;
+      // Inputs are only applied once simulation advances, so every peer of the network game applies
+      // them at the same tick, no matter how long it was waiting for the others
+      let advanced = lockstep.poll(&world, self.netplay.as_ref());
+      if !advanced && self.netplay.as_ref().is_some_and(RemotePlayers::is_lost) {
+        // Host is gone, nothing else is coming
+        break 'round RoundEnd::Game;
+      }
+      if advanced {
+        if let Some(since) = stalled_since.take() {
+          // Stalled time doesn't count towards the round time
+          paused_time += since.elapsed();
+          ctx.with_render_context(|canvas| self.render_game_screen(canvas, &world))?;
+        }
+        previous.clear();
+        if options.smooth_movement {
+          previous.extend(world.actors.iter().map(|actor| actor.pos));
+        }
+        if let Some(ref netplay) = self.netplay {
+          // Simulation moves past the tick, so inputs of the local player for it are final
+          netplay.send(world.round_counter, &held_keys);
+        }
+        world.tick();
+        #[cfg(feature = "audience")]
+        if let Some(ref audience) = self.audience {
+          // Other peers of the network game don't get the audience events
+          if self.netplay.is_none() {
+            audience.apply_events(&mut world);
+          }
+        }
+        if let Some(ref broadcast) = self.broadcast {
+          broadcast.publish(&world);
+        }
+      } else if stalled_since.is_none() {
+        previous.clear();
+        stalled_since = Some(Instant::now());
+        let late: Vec<usize> = lockstep.schedule.late_players(world.round_counter + 1).collect();
+        ctx.with_render_context(|canvas| self.render_stall_indicator(canvas, &world, &late))?;
+      }
+      ctx.report_status(GameStatus::Round {
+        round,
+        total_rounds,
+        level: level_name,
+        players: world.players.len(),
+        alive: world.alive_players(),
+      });
+
+      // Drain input events every tick and buffer player commands, so they are applied on the next
+      // simulation step.
+      let mut paused = false;
+      let mut quit_requested = std::mem::take(&mut window_closed);
+      for event in ctx.poll_iter() {
+        if let Event::KeyDown {
+          scancode: Some(scancode),
+          ..
+        } = event
+        {
+          match scancode {
+            // Network game goes on without the player who leaves
+            Scancode::Escape if self.netplay.is_some() => quit_requested = true,
+            Scancode::Escape if world.bonus_round => break 'round RoundEnd::Round,
+            Scancode::Escape if world.campaign_mode => {
+              // Artificial death
+              world.players[0].lives -= 1;
+              break 'round RoundEnd::Failed;
+            }
+            Scancode::Escape => break 'round RoundEnd::Round,
+            Scancode::F10 => quit_requested = true,
+            Scancode::F9 => {
+              capture = match capture.take() {
+                // Stopped capture is written in the background
+                Some(_) => None,
+                None => Some(RoundCapture::start(&game_dir, round, level_name)),
+              };
+            }
+            // FIXME: some better scancode?
+            Scancode::Pause if self.netplay.is_none() => {
+              paused = true;
+            }
+            Scancode::F5 => {
+              if music_on {
+                sdl2::mixer::Music::pause();
+              } else {
+                sdl2::mixer::Music::resume();
               }
-              _ => {}
+              music_on = !music_on;
+            }
+            Scancode::F3 if settings.tuning.is_some() => {
+              heat = HeatOverlay::cycle(heat);
+              heat_changed = true;
+            }
+            Scancode::F4 if settings.tuning.is_some() => {
+              show_profile = !show_profile;
+              // Map under the overlay needs to be redrawn once it is turned off
+              heat_changed = true;
             }
+            _ => {}
+          }
 
-            for player in 0..world.players.len() {
-              let keys = world.players[player].keys;
-              for key in Key::all_keys() {
-                if keys[key] == Some(scancode) {
-                  world.player_action(player, key);
+          for (player, last_input) in last_input.iter_mut().enumerate() {
+            let keys = world.players[player].keys;
+            for key in Key::all_keys() {
+              if keys[key] == Some(scancode) {
+                *last_input = world.round_counter;
+                // Local players can only exceed the rate limit by mashing keys; excess presses are dropped
+                if lockstep.schedule.push(world.round_counter, player, key).is_ok() {
+                  if let Some(ref netplay) = self.netplay {
+                    netplay.key_pressed(key);
+                  }
                 }
               }
             }
           }
+        } else if let Event::Quit { .. } = event {
+          quit_requested = true;
         }
-        if paused {
-          // If we were paused, add to a
+      }
+
+      // Keys held down are scheduled the same way as key presses, so every peer of the network game
+      // sees them held at the same ticks
+      for player in (0..world.players.len()).filter(|player| !remote(*player)) {
+        let keys = world.players[player].keys;
+        held_keys[player] = Key::mask(|key| keys[key].is_some_and(|scancode| ctx.is_key_pressed(scancode)));
+        lockstep.schedule.hold(world.round_counter, player, held_keys[player]);
+      }
+
+      let voting = lockstep.voting();
+      let mut input_tick = false;
+      if advanced {
+        let applied = lockstep.apply_inputs(&mut world, self.netplay.as_ref());
+        for player in applied.players.into_iter().filter(|player| remote(*player)) {
+          last_input[player] = world.round_counter;
+        }
+        input_tick = applied.input_tick;
+      }
+      if let Some(ref netplay) = self.netplay {
+        if let Some((player, tick)) = netplay.take_desync() {
+          // Match goes on, as there is no way to tell which peer is right
           let start = Instant::now();
-          ctx.wait_key_pressed();
+          let action = format!("Game of {} went out of sync", world.players[player].stats.name);
+          self.desync_warning(ctx, tick, &action)?;
           paused_time += start.elapsed();
+          ctx.with_render_context(|canvas| self.render_game_screen(canvas, &world))?;
         }
       }
+      if let Some((ref level, ref meta, ref saved)) = restart {
+        if input_tick {
+          if lockstep.restart_voted() {
+            // Unanimous vote: start the round over. Held keys are scheduled in lockstep, so every
+            // peer of the network game restarts at the same tick; observers get the full snapshot
+            // of the restarted round (see `Broadcast::reset`).
+            let players = world.players;
+            players.clone_from_slice(saved);
+            let rng = match self.netplay {
+              Some(ref netplay) => netplay.next_epoch(),
+              None => WorldRng::random(),
+            };
+            world = World::create(
+              level.clone(),
+              players,
+              darkness,
+              &settings.options,
+              false,
+              meta.clone(),
+              rng,
+            );
+            if let Some(ref path) = settings.tuning {
+              world.apply_tuning(Tuning::load(ctx.vfs(), path));
+              world.profile.enable();
+            }
+            if let Some(ref broadcast) = self.broadcast {
+              broadcast.reset();
+            }
+            ctx.with_render_context(|canvas| self.render_game_screen(canvas, &world))?;
+            hud.invalidate();
+            if hud.panels_outdated() {
+              ctx.with_screen_context(|canvas| self.render_hud_panels(canvas, &mut hud, &world))?;
+            }
+            ctx.present()?;
+            start = Instant::now();
+            paused_time = Duration::from_secs(0);
+            lockstep.restart();
+            feed.clear();
+            heatmap = heatmap.map(|_| RoundHeatmap::default());
+            last_input.iter_mut().for_each(|tick| *tick = 0);
+            held_keys.iter_mut().for_each(|held| *held = 0);
+            continue 'round;
+          }
+          if lockstep.voting() {
+            ctx.with_render_context(|canvas| self.render_restart_vote(canvas, &world, &lockstep.restart_votes))?;
+          } else if voting {
+            // Vote is over, bring back player names
+            world.update.players_info = true;
+          }
+        }
+      }
+      if paused {
+        // If we were paused, add to a
+        let start = Instant::now();
+        ctx.present_with(PostEffects::tint(PAUSE_TINT))?;
+        ctx.wait_key_pressed();
+        paused_time += start.elapsed();
+      }
+      if quit_requested {
+        // Game is paused while the question is on the screen
+        let start = Instant::now();
+        let quit = self.confirm(ctx, "Quit the game?")?;
+        paused_time += start.elapsed();
+        if quit {
+          break 'round RoundEnd::Game;
+        }
+      }
+      let idle = (0..world.players.len()).find(|player| {
+        idle_ticks > 0 && !world.actors[*player].is_dead && world.round_counter - last_input[*player] >= idle_ticks
+      });
+      if let Some(player) = idle {
+        // Somebody stepped away: wait for them to come back
+        let start = Instant::now();
+        ctx.with_render_context(|canvas| self.render_idle_overlay(canvas, &world, player))?;
+        ctx.present()?;
+        ctx.wait_key_pressed();
+        paused_time += start.elapsed();
+        ctx.with_render_context(|canvas| self.render_full_map(canvas, &world, false))?;
+        last_input.iter_mut().for_each(|tick| *tick = world.round_counter);
+      }
 
-      let round_time = start.elapsed() - paused_time;
+      round_time = if self.netplay.is_some() {
+        // Peers of the network game have to end the round at the same tick
+        TICK_DURATION * world.round_counter as u32
+      } else {
+        start.elapsed() - paused_time
+      };
+      hud.track(&mut world.update, world.round_counter);
       // Apply all rendering updates
+      digging.resize(world.actors.len(), Digging::Hands);
+      for update in &world.update.queue {
+        if let Update::Actor(actor, dig) = *update {
+          digging[actor] = dig;
+        }
+      }
+      let max_splatters = ctx.post_effect_limits().max_splatters;
       ctx.with_render_context(|canvas| {
-        if world.update.players_info {
-          self.render_players_info(canvas, &world)?;
-          if world.campaign_mode {
-            self.render_lives(canvas, world.players.len() as i32, world.players[0].lives)?;
+        self.render_updates(canvas, &mut world, self.viewer(), max_splatters)?;
+        self.render_hud(canvas, &mut hud, &world)?;
+        if heat.is_some() || heat_changed {
+          // Overlay is drawn over the whole map, so map is redrawn completely every frame. Overlay
+          // reveals the whole map; once it is turned off, map is redrawn as seen by players.
+          self.render_full_map(canvas, &world, heat.is_some())?;
+          if let Some(heat) = heat {
+            render_heat_overlay(canvas, &world.maps, heat)?;
+            self.font.render(canvas, 12, 42, Color::WHITE, heat.label())?;
           }
-          world.update.players_info = false;
+          heat_changed = false;
         }
-
-        // Go through each update and render it
-        for update in &world.update.queue {
-          match *update {
-            Update::Actor(actor, digging) => {
-              let cheat = if actor < world.players.len() {
-                world.players[actor].glyph_cheat()
-              } else {
-                None
-              };
-              let actor = &world.actors[actor];
-              self.render_actor(canvas, actor, cheat, digging)?;
-            }
-            Update::Map(cursor) => {
-              self.reveal_map_square(canvas, cursor, &mut world.maps)?;
-            }
-            Update::Border(cursor) => {
-              self.render_dirt_border(canvas, cursor, &world.maps.level)?;
-            }
-            Update::BurnedBorder(cursor) => {
-              self.render_burned_border(canvas, cursor, &world.maps.level)?;
-            }
-            Update::Splatter(cursor, dir, splatter) => {
-              self.render_splatter(canvas, cursor, dir, splatter)?;
-            }
-          }
+        if show_profile {
+          render_profile_overlay(canvas, &self.font, &world.profile)?;
         }
 
-        // Update end of round indicator
         if !world.campaign_mode {
-          let width = ((635 * round_time.as_millis()) / settings.options.round_time.as_millis()).min(635) as i32;
-          canvas.set_draw_color(self.players.palette[0]);
-          canvas
-            .fill_rect(Rect::new(636 - width, 473, width as u32, 5))
-            .map_err(SdlError)?;
+          self.render_time_bar(canvas, round_time, settings.options.round_time)?;
         }
-
-        world.update.queue.clear();
         Ok(())
       })?;
+      for event in &world.events.queue {
+        if kill_feed {
+          feed.push(*event, world.round_counter);
+        }
+        if let Some(ref mut heatmap) = heatmap {
+          heatmap.record(event, &world);
+        }
+      }
+      if kill_feed && feed.tick(world.round_counter) {
+        if hud.has_panels() {
+          ctx.with_screen_context(|canvas| self.render_panel_feed(canvas, &hud, &world, &feed))?;
+        } else {
+          ctx.with_render_context(|canvas| self.render_kill_feed(canvas, &world, &feed))?;
+        }
+      }
+      world.events.clear();
+      if hud.panels_outdated() {
+        ctx.with_screen_context(|canvas| self.render_hud_panels(canvas, &mut hud, &world))?;
+      }
 
       if !world.campaign_mode && round_time >= settings.options.round_time {
         break RoundEnd::Round;
@@ -426,31 +1209,386 @@ impl Application<'_> {
         break RoundEnd::Round;
       }
 
+      let mut effects = PostEffects::shake(world.shake_offset());
       if world.flash {
-        ctx.present_flash()?;
-      } else if world.shake % 2 != 0 {
-        ctx.present_shake(world.shake)?;
-      } else {
-        ctx.present()?;
+        effects = effects.combine(PostEffects::flash());
+      }
+      // Smooth movement: show an extra frame with actors half way between the last two ticks (actors
+      // list could have changed during the tick, in that case we don't interpolate)
+      let interpolate = !previous.is_empty() && previous.len() == world.actors.len();
+      if interpolate {
+        let current: Vec<Position> = world.actors.iter().map(|actor| actor.pos).collect();
+        let halfway: Vec<Position> = previous.iter().zip(&current).map(|(a, b)| halfway(*a, *b)).collect();
+        ctx.with_render_context(|canvas| self.move_actors(canvas, &world, &digging, &current, &halfway))?;
+        ctx.present_with(effects)?;
+        std::thread::sleep(TICK_DURATION / 2);
+        ctx.with_render_context(|canvas| self.move_actors(canvas, &world, &digging, &halfway, &current))?;
+      }
+      ctx.present_with(effects)?;
+      if let Some(ref mut capture) = capture {
+        if capture.wants(world.round_counter) {
+          capture.push(ctx.read_buffer()?);
+        }
       }
 
       // Play sound effects
       for request in &world.effects.queue {
+        // Don't give away exact location of what is going on in the darkness
+        let request = if settings.options.muffled_fog_sounds {
+          request.muffled(&world.maps, self.viewer())
+        } else {
+          *request
+        };
         self.effects.play(request.effect, request.frequency, request.location)?;
       }
-      world.effects.queue.clear();
+      world.effects.clear();
+      world.audible_fuses(MAX_FUSE_SOUNDS, &mut fuses);
+      if settings.options.muffled_fog_sounds {
+        fuses.retain(|fuse| Visibility::Revealed.is_visible(self.viewer(), *fuse, &world.maps));
+      }
+      self.effects.play_fuses(&fuses)?;
 
-      std::thread::sleep(std::time::Duration::from_millis(20));
+      std::thread::sleep(if interpolate { TICK_DURATION / 2 } else { TICK_DURATION });
     };
+    self.effects.play_fuses(&[])?;
+    if world.maps.darkness && exit_reason != RoundEnd::Game && !settings.scripted {
+      self.post_round_review(ctx, &world)?;
+    }
 
     sdl2::mixer::Music::halt();
-    ctx.animate(Animation::FadeDown, 7)?;
+    if !settings.scripted {
+      ctx.transition(Animation::FadeDown)?;
+    }
+    if panel_width > 0 {
+      // Side panels are not used outside of the game
+      ctx.with_screen_context(|canvas| {
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+        Ok(())
+      })?;
+    }
+
+    if let Some(heatmap) = heatmap.filter(|_| exit_reason != RoundEnd::Game) {
+      if let Err(err) = heatmap.save(ctx.game_dir(), round, level_name) {
+        eprintln!("{:#}", anyhow::Error::from(err));
+      }
+    }
+
+    if let Some(capture) = capture {
+      capture.finish();
+    }
+
+    if settings.tuning.is_some() {
+      if let Err(err) = world.profile.save(ctx.game_dir(), round, level_name) {
+        eprintln!("Failed to save tick profile: {:#}", err);
+      }
+    }
+
+    let settlement = world.end_of_round();
+    let fast_forward = campaign_mode && settings.options.fast_forward;
+    if exit_reason != RoundEnd::Game && bonus.is_none() && !settings.scripted && !fast_forward {
+      self.cash_tally(ctx, world.players, &settlement)?;
+    }
+    Ok((exit_reason, round_time, world.exited, settlement))
+  }
+
+  /// Briefly show round information over the game screen. Any key press skips the overlay. Returns
+  /// `true` if the window was closed meanwhile.
+  fn round_intro(
+    &self,
+    ctx: &mut ApplicationContext,
+    world: &World,
+    round: u16,
+    total_rounds: u16,
+    level_name: &str,
+    settings: &GameSettings,
+  ) -> Result<bool, anyhow::Error> {
+    let objective = if world.bonus_round {
+      "Grab treasures before time runs out"
+    } else if world.campaign_mode {
+      "Collect gold and find the exit"
+    } else if settings.options.win == WinCondition::ByWins {
+      "Most round wins takes it all"
+    } else {
+      "Most money takes it all"
+    };
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(170, 190, 300, 66)).map_err(SdlError)?;
+      let roles = &self.players.roles;
+      let round_text = if world.bonus_round {
+        "Bonus round".to_owned()
+      } else {
+        format!("Round {} of {}", round + 1, total_rounds)
+      };
+      self
+        .font
+        .render(canvas, 180, 198, roles[PaletteRole::TextPrimary], &round_text)?;
+      self
+        .font
+        .render(canvas, 180, 212, roles[PaletteRole::Highlight], level_name)?;
+      self
+        .font
+        .render(canvas, 180, 226, roles[PaletteRole::TextSecondary], objective)?;
+      if world.maps.darkness {
+        self
+          .font
+          .render(canvas, 180, 240, roles[PaletteRole::Notice], "Darkness")?;
+      }
+      Ok(())
+    })?;
+    ctx.present()?;
+
+    // Show overlay for 2 seconds
+    let mut closed = false;
+    'wait: for _ in 0..100 {
+      for event in ctx.poll_iter() {
+        match event {
+          Event::KeyDown { .. } => break 'wait,
+          Event::Quit { .. } => {
+            closed = true;
+            break 'wait;
+          }
+          _ => {}
+        }
+      }
+      std::thread::sleep(Duration::from_millis(20));
+    }
 
-    world.end_of_round();
-    Ok(exit_reason)
+    ctx.with_render_context(|canvas| {
+      self.render_game_screen(canvas, world)?;
+      Ok(())
+    })?;
+    ctx.present()?;
+    Ok(closed)
   }
 
-  fn render_game_screen(&self, canvas: &mut WindowCanvas, world: &World) -> Result<(), anyhow::Error> {
+  /// Render updates of the world state as seen by the given viewer. Splatters beyond the given
+  /// amount are skipped.
+  pub(super) fn render_updates(
+    &self,
+    canvas: &mut WindowCanvas,
+    world: &mut World,
+    viewer: Viewer,
+    max_splatters: usize,
+  ) -> Result<(), anyhow::Error> {
+    // Go through each update and render it (unless it's not visible to the viewer)
+    let maps = &world.maps;
+    let revealed = |cursor: Cursor| Visibility::Revealed.is_visible(viewer, cursor, maps);
+    let owned = |player: usize, cursor: Cursor| Visibility::Player(player).is_visible(viewer, cursor, maps);
+    let mut splatters = 0;
+    for update in &world.update.queue {
+      match *update {
+        Update::Actor(actor, digging) => {
+          let cheat = if actor < world.players.len() {
+            world.players[actor].glyph_cheat()
+          } else if !revealed(world.actors[actor].pos.cursor()) {
+            // Monsters lurking in the darkness
+            continue;
+          } else {
+            None
+          };
+          let actor = &world.actors[actor];
+          self.render_actor(canvas, actor, cheat, digging)?;
+        }
+        Update::Map(cursor, visibility) => {
+          if !visibility.is_visible(viewer, cursor, maps) {
+            continue;
+          }
+          self.render_cell(canvas, world, viewer, cursor)?;
+        }
+        Update::Border(cursor) if revealed(cursor) => {
+          self.render_dirt_border(canvas, cursor, &maps.level)?;
+        }
+        Update::BurnedBorder(cursor) if revealed(cursor) => {
+          self.render_burned_border(canvas, cursor, maps)?;
+        }
+        Update::Splatter(cursor, dir, splatter) if revealed(cursor) && splatters < max_splatters => {
+          splatters += 1;
+          self.render_splatter(canvas, cursor, dir, splatter)?;
+        }
+        Update::JumpTarget(player, cursor) if owned(player, cursor) => {
+          render_jump_marker(canvas, player, cursor)?;
+        }
+        Update::Spark(cursor) if revealed(cursor) => {
+          render_spark(canvas, cursor)?;
+        }
+        Update::Border(_)
+        | Update::BurnedBorder(_)
+        | Update::Splatter(..)
+        | Update::JumpTarget(..)
+        | Update::Spark(_) => {}
+      }
+    }
+    world.update.clear();
+    Ok(())
+  }
+
+  /// Who is looking at the game screen: in the network game, screen belongs to the local player only
+  fn viewer(&self) -> Viewer {
+    self
+      .netplay
+      .as_ref()
+      .and_then(RemotePlayers::local)
+      .map_or(Viewer::Shared, Viewer::Player)
+  }
+
+  /// Render map cell together with markers on it visible to the viewer
+  fn render_cell(
+    &self,
+    canvas: &mut WindowCanvas,
+    world: &World,
+    viewer: Viewer,
+    cursor: Cursor,
+  ) -> Result<(), anyhow::Error> {
+    let maps = &world.maps;
+    let owned = |player: usize| Visibility::Player(player).is_visible(viewer, cursor, maps);
+    let overlay = world.death_overlay(cursor).or_else(|| world.visual_overlay(cursor));
+    self.render_map_square(canvas, cursor, overlay, maps)?;
+    if let Some(player) = world.jump_target_owner(cursor).filter(|player| owned(*player)) {
+      render_jump_marker(canvas, player, cursor)?;
+    }
+    if let Some(player) = world.radio_ping_owner(cursor).filter(|player| owned(*player)) {
+      render_radio_ping(canvas, player, cursor)?;
+    }
+    Ok(())
+  }
+
+  /// Move rendered actors from one set of positions to another: cells under actors which have moved
+  /// are restored first, then all visible actors are rendered in their new positions.
+  fn move_actors(
+    &self,
+    canvas: &mut WindowCanvas,
+    world: &World,
+    digging: &[Digging],
+    from: &[Position],
+    to: &[Position],
+  ) -> Result<(), anyhow::Error> {
+    let maps = &world.maps;
+    let viewer = self.viewer();
+    let revealed = |cursor: Cursor| Visibility::Revealed.is_visible(viewer, cursor, maps);
+    for (from, to) in from.iter().zip(to) {
+      if from == to {
+        continue;
+      }
+      // Actor glyph covers up to four cells
+      let top_left = Position::new(from.x - 5, from.y - 5).cursor();
+      let bottom_right = Position::new(from.x + 4, from.y + 4).cursor();
+      for cursor in Cursor::rect(top_left, bottom_right) {
+        if revealed(cursor) {
+          self.render_cell(canvas, world, viewer, cursor)?;
+        } else {
+          canvas.set_draw_color(Color::BLACK);
+          canvas.fill_rect(cell_rect(cursor)).map_err(SdlError)?;
+        }
+      }
+    }
+    for (idx, (actor, pos)) in world.actors.iter().zip(to).enumerate() {
+      if actor.is_dead || (idx >= world.players.len() && !revealed(pos.cursor())) {
+        continue;
+      }
+      let cheat = world.players.get(idx).and_then(PlayerComponent::glyph_cheat);
+      self.render_actor_at(canvas, actor, *pos, cheat, digging[idx])?;
+    }
+    Ok(())
+  }
+
+  /// Redraw the whole map with all actors on it. If `reveal` is set, cells hidden in darkness are
+  /// shown, too.
+  fn render_full_map(&self, canvas: &mut WindowCanvas, world: &World, reveal: bool) -> Result<(), anyhow::Error> {
+    let maps = &world.maps;
+    let visible = |cursor: Cursor| reveal || Visibility::Revealed.is_visible(Viewer::Shared, cursor, maps);
+    for cursor in Cursor::all() {
+      if visible(cursor) {
+        let overlay = world.death_overlay(cursor).or_else(|| world.visual_overlay(cursor));
+        self.render_map_square(canvas, cursor, overlay, maps)?;
+      } else {
+        canvas.set_draw_color(Color::BLACK);
+        canvas.fill_rect(cell_rect(cursor)).map_err(SdlError)?;
+      }
+    }
+    for (idx, actor) in world.actors.iter().enumerate() {
+      if actor.is_dead || (idx >= world.players.len() && !visible(actor.pos.cursor())) {
+        continue;
+      }
+      let cheat = world.players.get(idx).and_then(PlayerComponent::glyph_cheat);
+      self.render_actor(canvas, actor, cheat, Digging::Hands)?;
+    }
+    Ok(())
+  }
+
+  /// Apply level palette and tile sheet. If skin cannot be loaded, level is played with the regular
+  /// glyphs.
+  fn apply_level_skin(&self, ctx: &ApplicationContext<'_, 't>, meta: &LevelMeta) -> LevelSkin<'_, 't> {
+    if meta.has_skin() {
+      let tiles = meta.tiles.as_deref().unwrap_or("SIKA.SPY");
+      match ctx.load_glyph_sheet(tiles, &meta.palette) {
+        Ok(sheet) => self.glyphs.set_skin(Some(sheet)),
+        Err(err) => eprintln!("Failed to load level skin: {:#}", err),
+      }
+    }
+    LevelSkin(&self.glyphs)
+  }
+
+  /// Let players review the round played in darkness: while review key is held, the whole map is
+  /// revealed together with paths of all players. Any other key (or timeout) skips the review.
+  fn post_round_review(&self, ctx: &mut ApplicationContext, world: &World) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      let roles = &self.players.roles;
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(170, 220, 300, 24)).map_err(SdlError)?;
+      self.font.render(
+        canvas,
+        180,
+        228,
+        roles[PaletteRole::TextPrimary],
+        "Hold TAB to reveal the map",
+      )?;
+      Ok(())
+    })?;
+    ctx.present()?;
+
+    let mut reviewing = false;
+    let mut idle = 0;
+    while idle < REVIEW_WAIT_FRAMES {
+      let mut skip = false;
+      for event in ctx.poll_iter() {
+        if let Event::KeyDown {
+          scancode: Some(scancode),
+          ..
+        } = event
+        {
+          skip |= scancode != Scancode::Tab;
+        }
+      }
+      if skip {
+        break;
+      }
+      let held = ctx.is_key_pressed(Scancode::Tab);
+      if held != reviewing {
+        reviewing = held;
+        ctx.with_render_context(|canvas| {
+          self.render_full_map(canvas, world, reviewing)?;
+          if reviewing {
+            for (player, actor) in world.actors.iter().take(world.players.len()).enumerate() {
+              render_path(canvas, player, &actor.path)?;
+            }
+          }
+          Ok(())
+        })?;
+        ctx.present()?;
+      }
+      if reviewing {
+        idle = 0;
+      } else {
+        idle += 1;
+      }
+      std::thread::sleep(Duration::from_millis(20));
+    }
+    Ok(())
+  }
+
+  pub(super) fn render_game_screen(&self, canvas: &mut WindowCanvas, world: &World) -> Result<(), anyhow::Error> {
     canvas.copy(&self.players.texture, None, None).map_err(SdlError)?;
 
     self.render_level(canvas, &world.maps.level, world.maps.darkness)?;
@@ -469,25 +1607,11 @@ impl Application<'_> {
       }
     }
 
-    self.render_players_info(canvas, world)?;
-    if world.campaign_mode {
-      self.render_lives(canvas, world.players.len() as i32, world.players[0].lives)?;
-    } else {
-      // Time bar
-      canvas.set_draw_color(self.players.palette[6]);
-      canvas.fill_rect(Rect::new(2, 473, 635, 5)).map_err(SdlError)?;
-    }
-    Ok(())
+    self.render_full_hud(canvas, world)
   }
 
   fn render_level(&self, canvas: &mut WindowCanvas, level: &LevelMap, darkness: bool) -> Result<(), anyhow::Error> {
-    let mut render = |cursor: Cursor| {
-      let glyph = Glyph::Map(level[cursor]);
-      let pos = cursor.position();
-      self
-        .glyphs
-        .render(canvas, i32::from(pos.x) - 5, i32::from(pos.y) - 5, glyph)
-    };
+    let mut render = |cursor: Cursor| self.render_map_glyph(canvas, cursor, level[cursor]);
     if darkness {
       // Only render borders
       for row in 0..MAP_ROWS {
@@ -521,10 +1645,6 @@ impl Application<'_> {
     cursor: Cursor,
     level: &LevelMap,
   ) -> Result<(), anyhow::Error> {
-    let pos = cursor.position();
-    let pos_x = i32::from(pos.x);
-    let pos_y = i32::from(pos.y);
-
     // Dirt
     for dir in Direction::all() {
       let value = level[cursor.to(dir)];
@@ -536,13 +1656,10 @@ impl Application<'_> {
         _ => false,
       };
       if (value >= MapValue::Sand1 && value <= MapValue::HeavyGravel) || is_corner {
-        let (dx, dy) = border_offset(dir);
-        self.glyphs.render(
-          canvas,
-          pos_x + dx,
-          pos_y + dy,
-          Glyph::SandBorder(dir.reverse(), Border::Normal),
-        )?;
+        let (x, y) = border_origin(cursor, dir);
+        self
+          .glyphs
+          .render(canvas, x, y, Glyph::SandBorder(dir.reverse(), Border::Normal))?;
       }
     }
 
@@ -550,31 +1667,22 @@ impl Application<'_> {
     for dir in Direction::all() {
       let value = level[cursor.to(dir)];
       if value.is_stone() {
-        let (dx, dy) = border_offset(dir);
-        self.glyphs.render(
-          canvas,
-          pos_x + dx,
-          pos_y + dy,
-          Glyph::StoneBorder(dir.reverse(), Border::Normal),
-        )?;
+        let (x, y) = border_origin(cursor, dir);
+        self
+          .glyphs
+          .render(canvas, x, y, Glyph::StoneBorder(dir.reverse(), Border::Normal))?;
       }
     }
     Ok(())
   }
 
   /// Render burned border for both stone and dirt blocks
-  fn render_burned_border(
-    &self,
-    canvas: &mut WindowCanvas,
-    cursor: Cursor,
-    level: &LevelMap,
-  ) -> Result<(), anyhow::Error> {
-    let pos = cursor.position();
-    let pos_x = i32::from(pos.x);
-    let pos_y = i32::from(pos.y);
-
+  fn render_burned_border(&self, canvas: &mut WindowCanvas, cursor: Cursor, maps: &Maps) -> Result<(), anyhow::Error> {
+    let level = &maps.level;
+    // Cells burned by the explosion are shown in the visual layer
+    let exploded = |cursor: Cursor| maps.visual[cursor].is_some_and(|visual| visual.frame == MapValue::Explosion);
     let value = level[cursor];
-    if value == MapValue::Explosion || value == MapValue::MonsterDying {
+    if exploded(cursor) || value == MapValue::MonsterDying {
       for dir in Direction::all() {
         let value = level[cursor.to(dir)];
         let glyph = if value.is_sand() || value == MapValue::LightGravel || value == MapValue::HeavyGravel {
@@ -584,21 +1692,18 @@ impl Application<'_> {
         } else {
           continue;
         };
-        let (dx, dy) = border_offset(dir);
-        self.glyphs.render(canvas, pos_x + dx, pos_y + dy, glyph)?;
+        let (x, y) = border_origin(cursor, dir);
+        self.glyphs.render(canvas, x, y, glyph)?;
       }
     } else if value == MapValue::HeavyGravel {
       // FIXME: not sure when this one is triggered?
       for dir in Direction::all() {
         let value = level[cursor.to(dir)];
-        if value.is_passable() || value == MapValue::Explosion || value == MapValue::MonsterDying {
-          let (dx, dy) = border_offset(dir);
-          self.glyphs.render(
-            canvas,
-            pos_x + dx,
-            pos_y + dy,
-            Glyph::SandBorder(dir.reverse(), Border::Burned),
-          )?;
+        if value.is_passable() || exploded(cursor.to(dir)) || value == MapValue::MonsterDying {
+          let (x, y) = border_origin(cursor, dir);
+          self
+            .glyphs
+            .render(canvas, x, y, Glyph::SandBorder(dir.reverse(), Border::Burned))?;
         }
       }
     }
@@ -618,7 +1723,7 @@ impl Application<'_> {
       SplatterKind::Slime => 4,
     };
     canvas.set_draw_color(self.players.palette[color]);
-    let pos = cursor.position();
+    let center = cell_center(cursor);
     loop {
       let (delta_x, delta_y) = match dir {
         Direction::Left => (-5 - rng.gen_range(0..3), rng.gen_range(-5..5)),
@@ -627,7 +1732,7 @@ impl Application<'_> {
         Direction::Down => (rng.gen_range(-5..5), 5 + rng.gen_range(0..3)),
       };
       canvas
-        .draw_point((i32::from(pos.x) + delta_x, i32::from(pos.y) + delta_y))
+        .draw_point((center.x() + delta_x, center.y() + delta_y))
         .map_err(SdlError)?;
       if rng.gen_range(0..10) == 0 {
         break;
@@ -636,91 +1741,72 @@ impl Application<'_> {
     Ok(())
   }
 
-  fn render_players_info(&self, canvas: &mut WindowCanvas, world: &World) -> Result<(), anyhow::Error> {
-    // Erase extra players
-    let players_len = world.players.len() as u16;
-    if players_len < 4 {
-      let rect = Rect::new(i32::from(players_len) * 160, 0, u32::from(4 - players_len) * 160, 30);
-      canvas.set_draw_color(Color::BLACK);
-      canvas.fill_rect(rect).map_err(SdlError)?;
-    }
-
-    // Current weapon selection
-    const PLAYER_X: [i32; 4] = [12, 174, 337, 500];
-    let palette = &self.players.palette;
-    for (idx, player) in world.players.iter().enumerate() {
-      let pos_x = PLAYER_X[idx];
-      self
-        .glyphs
-        .render(canvas, pos_x, 0, Glyph::Selection(player.selection))?;
-      self.font.render(
-        canvas,
-        pos_x,
-        0,
-        palette[1],
-        &player.inventory[player.selection].to_string(),
-      )?;
-
-      canvas.set_draw_color(Color::BLACK);
-      canvas.fill_rect(Rect::new(pos_x + 50, 11, 40, 8)).map_err(SdlError)?;
-      self.font.render(
-        canvas,
-        pos_x + 50,
-        11,
-        palette[3],
-        &world.actors[idx].drilling.to_string(),
-      )?;
-      self
-        .font
-        .render(canvas, pos_x + 36, 1, palette[1], &player.stats.name)?;
-
-      canvas.set_draw_color(Color::BLACK);
-      canvas.fill_rect(Rect::new(pos_x + 50, 21, 40, 8)).map_err(SdlError)?;
-
-      let cash_idx = if world.campaign_mode { 0 } else { idx };
-      let total_cash = world.players[cash_idx].cash + world.actors[cash_idx].accumulated_cash;
-      self
-        .font
-        .render(canvas, pos_x + 50, 21, palette[5], &total_cash.to_string())?;
-    }
+  /// Show which player game is waiting for
+  fn render_idle_overlay(&self, canvas: &mut WindowCanvas, world: &World, player: usize) -> Result<(), anyhow::Error> {
+    let roles = &self.players.roles;
+    canvas.set_draw_color(Color::BLACK);
+    canvas.fill_rect(Rect::new(170, 205, 300, 38)).map_err(SdlError)?;
+    let name = &world.players[player].stats.name;
+    self
+      .font
+      .render(canvas, 180, 213, roles[PaletteRole::TextPrimary], "Waiting for")?;
+    self
+      .font
+      .render(canvas, 276, 213, roles[PaletteRole::player(player)], name)?;
+    self.font.render(
+      canvas,
+      180,
+      227,
+      roles[PaletteRole::TextSecondary],
+      "Press any key to continue",
+    )?;
+    Ok(())
+  }
 
-    // Players health
-    const HEALTH_COLOR: [usize; 4] = [2, 3, 4, 6];
-    const HEALTH_BAR_LEFT: [i32; 4] = [142, 304, 467, 630];
-    for player in 0..world.players.len() {
-      let actor = &world.actors[player];
-      let health_bars = if actor.health == 0 {
-        0
-      } else {
-        (u32::from(actor.health) * 50 + 1) / (2 * u32::from(actor.max_health)) + 1
-      };
-      let left = HEALTH_BAR_LEFT[player];
-      canvas.set_draw_color(Color::BLACK);
-      if health_bars < 25 {
-        canvas
-          .fill_rect(Rect::new(left, 2, 8, 26 - health_bars))
-          .map_err(SdlError)?;
+  /// Render kill feed in the bottom left corner of the map. Map under the feed is rendered again
+  /// first, so old lines are erased.
+  fn render_kill_feed(&self, canvas: &mut WindowCanvas, world: &World, feed: &KillFeed) -> Result<(), anyhow::Error> {
+    let top_row = MAP_ROWS - 1 - FEED_LINES as u16;
+    let in_feed = |cursor: Cursor| cursor.row >= top_row && cursor.row < MAP_ROWS - 1 && cursor.col <= FEED_COLS;
+    for cursor in Cursor::all_without_borders().filter(|cursor| in_feed(*cursor)) {
+      if world.maps.darkness && world.maps.fog[cursor].dark {
+        canvas.set_draw_color(Color::BLACK);
+        canvas.fill_rect(cell_rect(cursor)).map_err(SdlError)?;
+        continue;
       }
-      if health_bars > 0 {
-        canvas.set_draw_color(palette[HEALTH_COLOR[player]]);
-        canvas
-          .fill_rect(Rect::new(left, 28 - (health_bars as i32), 8, health_bars))
-          .map_err(SdlError)?;
+      let value = world.maps.level[cursor];
+      self.render_map_glyph(canvas, cursor, value)?;
+      if DIRT_BORDER_BITMAP[value] {
+        self.render_dirt_border(canvas, cursor, &world.maps.level)?;
+      }
+      if let Some(player) = world.jump_target_owner(cursor) {
+        render_jump_marker(canvas, player, cursor)?;
+      }
+    }
+    for (idx, actor) in world.actors.iter().enumerate() {
+      if !actor.is_dead && in_feed(actor.pos.cursor()) {
+        let cheat = world.players.get(idx).and_then(|player| player.glyph_cheat());
+        self.render_actor(canvas, actor, cheat, Digging::Hands)?;
       }
     }
-    Ok(())
-  }
 
-  fn render_lives(&self, canvas: &mut WindowCanvas, players: i32, lives: u16) -> Result<(), anyhow::Error> {
-    canvas.set_draw_color(Color::BLACK);
-    canvas
-      .fill_rect(Rect::new(160 * players, 2, 480, 28))
-      .map_err(SdlError)?;
-    for idx in 0..lives.max(3) {
-      let glyph = if idx < lives { Glyph::Life } else { Glyph::LifeLost };
-      self
-        .glyphs
-        .render(canvas, i32::from(idx * 16) + 160 * players, 2, glyph)?;
+    let roles = &self.players.roles;
+    let lines = feed.lines(world.round_counter).collect::<Vec<_>>();
+    let bottom = 30 + 10 * i32::from(MAP_ROWS - 1);
+    for (idx, (segments, brightness)) in lines.iter().enumerate() {
+      let y = bottom - 10 * (lines.len() - idx) as i32 + 1;
+      let mut x = 12;
+      let mut remaining = FEED_LINE_LENGTH;
+      for segment in segments.iter() {
+        let (text, color) = match segment.text(world, roles) {
+          Some(text) => text,
+          None => continue,
+        };
+        let text = text.chars().take(remaining).collect::<String>();
+        remaining -= text.chars().count();
+        self.font.render(canvas, x, y, fade(color, *brightness), &text)?;
+        x += 8 * text.chars().count() as i32;
+      }
     }
     Ok(())
   }
@@ -731,6 +1817,19 @@ impl Application<'_> {
     actor: &ActorComponent,
     cheat: Option<GlyphCheat>,
     digging: Digging,
+  ) -> Result<(), anyhow::Error> {
+    self.render_actor_at(canvas, actor, actor.pos, cheat, digging)
+  }
+
+  /// Render actor at the given position (which could be different from the actor position when
+  /// movement is smoothed)
+  fn render_actor_at(
+    &self,
+    canvas: &mut WindowCanvas,
+    actor: &ActorComponent,
+    pos: Position,
+    cheat: Option<GlyphCheat>,
+    digging: Digging,
   ) -> Result<(), anyhow::Error> {
     let phase = match actor.animation / 5 {
       _ if !actor.moving => AnimationPhase::Phase1,
@@ -742,8 +1841,7 @@ impl Application<'_> {
       _ => AnimationPhase::Phase2,
     };
 
-    let pos_x = i32::from(actor.pos.x) - 5;
-    let pos_y = i32::from(actor.pos.y) - 5;
+    let (pos_x, pos_y) = actor_origin(pos);
     // Check for glyph-related cheat codes
 
     let kind = match cheat {
@@ -752,36 +1850,144 @@ impl Application<'_> {
       Some(GlyphCheat::Invisible) => return Ok(()),
     };
     let glyph = Glyph::Monster(kind, actor.facing, digging, phase);
-    self.glyphs.render(canvas, pos_x, pos_y, glyph)?;
+    if kind == ActorKind::Trader {
+      // Trader re-uses alien sprite, so make it golden to stand out
+      self
+        .glyphs
+        .render_tinted(canvas, pos_x, pos_y, glyph, Color::RGB(255, 215, 0))?;
+    } else {
+      self.glyphs.render(canvas, pos_x, pos_y, glyph)?;
+    }
     Ok(())
   }
 
-  fn reveal_map_square(&self, canvas: &mut WindowCanvas, cursor: Cursor, maps: &mut Maps) -> Result<(), anyhow::Error> {
-    // FIXME: temporary. Need to figure out what to do with time bar
-    if cursor.row == MAP_ROWS - 1 {
-      return Ok(());
+  /// Render map cell and reveal it. `overlay` is the animation frame to render instead of the cell value.
+  fn render_map_square(
+    &self,
+    canvas: &mut WindowCanvas,
+    cursor: Cursor,
+    overlay: Option<MapValue>,
+    maps: &Maps,
+  ) -> Result<(), anyhow::Error> {
+    let value = overlay.unwrap_or(maps.level[cursor]);
+    if value == MapValue::Exit && maps.exit_locked {
+      // Locked exit is dimmed until enough gold is collected
+      let (x, y) = cell_origin(cursor);
+      self
+        .glyphs
+        .render_tinted(canvas, x, y, Glyph::Map(value), Color::RGB(96, 96, 96))?;
+    } else {
+      self.render_map_glyph(canvas, cursor, value)?;
     }
-
-    let glyph = Glyph::Map(maps.level[cursor]);
-    let pos = cursor.position();
-    self
-      .glyphs
-      .render(canvas, i32::from(pos.x) - 5, i32::from(pos.y) - 5, glyph)?;
-    // FIXME: move to world?
-    maps.fog[cursor].reveal();
     Ok(())
   }
+
+  /// Render map value glyph into the given cell
+  fn render_map_glyph(&self, canvas: &mut WindowCanvas, cursor: Cursor, value: MapValue) -> Result<(), anyhow::Error> {
+    let glyph = Glyph::Map(value);
+    let (x, y) = cell_origin(cursor);
+    if value == MapValue::BiomassHeart {
+      // Heart re-uses biomass image, so make it red to stand out
+      self.glyphs.render_tinted(canvas, x, y, glyph, Color::RGB(255, 96, 96))
+    } else if value == MapValue::Fire {
+      // Fire re-uses explosion image, so make it orange to tell them apart
+      self.glyphs.render_tinted(canvas, x, y, glyph, Color::RGB(255, 160, 64))
+    } else if let Some(color) = value.key_color() {
+      self.glyphs.render(canvas, x, y, glyph)?;
+      render_key(canvas, x, y, color)
+    } else if let Some(color) = value.locked_door_color() {
+      // Locked doors are tinted with the color of the key opening them
+      self.glyphs.render_tinted(canvas, x, y, glyph, color.color())
+    } else {
+      self.glyphs.render(canvas, x, y, glyph)
+    }
+  }
+}
+
+/// Render door key of the given color with its top left corner at the given position
+fn render_key(canvas: &mut WindowCanvas, x: i32, y: i32, color: KeyColor) -> Result<(), anyhow::Error> {
+  canvas.set_draw_color(color.color());
+  // Bow, shaft and two teeth
+  for (dx, dy, w, h) in [
+    (1, 3, 4, 1),
+    (1, 6, 4, 1),
+    (1, 4, 1, 2),
+    (4, 4, 1, 2),
+    (5, 4, 4, 1),
+    (6, 5, 1, 2),
+    (8, 5, 1, 2),
+  ] {
+    canvas.fill_rect(Rect::new(x + dx, y + dy, w, h)).map_err(SdlError)?;
+  }
+  Ok(())
+}
+
+/// Render faint marker of the jumping bomb landing spot in the color of the player who placed it
+fn render_jump_marker(canvas: &mut WindowCanvas, player: usize, cursor: Cursor) -> Result<(), anyhow::Error> {
+  let (x, y) = cell_origin(cursor);
+  canvas.set_draw_color(JUMP_MARKER_COLORS[player]);
+  for (dx, dy) in [(1, 1), (7, 1), (1, 7), (7, 7)] {
+    canvas.fill_rect(Rect::new(x + dx, y + dy, 2, 2)).map_err(SdlError)?;
+  }
+  Ok(())
+}
+
+/// Render trail of the player as a line through the centers of the visited cells. Jumps (teleports)
+/// are not connected.
+fn render_path(canvas: &mut WindowCanvas, player: usize, path: &PathRecorder) -> Result<(), anyhow::Error> {
+  canvas.set_draw_color(JUMP_MARKER_COLORS[player]);
+  for step in path.cells().windows(2) {
+    let (rows, cols) = step[0].distance(step[1]);
+    if rows + cols == 1 {
+      canvas
+        .draw_line(cell_center(step[0]), cell_center(step[1]))
+        .map_err(SdlError)?;
+    }
+  }
+  if let Some(last) = path.cells().last() {
+    let pos = cell_center(*last);
+    canvas
+      .fill_rect(Rect::new(pos.x() - 1, pos.y() - 1, 3, 3))
+      .map_err(SdlError)?;
+  }
+  Ok(())
 }
 
-fn border_offset(dir: Direction) -> (i32, i32) {
-  match dir {
-    Direction::Left => (-9, -5),
-    Direction::Right => (5, -5),
-    Direction::Up => (-5, -8),
-    Direction::Down => (-5, 5),
+/// Position half way between two positions. Actors jumping further than few pixels in a single tick
+/// (teleported, for example) are not interpolated.
+fn halfway(from: Position, to: Position) -> Position {
+  let distance = (i32::from(from.x) - i32::from(to.x)).abs() + (i32::from(from.y) - i32::from(to.y)).abs();
+  if distance > MAX_INTERPOLATED_STEP {
+    to
+  } else {
+    Position::new((from.x + to.x) / 2, (from.y + to.y) / 2)
   }
 }
 
+/// Frame around the radio bomb which was just triggered by its owner
+fn render_radio_ping(canvas: &mut WindowCanvas, player: usize, cursor: Cursor) -> Result<(), anyhow::Error> {
+  let (x, y) = cell_origin(cursor);
+  canvas.set_draw_color(JUMP_MARKER_COLORS[player]);
+  canvas.draw_rect(Rect::new(x, y, 10, 10)).map_err(SdlError)?;
+  Ok(())
+}
+
+/// Draw a single spark at a random spot around the fuse (upper part of the cell)
+fn render_spark(canvas: &mut WindowCanvas, cursor: Cursor) -> Result<(), anyhow::Error> {
+  let (x, y) = cell_origin(cursor);
+  let mut rng = rand::thread_rng();
+  let x = x + rng.gen_range(2..8);
+  let y = y + rng.gen_range(0..4);
+  let color = if rng.gen() {
+    Color::RGB(255, 240, 128)
+  } else {
+    Color::RGB(255, 160, 64)
+  };
+  canvas.set_draw_color(color);
+  canvas.fill_rect(Rect::new(x, y, 1, 1)).map_err(SdlError)?;
+  Ok(())
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PlayerWin {
   Lose,
@@ -789,16 +1995,11 @@ pub enum PlayerWin {
   Win,
 }
 
-fn compute_score(players: &[PlayerComponent], player: usize, win: WinCondition) -> PlayerWin {
-  let scorefn = |player: &PlayerComponent| match win {
-    WinCondition::ByWins => player.rounds_win,
-    WinCondition::ByMoney => player.cash,
-  };
-  let score = scorefn(&players[player]);
-  let bested_by = players.iter().filter(|player| scorefn(player) > score).count();
-  if bested_by == 0 {
+fn compute_score(ranks: &[usize], player: usize) -> PlayerWin {
+  let rank = ranks[player];
+  if rank == 0 {
     PlayerWin::Win
-  } else if bested_by == players.len() - 1 {
+  } else if rank == ranks.len() - 1 {
     PlayerWin::Lose
   } else {
     PlayerWin::Draw
@@ -812,9 +2013,11 @@ fn update_player_stats(
   win: WinCondition,
 ) -> Result<(), anyhow::Error> {
   let mut roster = PlayersRoster::load(game_dir)?;
+  let ranks = compute_ranks(players, win);
   for idx in 0..players.len() {
-    let is_win = compute_score(players, idx, win) == PlayerWin::Win;
+    let is_win = compute_score(&ranks, idx) == PlayerWin::Win;
     let stats = &mut players[idx].stats;
+    stats.ranks[ranks[idx]] += 1;
     let tournament = stats.tournaments as usize;
     let history_len = stats.history.len();
     stats.history[tournament % history_len] = 123;
@@ -0,0 +1,520 @@
+//! Heads-up display of the round: info bars of the players (selection, cash, health and armor),
+//! campaign lives, time bar and side panels of the widescreen mode. HUD tracks which of its parts
+//! are outdated, so the round loop only feeds it with the world updates and asks it to render.
+use crate::coords::{
+  minimap_point, player_area_x, player_info, time_bar, time_bar_elapsed, HEALTH_BAR_LEFT, INFO_CASH, INFO_CLONES,
+  INFO_DRILLING, INFO_NAME, INFO_RADIOS, INFO_SELECTION, INFO_STATUS, TIME_BAR_WIDTH,
+};
+use crate::error::ApplicationError::SdlError;
+use crate::glyphs::Glyph;
+use crate::lockstep::RESTART_VOTE_TICKS;
+use crate::menu::feed::{fade, KillFeed};
+use crate::palette::PaletteRole;
+use crate::world::equipment::Equipment;
+use crate::world::map::MAP_COLS;
+use crate::world::position::Cursor;
+use crate::world::{UpdateQueue, World};
+use crate::{Application, SCREEN_HEIGHT, SCREEN_WIDTH};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use std::time::Duration;
+
+/// Vertical position of each player inventory in the widescreen side panels. Players 1 and 2 are
+/// shown on the left panel, players 3 and 4 are shown on the right panel, below the minimap.
+const INVENTORY_Y: [i32; 4] = [4, 126, 56, 178];
+
+/// Height of the player inventory in the side panel: name plus up to 10 rows of items
+const INVENTORY_HEIGHT: u32 = 120;
+
+/// Top of the kill feed in the left side panel, below the inventories of players 1 and 2
+const FEED_PANEL_Y: i32 = 250;
+
+/// How often (in ticks) minimap in the side panel is redrawn
+const MINIMAP_INTERVAL: usize = 10;
+
+/// How long (in ticks) the warning is shown or hidden once time limit runs out
+const HURRY_FLASH_TICKS: usize = 25;
+
+/// Colors of the jumping bomb landing markers (and minimap dots) for each player (blue, red, green,
+/// yellow)
+pub(super) const JUMP_MARKER_COLORS: [Color; 4] = [
+  Color::RGB(64, 64, 160),
+  Color::RGB(160, 48, 48),
+  Color::RGB(48, 140, 48),
+  Color::RGB(160, 150, 48),
+];
+
+/// Outdated parts of the HUD
+pub struct Hud {
+  /// Width of the widescreen side panels (zero if there are no side panels)
+  panel_width: i32,
+  /// Player info bars need to be rendered again
+  players_info: bool,
+  /// Side panels need to be rendered again
+  side_panels: bool,
+}
+
+impl Hud {
+  /// Create HUD with all parts outdated
+  pub fn new(panel_width: i32) -> Self {
+    Hud {
+      panel_width,
+      players_info: true,
+      side_panels: true,
+    }
+  }
+
+  /// Mark all parts of the HUD as outdated (for example, when the whole screen is rendered again)
+  pub fn invalidate(&mut self) {
+    self.players_info = true;
+    self.side_panels = true;
+  }
+
+  /// Widescreen mode has side panels (kill feed is shown there instead of the map corner)
+  pub fn has_panels(&self) -> bool {
+    self.panel_width > 0
+  }
+
+  /// Side panels need to be rendered again (switching to the whole screen canvas is not free, so
+  /// round loop checks it first)
+  pub fn panels_outdated(&self) -> bool {
+    self.has_panels() && self.side_panels
+  }
+
+  /// Collect changes of the players info from the world update queue. Minimap is refreshed
+  /// periodically, as it changes with every dug cell.
+  pub fn track(&mut self, update: &mut UpdateQueue, tick: usize) {
+    if std::mem::take(&mut update.players_info) {
+      self.players_info = true;
+      self.side_panels = true;
+    }
+    if tick % MINIMAP_INTERVAL == 0 {
+      self.side_panels = true;
+    }
+  }
+}
+
+impl Application<'_> {
+  /// Render outdated parts of the HUD on top of the playfield
+  pub(super) fn render_hud(
+    &self,
+    canvas: &mut WindowCanvas,
+    hud: &mut Hud,
+    world: &World,
+  ) -> Result<(), anyhow::Error> {
+    if std::mem::take(&mut hud.players_info) {
+      self.render_players_info(canvas, world)?;
+      if world.campaign_mode {
+        self.render_lives(canvas, world.players.len(), world.players[0].lives)?;
+        self.render_time_limit(canvas, world)?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Render outdated side panels of the widescreen mode (given canvas is the whole screen)
+  pub(super) fn render_hud_panels(
+    &self,
+    canvas: &mut WindowCanvas,
+    hud: &mut Hud,
+    world: &World,
+  ) -> Result<(), anyhow::Error> {
+    if hud.panels_outdated() {
+      self.render_side_panels(canvas, world, hud.panel_width)?;
+      hud.side_panels = false;
+    }
+    Ok(())
+  }
+
+  /// Render all parts of the HUD on top of the playfield, as part of rendering the whole game screen
+  pub(super) fn render_full_hud(&self, canvas: &mut WindowCanvas, world: &World) -> Result<(), anyhow::Error> {
+    self.render_players_info(canvas, world)?;
+    if world.campaign_mode {
+      self.render_lives(canvas, world.players.len(), world.players[0].lives)?;
+    } else {
+      // Time bar
+      canvas.set_draw_color(self.players.roles[PaletteRole::Notice]);
+      canvas.fill_rect(time_bar()).map_err(SdlError)?;
+    }
+    Ok(())
+  }
+
+  /// Update end of round indicator. Time bar is drawn over the bottom row of the map, so it's redrawn
+  /// completely in case any of the cells under it were updated.
+  pub(super) fn render_time_bar(
+    &self,
+    canvas: &mut WindowCanvas,
+    round_time: Duration,
+    total: Duration,
+  ) -> Result<(), anyhow::Error> {
+    let width = TIME_BAR_WIDTH as u128 * round_time.as_millis() / total.as_millis();
+    canvas.set_draw_color(self.players.roles[PaletteRole::Notice]);
+    canvas.fill_rect(time_bar()).map_err(SdlError)?;
+    canvas.set_draw_color(self.players.roles[PaletteRole::Background]);
+    canvas
+      .fill_rect(time_bar_elapsed(width.min(u128::from(TIME_BAR_WIDTH)) as u32))
+      .map_err(SdlError)?;
+    Ok(())
+  }
+
+  /// Render info bar of every player on the top of the screen: selection, name, cash, drilling
+  /// power, clones, health and armor
+  fn render_players_info(&self, canvas: &mut WindowCanvas, world: &World) -> Result<(), anyhow::Error> {
+    // Erase extra players
+    let players_len = world.players.len();
+    if players_len < 4 {
+      let rect = Rect::new(
+        player_area_x(players_len),
+        0,
+        (player_area_x(4) - player_area_x(players_len)) as u32,
+        30,
+      );
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(rect).map_err(SdlError)?;
+    }
+
+    // Current weapon selection
+    let roles = &self.players.roles;
+    for (idx, player) in world.players.iter().enumerate() {
+      let field = |anchor| player_info(idx, anchor);
+      // Player standing next to the trader sees the offer instead of the current selection
+      let offer = world.trade_offer(idx);
+      let (selection, count) = match offer {
+        Some(offer) => (offer.item, offer.count),
+        None => (player.selection, player.inventory[player.selection]),
+      };
+      let (x, y) = field(INFO_SELECTION);
+      self.glyphs.render(canvas, x, y, Glyph::Selection(selection))?;
+      self
+        .font
+        .render(canvas, x, y, roles[PaletteRole::TextPrimary], &count.to_string())?;
+
+      // Armed radio bombs badge
+      let (x, y) = field(INFO_RADIOS);
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(x, y, 18, 8)).map_err(SdlError)?;
+      let radios = world.armed_radios(idx);
+      if radios > 0 {
+        let text = format!("R{}", radios.min(9));
+        self.font.render(canvas, x, y, roles[PaletteRole::player(idx)], &text)?;
+      }
+
+      let (x, y) = field(INFO_DRILLING);
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(x, y, 40, 8)).map_err(SdlError)?;
+      self.font.render(
+        canvas,
+        x,
+        y,
+        roles[PaletteRole::TextSecondary],
+        &world.actors[idx].drilling.to_string(),
+      )?;
+      let (x, y) = field(INFO_NAME);
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(x, y, 96, 8)).map_err(SdlError)?;
+      if let Some(offer) = offer {
+        self
+          .font
+          .render(canvas, x, y, roles[PaletteRole::Cash], &format!("BUY {}$", offer.price))?;
+      } else {
+        self
+          .font
+          .render(canvas, x, y, roles[PaletteRole::TextPrimary], &player.stats.name)?;
+      }
+
+      let (x, y) = field(INFO_CASH);
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(x, y, 40, 8)).map_err(SdlError)?;
+
+      let cash_idx = if world.campaign_mode { 0 } else { idx };
+      let total_cash = world.players[cash_idx].cash + world.actors[cash_idx].accumulated_cash;
+      self
+        .font
+        .render(canvas, x, y, roles[PaletteRole::Cash], &total_cash.to_string())?;
+
+      // Amount of alive clones
+      let (x, y) = field(INFO_CLONES);
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(x, y, 40, 8)).map_err(SdlError)?;
+      let clones = world.player_clones(idx).count();
+      if clones > 0 {
+        self
+          .font
+          .render(canvas, x, y, roles[PaletteRole::TextPrimary], &format!("C:{}", clones))?;
+      }
+
+      // Time left in the bonus round
+      if world.bonus_round {
+        let (x, y) = field(INFO_STATUS);
+        canvas.set_draw_color(Color::BLACK);
+        canvas.fill_rect(Rect::new(x, y, 72, 8)).map_err(SdlError)?;
+        let text = format!("T:{}", world.bonus_seconds_left());
+        self.font.render(canvas, x, y, roles[PaletteRole::Danger], &text)?;
+      }
+
+      // Gold needed to unlock the exit
+      if world.exit_gold > 0 {
+        let (x, y) = field(INFO_STATUS);
+        canvas.set_draw_color(Color::BLACK);
+        canvas.fill_rect(Rect::new(x, y, 72, 8)).map_err(SdlError)?;
+        let collected = world.collected_gold().min(world.exit_gold);
+        let color = if world.maps.exit_locked {
+          roles[PaletteRole::Danger]
+        } else {
+          roles[PaletteRole::TextPrimary]
+        };
+        let text = format!("E:{}/{}", collected, world.exit_gold);
+        self.font.render(canvas, x, y, color, &text)?;
+      }
+    }
+
+    // Players health
+    for player in 0..world.players.len() {
+      let actor = &world.actors[player];
+      let health_bars = if actor.health == 0 {
+        0
+      } else {
+        (u32::from(actor.health) * 50 + 1) / (2 * u32::from(actor.max_health)) + 1
+      };
+      let left = HEALTH_BAR_LEFT[player];
+      canvas.set_draw_color(Color::BLACK);
+      if health_bars < 25 {
+        canvas
+          .fill_rect(Rect::new(left, 2, 8, 26 - health_bars))
+          .map_err(SdlError)?;
+      }
+      if health_bars > 0 {
+        canvas.set_draw_color(roles[PaletteRole::player(player)]);
+        canvas
+          .fill_rect(Rect::new(left, 28 - (health_bars as i32), 8, health_bars))
+          .map_err(SdlError)?;
+      }
+
+      // Armor is shown as a thin bar to the left of the health bar
+      if actor.max_armor > 0 {
+        let armor_bars = (u32::from(actor.armor) * 26).div_ceil(u32::from(actor.max_armor));
+        canvas.set_draw_color(Color::BLACK);
+        canvas.fill_rect(Rect::new(left - 3, 2, 2, 26)).map_err(SdlError)?;
+        if armor_bars > 0 {
+          canvas.set_draw_color(roles[PaletteRole::TextPrimary]);
+          canvas
+            .fill_rect(Rect::new(left - 3, 28 - (armor_bars as i32), 2, armor_bars))
+            .map_err(SdlError)?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Render lives left in the campaign, in place of the info bars of the missing players
+  fn render_lives(&self, canvas: &mut WindowCanvas, players: usize, lives: u16) -> Result<(), anyhow::Error> {
+    let left = player_area_x(players);
+    canvas.set_draw_color(Color::BLACK);
+    canvas.fill_rect(Rect::new(left, 2, 480, 28)).map_err(SdlError)?;
+    for idx in 0..lives.max(3) {
+      let glyph = if idx < lives { Glyph::Life } else { Glyph::LifeLost };
+      self.glyphs.render(canvas, left + i32::from(idx * 16), 2, glyph)?;
+    }
+    Ok(())
+  }
+
+  /// Time left until monsters get enraged, then a flashing warning (campaign mode)
+  fn render_time_limit(&self, canvas: &mut WindowCanvas, world: &World) -> Result<(), anyhow::Error> {
+    let seconds = match world.time_limit_seconds_left() {
+      Some(seconds) => seconds,
+      None => return Ok(()),
+    };
+    let roles = &self.players.roles;
+    let pos_x = player_area_x(world.players.len());
+    if !world.enraged {
+      let text = format!("Time: {}:{:02}", seconds / 60, seconds % 60);
+      let color = if seconds <= 10 {
+        roles[PaletteRole::Danger]
+      } else {
+        roles[PaletteRole::TextPrimary]
+      };
+      self.font.render(canvas, pos_x, 21, color, &text)?;
+    } else if (world.round_counter / HURRY_FLASH_TICKS) % 2 == 0 {
+      self
+        .font
+        .render(canvas, pos_x, 21, roles[PaletteRole::Danger], "HURRY UP!")?;
+    }
+    Ok(())
+  }
+
+  /// Show progress of the restart vote in place of player names
+  pub(super) fn render_restart_vote(
+    &self,
+    canvas: &mut WindowCanvas,
+    world: &World,
+    votes: &[usize],
+  ) -> Result<(), anyhow::Error> {
+    let roles = &self.players.roles;
+    for (idx, votes) in votes.iter().enumerate() {
+      let (x, y) = player_info(idx, INFO_NAME);
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(x, y, 96, 8)).map_err(SdlError)?;
+      let text = if *votes > 0 {
+        format!("RESTART {}%", 100 * votes.min(&RESTART_VOTE_TICKS) / RESTART_VOTE_TICKS)
+      } else {
+        world.players[idx].stats.name.clone()
+      };
+      self.font.render(canvas, x, y, roles[PaletteRole::player(idx)], &text)?;
+    }
+    Ok(())
+  }
+
+  /// Show which players the network game is waiting for
+  pub(super) fn render_stall_indicator(
+    &self,
+    canvas: &mut WindowCanvas,
+    world: &World,
+    late: &[usize],
+  ) -> Result<(), anyhow::Error> {
+    let names: Vec<&str> = late
+      .iter()
+      .map(|&player| world.players[player].stats.name.as_str())
+      .collect();
+    // Names are truncated, so the indicator fits on the screen
+    let text = format!("WAITING FOR {}", names.join(", "))
+      .chars()
+      .take(((SCREEN_WIDTH - 16) / 8) as usize)
+      .collect::<String>();
+    let width = (text.chars().count() * 8) as u32 + 16;
+    let x = SCREEN_WIDTH.saturating_sub(width) as i32 / 2;
+    canvas.set_draw_color(Color::BLACK);
+    canvas.fill_rect(Rect::new(x, 232, width, 16)).map_err(SdlError)?;
+    self
+      .font
+      .render(canvas, x + 8, 236, self.players.roles[PaletteRole::TextPrimary], &text)?;
+    Ok(())
+  }
+
+  /// Render side panels of the widescreen mode: full inventory of every player and the minimap
+  fn render_side_panels(
+    &self,
+    canvas: &mut WindowCanvas,
+    world: &World,
+    panel_width: i32,
+  ) -> Result<(), anyhow::Error> {
+    let right = panel_width + SCREEN_WIDTH as i32;
+    for (player, &y) in INVENTORY_Y.iter().enumerate().take(world.players.len()) {
+      let x = if player < 2 { 0 } else { right };
+      self.render_inventory(canvas, x, y, panel_width, world, player)?;
+    }
+    self.render_minimap(canvas, right + (panel_width - i32::from(MAP_COLS)) / 2, 4, world)?;
+    Ok(())
+  }
+
+  /// Render kill feed in the left side panel (given canvas is the whole screen). Lines are wrapped to
+  /// the panel width.
+  pub(super) fn render_panel_feed(
+    &self,
+    canvas: &mut WindowCanvas,
+    hud: &Hud,
+    world: &World,
+    feed: &KillFeed,
+  ) -> Result<(), anyhow::Error> {
+    canvas.set_draw_color(Color::BLACK);
+    let height = SCREEN_HEIGHT - FEED_PANEL_Y as u32;
+    canvas
+      .fill_rect(Rect::new(0, FEED_PANEL_Y, hud.panel_width as u32, height))
+      .map_err(SdlError)?;
+
+    let columns = ((hud.panel_width - 4) / 8).max(1) as usize;
+    let mut y = FEED_PANEL_Y;
+    for (segments, brightness) in feed.lines(world.round_counter) {
+      let mut column = 0;
+      for (text, color) in segments
+        .iter()
+        .filter_map(|segment| segment.text(world, &self.players.roles))
+      {
+        let mut chars = text.chars().peekable();
+        while chars.peek().is_some() {
+          if column == columns {
+            column = 0;
+            y += 10;
+          }
+          let chunk = chars.by_ref().take(columns - column).collect::<String>();
+          let x = 2 + 8 * column as i32;
+          self.font.render(canvas, x, y, fade(color, brightness), &chunk)?;
+          column += chunk.chars().count();
+        }
+      }
+      y += 12;
+    }
+    Ok(())
+  }
+
+  /// Render all items player has, three items per row
+  fn render_inventory(
+    &self,
+    canvas: &mut WindowCanvas,
+    x: i32,
+    y: i32,
+    width: i32,
+    world: &World,
+    player: usize,
+  ) -> Result<(), anyhow::Error> {
+    canvas.set_draw_color(Color::BLACK);
+    canvas
+      .fill_rect(Rect::new(x, y, width as u32, INVENTORY_HEIGHT))
+      .map_err(SdlError)?;
+
+    let roles = &self.players.roles;
+    let player = &world.players[player];
+    self
+      .font
+      .render(canvas, x + 2, y, roles[PaletteRole::TextPrimary], &player.stats.name)?;
+    let items = Equipment::all_equipment().filter(|item| player.inventory[*item] > 0);
+    for (pos, item) in items.enumerate() {
+      let item_x = x + 2 + (pos as i32 % 3) * 35;
+      let item_y = y + 10 + (pos as i32 / 3) * 11;
+      self
+        .glyphs
+        .render_scaled(canvas, Rect::new(item_x, item_y, 10, 10), Glyph::Selection(item))?;
+      let color = if item == player.selection {
+        roles[PaletteRole::Highlight]
+      } else {
+        roles[PaletteRole::TextPrimary]
+      };
+      let count = player.inventory[item].to_string();
+      self.font.render(canvas, item_x + 11, item_y + 1, color, &count)?;
+    }
+    Ok(())
+  }
+
+  /// Render overview of the whole map, one pixel per cell
+  fn render_minimap(&self, canvas: &mut WindowCanvas, x: i32, y: i32, world: &World) -> Result<(), anyhow::Error> {
+    let roles = &self.players.roles;
+    for cursor in Cursor::all() {
+      let value = world.maps.level[cursor];
+      let color = if world.maps.darkness && world.maps.fog[cursor].dark {
+        Color::BLACK
+      } else if value.is_treasure() {
+        roles[PaletteRole::Highlight]
+      } else if value.is_passable() {
+        Color::BLACK
+      } else if value.is_sand() {
+        Color::RGB(110, 70, 30)
+      } else {
+        Color::RGB(90, 90, 90)
+      };
+      canvas.set_draw_color(color);
+      canvas.draw_point(minimap_point((x, y), cursor)).map_err(SdlError)?;
+    }
+
+    for (player, actor) in world.actors.iter().take(world.players.len()).enumerate() {
+      if actor.is_dead {
+        continue;
+      }
+      let point = minimap_point((x, y), actor.pos.cursor());
+      canvas.set_draw_color(JUMP_MARKER_COLORS[player]);
+      canvas
+        .fill_rect(Rect::new(point.x() - 1, point.y() - 1, 3, 3))
+        .map_err(SdlError)?;
+    }
+    Ok(())
+  }
+}
@@ -1,6 +1,7 @@
 use crate::context::{Animation, ApplicationContext};
 use crate::error::ApplicationError::SdlError;
-use crate::keys::{Key, KeysConfig};
+use crate::keys::{ControlStyle, Key, KeysConfig};
+use crate::palette::PaletteRole;
 use crate::Application;
 use sdl2::keyboard::Scancode;
 use sdl2::pixels::Color;
@@ -19,9 +20,9 @@ impl Application<'_> {
       self.render_configured_keys(canvas, keys_config)?;
       Ok(())
     })?;
-    ctx.animate(Animation::FadeUp, 7)?;
+    ctx.transition(Animation::FadeUp)?;
 
-    let color = self.keys.palette[5];
+    let color = self.keys.roles[PaletteRole::Highlight];
     'outer: for player in 0..4 {
       for key in Key::all_keys() {
         let (scan, _) = ctx.wait_key_pressed();
@@ -48,11 +49,27 @@ impl Application<'_> {
         })?;
         ctx.present()?;
       }
+
+      // Left and right keys of the player switch the control style, any other key confirms it
+      loop {
+        let (scan, _) = ctx.wait_key_pressed();
+        if scan == Scancode::F10 {
+          break 'outer;
+        }
+        let keys = &mut keys_config.keys[player];
+        if Some(scan) != keys[Key::Left] && Some(scan) != keys[Key::Right] {
+          break;
+        }
+        keys.style = keys.style.toggle();
+        let style = keys.style;
+        ctx.with_render_context(|canvas| self.render_control_style(canvas, player, style, color))?;
+        ctx.present()?;
+      }
     }
 
     // Save all assigned keys
     keys_config.save(ctx.game_dir())?;
-    ctx.animate(Animation::FadeDown, 7)?;
+    ctx.transition(Animation::FadeDown)?;
     Ok(())
   }
 
@@ -80,9 +97,26 @@ impl Application<'_> {
           }
         }
       }
+      let color = self.keys.palette[COLORS[2]];
+      self.render_control_style(canvas, player, keys_config.keys[player].style, color)?;
     }
     Ok(())
   }
+
+  /// Render control style of the player next to their keys
+  fn render_control_style(
+    &self,
+    canvas: &mut WindowCanvas,
+    player: usize,
+    style: ControlStyle,
+    color: Color,
+  ) -> Result<(), anyhow::Error> {
+    let y = key_pos_y(player, Key::Left);
+    canvas.set_draw_color(Color::BLACK);
+    canvas.fill_rect(Rect::new(516, y, 96, 8)).map_err(SdlError)?;
+    self.font.render(canvas, 516, y, color, style.label())?;
+    Ok(())
+  }
 }
 
 fn key_pos_y(player: usize, key: Key) -> i32 {
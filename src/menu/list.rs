@@ -0,0 +1,161 @@
+//! Framework for the list-based menus: pointer navigation with wraparound, scrolling of the long
+//! lists and rendering of the visible items. Menus provide the layout and a callback which renders
+//! a single item; pointer glyph is handled by the list itself.
+use crate::context::ApplicationContext;
+use crate::error::ApplicationError::SdlError;
+use crate::glyphs::Glyph;
+use crate::keys::MenuAction;
+use crate::Application;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+
+/// Placement of the list menu on the screen
+pub struct ListLayout {
+  /// Position of the first item
+  pub left: i32,
+  pub top: i32,
+  /// Distance between items
+  pub item_height: i32,
+  /// Maximum amount of items shown at once; longer lists are scrolled
+  pub visible: usize,
+  /// Glyph pointing to the selected item
+  pub pointer: Glyph,
+  /// Position of the pointer relative to the item position
+  pub pointer_offset: (i32, i32),
+}
+
+/// Change of the list menu after the navigation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListChange {
+  Unchanged,
+  /// Pointer moved from the given item, but the same items are visible
+  Pointer {
+    previous: usize,
+  },
+  /// List was scrolled, all visible items need to be rendered again
+  Scrolled,
+}
+
+/// State of the list menu: amount of items, selected item and the first visible item
+pub struct ListMenu {
+  layout: ListLayout,
+  len: usize,
+  selected: usize,
+  top: usize,
+}
+
+impl ListMenu {
+  pub fn new(layout: ListLayout, len: usize) -> Self {
+    ListMenu {
+      layout,
+      len,
+      selected: 0,
+      top: 0,
+    }
+  }
+
+  pub fn selected(&self) -> usize {
+    self.selected
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Select given item, scrolling the list if necessary
+  pub fn select(&mut self, selected: usize) -> ListChange {
+    let selected = selected.min(self.len.saturating_sub(1));
+    if selected == self.selected {
+      return ListChange::Unchanged;
+    }
+    let previous = self.selected;
+    self.selected = selected;
+    let visible = self.layout.visible.max(1);
+    let top = if selected < self.top {
+      selected
+    } else if selected >= self.top + visible {
+      selected + 1 - visible
+    } else {
+      self.top
+    };
+    if top != self.top {
+      self.top = top;
+      ListChange::Scrolled
+    } else {
+      ListChange::Pointer { previous }
+    }
+  }
+
+  /// Move the pointer up or down (wrapping around the ends of the list). Other actions are left to
+  /// the menu itself.
+  pub fn navigate(&mut self, action: MenuAction) -> ListChange {
+    if self.len == 0 {
+      return ListChange::Unchanged;
+    }
+    match action {
+      MenuAction::Down => self.select((self.selected + 1) % self.len),
+      MenuAction::Up => self.select((self.selected + self.len - 1) % self.len),
+      _ => ListChange::Unchanged,
+    }
+  }
+
+  /// Visible items together with their positions on the screen
+  pub fn visible_items(&self) -> impl Iterator<Item = (usize, i32, i32)> + '_ {
+    (self.top..self.len)
+      .take(self.layout.visible)
+      .map(move |idx| (idx, self.layout.left, self.item_y(idx)))
+  }
+
+  fn item_y(&self, idx: usize) -> i32 {
+    self.layout.top + (idx - self.top) as i32 * self.layout.item_height
+  }
+
+  fn pointer_pos(&self, idx: usize) -> (i32, i32) {
+    let (dx, dy) = self.layout.pointer_offset;
+    (self.layout.left + dx, self.item_y(idx) + dy)
+  }
+}
+
+impl Application<'_> {
+  /// Render all visible items of the list menu with the pointer next to the selected one
+  pub(super) fn render_list(
+    &self,
+    canvas: &mut WindowCanvas,
+    menu: &ListMenu,
+    mut render_item: impl FnMut(&mut WindowCanvas, usize, i32, i32) -> Result<(), anyhow::Error>,
+  ) -> Result<(), anyhow::Error> {
+    for (idx, x, y) in menu.visible_items() {
+      render_item(canvas, idx, x, y)?;
+    }
+    if !menu.is_empty() {
+      let (x, y) = menu.pointer_pos(menu.selected);
+      self.glyphs.render(canvas, x, y, menu.layout.pointer)?;
+    }
+    Ok(())
+  }
+
+  /// Move pointer from the previous item to the selected one and present the result
+  pub(super) fn update_list_pointer(
+    &self,
+    ctx: &mut ApplicationContext,
+    menu: &ListMenu,
+    previous: usize,
+  ) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      let (old_x, old_y) = menu.pointer_pos(previous);
+      let (w, h) = menu.layout.pointer.dimensions();
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(old_x, old_y, w, h)).map_err(SdlError)?;
+      let (x, y) = menu.pointer_pos(menu.selected);
+      self.glyphs.render(canvas, x, y, menu.layout.pointer)?;
+      Ok(())
+    })?;
+    ctx.present()?;
+    Ok(())
+  }
+}
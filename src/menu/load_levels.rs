@@ -1,16 +1,17 @@
 use crate::context::{Animation, ApplicationContext};
 use crate::error::ApplicationError::SdlError;
-use crate::menu::preview::generate_preview;
-use crate::world::map::{LevelInfo, LevelMap};
+use crate::keys::MenuAction;
+use crate::menu::preview::Preview;
+use crate::options::LevelRotation;
+use crate::palette::PaletteRole;
+use crate::vfs::Vfs;
+use crate::world::map::{LevelInfo, LevelMap, LevelMeta};
 use crate::Application;
 use rand::prelude::*;
 use sdl2::keyboard::Scancode;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::render::{Texture, TextureCreator, WindowCanvas};
-use sdl2::video::WindowContext;
-use std::collections::hash_map::{Entry, HashMap};
-use std::path::Path;
+use sdl2::render::WindowCanvas;
 use std::rc::Rc;
 
 // paletted indices
@@ -74,7 +75,7 @@ impl State {
 
 impl Application<'_> {
   pub fn load_levels(&self, ctx: &mut ApplicationContext, rounds: usize) -> Result<Vec<Rc<LevelInfo>>, anyhow::Error> {
-    let mut levels = find_levels(ctx.game_dir())?;
+    let mut levels = find_levels(ctx.vfs());
 
     // We cannot show more than that
     levels.truncate(327);
@@ -82,7 +83,7 @@ impl Application<'_> {
       ctx.with_render_context(|canvas| {
         canvas.set_draw_color(Color::BLACK);
         canvas.clear();
-        let color = self.main_menu.palette[1];
+        let color = self.main_menu.roles[PaletteRole::TextPrimary];
         self.font.render(
           canvas,
           130,
@@ -99,9 +100,9 @@ impl Application<'_> {
         )?;
         Ok(())
       })?;
-      ctx.animate(Animation::FadeUp, 7)?;
+      ctx.transition(Animation::FadeUp)?;
       ctx.wait_key_pressed();
-      ctx.animate(Animation::FadeDown, 7)?;
+      ctx.transition(Animation::FadeDown)?;
       return Ok(Vec::new());
     }
 
@@ -112,9 +113,9 @@ impl Application<'_> {
     };
 
     self.render_levels_menu(ctx, &state)?;
-    ctx.animate(Animation::FadeUp, 7)?;
+    ctx.transition(Animation::FadeUp)?;
     let levels = self.level_select_loop(ctx, state, rounds)?;
-    ctx.animate(Animation::FadeDown, 7)?;
+    ctx.transition(Animation::FadeDown)?;
     Ok(levels)
   }
 
@@ -124,23 +125,22 @@ impl Application<'_> {
     mut state: State,
     rounds: usize,
   ) -> Result<Vec<Rc<LevelInfo>>, anyhow::Error> {
-    let mut previews = HashMap::new();
     loop {
       let (scan, _) = ctx.wait_key_pressed();
       let last_cursor = state.cursor;
       let mut need_update = false;
-      match scan {
-        Scancode::Escape => break,
-        Scancode::Return | Scancode::KpEnter if state.level_pick.len() < rounds => {
+      match ctx.menu_action(scan) {
+        MenuAction::Back => break,
+        MenuAction::Select if state.level_pick.len() < rounds => {
           state.select_current();
           need_update = true;
         }
-        Scancode::Left | Scancode::Kp4 => state.left(),
-        Scancode::Up | Scancode::Kp8 => state.up(),
-        Scancode::Right | Scancode::Kp6 => state.right(),
-        Scancode::Down | Scancode::Kp2 => state.down(),
+        MenuAction::Left => state.left(),
+        MenuAction::Up => state.up(),
+        MenuAction::Right => state.right(),
+        MenuAction::Down => state.down(),
 
-        Scancode::F1 => {
+        MenuAction::Other(Scancode::F1) => {
           state.randomize(rounds);
 
           // Refresh the whole menu
@@ -157,29 +157,30 @@ impl Application<'_> {
       }
 
       if last_cursor != state.cursor || need_update {
-        let texture_creator = ctx.texture_creator();
         ctx.with_render_context(|canvas| {
           self.render_selected_count(canvas, state.level_pick.len())?;
           self.render_slot(canvas, &state, last_cursor)?;
           self.render_slot(canvas, &state, state.cursor)?;
-          if last_cursor != state.cursor {
-            let preview = match previews.entry(state.cursor) {
-              Entry::Occupied(v) => v.into_mut(),
-              Entry::Vacant(v) => {
-                let texture = self.generate_preview(texture_creator, &state.levels[state.cursor])?;
-                v.insert(texture)
-              }
-            };
-            let rect = Rect::new(330, 7, 64, 45);
-            if let Some(preview) = preview {
-              canvas.copy(preview, None, rect).map_err(SdlError)?;
-            } else {
+          Ok(())
+        })?;
+        if last_cursor != state.cursor {
+          let preview = match &*state.levels[state.cursor] {
+            LevelInfo::Random => Preview::Hidden,
+            LevelInfo::File { map, .. } => Preview::Full(map),
+          };
+          let rect = Rect::new(330, 7, 64, 45);
+          let rendered = match preview.cached(ctx, &self.levels_menu)? {
+            Some(preview) => ctx.render_cached(preview, rect)?,
+            None => false,
+          };
+          if !rendered {
+            ctx.with_render_context(|canvas| {
               canvas.set_draw_color(Color::BLACK);
               canvas.fill_rect(rect).map_err(SdlError)?;
-            }
+              Ok(())
+            })?;
           }
-          Ok(())
-        })?;
+        }
         ctx.present()?;
       }
     }
@@ -203,9 +204,9 @@ impl Application<'_> {
     let rect = Rect::new(column * 80, row * 10 + 74, 70, 8);
 
     if active {
-      canvas.set_draw_color(self.levels_menu.palette[1]);
+      canvas.set_draw_color(self.levels_menu.roles[PaletteRole::TextPrimary]);
     } else {
-      canvas.set_draw_color(self.levels_menu.palette[0]);
+      canvas.set_draw_color(self.levels_menu.roles[PaletteRole::Background]);
     }
     canvas.fill_rect(rect).map_err(SdlError)?;
 
@@ -232,9 +233,13 @@ impl Application<'_> {
   fn render_selected_count(&self, canvas: &mut WindowCanvas, selected: usize) -> Result<(), anyhow::Error> {
     canvas.set_draw_color(Color::BLACK);
     canvas.fill_rect(Rect::new(15, 15, 24, 8)).map_err(SdlError)?;
-    self
-      .font
-      .render(canvas, 15, 15, self.levels_menu.palette[1], &selected.to_string())?;
+    self.font.render(
+      canvas,
+      15,
+      15,
+      self.levels_menu.roles[PaletteRole::TextPrimary],
+      &selected.to_string(),
+    )?;
     Ok(())
   }
 
@@ -249,29 +254,59 @@ impl Application<'_> {
     })?;
     Ok(())
   }
+}
 
-  fn generate_preview<'t>(
-    &self,
-    texture_creator: &'t TextureCreator<WindowContext>,
-    level: &LevelInfo,
-  ) -> Result<Option<Texture<'t>>, anyhow::Error> {
-    match level {
-      LevelInfo::Random => Ok(None),
-      LevelInfo::File { map, .. } => Ok(Some(generate_preview(map, texture_creator, &self.levels_menu.palette)?)),
+/// Levels to play in each round of the game
+pub enum LevelSchedule {
+  /// Levels picked in the levels menu; rounds beyond the picked ones are played on random maps
+  Picked(Vec<Rc<LevelInfo>>),
+  /// All levels of the game directory, played one after another and starting over once all of
+  /// them are played
+  Rotation(Vec<Rc<LevelInfo>>),
+}
+
+impl LevelSchedule {
+  /// Create schedule for the game. Levels picked in the menu take priority over the rotation.
+  pub fn new(vfs: &Vfs, picked: &[Rc<LevelInfo>], rotation: LevelRotation) -> Self {
+    if !picked.is_empty() || rotation == LevelRotation::Off {
+      return LevelSchedule::Picked(picked.to_vec());
+    }
+
+    let mut levels = find_levels(vfs);
+    levels.retain(|level| !matches!(level.as_ref(), LevelInfo::Random));
+    if rotation == LevelRotation::Shuffled {
+      levels.shuffle(&mut rand::thread_rng());
+    }
+    if levels.is_empty() {
+      // Nothing to rotate through
+      LevelSchedule::Picked(Vec::new())
+    } else {
+      LevelSchedule::Rotation(levels)
+    }
+  }
+
+  /// Level to play in the given round
+  pub fn level(&self, round: u16) -> &LevelInfo {
+    let round = usize::from(round);
+    match self {
+      LevelSchedule::Picked(levels) => levels.get(round).map_or(&LevelInfo::Random, Rc::as_ref),
+      LevelSchedule::Rotation(levels) => &levels[round % levels.len()],
     }
   }
 }
 
-fn find_levels(path: &Path) -> Result<Vec<Rc<LevelInfo>>, anyhow::Error> {
+/// Find all levels in the game files (including asset packs). Levels which cannot be read are skipped.
+fn find_levels(vfs: &Vfs) -> Vec<Rc<LevelInfo>> {
   let mut result = Vec::new();
-  for entry in (path.read_dir()?).flatten() {
-    let path = entry.path();
-    if path.is_file() && path.extension().map_or(false, |f| f == "mne" || f == "MNE") {
-      let data = std::fs::read(&path)?;
-      if let Ok(map) = LevelMap::from_file_map(data) {
-        let name = path.file_stem().unwrap().to_string_lossy().to_uppercase();
-        result.push(Rc::new(LevelInfo::File { name, map }));
-      }
+  for name in vfs.list(&["MNE"]) {
+    let data = match vfs.read(&name) {
+      Ok(file) => file.data,
+      Err(_) => continue,
+    };
+    let meta = LevelMeta::from_file_map(&data);
+    if let Ok(map) = LevelMap::from_file_map(data) {
+      let name = name.trim_end_matches(".MNE").to_owned();
+      result.push(Rc::new(LevelInfo::File { name, map, meta }));
     }
   }
   result.push(Rc::new(LevelInfo::Random));
@@ -279,5 +314,5 @@ fn find_levels(path: &Path) -> Result<Vec<Rc<LevelInfo>>, anyhow::Error> {
     LevelInfo::Random => (false, String::new()),
     LevelInfo::File { name, .. } => (true, name.to_owned()),
   });
-  Ok(result)
+  result
 }
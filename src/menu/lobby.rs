@@ -0,0 +1,839 @@
+//! Network lobby: host waits for players to join and sets the game options, players pick their
+//! roster identity and mark themselves as ready, then host starts the match. Lobby protocol is a
+//! sequence of length-prefixed messages over TCP. Once match is started, players send their inputs
+//! over the same connection, and host relays them to everyone else (see `netplay`). Everyone in the
+//! lobby can chat until the match starts; host relays chat messages, too.
+use crate::chat::{Chat, ChatInput};
+use crate::context::{Animation, ApplicationContext, PostEffectLimits};
+use crate::keys::ControlStyle;
+use crate::netplay::{PeerMessage, RemotePlayers, StateChecksum, TickInput};
+use crate::options::{LevelRotation, Options, StallPolicy, StartingLoadout, WinCondition};
+use crate::palette::PaletteRole;
+use crate::settings::GameSettings;
+use crate::Application;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::Color;
+use sdl2::render::WindowCanvas;
+use std::convert::TryFrom;
+use std::io::{BufReader, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Maximum amount of people in the lobby, including the host
+const MAX_MEMBERS: usize = 4;
+
+const MSG_JOIN: u8 = 0;
+const MSG_READY: u8 = 1;
+const MSG_STATE: u8 = 2;
+const MSG_START: u8 = 3;
+const MSG_INPUT: u8 = 4;
+const MSG_LEFT: u8 = 5;
+const MSG_CHAT: u8 = 6;
+const MSG_CHECKSUM: u8 = 7;
+
+const READY_COLOR: Color = Color::RGB(0, 200, 0);
+
+#[derive(Error, Debug)]
+#[error("invalid lobby message")]
+struct InvalidMessage;
+
+enum LobbyMessage {
+  /// Client joins with the selected roster identity and the control style of its player
+  Join { name: String, style: ControlStyle },
+  /// Client readiness changed
+  Ready(bool),
+  /// Current lobby state, sent by the host on every change. Options are encoded with
+  /// `Options::to_bytes`.
+  State { options: Vec<u8>, members: Vec<Member> },
+  /// Host started the match with the given options (encoded with `Options::to_bytes`) and seed.
+  /// Players are given by their names and control styles; `slot` is the player of the client.
+  Start {
+    options: Vec<u8>,
+    seed: u64,
+    slot: u8,
+    players: Vec<(String, ControlStyle)>,
+  },
+  /// Player inputs during the match
+  Input(TickInput),
+  /// Player left the match
+  Left(u8),
+  /// Chat message; host relays messages of the clients prefixed with their names
+  Chat(String),
+  /// Checksum of the world state of the player during the match
+  Checksum(StateChecksum),
+}
+
+#[derive(Clone)]
+pub struct Member {
+  pub name: String,
+  pub ready: bool,
+}
+
+fn write_message(mut stream: &TcpStream, message: &LobbyMessage) -> Result<(), std::io::Error> {
+  let mut buf = Vec::new();
+  match message {
+    LobbyMessage::Join { name, style } => {
+      buf.write_u8(MSG_JOIN)?;
+      write_string(&mut buf, name)?;
+      buf.write_u8(*style as u8)?;
+    }
+    LobbyMessage::Ready(ready) => {
+      buf.write_u8(MSG_READY)?;
+      buf.write_u8(u8::from(*ready))?;
+    }
+    LobbyMessage::State { options, members } => {
+      buf.write_u8(MSG_STATE)?;
+      write_bytes(&mut buf, options)?;
+      buf.write_u8(members.len() as u8)?;
+      for member in members {
+        write_string(&mut buf, &member.name)?;
+        buf.write_u8(u8::from(member.ready))?;
+      }
+    }
+    LobbyMessage::Start {
+      options,
+      seed,
+      slot,
+      players,
+    } => {
+      buf.write_u8(MSG_START)?;
+      write_bytes(&mut buf, options)?;
+      buf.write_u64::<LittleEndian>(*seed)?;
+      buf.write_u8(*slot)?;
+      buf.write_u8(players.len() as u8)?;
+      for (name, style) in players {
+        write_string(&mut buf, name)?;
+        buf.write_u8(*style as u8)?;
+      }
+    }
+    LobbyMessage::Input(input) => {
+      buf.write_u8(MSG_INPUT)?;
+      input.write_to(&mut buf)?;
+    }
+    LobbyMessage::Left(player) => {
+      buf.write_u8(MSG_LEFT)?;
+      buf.write_u8(*player)?;
+    }
+    LobbyMessage::Chat(text) => {
+      buf.write_u8(MSG_CHAT)?;
+      write_string(&mut buf, text)?;
+    }
+    LobbyMessage::Checksum(checksum) => {
+      buf.write_u8(MSG_CHECKSUM)?;
+      checksum.write_to(&mut buf)?;
+    }
+  }
+  stream.write_u32::<LittleEndian>(buf.len() as u32)?;
+  stream.write_all(&buf)
+}
+
+fn read_message(r: &mut impl Read) -> Result<LobbyMessage, anyhow::Error> {
+  let len = r.read_u32::<LittleEndian>()?;
+  let mut r = r.take(u64::from(len));
+  let message = match r.read_u8()? {
+    MSG_JOIN => LobbyMessage::Join {
+      name: read_string(&mut r)?,
+      style: ControlStyle::try_from(r.read_u8()?)?,
+    },
+    MSG_READY => LobbyMessage::Ready(r.read_u8()? != 0),
+    MSG_STATE => {
+      let mut options = vec![0; usize::from(r.read_u8()?)];
+      r.read_exact(&mut options)?;
+      let count = r.read_u8()?;
+      let mut members = Vec::with_capacity(usize::from(count));
+      for _ in 0..count {
+        members.push(Member {
+          name: read_string(&mut r)?,
+          ready: r.read_u8()? != 0,
+        });
+      }
+      LobbyMessage::State { options, members }
+    }
+    MSG_START => {
+      let mut options = vec![0; usize::from(r.read_u8()?)];
+      r.read_exact(&mut options)?;
+      let seed = r.read_u64::<LittleEndian>()?;
+      let slot = r.read_u8()?;
+      let count = r.read_u8()?;
+      let mut players = Vec::with_capacity(usize::from(count));
+      for _ in 0..count {
+        players.push((read_string(&mut r)?, ControlStyle::try_from(r.read_u8()?)?));
+      }
+      if usize::from(slot) >= players.len() || players.len() > MAX_MEMBERS {
+        return Err(InvalidMessage.into());
+      }
+      LobbyMessage::Start {
+        options,
+        seed,
+        slot,
+        players,
+      }
+    }
+    MSG_INPUT => LobbyMessage::Input(TickInput::read_from(&mut r)?),
+    MSG_LEFT => LobbyMessage::Left(r.read_u8()?),
+    MSG_CHAT => LobbyMessage::Chat(read_string(&mut r)?),
+    MSG_CHECKSUM => LobbyMessage::Checksum(StateChecksum::read_from(&mut r)?),
+    _ => return Err(InvalidMessage.into()),
+  };
+  std::io::copy(&mut r, &mut std::io::sink())?;
+  Ok(message)
+}
+
+/// Write length-prefixed bytes; unlike strings, encoded options cannot be cut short
+fn write_bytes(buf: &mut Vec<u8>, value: &[u8]) -> Result<(), std::io::Error> {
+  let len = u8::try_from(value.len()).map_err(|_| options_too_long())?;
+  buf.write_u8(len)?;
+  buf.write_all(value)
+}
+
+/// Encode options to send them to the clients; options are sent with a single byte length
+fn encode_options(options: &Options) -> Result<Vec<u8>, std::io::Error> {
+  let encoded = options.to_bytes();
+  if encoded.len() > usize::from(u8::MAX) {
+    return Err(options_too_long());
+  }
+  Ok(encoded)
+}
+
+fn options_too_long() -> std::io::Error {
+  std::io::Error::new(ErrorKind::InvalidInput, "encoded options are too long")
+}
+
+fn write_string(buf: &mut Vec<u8>, value: &str) -> Result<(), std::io::Error> {
+  let bytes = &value.as_bytes()[..value.len().min(255)];
+  buf.write_u8(bytes.len() as u8)?;
+  buf.write_all(bytes)
+}
+
+fn read_string(r: &mut impl Read) -> Result<String, anyhow::Error> {
+  let mut buf = vec![0; usize::from(r.read_u8()?)];
+  r.read_exact(&mut buf)?;
+  Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Read messages from the stream in a background thread. `None` is sent once connection is over.
+fn spawn_reader<T: Copy + Send + 'static>(
+  stream: &TcpStream,
+  sender: Sender<(T, Option<LobbyMessage>)>,
+  id: T,
+) -> Result<(), std::io::Error> {
+  let mut reader = BufReader::new(stream.try_clone()?);
+  std::thread::spawn(move || loop {
+    let message = read_message(&mut reader).ok();
+    let done = message.is_none();
+    if sender.send((id, message)).is_err() || done {
+      break;
+    }
+  });
+  Ok(())
+}
+
+/// Write messages of the match to the stream in a background thread, so game never waits for the
+/// network. Once the sender is dropped, connection is closed for writing after all messages are
+/// written.
+fn spawn_writer(stream: &TcpStream) -> Result<Sender<PeerMessage>, std::io::Error> {
+  let stream = stream.try_clone()?;
+  stream.set_nodelay(true)?;
+  let (sender, messages) = channel();
+  std::thread::spawn(move || {
+    for message in messages {
+      let message = match message {
+        PeerMessage::Input(input) => LobbyMessage::Input(input),
+        PeerMessage::Checksum(checksum) => LobbyMessage::Checksum(checksum),
+        PeerMessage::Left(player) => LobbyMessage::Left(player as u8),
+      };
+      if write_message(&stream, &message).is_err() {
+        break;
+      }
+    }
+    let _ = stream.shutdown(Shutdown::Write);
+  });
+  Ok(sender)
+}
+
+/// Settings of the network match. Every peer simulates the match on its own, so the match can only
+/// use what all peers have: levels are random (other peers might not have the levels of the host)
+/// and game constants are the default ones.
+fn match_settings(settings: &mut GameSettings) {
+  settings.levels.clear();
+  settings.tuning = None;
+  match_options(&mut settings.options);
+}
+
+/// Options of the network match: there is no shop (nobody would see what others bought there, so
+/// everyone gets the loadout instead) and late players are always waited for
+pub fn match_options(options: &mut Options) {
+  options.campaign_mode = false;
+  options.level_rotation = LevelRotation::Off;
+  options.stall_policy = StallPolicy::Wait;
+  if options.loadout_items().is_none() {
+    options.loadout = StartingLoadout::Basic;
+  }
+}
+
+/// Report inputs of the remote players dropped during the match
+pub fn report_violations(netplay: &RemotePlayers) {
+  let violations = netplay.violations();
+  if violations > 0 {
+    eprintln!("Dropped {} invalid inputs of the remote players", violations);
+  }
+}
+
+/// Client connected to the host lobby
+struct Client {
+  stream: TcpStream,
+  /// `None` until client sends its identity
+  member: Option<Member>,
+  style: ControlStyle,
+}
+
+/// Send the message to every client; clients who cannot be reached are dropped
+fn broadcast(clients: &mut [Option<Client>], message: &LobbyMessage) {
+  for client in clients.iter_mut() {
+    if client
+      .as_ref()
+      .is_some_and(|client| write_message(&client.stream, message).is_err())
+    {
+      *client = None;
+    }
+  }
+}
+
+/// Lobby as seen by the host: clients who connected and what they have sent. Used both by the host
+/// who plays the match and by the dedicated host.
+pub struct HostLobby {
+  listener: TcpListener,
+  /// Maximum amount of clients in the lobby
+  capacity: usize,
+  sender: Sender<(usize, Option<LobbyMessage>)>,
+  messages: Receiver<(usize, Option<LobbyMessage>)>,
+  clients: Vec<Option<Client>>,
+  /// Chat messages of the clients received since they were last taken
+  chat: Vec<String>,
+}
+
+impl HostLobby {
+  pub fn bind(address: &str, capacity: usize) -> Result<Self, std::io::Error> {
+    let listener = TcpListener::bind(address)?;
+    listener.set_nonblocking(true)?;
+    let (sender, messages) = channel();
+    Ok(HostLobby {
+      listener,
+      capacity,
+      sender,
+      messages,
+      clients: Vec::new(),
+      chat: Vec::new(),
+    })
+  }
+
+  /// Accept new clients and handle messages of the connected ones. Returns if members of the lobby
+  /// have changed.
+  pub fn poll(&mut self) -> Result<bool, std::io::Error> {
+    match self.listener.accept() {
+      Ok((stream, _)) => {
+        let joined = self.clients.iter().flatten().count();
+        if joined < self.capacity && stream.set_nonblocking(false).is_ok() {
+          spawn_reader(&stream, self.sender.clone(), self.clients.len())?;
+          self.clients.push(Some(Client {
+            stream,
+            member: None,
+            style: ControlStyle::default(),
+          }));
+        }
+      }
+      Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+      Err(err) => return Err(err),
+    }
+
+    let mut changed = false;
+    for (id, message) in self.messages.try_iter() {
+      let client = match &mut self.clients[id] {
+        Some(client) => client,
+        None => continue,
+      };
+      match message {
+        Some(LobbyMessage::Join { name, style }) => {
+          client.member = Some(Member { name, ready: false });
+          client.style = style;
+        }
+        Some(LobbyMessage::Ready(ready)) => {
+          if let Some(member) = &mut client.member {
+            member.ready = ready;
+          }
+        }
+        Some(LobbyMessage::Chat(text)) => {
+          // Only members who have joined can chat; that doesn't change the lobby
+          let line = client
+            .member
+            .as_ref()
+            .map(|member| format!("{}: {}", member.name, text));
+          if let Some(line) = line {
+            broadcast(&mut self.clients, &LobbyMessage::Chat(line.clone()));
+            self.chat.push(line);
+          }
+          continue;
+        }
+        // Disconnected or misbehaving client
+        _ => self.clients[id] = None,
+      }
+      changed = true;
+    }
+    Ok(changed)
+  }
+
+  /// Send chat message to every client
+  pub fn say(&mut self, line: &str) {
+    broadcast(&mut self.clients, &LobbyMessage::Chat(line.to_owned()));
+  }
+
+  /// Chat messages of the clients received since the last call
+  pub fn take_chat(&mut self) -> Vec<String> {
+    std::mem::take(&mut self.chat)
+  }
+
+  /// Clients who have joined the lobby
+  pub fn members(&self) -> impl Iterator<Item = &Member> {
+    self
+      .clients
+      .iter()
+      .flatten()
+      .filter_map(|client| client.member.as_ref())
+  }
+
+  /// Send the lobby state to every client; clients who cannot be reached are dropped
+  pub fn send_state(&mut self, options: &Options, members: &[Member]) -> Result<(), std::io::Error> {
+    let state = LobbyMessage::State {
+      options: encode_options(options)?,
+      members: members.to_vec(),
+    };
+    broadcast(&mut self.clients, &state);
+    Ok(())
+  }
+
+  /// Start the match: host player (if host plays) is the first one, followed by the clients who
+  /// joined. Clients get the options and the seed of the match, and host relays inputs of every
+  /// client to the match and to all other clients; relay stops once all connections are closed.
+  pub fn start(
+    self,
+    host: Option<(String, ControlStyle)>,
+    options: &mut Options,
+  ) -> Result<RemotePlayers, std::io::Error> {
+    let HostLobby {
+      listener,
+      sender,
+      messages,
+      clients,
+      ..
+    } = self;
+    drop(listener);
+    drop(sender);
+
+    let local = host.as_ref().map(|_| 0);
+    let mut players: Vec<(String, ControlStyle)> = host.into_iter().collect();
+    let mut slots = vec![None; clients.len()];
+    for (id, client) in clients.iter().enumerate() {
+      if let Some(Client {
+        member: Some(member),
+        style,
+        ..
+      }) = client
+      {
+        slots[id] = Some(players.len());
+        players.push((member.name.clone(), *style));
+      }
+    }
+    options.players = players.len() as u8;
+    let encoded = encode_options(options)?;
+    let seed = rand::random();
+    let mut writers = vec![None; clients.len()];
+    for (id, client) in clients.iter().enumerate() {
+      if let (Some(client), Some(slot)) = (client, slots[id]) {
+        let start = LobbyMessage::Start {
+          options: encoded.clone(),
+          seed,
+          slot: slot as u8,
+          players: players.clone(),
+        };
+        // Client who didn't get the message is noticed as gone once its connection is closed
+        if write_message(&client.stream, &start).is_ok() {
+          writers[id] = Some(spawn_writer(&client.stream)?);
+        }
+      }
+    }
+
+    let (inputs_sender, inputs) = channel();
+    let relay = writers.clone();
+    std::thread::spawn(move || {
+      for (id, message) in messages {
+        let slot = match slots[id] {
+          Some(slot) => slot,
+          None => continue,
+        };
+        let message = match message {
+          Some(LobbyMessage::Input(mut input)) => {
+            input.player = slot;
+            PeerMessage::Input(input)
+          }
+          Some(LobbyMessage::Checksum(mut checksum)) => {
+            checksum.player = slot;
+            PeerMessage::Checksum(checksum)
+          }
+          Some(_) => continue,
+          None => PeerMessage::Left(slot),
+        };
+        for (other, writer) in relay.iter().enumerate() {
+          if let Some(writer) = writer.as_ref().filter(|_| other != id) {
+            let _ = writer.send(message.clone());
+          }
+        }
+        if inputs_sender.send(message).is_err() {
+          break;
+        }
+      }
+    });
+    let writers = writers.into_iter().flatten().collect();
+    let connections = clients
+      .iter()
+      .flatten()
+      .map(|client| client.stream.try_clone())
+      .collect::<Result<_, _>>()?;
+    Ok(RemotePlayers::new(players, local, seed, inputs, writers, connections))
+  }
+}
+
+impl<'t> Application<'t> {
+  /// Host a network game: run the lobby on the given address and start the match once everyone is
+  /// ready. Host plays the first player and everyone who joined controls one of the others.
+  pub fn host_game(&mut self, ctx: &mut ApplicationContext<'_, 't>, address: &str) -> Result<(), anyhow::Error> {
+    let mut settings = GameSettings::load(ctx.game_dir());
+    ctx.set_transition_speed(settings.options.transitions);
+    ctx.set_post_effect_limits(PostEffectLimits::from_options(&settings.options));
+    ctx.set_widescreen(settings.options.widescreen)?;
+    ctx.set_menu_keys(&settings.keys);
+
+    let host = Member {
+      name: match self.registered.borrow().as_str() {
+        "" => "Host".to_owned(),
+        name => name.to_owned(),
+      },
+      ready: true,
+    };
+    let mut lobby = HostLobby::bind(address, MAX_MEMBERS - 1)?;
+    let mut chat = Chat::default();
+
+    let mut changed = true;
+    let mut fade_in = true;
+    loop {
+      changed |= lobby.poll()?;
+      let members: Vec<Member> = std::iter::once(&host).chain(lobby.members()).cloned().collect();
+      let everyone_ready = members.len() > 1 && members.iter().all(|member| member.ready);
+      let mut redraw = chat.expire();
+      for line in lobby.take_chat() {
+        chat.push_line(line);
+        redraw = true;
+      }
+
+      let mut start = false;
+      let mut options = false;
+      let mut leave = false;
+      for event in ctx.poll_iter() {
+        match chat.handle_event(&event) {
+          ChatInput::Send(message) => {
+            let line = format!("{}: {}", host.name, message);
+            lobby.say(&line);
+            chat.push_line(line);
+            redraw = true;
+            continue;
+          }
+          ChatInput::Handled => {
+            redraw = true;
+            continue;
+          }
+          ChatInput::Ignored => {}
+        }
+        if let Event::KeyDown {
+          scancode: Some(scancode),
+          ..
+        } = event
+        {
+          match scancode {
+            Scancode::Escape => leave = true,
+            Scancode::Return | Scancode::KpEnter if everyone_ready => start = true,
+            Scancode::F2 => options = true,
+            _ => {}
+          }
+        }
+      }
+      if leave {
+        ctx.transition(Animation::FadeDown)?;
+        return Ok(());
+      }
+
+      if options {
+        ctx.transition(Animation::FadeDown)?;
+        self.options_menu(ctx, &mut settings)?;
+        changed = true;
+        fade_in = true;
+      }
+
+      if changed {
+        lobby.send_state(&settings.options, &members)?;
+      }
+      if changed || redraw {
+        changed = false;
+        self.render_lobby(ctx, &settings.options, &members, true, everyone_ready, &chat)?;
+        if fade_in {
+          fade_in = false;
+          ctx.transition(Animation::FadeUp)?;
+        } else {
+          ctx.present()?;
+        }
+      }
+
+      if start {
+        match_settings(&mut settings);
+        let host_player = (host.name.clone(), settings.keys.keys[0].style);
+        self.netplay = Some(lobby.start(Some(host_player), &mut settings.options)?);
+        ctx.transition(Animation::FadeDown)?;
+        let result = self.play_game(ctx, &settings, false);
+        if let Some(netplay) = self.netplay.take() {
+          netplay.leave();
+          report_violations(&netplay);
+        }
+        result?;
+        return Ok(());
+      }
+
+      std::thread::sleep(Duration::from_millis(20));
+    }
+  }
+
+  /// Join a network game hosted on the given address. Player is controlled with the keys of the first
+  /// player.
+  pub fn join_game(&mut self, ctx: &mut ApplicationContext<'_, 't>, address: &str) -> Result<(), anyhow::Error> {
+    let mut settings = GameSettings::load(ctx.game_dir());
+    ctx.set_transition_speed(settings.options.transitions);
+    ctx.set_post_effect_limits(PostEffectLimits::from_options(&settings.options));
+    ctx.set_widescreen(settings.options.widescreen)?;
+    ctx.set_menu_keys(&settings.keys);
+
+    let selected = self.players_select_menu(ctx, 1)?;
+    let name = match selected.into_iter().next() {
+      Some(selected) => selected.name,
+      None => return Ok(()),
+    };
+
+    let stream = TcpStream::connect(address)?;
+    let (sender, messages) = channel();
+    spawn_reader(&stream, sender, ())?;
+    let join = LobbyMessage::Join {
+      name,
+      style: settings.keys.keys[0].style,
+    };
+    write_message(&stream, &join)?;
+
+    let mut ready = false;
+    let mut options = Options::default();
+    let mut members = Vec::new();
+    let mut chat = Chat::default();
+    let mut changed = true;
+    let mut fade_in = true;
+    let (host_options, seed, slot, players) = 'lobby: loop {
+      changed |= chat.expire();
+      for ((), message) in messages.try_iter() {
+        match message {
+          Some(LobbyMessage::State {
+            options: host_options,
+            members: host_members,
+          }) => {
+            options = Options::from_bytes(&host_options);
+            members = host_members;
+            changed = true;
+          }
+          Some(LobbyMessage::Start {
+            options,
+            seed,
+            slot,
+            players,
+          }) => break 'lobby (options, seed, slot, players),
+          Some(LobbyMessage::Chat(line)) => {
+            chat.push_line(line);
+            changed = true;
+          }
+          Some(_) => {}
+          None => {
+            ctx.transition(Animation::FadeDown)?;
+            return Err(anyhow::anyhow!("disconnected from the lobby"));
+          }
+        }
+      }
+
+      let mut leave = false;
+      for event in ctx.poll_iter() {
+        match chat.handle_event(&event) {
+          ChatInput::Send(message) => {
+            // Host sends the message back to everyone, including us
+            write_message(&stream, &LobbyMessage::Chat(message))?;
+            changed = true;
+            continue;
+          }
+          ChatInput::Handled => {
+            changed = true;
+            continue;
+          }
+          ChatInput::Ignored => {}
+        }
+        if let Event::KeyDown {
+          scancode: Some(scancode),
+          ..
+        } = event
+        {
+          match scancode {
+            Scancode::Escape => leave = true,
+            Scancode::Return | Scancode::KpEnter | Scancode::Space => {
+              ready = !ready;
+              write_message(&stream, &LobbyMessage::Ready(ready))?;
+            }
+            _ => {}
+          }
+        }
+      }
+      if leave {
+        ctx.transition(Animation::FadeDown)?;
+        return Ok(());
+      }
+
+      if changed {
+        changed = false;
+        self.render_lobby(ctx, &options, &members, false, false, &chat)?;
+        if fade_in {
+          fade_in = false;
+          ctx.transition(Animation::FadeUp)?;
+        } else {
+          ctx.present()?;
+        }
+      }
+      std::thread::sleep(Duration::from_millis(20));
+    };
+
+    ctx.transition(Animation::FadeDown)?;
+    settings.options = Options::from_bytes(&host_options);
+    match_settings(&mut settings);
+    // Inputs of the other players come from the host; channel is closed once host is gone
+    let (inputs_sender, inputs) = channel();
+    std::thread::spawn(move || {
+      for ((), message) in messages {
+        let message = match message {
+          Some(LobbyMessage::Input(input)) => PeerMessage::Input(input),
+          Some(LobbyMessage::Checksum(checksum)) => PeerMessage::Checksum(checksum),
+          Some(LobbyMessage::Left(player)) => PeerMessage::Left(usize::from(player)),
+          Some(_) => continue,
+          None => break,
+        };
+        if inputs_sender.send(message).is_err() {
+          break;
+        }
+      }
+    });
+    let writer = spawn_writer(&stream)?;
+    let local = usize::from(slot);
+    self.netplay = Some(RemotePlayers::new(
+      players,
+      Some(local),
+      seed,
+      inputs,
+      vec![writer],
+      vec![stream],
+    ));
+    let result = self.play_game(ctx, &settings, false);
+    let lost = self.netplay.as_ref().is_some_and(RemotePlayers::is_lost);
+    if let Some(netplay) = self.netplay.take() {
+      netplay.leave();
+      report_violations(&netplay);
+    }
+    result?;
+    if lost {
+      return Err(anyhow::anyhow!("lost connection to the host"));
+    }
+    Ok(())
+  }
+
+  fn render_lobby(
+    &self,
+    ctx: &mut ApplicationContext,
+    options: &Options,
+    members: &[Member],
+    host: bool,
+    can_start: bool,
+    chat: &Chat,
+  ) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let roles = &self.options_menu.roles;
+      self
+        .font
+        .render(canvas, 264, 80, roles[PaletteRole::TextPrimary], "NETWORK LOBBY")?;
+      self.render_lobby_options(canvas, options)?;
+
+      for idx in 0..MAX_MEMBERS {
+        let y = 240 + 16 * idx as i32;
+        match members.get(idx) {
+          Some(member) => {
+            let (mark, color) = if member.ready {
+              ("[X]", READY_COLOR)
+            } else {
+              ("[ ]", roles[PaletteRole::Muted])
+            };
+            self.font.render(canvas, 200, y, color, mark)?;
+            self
+              .font
+              .render(canvas, 240, y, roles[PaletteRole::TextPrimary], &member.name)?;
+          }
+          None => self.font.render(canvas, 240, y, roles[PaletteRole::Muted], "...")?,
+        }
+      }
+
+      let help = match (host, can_start) {
+        (true, true) => "Enter to start, F2 for options, Escape to leave",
+        (true, false) => "Waiting for players, F2 for options, Escape to leave",
+        (false, _) => "Enter to toggle ready, Escape to leave",
+      };
+      let x = (640 - 8 * help.len() as i32) / 2;
+      self.font.render(canvas, x, 340, roles[PaletteRole::Muted], help)?;
+      self
+        .font
+        .render(canvas, 272, 356, roles[PaletteRole::Muted], "T to chat")?;
+      chat.render(canvas, &self.font, roles[PaletteRole::TextPrimary])
+    })
+  }
+
+  fn render_lobby_options(&self, canvas: &mut WindowCanvas, options: &Options) -> Result<(), anyhow::Error> {
+    let color = self.options_menu.roles[PaletteRole::Muted];
+    let win = match options.win {
+      WinCondition::ByWins => "by wins",
+      WinCondition::ByMoney => "by money",
+    };
+    let lines = [
+      format!(
+        "Rounds: {}   Cash: {}   Treasures: {}",
+        options.rounds, options.cash, options.treasures
+      ),
+      format!(
+        "Round time: {}s   Darkness: {}   Win {}",
+        options.round_time.as_secs(),
+        if options.darkness { "on" } else { "off" },
+        win
+      ),
+    ];
+    for (idx, line) in lines.iter().enumerate() {
+      self.font.render(canvas, 140, 120 + 16 * idx as i32, color, line)?;
+    }
+    Ok(())
+  }
+}
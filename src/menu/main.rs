@@ -1,108 +1,197 @@
-use crate::context::{Animation, ApplicationContext};
+use crate::args::Args;
+use crate::context::{Animation, ApplicationContext, PostEffectLimits};
 use crate::error::ApplicationError::SdlError;
 use crate::glyphs::Glyph;
+use crate::keys::MenuAction;
+use crate::menu::list::{ListChange, ListLayout, ListMenu};
+use crate::palette::PaletteRole;
 use crate::settings::GameSettings;
+use crate::status::GameStatus;
+use crate::world::map::LevelInfo;
 use crate::Application;
-use sdl2::keyboard::Scancode;
+use anyhow::Context;
+use sdl2::keyboard::{Keycode, Scancode};
 use sdl2::pixels::Color;
-use sdl2::rect::Rect;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// How long (in frames) "press any key" prompt on the title screen stays shown or hidden
+const TITLE_BLINK_FRAMES: usize = 30;
+
+const TITLE_PROMPT_COLOR: Color = Color::RGB(200, 200, 200);
+
+/// How long title screen waits for a key before switching to the attract mode
+const ATTRACT_DELAY: Duration = Duration::from_secs(20);
+
+/// Screens shown by the attract mode, in turns
+enum AttractStage {
+  Credits,
+  Demo,
+}
 
 /// Selected item in the main menu
 #[derive(Clone, Copy, PartialEq)]
-#[repr(usize)]
 enum SelectedMenu {
   NewGame,
   Options,
   Info,
   Quit,
+  /// Not on the main menu texture, rendered below the other items
+  Network,
 }
 
 impl SelectedMenu {
-  /// Get menu item that is next to the current one
-  fn next(self) -> SelectedMenu {
-    match self {
-      SelectedMenu::NewGame => SelectedMenu::Options,
-      SelectedMenu::Options => SelectedMenu::Info,
-      SelectedMenu::Info => SelectedMenu::Quit,
-      SelectedMenu::Quit => SelectedMenu::NewGame,
-    }
-  }
-
-  /// Get menu item that is previous to the current one
-  fn prev(self) -> SelectedMenu {
-    self.next().next().next()
-  }
+  /// All items, in the order they are shown in the main menu
+  const ALL: [SelectedMenu; 5] = [
+    SelectedMenu::NewGame,
+    SelectedMenu::Options,
+    SelectedMenu::Info,
+    SelectedMenu::Quit,
+    SelectedMenu::Network,
+  ];
 
-  /// Shovel position in the main menu based on the selected item. Should correspond to the main menu texture.
-  fn shovel_pos(self) -> (i32, i32) {
-    (222, 136 + 48 * self as i32)
+  /// Layout of the main menu. Items themselves are drawn on the main menu texture, so only the
+  /// shovel is rendered; should correspond to the main menu texture.
+  fn layout() -> ListLayout {
+    ListLayout {
+      left: 222,
+      top: 136,
+      item_height: 48,
+      visible: SelectedMenu::ALL.len(),
+      pointer: Glyph::ShovelPointer,
+      pointer_offset: (0, 0),
+    }
   }
 }
 
-impl Application<'_> {
-  pub fn main_menu(self, ctx: &mut ApplicationContext, campaign_mode: bool) -> Result<(), anyhow::Error> {
+impl<'t> Application<'t> {
+  pub fn main_menu(mut self, ctx: &mut ApplicationContext<'_, 't>, args: &Args) -> Result<(), anyhow::Error> {
+    let mut settings = GameSettings::load(ctx.game_dir());
+    settings.options.campaign_mode = args.campaign_mode;
+    settings.tuning = args.practice.clone();
+    ctx.set_transition_speed(settings.options.transitions);
+    ctx.set_post_effect_limits(PostEffectLimits::from_options(&settings.options));
+    ctx.set_widescreen(settings.options.widescreen)?;
+    ctx.set_menu_keys(&settings.keys);
+
     self.music1.play(-1).map_err(SdlError)?;
 
-    ctx.render_texture(&self.title.texture)?;
-    ctx.animate(Animation::FadeUp, 7)?;
-    let (scancode, _) = ctx.wait_key_pressed();
-    ctx.animate(Animation::FadeDown, 7)?;
-    if scancode == Scancode::Escape {
-      return Ok(());
+    if !args.skip_intro && !args.quickstart && !settings.options.skip_intro {
+      let (scancode, _) = self.title_screen(ctx)?;
+      if ctx.menu_action(scancode) == MenuAction::Back {
+        return Ok(());
+      }
     }
 
-    self.main_menu_loop(ctx, campaign_mode)?;
+    if args.quickstart {
+      self.play_game(ctx, &settings, true)?;
+      self.music1.play(-1).map_err(SdlError)?;
+    }
+
+    self.main_menu_loop(ctx, settings)?;
     Ok(())
   }
 
-  /// Returns when exiting the game
-  fn main_menu_loop(&self, ctx: &mut ApplicationContext, campaign_mode: bool) -> Result<(), anyhow::Error> {
+  /// Play a game on the given level right away, without showing any menus. Returns the exit code
+  /// of the process: number of the winner or 0 if there is no single winner.
+  pub fn play_from_command_line(
+    &mut self,
+    ctx: &mut ApplicationContext<'_, 't>,
+    args: &Args,
+  ) -> Result<i32, anyhow::Error> {
     let mut settings = GameSettings::load(ctx.game_dir());
-    settings.options.campaign_mode = campaign_mode;
+    settings.scripted = true;
+    settings.tuning = args.practice.clone();
+    settings.options.campaign_mode = false;
+    settings.options.players = args.players.unwrap_or(2);
+    if let Some(rounds) = args.rounds {
+      settings.options.rounds = rounds;
+    }
+    ctx.set_transition_speed(settings.options.transitions);
+    ctx.set_post_effect_limits(PostEffectLimits::from_options(&settings.options));
+    ctx.set_widescreen(settings.options.widescreen)?;
+
+    let level = match args.play.as_deref() {
+      None | Some("random") => LevelInfo::Random,
+      Some(path) => {
+        let path = Path::new(path);
+        // Level could be given relative to the game directory
+        let path = if path.is_file() {
+          path.to_owned()
+        } else {
+          ctx.game_dir().join(path)
+        };
+        LevelInfo::load_file(&path).with_context(|| format!("Failed to load level '{}'", path.display()))?
+      }
+    };
+    let level = Rc::new(level);
+    settings.levels = vec![level; usize::from(settings.options.rounds)];
+
+    let winner = self.play_game(ctx, &settings, false)?;
+    Ok(winner.map_or(0, |winner| winner as i32 + 1))
+  }
 
-    let mut selected_item = SelectedMenu::NewGame;
+  /// Returns when exiting the game
+  fn main_menu_loop(
+    &mut self,
+    ctx: &mut ApplicationContext<'_, 't>,
+    mut settings: GameSettings,
+  ) -> Result<(), anyhow::Error> {
+    let mut menu = ListMenu::new(SelectedMenu::layout(), SelectedMenu::ALL.len());
     loop {
-      self.render_main_menu(ctx, selected_item)?;
-      ctx.animate(Animation::FadeUp, 7)?;
-      self.main_menu_navigation_loop(ctx, &mut selected_item)?;
-      ctx.animate(Animation::FadeDown, 7)?;
-      match selected_item {
+      self.reload_assets(ctx);
+      ctx.report_status(GameStatus::Menu);
+      self.render_main_menu(ctx, &menu)?;
+      ctx.transition(Animation::FadeUp)?;
+      self.main_menu_navigation_loop(ctx, &mut menu)?;
+      ctx.transition(Animation::FadeDown)?;
+      match SelectedMenu::ALL[menu.selected()] {
         SelectedMenu::Quit => break Ok(()),
         SelectedMenu::NewGame => {
-          self.play_game(ctx, &settings)?;
+          self.play_game(ctx, &settings, false)?;
+          // Previews of the played levels are not needed anymore
+          ctx.textures().clear();
           self.music1.play(-1).map_err(SdlError)?;
         }
-        SelectedMenu::Options => self.options_menu(ctx, &mut settings)?,
+        SelectedMenu::Options => {
+          self.options_menu(ctx, &mut settings)?;
+          ctx.set_transition_speed(settings.options.transitions);
+          ctx.set_post_effect_limits(PostEffectLimits::from_options(&settings.options));
+          ctx.set_widescreen(settings.options.widescreen)?;
+          ctx.set_menu_keys(&settings.keys);
+        }
         SelectedMenu::Info => self.info_menu(ctx)?,
+        SelectedMenu::Network => {
+          self.network_menu(ctx)?;
+          // Network game uses the settings saved on disk; restore the ones of the main menu
+          ctx.set_transition_speed(settings.options.transitions);
+          ctx.set_post_effect_limits(PostEffectLimits::from_options(&settings.options));
+          ctx.set_widescreen(settings.options.widescreen)?;
+          ctx.set_menu_keys(&settings.keys);
+          ctx.textures().clear();
+          self.music1.play(-1).map_err(SdlError)?;
+        }
       }
     }
   }
 
   /// Runs navigation inside main menu. Return
-  fn main_menu_navigation_loop(
-    &self,
-    ctx: &mut ApplicationContext,
-    selected: &mut SelectedMenu,
-  ) -> Result<(), anyhow::Error> {
+  fn main_menu_navigation_loop(&self, ctx: &mut ApplicationContext, menu: &mut ListMenu) -> Result<(), anyhow::Error> {
     loop {
       let (scancode, _keycode) = ctx.wait_key_pressed();
 
-      match scancode {
-        Scancode::Down | Scancode::Kp2 => {
-          let next = selected.next();
-          self.update_shovel(ctx, *selected, next)?;
-          *selected = next;
+      match ctx.menu_action(scancode) {
+        action @ (MenuAction::Down | MenuAction::Up) => {
+          if let ListChange::Pointer { previous } = menu.navigate(action) {
+            self.update_list_pointer(ctx, menu, previous)?;
+          }
         }
-        Scancode::Up | Scancode::Kp8 => {
-          let prev = selected.prev();
-          self.update_shovel(ctx, *selected, prev)?;
-          *selected = prev;
-        }
-        Scancode::Escape => {
-          *selected = SelectedMenu::Quit;
+        MenuAction::Back => {
+          menu.select(SelectedMenu::Quit as usize);
           break;
         }
-        Scancode::Kp3 | Scancode::Return | Scancode::Return2 | Scancode::KpEnter => {
+        MenuAction::Select | MenuAction::Other(Scancode::Kp3) => {
           break;
         }
         _ => {}
@@ -112,61 +201,94 @@ impl Application<'_> {
   }
 
   /// Display main menu with selected option, plus animation
-  fn render_main_menu(&self, ctx: &mut ApplicationContext, selected: SelectedMenu) -> Result<(), anyhow::Error> {
+  fn render_main_menu(&self, ctx: &mut ApplicationContext, menu: &ListMenu) -> Result<(), anyhow::Error> {
     let texture = &self.main_menu;
-    let glyphs = &self.glyphs;
     ctx.with_render_context(|canvas| {
       canvas.copy(&texture.texture, None, None).map_err(SdlError)?;
 
       // Render "Registered to"
-      let pos = ((26 - self.registered.len()) * 4 + 254) as i32;
-      let palette = &self.main_menu.palette;
-      self.font.render(canvas, pos - 1, 437, palette[10], &self.registered)?;
-      self.font.render(canvas, pos + 1, 437, palette[8], &self.registered)?;
-      self.font.render(canvas, pos, 437, palette[0], &self.registered)?;
-
-      let (x, y) = selected.shovel_pos();
-      glyphs.render(canvas, x, y, Glyph::ShovelPointer)?;
-      Ok(())
+      let registered = self.registered.borrow();
+      let pos = ((26 - registered.len()) * 4 + 254) as i32;
+      let roles = &self.main_menu.roles;
+      self
+        .font
+        .render(canvas, pos - 1, 437, roles[PaletteRole::Bevel], &registered)?;
+      self
+        .font
+        .render(canvas, pos + 1, 437, roles[PaletteRole::Muted], &registered)?;
+      self
+        .font
+        .render(canvas, pos, 437, roles[PaletteRole::Background], &registered)?;
+
+      self.render_list(canvas, menu, |canvas, idx, x, y| {
+        if SelectedMenu::ALL[idx] == SelectedMenu::Network {
+          let color = self.main_menu.roles[PaletteRole::TextPrimary];
+          self.font.render(canvas, x + 56, y + 12, color, "NETWORK GAME")?;
+        }
+        Ok(())
+      })
     })?;
     Ok(())
   }
 
-  fn update_shovel(
-    &self,
-    ctx: &mut ApplicationContext,
-    previous: SelectedMenu,
-    selected: SelectedMenu,
-  ) -> Result<(), anyhow::Error> {
-    ctx.with_render_context(|canvas| {
-      let (old_x, old_y) = previous.shovel_pos();
-      let (w, h) = Glyph::ShovelPointer.dimensions();
-      canvas.set_draw_color(Color::RGB(0, 0, 0));
-      canvas.fill_rect(Rect::new(old_x, old_y, w, h)).map_err(SdlError)?;
-      let (x, y) = selected.shovel_pos();
-      self.glyphs.render(canvas, x, y, Glyph::ShovelPointer)?;
-      Ok(())
-    })?;
-    ctx.present()?;
-    Ok(())
+  /// Show title screen until some key is pressed. If nobody presses anything for a while, alternate
+  /// between the title screen, credits and a demo game.
+  fn title_screen(&self, ctx: &mut ApplicationContext) -> Result<(Scancode, Keycode), anyhow::Error> {
+    let mut attract = [AttractStage::Credits, AttractStage::Demo].iter().cycle();
+    loop {
+      ctx.render_texture(&self.title.texture)?;
+      ctx.transition(Animation::FadeUp)?;
+      let key = self.wait_title_screen(ctx)?;
+      ctx.transition(Animation::FadeDown)?;
+      if let Some(key) = key {
+        return Ok(key);
+      }
+
+      // Any key pressed during attract mode brings us back to the title screen
+      match attract.next().unwrap() {
+        AttractStage::Credits => self.credits_scroll(ctx)?,
+        AttractStage::Demo => self.attract_demo(ctx)?,
+      };
+    }
+  }
+
+  /// Wait on the title screen, blinking "press any key" prompt. Returns `None` if no key was pressed
+  /// for a while.
+  fn wait_title_screen(&self, ctx: &mut ApplicationContext) -> Result<Option<(Scancode, Keycode)>, anyhow::Error> {
+    ctx.wait_key_pressed_idle(Some(ATTRACT_DELAY), |ctx, frame| {
+      if frame % TITLE_BLINK_FRAMES != 0 {
+        return Ok(());
+      }
+      let visible = (frame / TITLE_BLINK_FRAMES) % 2 == 0;
+      ctx.with_render_context(|canvas| {
+        canvas.copy(&self.title.texture, None, None).map_err(SdlError)?;
+        if visible {
+          self
+            .font
+            .render(canvas, 268, 466, TITLE_PROMPT_COLOR, "PRESS ANY KEY")?;
+        }
+        Ok(())
+      })?;
+      ctx.present()
+    })
   }
 
   fn info_menu(&self, ctx: &mut ApplicationContext) -> Result<(), anyhow::Error> {
     let mut key = Scancode::Escape;
     for info in &self.info {
       ctx.render_texture(&info.texture)?;
-      ctx.animate(Animation::FadeUp, 7)?;
+      ctx.transition(Animation::FadeUp)?;
       key = ctx.wait_key_pressed().0;
-      ctx.animate(Animation::FadeDown, 7)?;
-      if key == Scancode::Escape {
+      ctx.transition(Animation::FadeDown)?;
+      if ctx.menu_action(key) == MenuAction::Back {
         break;
       }
     }
     if key == Scancode::Tab {
       ctx.render_texture(&self.codes.texture)?;
-      ctx.animate(Animation::FadeUp, 7)?;
+      ctx.transition(Animation::FadeUp)?;
       ctx.wait_key_pressed();
-      ctx.animate(Animation::FadeDown, 7)?;
+      ctx.transition(Animation::FadeDown)?;
     }
     Ok(())
   }
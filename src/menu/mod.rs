@@ -1,8 +1,23 @@
+mod attract;
+mod campaign;
+mod confirm;
+mod extended;
+mod feed;
 mod game;
+mod hud;
 mod keys;
+mod list;
 mod load_levels;
+pub mod lobby;
 mod main;
+mod mods;
+mod network;
+mod observe;
 mod options;
+mod overlay;
 mod players;
 mod preview;
+mod registration;
 pub mod shop;
+mod tally;
+mod tooltip;
@@ -0,0 +1,114 @@
+//! Asset pack manager: enable or disable packs found in the `mods` directory and change their load
+//! order. Packs lower in the list override the ones above them.
+use crate::context::{Animation, ApplicationContext};
+use crate::glyphs::Glyph;
+use crate::keys::MenuAction;
+use crate::menu::list::{ListLayout, ListMenu};
+use crate::mods::{ModList, MODS_DIR};
+use crate::palette::PaletteRole;
+use crate::Application;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::Color;
+
+const LIST_X: i32 = 160;
+const LIST_Y: i32 = 120;
+const ITEM_HEIGHT: i32 = 16;
+
+/// Maximum amount of packs shown on the screen
+const MAX_VISIBLE: usize = 18;
+
+impl Application<'_> {
+  /// Asset pack manager. Changes are saved into `mods.cfg` and applied once the screen is closed.
+  pub(super) fn mods_menu(&self, ctx: &mut ApplicationContext) -> Result<(), anyhow::Error> {
+    let initial = ModList::load(ctx.game_dir());
+    let mut mods = initial.clone();
+    let layout = ListLayout {
+      left: LIST_X,
+      top: LIST_Y,
+      item_height: ITEM_HEIGHT,
+      visible: MAX_VISIBLE,
+      pointer: Glyph::ArrowPointer,
+      pointer_offset: (-56, -2),
+    };
+    let mut menu = ListMenu::new(layout, mods.packs.len());
+    self.render_mods(ctx, &mods, &menu)?;
+    ctx.transition(Animation::FadeUp)?;
+
+    loop {
+      let (scancode, _) = ctx.wait_key_pressed();
+      let selected = menu.selected();
+      match ctx.menu_action(scancode) {
+        action @ (MenuAction::Down | MenuAction::Up) => {
+          // Toggles and reordering change the items, too, so the whole list is always rendered again
+          menu.navigate(action);
+        }
+        MenuAction::Select | MenuAction::Other(Scancode::Space) if !menu.is_empty() => {
+          let pack = &mut mods.packs[selected];
+          pack.enabled = !pack.enabled;
+        }
+        MenuAction::Left if selected > 0 => {
+          mods.packs.swap(selected, selected - 1);
+          menu.select(selected - 1);
+        }
+        MenuAction::Right if selected + 1 < menu.len() => {
+          mods.packs.swap(selected, selected + 1);
+          menu.select(selected + 1);
+        }
+        MenuAction::Back => break,
+        _ => continue,
+      }
+      self.render_mods(ctx, &mods, &menu)?;
+      ctx.present()?;
+    }
+
+    if mods != initial {
+      mods.save(ctx.game_dir())?;
+      ctx.set_mods(&mods);
+    }
+    ctx.transition(Animation::FadeDown)?;
+    Ok(())
+  }
+
+  fn render_mods(&self, ctx: &mut ApplicationContext, mods: &ModList, menu: &ListMenu) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      self.font.render(
+        canvas,
+        264,
+        80,
+        self.options_menu.roles[PaletteRole::TextPrimary],
+        "ASSET PACKS",
+      )?;
+      let color = self.options_menu.roles[PaletteRole::Muted];
+      if mods.packs.is_empty() {
+        let text = format!("No asset packs found in the '{}' directory", MODS_DIR);
+        self.font.render(canvas, LIST_X - 32, LIST_Y, color, &text)?;
+      }
+
+      self.render_list(canvas, menu, |canvas, idx, x, y| {
+        let pack = &mods.packs[idx];
+        self
+          .glyphs
+          .render(canvas, x - 20, y - 3, Glyph::RadioButton(pack.enabled))?;
+        self.font.render(canvas, x, y, color, &pack.name)
+      })?;
+
+      let hints = LIST_Y + (MAX_VISIBLE as i32 + 1) * ITEM_HEIGHT;
+      self
+        .font
+        .render(canvas, LIST_X - 32, hints, color, "Enter to enable or disable a pack")?;
+      self.font.render(
+        canvas,
+        LIST_X - 32,
+        hints + ITEM_HEIGHT,
+        color,
+        "Left and right to change load order (lower overrides upper)",
+      )?;
+      self
+        .font
+        .render(canvas, LIST_X - 32, hints + 2 * ITEM_HEIGHT, color, "Escape to apply")?;
+      Ok(())
+    })
+  }
+}
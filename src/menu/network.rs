@@ -0,0 +1,121 @@
+//! Network game screen: host a LAN game or join one hosted on the given address (see `lobby`).
+use crate::context::{Animation, ApplicationContext, InputEvent};
+use crate::error::ApplicationError::SdlError;
+use crate::keys::MenuAction;
+use crate::palette::PaletteRole;
+use crate::Application;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+const MODE_X: i32 = 248;
+const MODE_Y: i32 = 168;
+const ADDRESS_X: i32 = 216;
+const ADDRESS_Y: i32 = 248;
+
+/// Port network games use if address doesn't have one
+const DEFAULT_PORT: u16 = 7374;
+
+const MAX_ADDRESS_LEN: usize = 40;
+
+impl<'t> Application<'t> {
+  /// Pick between hosting and joining the network game and enter the address. Failed network game
+  /// only brings player back to the main menu.
+  pub(super) fn network_menu(&mut self, ctx: &mut ApplicationContext<'_, 't>) -> Result<(), anyhow::Error> {
+    let mut host = true;
+    let mut address = String::new();
+    self.render_network_menu(ctx, host, &address)?;
+    ctx.transition(Animation::FadeUp)?;
+
+    loop {
+      match ctx.wait_input_event() {
+        InputEvent::KeyPress(scancode, _) => match ctx.text_menu_action(scancode) {
+          MenuAction::Select => break,
+          MenuAction::Back => {
+            ctx.transition(Animation::FadeDown)?;
+            return Ok(());
+          }
+          MenuAction::Up | MenuAction::Down => host = !host,
+          MenuAction::Other(Scancode::Delete) | MenuAction::Other(Scancode::Backspace) => {
+            address.pop();
+          }
+          _ => continue,
+        },
+        InputEvent::TextInput(text) => {
+          for ch in text.chars() {
+            if address.len() < MAX_ADDRESS_LEN && ch.is_ascii_graphic() {
+              address.push(ch);
+            }
+          }
+        }
+      }
+      self.render_network_menu(ctx, host, &address)?;
+      ctx.present()?;
+    }
+    ctx.transition(Animation::FadeDown)?;
+
+    let address = network_address(&address, host);
+    let result = if host {
+      self.host_game(ctx, &address)
+    } else {
+      self.join_game(ctx, &address)
+    };
+    if let Err(err) = result {
+      eprintln!("Network game on '{}' failed: {:#}", address, err);
+    }
+    Ok(())
+  }
+
+  fn render_network_menu(&self, ctx: &mut ApplicationContext, host: bool, address: &str) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let roles = &self.options_menu.roles;
+      let (primary, muted) = (roles[PaletteRole::TextPrimary], roles[PaletteRole::Muted]);
+      self.font.render(canvas, 264, 80, primary, "NETWORK GAME")?;
+      for (idx, (label, selected)) in [("Host game", host), ("Join game", !host)].iter().enumerate() {
+        let y = MODE_Y + 16 * idx as i32;
+        let (mark, color) = if *selected { (">", primary) } else { (" ", muted) };
+        self.font.render(canvas, MODE_X - 16, y, color, mark)?;
+        self.font.render(canvas, MODE_X, y, color, label)?;
+      }
+
+      let prompt = if host {
+        "Address to listen on:"
+      } else {
+        "Address of the host:"
+      };
+      self.font.render(canvas, ADDRESS_X, ADDRESS_Y - 16, muted, prompt)?;
+      if address.is_empty() {
+        let default = network_address("", host);
+        self.font.render(canvas, ADDRESS_X, ADDRESS_Y, muted, &default)?;
+      } else {
+        self.font.render(canvas, ADDRESS_X, ADDRESS_Y, primary, address)?;
+      }
+      if address.len() < MAX_ADDRESS_LEN {
+        canvas.set_draw_color(muted);
+        let rect = Rect::new(ADDRESS_X + 1 + 8 * (address.len() as i32), ADDRESS_Y + 6, 8, 2);
+        canvas.fill_rect(rect).map_err(SdlError)?;
+      }
+      self.font.render(
+        canvas,
+        ADDRESS_X,
+        ADDRESS_Y + 32,
+        muted,
+        "Up/Down to switch, Enter to start, Escape to cancel",
+      )?;
+      Ok(())
+    })
+  }
+}
+
+/// Address entered by the player, with the default port added if needed. Host listens on all
+/// interfaces and client connects to this machine by default.
+fn network_address(address: &str, host: bool) -> String {
+  match address {
+    "" if host => format!("0.0.0.0:{}", DEFAULT_PORT),
+    "" => format!("127.0.0.1:{}", DEFAULT_PORT),
+    address if address.contains(':') => address.to_owned(),
+    address => format!("{}:{}", address, DEFAULT_PORT),
+  }
+}
@@ -0,0 +1,305 @@
+//! Observer mode: read-only view of the game streamed by another instance (see `--broadcast`).
+//! Nothing is simulated locally, world state comes from the host as snapshots and deltas. Darkness
+//! is never applied, so observer always sees the whole level. Observers can chat with each other,
+//! as they have no player controls to conflict with, and move the camera around: `Z` zooms in and
+//! out, arrows move the zoomed in camera and `1`-`4` make it follow the player.
+use crate::broadcast::{BroadcastReceiver, Frame};
+use crate::chat::{Chat, ChatInput};
+use crate::context::{Animation, ApplicationContext, PostEffects};
+use crate::menu::hud::Hud;
+use crate::options::Options;
+use crate::palette::PaletteRole;
+use crate::world::map::{LevelMap, LevelMeta};
+use crate::world::player::PlayerComponent;
+use crate::world::rng::WorldRng;
+use crate::world::snapshot::WorldSnapshot;
+use crate::world::{Viewer, World};
+use crate::{Application, SCREEN_HEIGHT, SCREEN_WIDTH};
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use std::time::Duration;
+
+/// How long desync warning is shown (unless skipped with any key)
+const DESYNC_WARNING_TIME: Duration = Duration::from_secs(3);
+
+/// Part of the playfield shown by the zoomed in camera
+const ZOOM_VIEW: (u32, u32) = (SCREEN_WIDTH / 2, SCREEN_HEIGHT / 2);
+
+/// How far camera moves with each arrow key press
+const CAMERA_STEP: i32 = 40;
+
+/// Free camera of the observer. Camera shows the whole playfield unless zoomed in; zoomed in camera
+/// either follows the player or is moved around with arrow keys.
+struct Camera {
+  zoomed: bool,
+  /// Center of the view, in playfield coordinates
+  center: (i32, i32),
+  /// Player camera follows
+  follow: Option<usize>,
+}
+
+impl Default for Camera {
+  fn default() -> Self {
+    Camera {
+      zoomed: false,
+      center: (SCREEN_WIDTH as i32 / 2, SCREEN_HEIGHT as i32 / 2),
+      follow: None,
+    }
+  }
+}
+
+impl Camera {
+  /// Handle camera keys; other keys are ignored
+  fn handle_key(&mut self, scancode: Scancode) {
+    let (dx, dy) = match scancode {
+      Scancode::Z => {
+        self.zoomed = !self.zoomed;
+        return;
+      }
+      Scancode::Num1 | Scancode::Num2 | Scancode::Num3 | Scancode::Num4 => {
+        self.follow = Some(scancode as usize - Scancode::Num1 as usize);
+        self.zoomed = true;
+        return;
+      }
+      Scancode::Left => (-CAMERA_STEP, 0),
+      Scancode::Right => (CAMERA_STEP, 0),
+      Scancode::Up => (0, -CAMERA_STEP),
+      Scancode::Down => (0, CAMERA_STEP),
+      _ => return,
+    };
+    self.follow = None;
+    self.zoomed = true;
+    self.center = (self.center.0 + dx, self.center.1 + dy);
+  }
+
+  /// Part of the screen to show; playfield is drawn at the given horizontal offset
+  fn view(&mut self, world: &World, offset: i32) -> Option<Rect> {
+    if !self.zoomed {
+      return None;
+    }
+    if let Some(actor) = self
+      .follow
+      .filter(|player| *player < world.players.len())
+      .map(|player| &world.actors[player])
+    {
+      self.center = (i32::from(actor.pos.x), i32::from(actor.pos.y));
+    }
+    let (width, height) = ZOOM_VIEW;
+    let max_x = (SCREEN_WIDTH - width) as i32;
+    let max_y = (SCREEN_HEIGHT - height) as i32;
+    self.center.0 = self.center.0.clamp(width as i32 / 2, max_x + width as i32 / 2);
+    self.center.1 = self.center.1.clamp(height as i32 / 2, max_y + height as i32 / 2);
+    let x = self.center.0 - width as i32 / 2;
+    let y = self.center.1 - height as i32 / 2;
+    Some(Rect::new(offset + x, y, width, height))
+  }
+}
+
+impl Application<'_> {
+  /// Watch the game broadcast on the given address until the stream is over or Escape is pressed
+  pub fn observe(&self, ctx: &mut ApplicationContext, address: &str) -> Result<(), anyhow::Error> {
+    let receiver = BroadcastReceiver::connect(address)?;
+    let name = match self.registered.borrow().as_str() {
+      "" => "Observer".to_owned(),
+      name => name.to_owned(),
+    };
+    let mut chat = Chat::default();
+    let mut camera = Camera::default();
+    let mut pending: Option<WorldSnapshot> = None;
+    loop {
+      // Every round starts with the full snapshot
+      let mut redraw = true;
+      let snapshot = loop {
+        if let Some(snapshot) = pending.take() {
+          break snapshot;
+        }
+        match self.observer_input(ctx, &mut chat, &mut camera, &receiver, &name)? {
+          None => return Ok(()),
+          Some(changed) => redraw |= changed,
+        }
+        match receiver.next_frame()? {
+          Some(Frame::Snapshot(snapshot)) => pending = Some(snapshot),
+          Some(Frame::Delta(_)) | Some(Frame::Resync) => {}
+          Some(Frame::Chat(line)) => {
+            chat.push_line(line);
+            redraw = true;
+          }
+          None => std::thread::sleep(Duration::from_millis(20)),
+        }
+        if chat.expire() || redraw {
+          redraw = false;
+          ctx.with_render_context(|canvas| {
+            canvas.set_draw_color(Color::BLACK);
+            canvas.clear();
+            let color = self.main_menu.roles[PaletteRole::TextPrimary];
+            self.font.render(canvas, 232, 200, color, "Waiting for the round")?;
+            chat.render(canvas, &self.font, color)
+          })?;
+          ctx.present()?;
+        }
+      };
+
+      let mut players: Vec<PlayerComponent> = (0..snapshot.players()).map(|_| PlayerComponent::default()).collect();
+      let options = Options::default();
+      let mut world = World::create(
+        LevelMap::empty(),
+        &mut players,
+        false,
+        &options,
+        false,
+        LevelMeta::default(),
+        WorldRng::random(),
+      );
+      world.apply_snapshot(&snapshot);
+      world.update.clear();
+
+      ctx.with_render_context(|canvas| self.render_game_screen(canvas, &world))?;
+      let mut hud = Hud::new(ctx.playfield_offset());
+      if hud.panels_outdated() {
+        ctx.with_screen_context(|canvas| self.render_hud_panels(canvas, &mut hud, &world))?;
+      }
+      ctx.transition(Animation::FadeUp)?;
+
+      loop {
+        let mut chat_changed = match self.observer_input(ctx, &mut chat, &mut camera, &receiver, &name)? {
+          None => {
+            ctx.transition(Animation::FadeDown)?;
+            return Ok(());
+          }
+          Some(changed) => changed,
+        };
+        let mut desync = None;
+        while let Some(frame) = receiver.next_frame()? {
+          match frame {
+            Frame::Delta(delta) if delta.base_tick == world.round_counter && delta.fits(world.players.len()) => {
+              world.apply_delta(&delta);
+              if delta.checksum.is_some_and(|checksum| checksum != world.checksum()) {
+                desync = Some(delta.tick);
+              }
+            }
+            // Missed part of the stream; wait for the next snapshot to resync
+            Frame::Delta(_) => {}
+            Frame::Snapshot(snapshot) if snapshot.tick >= world.round_counter => world.apply_snapshot(&snapshot),
+            // Snapshot of an earlier tick: next round or the restart of this one
+            Frame::Snapshot(snapshot) => {
+              pending = Some(snapshot);
+              break;
+            }
+            Frame::Chat(line) => {
+              chat.push_line(line);
+              chat_changed = true;
+            }
+            Frame::Resync => {}
+          }
+        }
+        if pending.is_some() {
+          break;
+        }
+        if let Some(tick) = desync {
+          self.desync_warning(ctx, tick, "Requesting full state from the host")?;
+          receiver.request_resync()?;
+          // Warning covered the whole screen
+          chat_changed = true;
+        }
+
+        hud.track(&mut world.update, world.round_counter);
+        chat_changed |= chat.expire();
+        let max_splatters = ctx.post_effect_limits().max_splatters;
+        ctx.with_render_context(|canvas| {
+          if chat_changed {
+            // Chat overlay shrunk or grew, restore the game screen under it
+            world.update.clear();
+            self.render_game_screen(canvas, &world)?;
+          } else {
+            self.render_updates(canvas, &mut world, Viewer::Shared, max_splatters)?;
+            self.render_hud(canvas, &mut hud, &world)?;
+          }
+          chat.render(canvas, &self.font, self.players.roles[PaletteRole::TextPrimary])
+        })?;
+        if hud.panels_outdated() {
+          ctx.with_screen_context(|canvas| self.render_hud_panels(canvas, &mut hud, &world))?;
+        }
+        let effects = PostEffects {
+          view: camera.view(&world, ctx.playfield_offset()),
+          ..Default::default()
+        };
+        ctx.present_with(effects)?;
+        std::thread::sleep(Duration::from_millis(20));
+      }
+      ctx.transition(Animation::FadeDown)?;
+    }
+  }
+
+  /// Warn that replicated (or simulated) state doesn't match the one of the other side (either a bug
+  /// or a tampered stream); `action` tells what happens next
+  pub(super) fn desync_warning(
+    &self,
+    ctx: &mut ApplicationContext,
+    tick: usize,
+    action: &str,
+  ) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let roles = &self.main_menu.roles;
+      self
+        .font
+        .render(canvas, 256, 200, roles[PaletteRole::TextPrimary], "DESYNC DETECTED")?;
+      let details = format!("State checksum mismatch at tick {}", tick);
+      self.font.render(
+        canvas,
+        320 - 4 * details.len() as i32,
+        220,
+        roles[PaletteRole::Muted],
+        &details,
+      )?;
+      self.font.render(
+        canvas,
+        320 - 4 * action.len() as i32,
+        240,
+        roles[PaletteRole::Muted],
+        action,
+      )?;
+      Ok(())
+    })?;
+    ctx.present()?;
+    ctx.wait_key_pressed_idle(Some(DESYNC_WARNING_TIME), |_, _| Ok(()))?;
+    Ok(())
+  }
+
+  /// Handle observer input: chat, camera and exit. Returns `None` if observer wants to leave, otherwise
+  /// returns if chat needs to be re-rendered.
+  fn observer_input(
+    &self,
+    ctx: &mut ApplicationContext,
+    chat: &mut Chat,
+    camera: &mut Camera,
+    receiver: &BroadcastReceiver,
+    name: &str,
+  ) -> Result<Option<bool>, anyhow::Error> {
+    let mut changed = false;
+    for event in ctx.poll_iter() {
+      match chat.handle_event(&event) {
+        ChatInput::Send(message) => {
+          receiver.send_chat(&format!("{}: {}", name, message))?;
+          changed = true;
+        }
+        ChatInput::Handled => changed = true,
+        ChatInput::Ignored => match event {
+          Event::KeyDown {
+            scancode: Some(Scancode::Escape),
+            ..
+          } => return Ok(None),
+          Event::KeyDown {
+            scancode: Some(scancode),
+            ..
+          } => camera.handle_key(scancode),
+          _ => {}
+        },
+      }
+    }
+    Ok(Some(changed))
+  }
+}
@@ -1,7 +1,9 @@
 use crate::context::{Animation, ApplicationContext};
 use crate::error::ApplicationError::SdlError;
 use crate::glyphs::Glyph;
-use crate::options::{Options, WinCondition};
+use crate::keys::MenuAction;
+use crate::options::{Options, SavedOptions, WinCondition};
+use crate::palette::PaletteRole;
 use crate::settings::GameSettings;
 use crate::Application;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
@@ -10,7 +12,60 @@ use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::WindowCanvas;
 use std::convert::TryInto;
-use std::time::Duration;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Delay after the last change before options are written to the disk
+const AUTOSAVE_DELAY: Duration = Duration::from_millis(750);
+
+/// Position of the "saved" indicator
+const SAVED_INDICATOR_POS: (i32, i32) = (560, 460);
+
+/// Debounced options persistence: options are saved once player stops changing them for a while.
+pub(super) struct Autosave {
+  saved: SavedOptions,
+  /// When pending changes should be written
+  deadline: Option<Instant>,
+}
+
+impl Autosave {
+  fn new(game_dir: &Path) -> Self {
+    Autosave {
+      saved: SavedOptions::load(game_dir),
+      deadline: None,
+    }
+  }
+
+  /// Record that options were changed
+  pub(super) fn changed(&mut self, options: &Options) {
+    if options.autosave {
+      self.deadline = Some(Instant::now() + AUTOSAVE_DELAY);
+    }
+  }
+
+  /// Wait for the next key press, writing pending changes once player stops changing options.
+  /// Callback is invoked each time options are written.
+  pub(super) fn wait_key_pressed(
+    &mut self,
+    ctx: &mut ApplicationContext,
+    options: &Options,
+    mut on_saved: impl FnMut(&mut ApplicationContext) -> Result<(), anyhow::Error>,
+  ) -> Result<(Scancode, Keycode), anyhow::Error> {
+    loop {
+      let deadline = match self.deadline {
+        Some(deadline) => deadline,
+        None => return Ok(ctx.wait_key_pressed()),
+      };
+      if let Some(key) = ctx.wait_key_pressed_timeout(deadline.saturating_duration_since(Instant::now())) {
+        return Ok(key);
+      }
+      self.deadline = None;
+      if options.save_changed(ctx.game_dir(), &mut self.saved)? {
+        on_saved(ctx)?;
+      }
+    }
+  }
+}
 
 /// Items in the options menu. Note that ordering must match the texture used for the menu.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, IntoPrimitive, TryFromPrimitive)]
@@ -202,11 +257,12 @@ impl GameOption {
 
 impl Application<'_> {
   pub fn options_menu(&self, ctx: &mut ApplicationContext, settings: &mut GameSettings) -> Result<(), anyhow::Error> {
+    let mut autosave = Autosave::new(ctx.game_dir());
     loop {
       self.render_options_menu(ctx, &settings.options, GameOption::MainMenu)?;
-      ctx.animate(Animation::FadeUp, 7)?;
-      let selected = self.option_menu_navigation_loop(ctx, &mut settings.options)?;
-      ctx.animate(Animation::FadeDown, 7)?;
+      ctx.transition(Animation::FadeUp)?;
+      let selected = self.option_menu_navigation_loop(ctx, &mut settings.options, &mut autosave)?;
+      ctx.transition(Animation::FadeDown)?;
 
       match selected {
         GameOption::LoadLevels => {
@@ -222,7 +278,7 @@ impl Application<'_> {
     }
 
     // Save options
-    settings.options.save(ctx.game_dir())?;
+    settings.options.save_changed(ctx.game_dir(), &mut autosave.saved)?;
     Ok(())
   }
 
@@ -230,62 +286,73 @@ impl Application<'_> {
     &self,
     ctx: &mut ApplicationContext,
     options: &mut Options,
+    autosave: &mut Autosave,
   ) -> Result<GameOption, anyhow::Error> {
     let mut selected = GameOption::MainMenu;
     loop {
-      let (scancode, keycode) = ctx.wait_key_pressed();
-      match scancode {
-        Scancode::Down | Scancode::Kp2 => {
+      let (scancode, keycode) = autosave.wait_key_pressed(ctx, options, |ctx| self.show_saved_indicator(ctx, true))?;
+      match ctx.menu_action(scancode) {
+        // Reset to defaults; goes first, as `D` could be also used for navigation
+        _ if keycode == Keycode::D => {
+          *options = Options::default();
+          autosave.changed(options);
+          ctx.with_render_context(|canvas| {
+            for option in GameOption::all_options() {
+              self.render_option_value(canvas, options, option)?;
+            }
+            Ok(())
+          })?;
+          self.show_saved_indicator(ctx, false)?;
+        }
+        MenuAction::Down => {
           let previous = selected;
           selected = selected.next();
           self.update_pointer(ctx, previous, selected)?;
         }
-        Scancode::Up | Scancode::Kp8 => {
+        MenuAction::Up => {
           let previous = selected;
           selected = selected.prev();
           self.update_pointer(ctx, previous, selected)?;
         }
-        Scancode::Escape => {
+        MenuAction::Back => {
           return Ok(GameOption::MainMenu);
         }
-        Scancode::Return | Scancode::KpEnter
+        MenuAction::Other(Scancode::Tab) => {
+          ctx.transition(Animation::FadeDown)?;
+          self.extended_options_menu(ctx, options, autosave)?;
+          self.render_options_menu(ctx, options, selected)?;
+          ctx.transition(Animation::FadeUp)?;
+        }
+        MenuAction::Select
           if selected == GameOption::RedefineKeys
             || selected == GameOption::LoadLevels
             || selected == GameOption::MainMenu =>
         {
           return Ok(selected);
         }
-        Scancode::Left => {
+        MenuAction::Left => {
           selected.value_minus(options);
+          autosave.changed(options);
           ctx.with_render_context(|canvas| {
             self.render_option_value(canvas, options, selected)?;
             Ok(())
           })?;
-          ctx.present()?;
+          self.show_saved_indicator(ctx, false)?;
         }
-        Scancode::Right => {
+        MenuAction::Right => {
           selected.value_plus(options);
+          autosave.changed(options);
           ctx.with_render_context(|canvas| {
             self.render_option_value(canvas, options, selected)?;
             Ok(())
           })?;
-          ctx.present()?;
+          self.show_saved_indicator(ctx, false)?;
         }
-        Scancode::Return | Scancode::KpEnter if selected == GameOption::RedefineKeys => {
+        MenuAction::Select if selected == GameOption::RedefineKeys => {
           panic!();
-          // ctx.animate(Animation::FadeDown, 7)?;
+          // ctx.transition(Animation::FadeDown)?;
           // self.redefine_keys_menu(ctx, &mut settings.keys)?;
-          // ctx.animate(Animation::FadeUp, 7)?;
-        }
-        _ if keycode == Keycode::D => {
-          *options = Options::default();
-          ctx.with_render_context(|canvas| {
-            for option in GameOption::all_options() {
-              self.render_option_value(canvas, options, option)?;
-            }
-            Ok(())
-          })?;
-          ctx.present()?;
+          // ctx.transition(Animation::FadeUp)?;
         }
         _ => {}
       }
@@ -306,6 +373,8 @@ impl Application<'_> {
       for option in GameOption::all_options() {
         self.render_option_value(canvas, options, option)?;
       }
+      let color = self.options_menu.roles[PaletteRole::Muted];
+      self.font.render(canvas, 248, 460, color, "TAB - more options")?;
       Ok(())
     })?;
     Ok(())
@@ -353,7 +422,7 @@ impl Application<'_> {
       };
       let mut rect = option.value_bar_rect();
       rect.set_width((value as u32) + 1);
-      canvas.set_draw_color(self.options_menu.palette[1]);
+      canvas.set_draw_color(self.options_menu.roles[PaletteRole::TextPrimary]);
       canvas.fill_rect(rect).map_err(SdlError)?;
     }
 
@@ -373,13 +442,30 @@ impl Application<'_> {
       _ => None,
     };
     if let Some(text) = text {
-      let text_color = self.options_menu.palette[8];
+      let text_color = self.options_menu.roles[PaletteRole::Muted];
       let (x, y) = option.text_pos();
       self.font.render(canvas, x, y, text_color, &text)?;
     }
     Ok(())
   }
 
+  /// Show or hide the indicator that options were saved, and present the screen
+  pub(super) fn show_saved_indicator(&self, ctx: &mut ApplicationContext, saved: bool) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      let (x, y) = SAVED_INDICATOR_POS;
+      canvas.set_draw_color(Color::BLACK);
+      canvas.fill_rect(Rect::new(x, y, 48, 8)).map_err(SdlError)?;
+      if saved {
+        self
+          .font
+          .render(canvas, x, y, self.options_menu.roles[PaletteRole::TextPrimary], "SAVED")?;
+      }
+      Ok(())
+    })?;
+    ctx.present()?;
+    Ok(())
+  }
+
   /// Update cursor icon
   fn update_pointer(
     &self,
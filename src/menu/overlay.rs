@@ -0,0 +1,96 @@
+//! Debug overlays (practice mode only): timer or hits values of the map cells rendered as heat colors
+//! over the level (useful for debugging fuse logic and digging balance) and time spent in the world
+//! subsystems.
+use crate::coords::cell_rect;
+use crate::error::ApplicationError::SdlError;
+use crate::fonts::Font;
+use crate::world::map::{MapValue, NEST_SPAWN_INTERVAL};
+use crate::world::position::Cursor;
+use crate::world::profile::{Subsystem, TickProfile};
+use crate::world::Maps;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, WindowCanvas};
+
+/// Opacity of the heat colors
+const HEAT_ALPHA: u8 = 160;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeatOverlay {
+  /// Timers of bombs, biomass and monster nests
+  Timer,
+  /// Remaining "health" of the cells
+  Hits,
+}
+
+impl HeatOverlay {
+  /// Next overlay in the cycle: timer, then hits, then no overlay
+  pub fn cycle(overlay: Option<HeatOverlay>) -> Option<HeatOverlay> {
+    match overlay {
+      None => Some(HeatOverlay::Timer),
+      Some(HeatOverlay::Timer) => Some(HeatOverlay::Hits),
+      Some(HeatOverlay::Hits) => None,
+    }
+  }
+
+  /// Label shown in the corner of the screen
+  pub fn label(self) -> &'static str {
+    match self {
+      HeatOverlay::Timer => "Timer",
+      HeatOverlay::Hits => "Hits",
+    }
+  }
+
+  /// Value of the cell and the value which gets the "hottest" color
+  fn value(self, maps: &Maps, cursor: Cursor) -> (u32, u32) {
+    match self {
+      HeatOverlay::Timer => (u32::from(maps.timer[cursor]), u32::from(NEST_SPAWN_INTERVAL)),
+      HeatOverlay::Hits if maps.level[cursor] == MapValue::MetalWall => (0, 1),
+      HeatOverlay::Hits => (maps.hits[cursor].max(0) as u32, 8000),
+    }
+  }
+}
+
+/// Render heat colors over all cells with non-zero value. Values are shown on logarithmic scale
+/// (from blue to red), as both timers and hits span few orders of magnitude.
+pub fn render_heat_overlay(canvas: &mut WindowCanvas, maps: &Maps, overlay: HeatOverlay) -> Result<(), anyhow::Error> {
+  canvas.set_blend_mode(BlendMode::Blend);
+  let result = Cursor::all().try_for_each(|cursor| {
+    let (value, max) = overlay.value(maps, cursor);
+    if value == 0 {
+      return Ok(());
+    }
+    let heat = (f64::from(value.min(max)) + 1.0).ln() / (f64::from(max) + 1.0).ln();
+    let red = (255.0 * heat) as u8;
+    canvas.set_draw_color(Color::RGBA(red, 0, 255 - red, HEAT_ALPHA));
+    canvas.fill_rect(cell_rect(cursor)).map_err(SdlError)
+  });
+  canvas.set_blend_mode(BlendMode::None);
+  result?;
+  Ok(())
+}
+
+/// Render time spent in each of the world subsystems during the last second of the game time
+pub fn render_profile_overlay(
+  canvas: &mut WindowCanvas,
+  font: &Font,
+  profile: &TickProfile,
+) -> Result<(), anyhow::Error> {
+  const LEFT: i32 = 12;
+  const TOP: i32 = 56;
+  canvas.set_draw_color(Color::BLACK);
+  canvas
+    .fill_rect(Rect::new(LEFT - 2, TOP - 2, 264, 10 * Subsystem::ALL.len() as u32 + 2))
+    .map_err(SdlError)?;
+  for (idx, subsystem) in Subsystem::ALL.iter().enumerate() {
+    let stats = profile.last_second(*subsystem);
+    let text = format!(
+      "{:<10}{:>8.2?}/s {:>5} calls",
+      subsystem.label(),
+      stats.time,
+      stats.calls
+    );
+    font.render(canvas, LEFT, TOP + 10 * idx as i32, Color::WHITE, &text)?;
+  }
+  Ok(())
+}
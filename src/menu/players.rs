@@ -5,6 +5,8 @@ use crate::context::{Animation, ApplicationContext, InputEvent};
 use crate::error::ApplicationError::SdlError;
 use crate::glyphs::Glyph;
 use crate::identities::Identities;
+use crate::keys::MenuAction;
+use crate::palette::PaletteRole;
 use crate::roster::{PlayersRoster, RosterInfo};
 use crate::Application;
 use sdl2::keyboard::Scancode;
@@ -116,7 +118,7 @@ impl Application<'_> {
       self.render_right_pane(canvas, &state)?;
       Ok(())
     })?;
-    ctx.animate(Animation::FadeUp, 7)?;
+    ctx.transition(Animation::FadeUp)?;
 
     let exit = loop {
       let last_active_player = state.active_player;
@@ -129,26 +131,24 @@ impl Application<'_> {
           state.select_player(selection);
         }
         InputEvent::TextInput(_) => continue,
-        InputEvent::KeyPress(scancode, _keycode) => match scancode {
-          Scancode::Down | Scancode::Kp2 => state.next_player(),
-          Scancode::Up | Scancode::Kp8 => state.previous_player(),
-          Scancode::Escape => {
+        InputEvent::KeyPress(scancode, _keycode) => match ctx.text_menu_action(scancode) {
+          MenuAction::Down => state.next_player(),
+          MenuAction::Up => state.previous_player(),
+          MenuAction::Back => {
             // Check that all players were selected
             if state.all_selected() {
               break false;
             }
           }
-          Scancode::Kp6 | Scancode::Return | Scancode::Return2 | Scancode::KpEnter | Scancode::Right
-            if state.active_player == 4 =>
-          {
+          MenuAction::Select | MenuAction::Right if state.active_player == 4 => {
             if state.all_selected() {
               break false;
             }
           }
-          Scancode::F10 => {
+          MenuAction::Other(Scancode::F10) => {
             break true;
           }
-          Scancode::Kp6 | Scancode::Return | Scancode::Return2 | Scancode::KpEnter | Scancode::Right => {
+          MenuAction::Select | MenuAction::Right => {
             let selection = self.players_name_select_menu(ctx, &mut state, None)?;
             state.select_player(selection);
           }
@@ -169,7 +169,7 @@ impl Application<'_> {
 
     state.identities.save(ctx.game_dir())?;
     state.roster.save(ctx.game_dir())?;
-    ctx.animate(Animation::FadeDown, 7)?;
+    ctx.transition(Animation::FadeDown)?;
 
     let mut selected = Vec::new();
     if !exit {
@@ -189,6 +189,29 @@ impl Application<'_> {
     Ok(selected)
   }
 
+  /// Get players selected in the previous game, without showing any menus. Returns `None` if
+  /// some of the players are missing.
+  pub fn last_selected_players(
+    &self,
+    ctx: &mut ApplicationContext,
+    total_players: u8,
+  ) -> Result<Option<Vec<SelectedPlayer>>, anyhow::Error> {
+    let roster = PlayersRoster::load(ctx.game_dir())?;
+    let identities = Identities::load(ctx.game_dir());
+    let selected = identities.players[..usize::from(total_players)]
+      .iter()
+      .map(|roster_index| {
+        let roster_index = (*roster_index)?;
+        let stats = roster.players[usize::from(roster_index)].as_ref()?;
+        Some(SelectedPlayer {
+          name: stats.name.to_owned(),
+          roster_index,
+        })
+      })
+      .collect();
+    Ok(selected)
+  }
+
   fn players_name_select_menu(
     &self,
     ctx: &mut ApplicationContext,
@@ -221,14 +244,14 @@ impl Application<'_> {
       };
 
       let last_arrow_pos = arrow_pos;
-      match scancode {
-        Scancode::Down | Scancode::Kp2 => {
+      match ctx.text_menu_action(scancode) {
+        MenuAction::Down => {
           arrow_pos = (arrow_pos + 1) % 32;
         }
-        Scancode::Up | Scancode::Kp8 => {
+        MenuAction::Up => {
           arrow_pos = (arrow_pos + 31) % 32;
         }
-        Scancode::Left | Scancode::Kp4 => {
+        MenuAction::Left => {
           // If we have player for the current index configured, pick it
           if state.stats(arrow_pos).is_some() {
             break Some(arrow_pos);
@@ -238,15 +261,22 @@ impl Application<'_> {
         }
         // No selection
         // FIXME: on F10, should exit from player selection screen
-        Scancode::Escape | Scancode::F10 => break None,
+        MenuAction::Back | MenuAction::Other(Scancode::F10) => break None,
         // Delete currently selected player
-        Scancode::Backspace | Scancode::Delete => {
-          state.delete_stats(arrow_pos);
-          ctx.with_render_context(|canvas| self.render_right_pane(canvas, state))?;
-          ctx.present()?;
+        MenuAction::Other(Scancode::Backspace) | MenuAction::Other(Scancode::Delete) => {
+          let question = state
+            .stats(arrow_pos)
+            .map(|stats| format!("Delete player {}?", stats.name));
+          if let Some(question) = question {
+            if self.confirm(ctx, &question)? {
+              state.delete_stats(arrow_pos);
+              ctx.with_render_context(|canvas| self.render_right_pane(canvas, state))?;
+              ctx.present()?;
+            }
+          }
         }
 
-        Scancode::Return | Scancode::KpEnter | Scancode::Return2 => {
+        MenuAction::Select => {
           self.edit_new_player_name(ctx, state, arrow_pos, None)?;
         }
 
@@ -286,7 +316,7 @@ impl Application<'_> {
       canvas.set_draw_color(Color::BLACK);
       let rect = Rect::new(x, y, 192, 8);
       canvas.fill_rect(rect).map_err(SdlError)?;
-      canvas.set_draw_color(self.select_players.palette[8]);
+      canvas.set_draw_color(self.select_players.roles[PaletteRole::Muted]);
       let rect = Rect::new(x + 1, y + 6, 8, 2);
       canvas.fill_rect(rect).map_err(SdlError)?;
       Ok(())
@@ -299,12 +329,12 @@ impl Application<'_> {
         .map(InputEvent::TextInput)
         .unwrap_or_else(|| ctx.wait_input_event())
       {
-        InputEvent::KeyPress(scancode, _) => match scancode {
-          Scancode::Return | Scancode::Return2 | Scancode::KpEnter | Scancode::Escape => {
+        InputEvent::KeyPress(scancode, _) => match ctx.text_menu_action(scancode) {
+          MenuAction::Select | MenuAction::Back => {
             // We are done -- exit the loop
             break;
           }
-          Scancode::Delete | Scancode::Backspace => {
+          MenuAction::Other(Scancode::Delete) | MenuAction::Other(Scancode::Backspace) => {
             if !name.is_empty() {
               name.truncate(name.len() - 1);
             }
@@ -328,10 +358,12 @@ impl Application<'_> {
         canvas.set_draw_color(Color::BLACK);
         let rect = Rect::new(x, y, 193, 8);
         canvas.fill_rect(rect).map_err(SdlError)?;
-        self.font.render(canvas, x, y, self.select_players.palette[1], &name)?;
+        self
+          .font
+          .render(canvas, x, y, self.select_players.roles[PaletteRole::TextPrimary], &name)?;
 
         if name.len() < 24 {
-          canvas.set_draw_color(self.select_players.palette[8]);
+          canvas.set_draw_color(self.select_players.roles[PaletteRole::Muted]);
           let rect = Rect::new(x + 1 + 8 * (name.len() as i32), y + 6, 8, 2);
           canvas.fill_rect(rect).map_err(SdlError)?;
         }
@@ -381,7 +413,7 @@ impl Application<'_> {
         .fill_rect(Rect::new(119, player * 53 + 40, 26 * 8, 10))
         .map_err(SdlError)?;
       if player < i32::from(state.players) {
-        let color = self.select_players.palette[1];
+        let color = self.select_players.roles[PaletteRole::TextPrimary];
         if let Some(stats) =
           state.identities.players[player as usize].and_then(|idx| state.roster.players[usize::from(idx)].as_ref())
         {
@@ -397,14 +429,16 @@ impl Application<'_> {
     let rect = Rect::new(RIGHT_PANEL_X + 2, RIGHT_PANEL_Y + 1, 198, 256);
     canvas.fill_rect(rect).map_err(SdlError)?;
 
-    let palette = &self.select_players.palette;
+    let roles = &self.select_players.roles;
     for idx in 0..32 {
       let x = RIGHT_PANEL_X + 2;
       let y = RIGHT_PANEL_Y + (idx as i32) * 8 + 1;
       if let Some(ref player) = state.roster.players[idx] {
-        self.font.render(canvas, x, y, palette[1], &player.name)?;
+        self
+          .font
+          .render(canvas, x, y, roles[PaletteRole::TextPrimary], &player.name)?;
       } else {
-        self.font.render(canvas, x, y, palette[3], "-")?;
+        self.font.render(canvas, x, y, roles[PaletteRole::TextSecondary], "-")?;
       }
     }
 
@@ -431,8 +465,8 @@ impl Application<'_> {
 
   /// Render player statistics
   fn render_stats(&self, canvas: &mut WindowCanvas, stats: Option<&RosterInfo>) -> Result<(), anyhow::Error> {
-    let white = self.select_players.palette[1];
-    let red_color = self.select_players.palette[3];
+    let white = self.select_players.roles[PaletteRole::TextPrimary];
+    let secondary = self.select_players.roles[PaletteRole::TextSecondary];
 
     canvas.set_draw_color(Color::BLACK);
 
@@ -478,7 +512,7 @@ impl Application<'_> {
         let percentage = (200 * wins + total) / total / 2;
         self
           .font
-          .render(canvas, 65, 378 + 72 * idx, red_color, &format!("{}%", percentage))?;
+          .render(canvas, 65, 378 + 72 * idx, secondary, &format!("{}%", percentage))?;
       }
     }
 
@@ -502,17 +536,17 @@ impl Application<'_> {
     let mut offset = (stats.tournaments as usize) % 34;
     let mut last_x = 367;
     let mut last_y = 457 - i32::from(stats.history[offset]);
-    let palette = &self.select_players.palette;
+    let roles = &self.select_players.roles;
     for _ in 1..34 {
       offset = (offset + 1) % 34;
       let value = stats.history[offset];
       let y = 457 - i32::from(value);
       let color = match (u16::from(value) * 4 + 67) / 134 {
-        0 => palette[3],
-        1 => palette[7],
-        2 => palette[6],
-        3 => palette[5],
-        _ => palette[4],
+        0 => roles[PaletteRole::Danger],
+        1 => roles[PaletteRole::Warning],
+        2 => roles[PaletteRole::Notice],
+        3 => roles[PaletteRole::Highlight],
+        _ => roles[PaletteRole::Success],
       };
       canvas.set_draw_color(color);
       canvas.draw_line((last_x, last_y), (last_x + 5, y)).map_err(SdlError)?;
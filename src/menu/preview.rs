@@ -1,20 +1,103 @@
+use crate::context::ApplicationContext;
+use crate::images::TexturePalette;
+use crate::palette::PaletteRole;
+use crate::textures::{TextureHandle, TextureKey};
 use crate::world::map::{LevelMap, MapValue};
+use crate::world::position::Cursor;
 use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::render::{Texture, TextureCreator};
 use sdl2::video::WindowContext;
 
+/// Size of the block of "satellite" preview pixels which get the same color
+const SATELLITE_BLOCK: u16 = 2;
+
+/// Map preview shown in the shop
+#[derive(Clone, Copy)]
+pub enum Preview<'a> {
+  /// No preview (darkness games)
+  Hidden,
+  Full(&'a LevelMap),
+  /// Obscured preview for darkness games: only terrain silhouette is shown
+  Satellite(&'a LevelMap),
+}
+
+impl Preview<'_> {
+  /// Get texture of the preview from the texture cache (generating it on the first use), if
+  /// preview is not hidden. Previews are shared by all screens showing the same level. Colors are
+  /// taken from the palette of the given texture.
+  pub fn cached(
+    self,
+    ctx: &mut ApplicationContext,
+    texture: &TexturePalette,
+  ) -> Result<Option<TextureHandle>, anyhow::Error> {
+    let (map, satellite) = match self {
+      Preview::Hidden => return Ok(None),
+      Preview::Full(map) => (map, false),
+      Preview::Satellite(map) => (map, true),
+    };
+    let values: Vec<u8> = Cursor::all().map(|cursor| map[cursor] as u8).collect();
+    let (palette, roles) = (&texture.palette, &texture.roles);
+    let (open, solid) = (roles[PaletteRole::Passage], roles[PaletteRole::Muted]);
+    let colors: Vec<(u8, u8, u8)> = palette.iter().chain([&open, &solid]).map(|color| color.rgb()).collect();
+    let key = TextureKey::of(("preview", satellite, values, colors));
+    let handle = ctx.textures().get_or_create(key, |texture_creator| {
+      if satellite {
+        generate_satellite_preview(map, texture_creator, open, solid)
+      } else {
+        generate_preview(map, texture_creator, palette)
+      }
+    })?;
+    Ok(Some(handle))
+  }
+}
+
 /// Generate texture for the map preview.
-pub fn generate_preview<'t>(
+fn generate_preview<'t>(
   map: &LevelMap,
   texture_creator: &'t TextureCreator<WindowContext>,
   palette: &[Color; 16],
+) -> Result<Texture<'t>, anyhow::Error> {
+  generate_texture(texture_creator, |row, col| palette[preview_pixel(map[row][col])])
+}
+
+/// Generate texture for the "satellite" map preview: only shows where the open areas are, in coarse
+/// blocks; neither items nor kinds of the terrain are visible.
+fn generate_satellite_preview<'t>(
+  map: &LevelMap,
+  texture_creator: &'t TextureCreator<WindowContext>,
+  open_color: Color,
+  solid_color: Color,
+) -> Result<Texture<'t>, anyhow::Error> {
+  generate_texture(texture_creator, |row, col| {
+    let top = row - row % SATELLITE_BLOCK;
+    let left = col - col % SATELLITE_BLOCK;
+    let mut open = 0;
+    let mut total = 0;
+    for row in top..(top + SATELLITE_BLOCK).min(45) {
+      for col in left..left + SATELLITE_BLOCK {
+        total += 1;
+        if map[row][col].is_passable() {
+          open += 1;
+        }
+      }
+    }
+    if 2 * open >= total {
+      open_color
+    } else {
+      solid_color
+    }
+  })
+}
+
+fn generate_texture<'t>(
+  texture_creator: &'t TextureCreator<WindowContext>,
+  pixel: impl Fn(u16, u16) -> Color,
 ) -> Result<Texture<'t>, anyhow::Error> {
   let mut texture = texture_creator.create_texture_static(PixelFormatEnum::RGB24, 64, 45)?;
   let mut image = Vec::with_capacity(45 * 64 * 3);
   for row in 0..45 {
     for col in 0..64 {
-      let color = preview_pixel(map[row][col]);
-      let color = palette[color];
+      let color = pixel(row, col);
       image.push(color.r);
       image.push(color.g);
       image.push(color.b);
@@ -34,7 +117,7 @@ fn preview_pixel(value: MapValue) -> usize {
     14
   } else if value == MapValue::MetalWall {
     8
-  } else if value == MapValue::Biomass {
+  } else if value == MapValue::Biomass || value == MapValue::BiomassHeart {
     4
   } else {
     12
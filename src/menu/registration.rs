@@ -0,0 +1,89 @@
+//! Screen to change the name the game is registered to.
+use crate::context::{Animation, ApplicationContext, InputEvent};
+use crate::error::ApplicationError::SdlError;
+use crate::keys::MenuAction;
+use crate::palette::PaletteRole;
+use crate::register::{save_registered, validate_name, MAX_REGISTERED_LEN};
+use crate::Application;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+const NAME_X: i32 = 216;
+const NAME_Y: i32 = 216;
+
+const ERROR_COLOR: Color = Color::RGB(255, 96, 96);
+
+impl Application<'_> {
+  /// Edit the registered name. Name is saved into `register.dat` once confirmed.
+  pub(super) fn registration_menu(&self, ctx: &mut ApplicationContext) -> Result<(), anyhow::Error> {
+    let mut name = self.registered.borrow().clone();
+    let mut error = String::new();
+    self.render_registration(ctx, &name, &error)?;
+    ctx.transition(Animation::FadeUp)?;
+
+    loop {
+      match ctx.wait_input_event() {
+        InputEvent::KeyPress(scancode, _) => match ctx.text_menu_action(scancode) {
+          MenuAction::Select => match validate_name(&name) {
+            Ok(()) => {
+              save_registered(ctx.game_dir(), &name)?;
+              *self.registered.borrow_mut() = name;
+              break;
+            }
+            Err(err) => error = err.to_string(),
+          },
+          MenuAction::Back => break,
+          MenuAction::Other(Scancode::Delete) | MenuAction::Other(Scancode::Backspace) => {
+            name.pop();
+          }
+          _ => continue,
+        },
+        InputEvent::TextInput(text) => {
+          for ch in text.chars() {
+            if name.len() < MAX_REGISTERED_LEN && (ch.is_ascii_graphic() || ch == ' ') {
+              name.push(ch);
+            }
+          }
+        }
+      }
+      self.render_registration(ctx, &name, &error)?;
+      ctx.present()?;
+    }
+    ctx.transition(Animation::FadeDown)?;
+    Ok(())
+  }
+
+  fn render_registration(&self, ctx: &mut ApplicationContext, name: &str, error: &str) -> Result<(), anyhow::Error> {
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let color = self.options_menu.roles[PaletteRole::Muted];
+      self.font.render(
+        canvas,
+        272,
+        80,
+        self.options_menu.roles[PaletteRole::TextPrimary],
+        "REGISTRATION",
+      )?;
+      self.font.render(canvas, NAME_X, NAME_Y - 16, color, "Registered to:")?;
+      self.font.render(
+        canvas,
+        NAME_X,
+        NAME_Y,
+        self.options_menu.roles[PaletteRole::TextPrimary],
+        name,
+      )?;
+      if name.len() < MAX_REGISTERED_LEN {
+        canvas.set_draw_color(color);
+        let rect = Rect::new(NAME_X + 1 + 8 * (name.len() as i32), NAME_Y + 6, 8, 2);
+        canvas.fill_rect(rect).map_err(SdlError)?;
+      }
+      self.font.render(canvas, NAME_X, NAME_Y + 24, ERROR_COLOR, error)?;
+      self
+        .font
+        .render(canvas, NAME_X, NAME_Y + 48, color, "Enter to save, Escape to cancel")?;
+      Ok(())
+    })
+  }
+}
@@ -1,11 +1,12 @@
 use crate::context::{Animation, ApplicationContext};
 use crate::error::ApplicationError::SdlError;
 use crate::glyphs::Glyph;
-use crate::keys::Key;
-use crate::menu::preview::generate_preview;
-use crate::options::Options;
-use crate::world::equipment::Equipment;
-use crate::world::map::LevelMap;
+use crate::keys::{Key, MenuAction};
+use crate::menu::preview::Preview;
+use crate::menu::tooltip::Tooltip;
+use crate::options::{ArmorModel, Options};
+use crate::palette::PaletteRole;
+use crate::world::equipment::{Equipment, EquipmentCategory};
 use crate::world::player::PlayerComponent;
 use crate::Application;
 use rand::Rng;
@@ -14,7 +15,79 @@ use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::WindowCanvas;
 use std::borrow::Cow;
-use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+/// How long item should stay selected before its tooltip is shown
+const TOOLTIP_DELAY: Duration = Duration::from_secs(1);
+
+/// Width of the tooltip panel; tooltip covers the shop items, so it is as wide as the items grid
+const TOOLTIP_WIDTH: u32 = 256;
+
+/// Area covered by the items grid (relative to the player side of the shop)
+const GRID_RECT: (i32, i32, u32, u32) = (32, 96, 256, 384);
+
+/// Number of item columns in the tab grid; every tab (including the exit slot) fits into four rows
+const GRID_COLUMNS: i32 = 4;
+
+/// Size of the item image inside the shop slot; smaller images are scaled up to it
+const ITEM_GLYPH_SIZE: u32 = 30;
+
+/// Top coordinate of the tab names
+const TABS_Y: i32 = 80;
+
+/// Shop items are split into tabs, each tab has its own grid of items (and the exit slot). Tabs group
+/// equipment categories, so items land in the tab of their category.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ShopTab {
+  Bombs,
+  Weapons,
+  Tools,
+  Utility,
+}
+
+impl ShopTab {
+  const ALL: [ShopTab; 4] = [ShopTab::Bombs, ShopTab::Weapons, ShopTab::Tools, ShopTab::Utility];
+
+  fn of(item: Equipment) -> ShopTab {
+    match item.category() {
+      EquipmentCategory::Bomb | EquipmentCategory::Trigger => ShopTab::Bombs,
+      EquipmentCategory::Weapon => ShopTab::Weapons,
+      EquipmentCategory::Terrain | EquipmentCategory::Upgrade => ShopTab::Tools,
+      EquipmentCategory::Utility => ShopTab::Utility,
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      ShopTab::Bombs => "Bombs",
+      ShopTab::Weapons => "Weapons",
+      ShopTab::Tools => "Tools",
+      ShopTab::Utility => "Utility",
+    }
+  }
+
+  /// Slots of the tab grid; `None` is the level exit, which is the last slot of every tab
+  fn slots(self) -> Vec<Option<Equipment>> {
+    Equipment::all_equipment()
+      .filter(|item| ShopTab::of(*item) == self)
+      .map(Some)
+      .chain(std::iter::once(None))
+      .collect()
+  }
+
+  /// Position of the slot in the tab grid
+  fn slot_index(self, slot: Option<Equipment>) -> usize {
+    self.slots().iter().position(|s| *s == slot).unwrap_or(0)
+  }
+
+  fn next(self) -> ShopTab {
+    ShopTab::ALL[(self as usize + 1) % ShopTab::ALL.len()]
+  }
+
+  fn prev(self) -> ShopTab {
+    ShopTab::ALL[(self as usize + ShopTab::ALL.len() - 1) % ShopTab::ALL.len()]
+  }
+}
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ShopResult {
@@ -29,9 +102,14 @@ pub struct Prices {
 
 struct PlayerState<'a> {
   entity: &'a mut PlayerComponent,
+  tab: ShopTab,
   /// `None` means level exit
   selection: Option<Equipment>,
   ready: bool,
+  /// When current item was selected
+  selected_at: Instant,
+  /// Area covered by the tooltip, if it is shown
+  tooltip: Option<Rect>,
 }
 
 struct State<'a> {
@@ -42,7 +120,9 @@ struct State<'a> {
 }
 
 impl Prices {
-  pub fn new(free_market: bool) -> Prices {
+  /// Prices of all equipment, scaled by the given multiplier (in percents) and randomized on top of
+  /// that in the free market mode
+  pub fn new(free_market: bool, multiplier: u32) -> Prices {
     // free market?
     let percentage = if free_market {
       let mut rng = rand::thread_rng();
@@ -50,6 +130,7 @@ impl Prices {
     } else {
       100u32
     };
+    let percentage = percentage * multiplier / 100;
 
     let mut prices = Prices::default();
     for equipment in Equipment::all_equipment() {
@@ -74,39 +155,59 @@ impl std::ops::IndexMut<Equipment> for Prices {
 }
 
 impl Application<'_> {
-  /// Run the shop logic
+  /// Run the shop logic. Prices are scaled by `price_percent` (campaign levels could be more
+  /// expensive).
+  #[allow(clippy::too_many_arguments)]
   pub fn shop(
     &self,
     ctx: &mut ApplicationContext,
     remaining_rounds: u16,
     options: &Options,
-    preview_map: Option<&LevelMap>,
+    price_percent: u32,
+    preview: Preview<'_>,
     shared_cash: &mut Option<u32>,
     left: Option<&mut PlayerComponent>,
     right: &mut PlayerComponent,
   ) -> Result<ShopResult, anyhow::Error> {
     let mut state = State {
-      prices: Prices::new(options.free_market),
+      prices: Prices::new(options.free_market, price_percent),
       remaining_rounds,
       left: left.map(|entity| PlayerState {
         entity,
+        tab: ShopTab::Bombs,
         selection: Some(Equipment::SmallBomb),
         ready: false,
+        selected_at: Instant::now(),
+        tooltip: None,
       }),
       right: PlayerState {
         entity: right,
+        tab: ShopTab::Bombs,
         selection: Some(Equipment::SmallBomb),
         ready: false,
+        selected_at: Instant::now(),
+        tooltip: None,
       },
     };
 
     // Render an initial shop screen
-    let texture_creator = ctx.texture_creator();
-    let palette = &self.shop.palette;
+    let preview = preview.cached(ctx, &self.shop)?;
+    let roles = &self.shop.roles;
     ctx.with_render_context(|canvas| {
       canvas.copy(&self.shop.texture, None, None).map_err(SdlError)?;
       let remaining = state.remaining_rounds.to_string();
-      self.font.render(canvas, 306, 120, palette[1], &remaining)?;
+      self
+        .font
+        .render(canvas, 306, 120, roles[PaletteRole::TextPrimary], &remaining)?;
+      if price_percent != 100 {
+        let multiplier = format!("x{}.{:02}", price_percent / 100, price_percent % 100);
+        let color = if price_percent > 100 {
+          roles[PaletteRole::Danger]
+        } else {
+          roles[PaletteRole::Highlight]
+        };
+        self.font.render(canvas, 300, 132, color, &multiplier)?;
+      }
 
       // Background
       if let Some(left) = &state.left {
@@ -116,29 +217,40 @@ impl Application<'_> {
 
       // All shop items
       if let Some(left) = &state.left {
+        self.render_tabs(canvas, 0, left)?;
         self.render_all_items(canvas, 0, left, &state.prices)?;
       }
       let right = &state.right;
+      self.render_tabs(canvas, 320, right)?;
       self.render_all_items(canvas, 320, right, &state.prices)?;
-
-      // Preview map
-      if let Some(map) = preview_map {
-        let tgt = Rect::new(288, 51, 64, 45);
-        let preview = generate_preview(map, texture_creator, &self.shop.palette)?;
-        canvas.copy(&preview, None, tgt).map_err(SdlError)?;
-      }
       Ok(())
     })?;
-    ctx.animate(Animation::FadeUp, 7)?;
+    // Preview map
+    if let Some(preview) = preview {
+      ctx.render_cached(preview, Rect::new(288, 51, 64, 45))?;
+    }
+    ctx.transition(Animation::FadeUp)?;
 
     let mut result = ShopResult::Continue;
     while state.left.as_ref().map_or(false, |state| !state.ready) || !state.right.ready {
-      let scan = ctx.wait_key_pressed().0;
-      match scan {
-        Scancode::Escape => break,
-        Scancode::F10 => {
-          result = ShopResult::ExitGame;
-          break;
+      let key = ctx.wait_key_pressed_idle(None, |ctx, _frame| {
+        if let Some(left) = &mut state.left {
+          self.show_tooltip(ctx, 0, options.armor_model, left)?;
+        }
+        self.show_tooltip(ctx, 320, options.armor_model, &mut state.right)
+      })?;
+      let scan = match key {
+        Some((scan, _)) => scan,
+        None => continue,
+      };
+      match ctx.menu_action(scan) {
+        MenuAction::Back => break,
+        MenuAction::Other(Scancode::F10) => {
+          if self.confirm(ctx, "Quit the game?")? {
+            result = ShopResult::ExitGame;
+            break;
+          }
+          continue;
         }
         _ => {}
       }
@@ -157,7 +269,7 @@ impl Application<'_> {
       )?;
     }
 
-    ctx.animate(Animation::FadeDown, 7)?;
+    ctx.transition(Animation::FadeDown)?;
     Ok(result)
   }
 
@@ -179,7 +291,11 @@ impl Application<'_> {
     }
 
     let cash = shared_cash.as_mut().unwrap_or(&mut state.entity.cash);
-    let offset = state.selection.map_or(Equipment::TOTAL as u8, |item| item as u8);
+    let last_tab = state.tab;
+    let slots = state.tab.slots();
+    let offset = state.tab.slot_index(state.selection);
+    // Moving past the end of the grid selects the level exit, as in the original game
+    let last = slots.len() - 1;
     if Some(scan) == state.entity.keys[Key::Bomb] {
       if let Some(selection) = state.selection {
         if *cash >= prices[selection] {
@@ -199,21 +315,46 @@ impl Application<'_> {
         }
       }
     } else if Some(scan) == state.entity.keys[Key::Right] {
-      state.selection = Equipment::try_from(offset + 1).ok();
+      state.selection = slots[(offset + 1).min(last)];
     } else if Some(scan) == state.entity.keys[Key::Left] {
-      state.selection = Equipment::try_from(offset.max(1) - 1).ok();
+      state.selection = slots[offset.max(1) - 1];
     } else if Some(scan) == state.entity.keys[Key::Down] {
-      state.selection = Equipment::try_from(offset + 4).ok();
+      state.selection = slots[(offset + GRID_COLUMNS as usize).min(last)];
     } else if Some(scan) == state.entity.keys[Key::Up] {
-      state.selection = Equipment::try_from(offset.max(4) - 4).ok();
+      state.selection = slots[offset.max(GRID_COLUMNS as usize) - GRID_COLUMNS as usize];
+    } else if Some(scan) == state.entity.keys[Key::Remote] {
+      state.tab = state.tab.next();
+      state.selection = state.tab.slots()[0];
+    } else if Some(scan) == state.entity.keys[Key::Stop] {
+      state.tab = state.tab.prev();
+      state.selection = state.tab.slots()[0];
     } else {
       // Nothing to re-render, skip re-rendering
       return Ok(());
     }
 
+    if last_selection != state.selection {
+      state.selected_at = Instant::now();
+    }
     ctx.with_render_context(|canvas| {
       let offsets = if left { (0, 0) } else { (420, 320) };
       self.render_player_stats(canvas, offsets.0, *shared_cash, state)?;
+      if last_tab != state.tab {
+        // Whole grid is replaced with the grid of the new tab (together with the tooltip)
+        let (x, y, width, height) = GRID_RECT;
+        let rect = Rect::new(x + offsets.1, y, width, height);
+        canvas.copy(&self.shop.texture, rect, rect).map_err(SdlError)?;
+        state.tooltip = None;
+        self.render_tabs(canvas, offsets.1, state)?;
+        self.render_all_items(canvas, offsets.1, state, prices)?;
+        return Ok(());
+      }
+      if let Some(rect) = state.tooltip.take() {
+        // Restore everything under the tooltip. If selection didn't change, tooltip will be shown again
+        // right away, with updated stats.
+        canvas.copy(&self.shop.texture, rect, rect).map_err(SdlError)?;
+        self.render_all_items(canvas, offsets.1, state, prices)?;
+      }
 
       if last_selection != state.selection {
         self.render_shop_slot(canvas, offsets.1, last_selection, state, prices)?;
@@ -225,6 +366,54 @@ impl Application<'_> {
     Ok(())
   }
 
+  /// Show tooltip for the selected item if it stays selected long enough
+  fn show_tooltip(
+    &self,
+    ctx: &mut ApplicationContext,
+    offset_x: i32,
+    armor_model: ArmorModel,
+    state: &mut PlayerState,
+  ) -> Result<(), anyhow::Error> {
+    let item = match state.selection {
+      Some(item) if !state.ready && state.tooltip.is_none() && state.selected_at.elapsed() >= TOOLTIP_DELAY => item,
+      _ => return Ok(()),
+    };
+
+    let roles = &self.shop.roles;
+    let mut tooltip = Tooltip::new(TOOLTIP_WIDTH)
+      .line(roles[PaletteRole::Highlight], item.name())
+      .line(roles[PaletteRole::TextSecondary], item.category().name())
+      .wrapped(roles[PaletteRole::TextPrimary], item.description());
+    if let Some((label, before, after)) = stat_preview(state.entity, item, armor_model) {
+      tooltip = tooltip.line(
+        roles[PaletteRole::Highlight],
+        format!("{}: {} -> {}", label, before, after),
+      );
+    }
+
+    // Show tooltip under the selected item, unless it is too close to the bottom of the screen
+    let row = (state.tab.slot_index(Some(item)) as i32) / GRID_COLUMNS;
+    let height = tooltip.height() as i32;
+    let below = row * 48 + 144;
+    let pos_y = if below + height <= 480 {
+      below
+    } else {
+      row * 48 + 96 - height
+    };
+    let rect = ctx.with_render_context(|canvas| {
+      tooltip.render(
+        canvas,
+        &self.font,
+        32 + offset_x,
+        pos_y,
+        roles[PaletteRole::TextPrimary],
+      )
+    })?;
+    ctx.present()?;
+    state.tooltip = Some(rect);
+    Ok(())
+  }
+
   fn render_player_stats(
     &self,
     canvas: &mut WindowCanvas,
@@ -234,7 +423,7 @@ impl Application<'_> {
   ) -> Result<(), anyhow::Error> {
     canvas.set_draw_color(Color::BLACK);
 
-    let palette = &self.shop.palette;
+    let roles = &self.shop.roles;
     canvas
       .fill_rect(Rect::new(35 + offset_x, 30, 7 * 8, 8))
       .map_err(SdlError)?;
@@ -243,39 +432,72 @@ impl Application<'_> {
       .map_err(SdlError)?;
 
     let power = 1 + state.entity.initial_drilling_power();
-    self
-      .font
-      .render(canvas, 35 + offset_x, 16, palette[1], &state.entity.stats.name)?;
-    self
-      .font
-      .render(canvas, 35 + offset_x, 30, palette[3], &power.to_string())?;
+    self.font.render(
+      canvas,
+      35 + offset_x,
+      16,
+      roles[PaletteRole::TextPrimary],
+      &state.entity.stats.name,
+    )?;
+    self.font.render(
+      canvas,
+      35 + offset_x,
+      30,
+      roles[PaletteRole::TextSecondary],
+      &power.to_string(),
+    )?;
     if let Some(cash) = shared_cash {
       let cash = cash.to_string();
 
       // Update cash for the both players
       canvas.fill_rect(Rect::new(35, 44, 7 * 8, 8)).map_err(SdlError)?;
       canvas.fill_rect(Rect::new(455, 44, 7 * 8, 8)).map_err(SdlError)?;
-      self.font.render(canvas, 35, 44, palette[5], &cash)?;
-      self.font.render(canvas, 455, 44, palette[5], &cash)?;
+      self.font.render(canvas, 35, 44, roles[PaletteRole::Cash], &cash)?;
+      self.font.render(canvas, 455, 44, roles[PaletteRole::Cash], &cash)?;
     } else {
       canvas
         .fill_rect(Rect::new(35 + offset_x, 44, 7 * 8, 8))
         .map_err(SdlError)?;
-      self
-        .font
-        .render(canvas, 35 + offset_x, 44, palette[5], &state.entity.cash.to_string())?;
+      self.font.render(
+        canvas,
+        35 + offset_x,
+        44,
+        roles[PaletteRole::Cash],
+        &state.entity.cash.to_string(),
+      )?;
     }
 
     if let Some(item) = state.selection {
       let item_count = state.entity.inventory[item];
-      self
-        .font
-        .render(canvas, 35 + offset_x, 58, palette[1], &item_count.to_string())?;
+      self.font.render(
+        canvas,
+        35 + offset_x,
+        58,
+        roles[PaletteRole::TextPrimary],
+        &item_count.to_string(),
+      )?;
+    }
+    Ok(())
+  }
+
+  /// Render names of the tabs, highlighting the current one
+  fn render_tabs(&self, canvas: &mut WindowCanvas, offset_x: i32, state: &PlayerState) -> Result<(), anyhow::Error> {
+    let roles = &self.shop.roles;
+    let rect = Rect::new(GRID_RECT.0 + offset_x, TABS_Y, GRID_RECT.2, 8);
+    canvas.copy(&self.shop.texture, rect, rect).map_err(SdlError)?;
+    for (idx, tab) in ShopTab::ALL.iter().enumerate() {
+      let color = if *tab == state.tab {
+        roles[PaletteRole::Highlight]
+      } else {
+        roles[PaletteRole::TextSecondary]
+      };
+      let pos_x = (idx as i32) * 64 + GRID_RECT.0 + 4 + offset_x;
+      self.font.render(canvas, pos_x, TABS_Y, color, tab.name())?;
     }
     Ok(())
   }
 
-  /// `None` for `selected` means that level exit is selected
+  /// Render all slots of the current tab
   fn render_all_items(
     &self,
     canvas: &mut WindowCanvas,
@@ -283,14 +505,13 @@ impl Application<'_> {
     state: &PlayerState,
     prices: &Prices,
   ) -> Result<(), anyhow::Error> {
-    for slot in Equipment::all_equipment() {
-      self.render_shop_slot(canvas, offset_x, Some(slot), state, prices)?;
+    for slot in state.tab.slots() {
+      self.render_shop_slot(canvas, offset_x, slot, state, prices)?;
     }
-    self.render_shop_slot(canvas, offset_x, None, state, prices)?;
     Ok(())
   }
 
-  /// `selected` is if slot is currently selected
+  /// `None` for `slot` is the level exit
   fn render_shop_slot(
     &self,
     canvas: &mut WindowCanvas,
@@ -300,10 +521,11 @@ impl Application<'_> {
     prices: &Prices,
   ) -> Result<(), anyhow::Error> {
     let palette = &self.shop.palette;
+    let roles = &self.shop.roles;
 
-    let item_index = slot.map(|item| item as usize).unwrap_or(Equipment::TOTAL) as i32;
-    let col = item_index % 4;
-    let row = item_index / 4;
+    let item_index = state.tab.slot_index(slot) as i32;
+    let col = item_index % GRID_COLUMNS;
+    let row = item_index / GRID_COLUMNS;
 
     let pos_x = col * 64 + 32 + offset_x;
     let pos_y = row * 48 + 96;
@@ -330,7 +552,8 @@ impl Application<'_> {
     let pos_x = col * 64 + 49 + offset_x;
     let pos_y = row * 48 + 99;
     let glyph = slot.map(Glyph::Selection).unwrap_or(Glyph::Ready);
-    self.glyphs.render(canvas, pos_x, pos_y, glyph)?;
+    let target = Rect::new(pos_x, pos_y, ITEM_GLYPH_SIZE, ITEM_GLYPH_SIZE);
+    self.glyphs.render_scaled(canvas, target, glyph)?;
 
     // Render item price
     let pos_x = col * 64 + 44 + offset_x;
@@ -339,11 +562,33 @@ impl Application<'_> {
     let text = slot
       .map(|slot| Cow::Owned(format!("{}$", prices[slot])))
       .unwrap_or_else(|| Cow::Borrowed("LEAVE"));
-    self.font.render(canvas, pos_x, pos_y, palette[5], &text)?;
+    self
+      .font
+      .render(canvas, pos_x, pos_y, roles[PaletteRole::Cash], &text)?;
     Ok(())
   }
 }
 
+/// Player stat changed by buying the item: name of the stat, its current value and the value after
+/// the purchase.
+fn stat_preview(
+  player: &PlayerComponent,
+  item: Equipment,
+  armor_model: ArmorModel,
+) -> Option<(&'static str, u16, u16)> {
+  let (label, stat): (_, fn(&PlayerComponent, ArmorModel) -> u16) = match item {
+    Equipment::SmallPickaxe | Equipment::LargePickaxe | Equipment::Drill => {
+      ("Drilling", |player, _| 1 + player.initial_drilling_power())
+    }
+    Equipment::Armor if armor_model == ArmorModel::Health => ("Health", PlayerComponent::initial_health),
+    Equipment::Armor => ("Armor", PlayerComponent::initial_armor),
+    _ => return None,
+  };
+  let mut upgraded = player.clone();
+  upgraded.inventory[item] += 1;
+  Some((label, stat(player, armor_model), stat(&upgraded, armor_model)))
+}
+
 fn adjust_price(price: u32, percentage: u32) -> u32 {
   ((price - 1) * percentage + 50) / 100 + 1
 }
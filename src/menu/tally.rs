@@ -0,0 +1,113 @@
+//! Cash tally shown between the end of the round and the shop: gold collected by each player,
+//! interest, gold inherited from the dead players and the bonus for the broke ones. Amounts are
+//! counted up, so players can follow where their money came from.
+use crate::context::{Animation, ApplicationContext};
+use crate::palette::PaletteRole;
+use crate::world::player::PlayerComponent;
+use crate::world::settlement::RoundSettlement;
+use crate::Application;
+use sdl2::pixels::Color;
+use std::time::Duration;
+
+/// Amounts are counted up over that many frames
+const TALLY_FRAMES: u32 = 25;
+const FRAME_DURATION: Duration = Duration::from_millis(40);
+
+const NAME_LEFT: i32 = 40;
+const HEADER_TOP: i32 = 150;
+const ROW_TOP: i32 = 180;
+const ROW_HEIGHT: i32 = 20;
+const COLLECTED_LEFT: i32 = 168;
+const INTEREST_LEFT: i32 = 256;
+const INHERITED_LEFT: i32 = 336;
+const BONUS_LEFT: i32 = 432;
+const CASH_LEFT: i32 = 512;
+
+impl Application<'_> {
+  /// Show how cash of each player has changed at the end of the round. Any key skips the counting;
+  /// screen is closed by the next key press.
+  pub(super) fn cash_tally(
+    &self,
+    ctx: &mut ApplicationContext,
+    players: &[PlayerComponent],
+    settlement: &RoundSettlement,
+  ) -> Result<(), anyhow::Error> {
+    self.render_cash_tally(ctx, players, settlement, 0)?;
+    ctx.transition(Animation::FadeUp)?;
+    let mut frame = 0;
+    while frame < TALLY_FRAMES {
+      frame = if ctx.wait_key_pressed_timeout(FRAME_DURATION).is_some() {
+        TALLY_FRAMES
+      } else {
+        frame + 1
+      };
+      self.render_cash_tally(ctx, players, settlement, frame)?;
+      ctx.present()?;
+    }
+    ctx.wait_key_pressed();
+    ctx.transition(Animation::FadeDown)?;
+    Ok(())
+  }
+
+  fn render_cash_tally(
+    &self,
+    ctx: &mut ApplicationContext,
+    players: &[PlayerComponent],
+    settlement: &RoundSettlement,
+    frame: u32,
+  ) -> Result<(), anyhow::Error> {
+    let roles = &self.main_menu.roles;
+    let counted = |amount: u32| u64::from(amount) * u64::from(frame) / u64::from(TALLY_FRAMES);
+    ctx.with_render_context(|canvas| {
+      canvas.set_draw_color(Color::BLACK);
+      canvas.clear();
+      let header = roles[PaletteRole::TextSecondary];
+      self.font.render(canvas, NAME_LEFT, HEADER_TOP, header, "Player")?;
+      self
+        .font
+        .render(canvas, COLLECTED_LEFT, HEADER_TOP, header, "Collected")?;
+      self
+        .font
+        .render(canvas, INTEREST_LEFT, HEADER_TOP, header, "Interest")?;
+      self
+        .font
+        .render(canvas, INHERITED_LEFT, HEADER_TOP, header, "Inherited")?;
+      self.font.render(canvas, BONUS_LEFT, HEADER_TOP, header, "Bonus")?;
+      self.font.render(canvas, CASH_LEFT, HEADER_TOP, header, "Cash")?;
+
+      for (idx, (player, outcome)) in players.iter().zip(&settlement.players).enumerate() {
+        let top = ROW_TOP + ROW_HEIGHT * idx as i32;
+        let color = roles[PaletteRole::TextPrimary];
+        let name = roles[PaletteRole::player(idx)];
+        self.font.render(canvas, NAME_LEFT, top, name, &player.stats.name)?;
+        let (collected, collected_color) = if outcome.lost {
+          (format!("-{}", counted(outcome.collected)), roles[PaletteRole::Danger])
+        } else {
+          (format!("+{}", counted(outcome.collected)), color)
+        };
+        self
+          .font
+          .render(canvas, COLLECTED_LEFT, top, collected_color, &collected)?;
+        let interest = format!("+{}", counted(outcome.interest));
+        self.font.render(canvas, INTEREST_LEFT, top, color, &interest)?;
+        let inherited = format!("+{}", counted(outcome.inherited));
+        self.font.render(canvas, INHERITED_LEFT, top, color, &inherited)?;
+        let bonus = format!("+{}", counted(outcome.bonus));
+        self.font.render(canvas, BONUS_LEFT, top, color, &bonus)?;
+        let cash = u64::from(outcome.before) + counted(outcome.after() - outcome.before);
+        self
+          .font
+          .render(canvas, CASH_LEFT, top, roles[PaletteRole::Cash], &cash.to_string())?;
+      }
+
+      if settlement.players.iter().any(|outcome| outcome.lost) {
+        let top = ROW_TOP + ROW_HEIGHT * settlement.players.len() as i32 + ROW_HEIGHT;
+        let text = "Gold collected by the dead players is shared by the survivors";
+        self
+          .font
+          .render(canvas, NAME_LEFT, top, roles[PaletteRole::Muted], text)?;
+      }
+      Ok(())
+    })
+  }
+}
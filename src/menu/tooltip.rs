@@ -0,0 +1,88 @@
+//! Tooltip: small framed panel with a few lines of text, rendered over other content.
+use crate::error::ApplicationError::SdlError;
+use crate::fonts::Font;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use std::borrow::Cow;
+
+/// Distance between lines of text
+const LINE_HEIGHT: u32 = 10;
+
+/// Space between the frame and the text
+const PADDING: u32 = 4;
+
+pub struct Tooltip<'a> {
+  /// Width of the panel, in pixels
+  width: u32,
+  lines: Vec<(Color, Cow<'a, str>)>,
+}
+
+impl<'a> Tooltip<'a> {
+  pub fn new(width: u32) -> Self {
+    Tooltip {
+      width,
+      lines: Vec::new(),
+    }
+  }
+
+  pub fn line(mut self, color: Color, text: impl Into<Cow<'a, str>>) -> Self {
+    self.lines.push((color, text.into()));
+    self
+  }
+
+  /// Add text, splitting it into multiple lines to fit the panel
+  pub fn wrapped(mut self, color: Color, text: &'a str) -> Self {
+    let width = ((self.width - 2 * PADDING) / 8) as usize;
+    for line in wrap_text(text, width) {
+      self.lines.push((color, Cow::Borrowed(line)));
+    }
+    self
+  }
+
+  pub fn height(&self) -> u32 {
+    LINE_HEIGHT * self.lines.len() as u32 + 2 * PADDING
+  }
+
+  /// Render tooltip with its top left corner at the given position. Returns the area covered.
+  pub fn render(
+    &self,
+    canvas: &mut WindowCanvas,
+    font: &Font<'_>,
+    x: i32,
+    y: i32,
+    frame: Color,
+  ) -> Result<Rect, anyhow::Error> {
+    let rect = Rect::new(x, y, self.width, self.height());
+    canvas.set_draw_color(Color::BLACK);
+    canvas.fill_rect(rect).map_err(SdlError)?;
+    canvas.set_draw_color(frame);
+    canvas.draw_rect(rect).map_err(SdlError)?;
+
+    let mut y = y + PADDING as i32;
+    for (color, text) in &self.lines {
+      font.render(canvas, x + PADDING as i32, y, *color, text)?;
+      y += LINE_HEIGHT as i32;
+    }
+    Ok(rect)
+  }
+}
+
+/// Split text into lines no longer than `width` characters, breaking at spaces
+fn wrap_text(text: &str, width: usize) -> Vec<&str> {
+  let mut lines = Vec::new();
+  let mut rest = text.trim();
+  while rest.chars().count() > width {
+    // Byte offsets of the characters at `width` and right after it (text is not always ASCII)
+    let mut offsets = rest.char_indices().skip(width).map(|(idx, _)| idx);
+    let end = offsets.next().unwrap_or(rest.len());
+    let limit = offsets.next().unwrap_or(rest.len());
+    let split = rest[..limit].rfind(' ').unwrap_or(end);
+    lines.push(rest[..split].trim_end());
+    rest = rest[split..].trim_start();
+  }
+  if !rest.is_empty() {
+    lines.push(rest);
+  }
+  lines
+}
@@ -0,0 +1,97 @@
+//! Asset packs ("mods"): directories or ZIP archives under `mods` in the game directory with
+//! replacement game files (images, fonts, sound samples and levels). Load order is stored in
+//! `mods.cfg`, one pack per line, prefixed with `+` if the pack is enabled and with `-` if it is
+//! not. Packs later in the load order override the earlier ones.
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Directory (relative to the game directory) with asset packs
+pub const MODS_DIR: &str = "mods";
+
+const MODS_FILE: &str = "mods.cfg";
+
+#[derive(Debug, Error)]
+#[error("Failed to save asset packs list to '{path}'")]
+pub struct ModsSaveError {
+  #[source]
+  source: std::io::Error,
+  path: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetPack {
+  /// Name of the pack directory or archive
+  pub name: String,
+  pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModList {
+  /// All known asset packs, in the load order
+  pub packs: Vec<AssetPack>,
+}
+
+impl ModList {
+  /// Load asset packs list. Packs found in the mods directory, but missing from the list are added
+  /// to the end of the load order (disabled); packs which are gone from the directory are dropped.
+  pub fn load(game_dir: &Path) -> Self {
+    let installed = installed_packs(game_dir);
+    let mut packs: Vec<AssetPack> = std::fs::read_to_string(game_dir.join(MODS_FILE))
+      .unwrap_or_default()
+      .lines()
+      .filter_map(|line| {
+        let line = line.trim();
+        let (enabled, name) = match line.chars().next()? {
+          '+' => (true, &line[1..]),
+          '-' => (false, &line[1..]),
+          _ => return None,
+        };
+        Some(AssetPack {
+          name: name.to_owned(),
+          enabled,
+        })
+      })
+      .filter(|pack| installed.contains(&pack.name))
+      .collect();
+    for name in installed {
+      if !packs.iter().any(|pack| pack.name == name) {
+        packs.push(AssetPack { name, enabled: false });
+      }
+    }
+    ModList { packs }
+  }
+
+  pub fn save(&self, game_dir: &Path) -> Result<(), ModsSaveError> {
+    let mut out = String::new();
+    for pack in &self.packs {
+      out.push(if pack.enabled { '+' } else { '-' });
+      out.push_str(&pack.name);
+      out.push('\n');
+    }
+    let path = game_dir.join(MODS_FILE);
+    std::fs::write(&path, out).map_err(|source| ModsSaveError { source, path })
+  }
+
+  /// Paths to the enabled packs, in the load order
+  pub fn enabled_paths<'a>(&'a self, game_dir: &'a Path) -> impl DoubleEndedIterator<Item = PathBuf> + 'a {
+    self
+      .packs
+      .iter()
+      .filter(|pack| pack.enabled)
+      .map(move |pack| game_dir.join(MODS_DIR).join(&pack.name))
+  }
+}
+
+/// Names of all pack directories and archives in the mods directory, sorted by name
+fn installed_packs(game_dir: &Path) -> Vec<String> {
+  let mut names: Vec<String> = match std::fs::read_dir(game_dir.join(MODS_DIR)) {
+    Ok(entries) => entries
+      .flatten()
+      .filter(|entry| entry.path().is_dir() || crate::vfs::is_archive(&entry.path()))
+      .map(|entry| entry.file_name().to_string_lossy().into_owned())
+      .collect(),
+    Err(_) => Vec::new(),
+  };
+  names.sort();
+  names
+}
@@ -0,0 +1,402 @@
+//! Network play: everyone who joined the lobby controls their own player. Every peer simulates the
+//! match on its own (lockstep): world random numbers come from the match seed picked by the host,
+//! and inputs of all players are applied at the same ticks on every peer. Each peer sends inputs of
+//! its player for every tick it simulates to the host, which relays them to the other peers; inputs
+//! are scheduled into the lockstep input schedule, so simulation waits for late players.
+//!
+//! Every round (and every restart of the round) starts a new epoch. Inputs are tagged with it, so
+//! inputs of the peer who got to the next round first are kept until the round starts here.
+//!
+//! Peers also exchange checksums of their world state every second, so a peer which went out of
+//! sync (because of a bug or a tampered client) is noticed.
+use crate::keys::{ControlStyle, Key, KeyBindings};
+use crate::lockstep::InputSchedule;
+use crate::world::rng::WorldRng;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+
+/// Peers only learn inputs of each other after the round trip through the host, so network games
+/// always have some input delay to hide it
+pub const MIN_INPUT_DELAY: u8 = 4;
+
+/// How often (in ticks) peers exchange checksums of their world state
+pub const CHECKSUM_INTERVAL: usize = 50;
+
+/// How many of the recent local checksums are kept to compare with the ones of the other peers.
+/// Peers can't get far ahead of each other, as simulation waits for the inputs of everyone.
+const KEPT_CHECKSUMS: usize = 4;
+
+/// Inputs of the player for a single tick
+#[derive(Clone, Debug, Default)]
+pub struct TickInput {
+  /// Round (or restart of the round) inputs are for
+  pub epoch: u32,
+  /// Player inputs are for; host fills it in for the inputs it relays
+  pub player: usize,
+  /// Tick keys were pressed at; inputs are sent for every tick, one after another
+  pub tick: usize,
+  /// Keys pressed at the tick
+  pub pressed: Vec<Key>,
+  /// Keys held down, as a bitmask indexed by `Key`
+  pub held: u8,
+}
+
+impl TickInput {
+  pub fn write_to(&self, w: &mut impl Write) -> Result<(), std::io::Error> {
+    w.write_u32::<LittleEndian>(self.epoch)?;
+    w.write_u8(self.player as u8)?;
+    w.write_u32::<LittleEndian>(self.tick as u32)?;
+    w.write_u8(self.pressed.len() as u8)?;
+    for key in &self.pressed {
+      w.write_u8(*key as u8)?;
+    }
+    w.write_u8(self.held)
+  }
+
+  pub fn read_from(r: &mut impl Read) -> Result<TickInput, anyhow::Error> {
+    let epoch = r.read_u32::<LittleEndian>()?;
+    let player = usize::from(r.read_u8()?);
+    let tick = r.read_u32::<LittleEndian>()? as usize;
+    let count = r.read_u8()?;
+    let mut pressed = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+      pressed.push(Key::try_from(r.read_u8()?)?);
+    }
+    let held = r.read_u8()?;
+    Ok(TickInput {
+      epoch,
+      player,
+      tick,
+      pressed,
+      held,
+    })
+  }
+}
+
+/// Checksum of the world state of the player's peer after the given tick
+#[derive(Clone, Debug)]
+pub struct StateChecksum {
+  pub epoch: u32,
+  /// Player whose peer computed the checksum; host fills it in for the checksums it relays
+  pub player: usize,
+  pub tick: usize,
+  pub checksum: u64,
+}
+
+impl StateChecksum {
+  pub fn write_to(&self, w: &mut impl Write) -> Result<(), std::io::Error> {
+    w.write_u32::<LittleEndian>(self.epoch)?;
+    w.write_u8(self.player as u8)?;
+    w.write_u32::<LittleEndian>(self.tick as u32)?;
+    w.write_u64::<LittleEndian>(self.checksum)
+  }
+
+  pub fn read_from(r: &mut impl Read) -> Result<StateChecksum, std::io::Error> {
+    Ok(StateChecksum {
+      epoch: r.read_u32::<LittleEndian>()?,
+      player: usize::from(r.read_u8()?),
+      tick: r.read_u32::<LittleEndian>()? as usize,
+      checksum: r.read_u64::<LittleEndian>()?,
+    })
+  }
+}
+
+/// Message peers exchange during the match
+#[derive(Clone, Debug)]
+pub enum PeerMessage {
+  Input(TickInput),
+  Checksum(StateChecksum),
+  /// Player left the match
+  Left(usize),
+}
+
+/// Players of the network match, as seen by this peer
+pub struct RemotePlayers {
+  /// Messages of the other peers; channel is closed once connection to the host is lost
+  inputs: Receiver<PeerMessage>,
+  /// Where inputs of the local player are sent to
+  outgoing: RefCell<Vec<Sender<PeerMessage>>>,
+  /// Connections to the other peers
+  connections: Vec<TcpStream>,
+  /// Names and control styles of all players, local and remote
+  players: Vec<(String, ControlStyle)>,
+  /// Player controlled from this machine (dedicated host doesn't play)
+  local: Option<usize>,
+  /// Seed of the match; every epoch derives its own seed from it
+  seed: u64,
+  state: RefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+  epoch: u32,
+  /// Inputs of the later epochs, received ahead of time
+  pending: Vec<TickInput>,
+  /// Epoch and tick of the last input received from each player
+  received: Vec<Option<(u32, usize)>>,
+  /// Players who left the match, with the epoch and tick of their last input
+  left: Vec<Option<Option<(u32, usize)>>>,
+  /// Keys pressed by the local player at the current tick
+  pressed: Vec<Key>,
+  /// Inputs of remote players which were dropped as invalid
+  violations: usize,
+  /// Checksums of the local world state at the recent ticks of the epoch
+  checksums: Vec<(usize, u64)>,
+  /// Checksums of the other peers which are not compared yet
+  remote_checksums: Vec<StateChecksum>,
+  /// Player whose world state didn't match the local one, and the tick it happened at
+  desync: Option<(usize, usize)>,
+  /// Connection to the host is lost
+  lost: bool,
+}
+
+impl RemotePlayers {
+  pub fn new(
+    players: Vec<(String, ControlStyle)>,
+    local: Option<usize>,
+    seed: u64,
+    inputs: Receiver<PeerMessage>,
+    outgoing: Vec<Sender<PeerMessage>>,
+    connections: Vec<TcpStream>,
+  ) -> Self {
+    let state = State {
+      received: vec![None; players.len()],
+      left: vec![None; players.len()],
+      ..Default::default()
+    };
+    RemotePlayers {
+      inputs,
+      outgoing: RefCell::new(outgoing),
+      connections,
+      players,
+      local,
+      seed,
+      state: RefCell::new(state),
+    }
+  }
+
+  pub fn names(&self) -> impl Iterator<Item = &str> {
+    self.players.iter().map(|(name, _)| name.as_str())
+  }
+
+  /// Player controlled from this machine
+  pub fn local(&self) -> Option<usize> {
+    self.local
+  }
+
+  pub fn is_remote(&self, player: usize) -> bool {
+    Some(player) != self.local
+  }
+
+  /// Key bindings of the remote player: no keys are bound, but control style is the one player has
+  /// chosen on their machine
+  pub fn remote_keys(&self, player: usize) -> KeyBindings {
+    let mut keys = KeyBindings::default();
+    keys.style = self.players[player].1;
+    keys
+  }
+
+  /// Amount of inputs of remote players dropped as invalid
+  pub fn violations(&self) -> usize {
+    self.state.borrow().violations
+  }
+
+  /// Check if connection to the host is lost (match cannot continue)
+  pub fn is_lost(&self) -> bool {
+    self.state.borrow().lost
+  }
+
+  /// Check if every remote player has left the match
+  pub fn is_abandoned(&self) -> bool {
+    let state = self.state.borrow();
+    (0..self.players.len()).all(|player| !self.is_remote(player) || state.left[player].is_some())
+  }
+
+  /// Start the new epoch (round or its restart). Returns random generator of the epoch world.
+  pub fn next_epoch(&self) -> WorldRng {
+    let mut state = self.state.borrow_mut();
+    state.epoch += 1;
+    state.pressed.clear();
+    state.checksums.clear();
+    let epoch = state.epoch;
+    state.remote_checksums.retain(|checksum| checksum.epoch >= epoch);
+    WorldRng::seeded(self.seed.wrapping_add(u64::from(state.epoch)))
+  }
+
+  /// Remember the key local player pressed at the current tick
+  pub fn key_pressed(&self, key: Key) {
+    self.state.borrow_mut().pressed.push(key);
+  }
+
+  /// Send inputs of the local player for the given tick to other peers; `held` is the keys each
+  /// player held down at the tick. Sent once simulation moves past the tick.
+  pub fn send(&self, tick: usize, held: &[u8]) {
+    let player = match self.local {
+      Some(player) => player,
+      None => return,
+    };
+    let mut state = self.state.borrow_mut();
+    let message = PeerMessage::Input(TickInput {
+      epoch: state.epoch,
+      player,
+      tick,
+      pressed: std::mem::take(&mut state.pressed),
+      held: held[player],
+    });
+    for peer in self.outgoing.borrow().iter() {
+      // Peers who are gone are noticed by the reading side
+      let _ = peer.send(message.clone());
+    }
+  }
+
+  /// Remember checksum of the local world state after the given tick and send it to the other peers
+  pub fn record_checksum(&self, tick: usize, checksum: u64) {
+    let mut state = self.state.borrow_mut();
+    if state.checksums.len() == KEPT_CHECKSUMS {
+      state.checksums.remove(0);
+    }
+    state.checksums.push((tick, checksum));
+    if let Some(player) = self.local {
+      let message = PeerMessage::Checksum(StateChecksum {
+        epoch: state.epoch,
+        player,
+        tick,
+        checksum,
+      });
+      for peer in self.outgoing.borrow().iter() {
+        let _ = peer.send(message.clone());
+      }
+    }
+    state.compare_checksums();
+  }
+
+  /// Player whose world went out of sync with the local one, and the tick it happened at. Reported
+  /// once.
+  pub fn take_desync(&self) -> Option<(usize, usize)> {
+    self.state.borrow_mut().desync.take()
+  }
+
+  /// Leave the match: connections are closed once everything sent is written, so other peers know
+  /// right away they shouldn't wait for this one
+  pub fn leave(&self) {
+    self.outgoing.borrow_mut().clear();
+    for connection in &self.connections {
+      let _ = connection.shutdown(Shutdown::Read);
+    }
+  }
+
+  /// Schedule inputs received from the remote players. Players who left never hold the simulation
+  /// again; they are treated as if they released all the keys right after their last input.
+  pub fn receive(&self, schedule: &mut InputSchedule) {
+    let mut state = self.state.borrow_mut();
+    let epoch = state.epoch;
+    let (due, pending): (Vec<TickInput>, Vec<TickInput>) = std::mem::take(&mut state.pending)
+      .into_iter()
+      .partition(|input| input.epoch == epoch);
+    state.pending = pending;
+    for input in due {
+      state.violations += schedule_input(schedule, input);
+    }
+    loop {
+      match self.inputs.try_recv() {
+        Ok(PeerMessage::Input(input)) if !state.accept(&input, self) => state.violations += 1,
+        Ok(PeerMessage::Input(input)) if input.epoch > epoch => state.pending.push(input),
+        // Inputs of the epoch which is over
+        Ok(PeerMessage::Input(input)) if input.epoch < epoch => {}
+        Ok(PeerMessage::Input(input)) => state.violations += schedule_input(schedule, input),
+        Ok(PeerMessage::Checksum(checksum))
+          if self.is_remote(checksum.player) && checksum.player < self.players.len() =>
+        {
+          if checksum.epoch >= epoch {
+            state.remote_checksums.push(checksum);
+          }
+        }
+        Ok(PeerMessage::Checksum(_)) => state.violations += 1,
+        Ok(PeerMessage::Left(player)) if self.is_remote(player) && player < self.players.len() => {
+          state.left[player] = Some(state.received[player]);
+        }
+        Ok(PeerMessage::Left(_)) => state.violations += 1,
+        Err(TryRecvError::Empty) => break,
+        Err(TryRecvError::Disconnected) => {
+          // Nothing else is coming; that's fine only if everyone else is gone, too
+          let remote = (0..self.players.len()).filter(|player| self.is_remote(*player));
+          state.lost |= remote.clone().any(|player| state.left[player].is_none());
+          break;
+        }
+      }
+    }
+
+    for player in 0..self.players.len() {
+      let released = match state.left[player] {
+        None => continue,
+        Some(Some((left, tick))) if left == epoch => tick + 1,
+        // Player has played this epoch through, they left in the later one
+        Some(Some((left, _))) if left > epoch => continue,
+        Some(_) => 0,
+      };
+      schedule.hold(released, player, 0);
+      schedule.leave(player);
+    }
+    state.compare_checksums();
+  }
+}
+
+impl State {
+  /// Check if input could come from the remote player and remember it as the last one received.
+  /// Inputs are sent for every tick, one epoch after another, so anything out of order is rejected.
+  fn accept(&mut self, input: &TickInput, players: &RemotePlayers) -> bool {
+    let player = input.player;
+    if !players.is_remote(player) || player >= players.players.len() || self.left[player].is_some() {
+      return false;
+    }
+    let valid = match self.received[player] {
+      Some((epoch, tick)) if epoch == input.epoch => input.tick == tick + 1,
+      Some((epoch, _)) if epoch > input.epoch => false,
+      _ => input.tick == 0,
+    };
+    if valid {
+      self.received[player] = Some((input.epoch, input.tick));
+    }
+    valid
+  }
+
+  /// Compare checksums of the other peers with the local ones for the same ticks. Checksums for the
+  /// ticks which are not simulated here yet are kept, the ones which are too old are dropped.
+  fn compare_checksums(&mut self) {
+    let epoch = self.epoch;
+    let checksums = &self.checksums;
+    let latest = checksums.last().map(|(tick, _)| *tick);
+    let mut desync = None;
+    self.remote_checksums.retain(|remote| {
+      if remote.epoch > epoch || latest.is_none_or(|latest| remote.tick > latest) {
+        return true;
+      }
+      let local = checksums.iter().find(|(tick, _)| *tick == remote.tick);
+      if local.is_some_and(|(_, checksum)| *checksum != remote.checksum) {
+        desync = desync.or(Some((remote.player, remote.tick)));
+      }
+      false
+    });
+    self.desync = self.desync.or(desync);
+  }
+}
+
+/// Schedule input of the current epoch. Inputs which were accepted can only be rejected by the
+/// schedule if peer sent commands its own schedule wouldn't take (too many of them or the same key
+/// twice), which also makes them violations; such inputs are still confirmed, so simulation doesn't
+/// wait for them forever. Returns amount of the rejected commands.
+fn schedule_input(schedule: &mut InputSchedule, input: TickInput) -> usize {
+  let mut rejected = 0;
+  for key in input.pressed {
+    if schedule.push(input.tick, input.player, key).is_err() {
+      rejected += 1;
+    }
+  }
+  schedule.hold(input.tick, input.player, input.held);
+  schedule.confirm(input.player, input.tick);
+  rejected
+}
@@ -1,8 +1,126 @@
+use crate::world::equipment::Equipment;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use std::convert::TryFrom;
 use std::io::Read;
 use std::path::Path;
 use std::time::Duration;
 
+/// Speed of the fade transitions between screens
+#[derive(Debug, PartialEq, Eq, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum TransitionSpeed {
+  /// Same speed as in the original game
+  Classic,
+  Fast,
+  /// No fades at all
+  Instant,
+}
+
+/// How armor bought in the shop protects players
+#[derive(Debug, PartialEq, Eq, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum ArmorModel {
+  /// Each armor adds 100 to the maximum health (original behavior)
+  Health,
+  /// Armor absorbs part of the damage and wears out while doing so
+  Reduction,
+}
+
+/// What network game does when remote player inputs are late
+#[derive(Debug, PartialEq, Eq, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum StallPolicy {
+  /// Wait for the inputs as long as it takes
+  Wait,
+  /// Wait for a while, then continue as if late player didn't press anything
+  Skip,
+}
+
+/// How levels are picked for the rounds if none were selected in the levels menu
+#[derive(Debug, PartialEq, Eq, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum LevelRotation {
+  /// Every round is played on a random map
+  Off,
+  /// Go through all levels found in the game directory, in alphabetical order
+  Alphabetical,
+  /// Go through all levels found in the game directory, shuffled once at the start of the game
+  Shuffled,
+}
+
+/// File in the game directory with items of the custom starting loadout. File consists of
+/// `<item>=<amount>` lines, where item is the name of the item as shown in the shop (for example,
+/// `Small bomb=20`). Unknown items and invalid amounts are ignored.
+const LOADOUT_FILE: &str = "loadout.cfg";
+
+/// Equipment each player starts the multiplayer game with, instead of visiting the shop before the
+/// first round
+#[derive(Debug, PartialEq, Eq, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum StartingLoadout {
+  /// No loadout, players visit the shop before the first round (original behavior)
+  Shop,
+  Basic,
+  Demolition,
+  Tunneler,
+  /// Items listed in the loadout file of the game directory
+  Custom,
+}
+
+impl StartingLoadout {
+  /// Items (and their amounts) of the preset loadout, `None` if there is no preset
+  fn preset(self) -> Option<&'static [(Equipment, u16)]> {
+    let items: &[(Equipment, u16)] = match self {
+      StartingLoadout::Shop | StartingLoadout::Custom => return None,
+      StartingLoadout::Basic => &[
+        (Equipment::SmallBomb, 20),
+        (Equipment::BigBomb, 10),
+        (Equipment::Dynamite, 5),
+        (Equipment::SmallRadio, 3),
+        (Equipment::Mine, 2),
+        (Equipment::Teleport, 1),
+      ],
+      StartingLoadout::Demolition => &[
+        (Equipment::BigBomb, 10),
+        (Equipment::Dynamite, 15),
+        (Equipment::LargeRadio, 3),
+        (Equipment::Napalm, 2),
+        (Equipment::Barrel, 1),
+      ],
+      StartingLoadout::Tunneler => &[
+        (Equipment::SmallPickaxe, 1),
+        (Equipment::Digger, 1),
+        (Equipment::Plastic, 5),
+        (Equipment::SmallBomb, 10),
+        (Equipment::Dynamite, 5),
+      ],
+    };
+    Some(items)
+  }
+}
+
+/// Maximum value for the clones cap option
+pub const MAX_CLONE_CAP: u8 = 10;
+
+/// Range of lives single player game could be started with
+pub const MIN_LIVES: u8 = 1;
+pub const MAX_LIVES: u8 = 5;
+
+/// Maximum amount of continues in single player game
+pub const MAX_CONTINUES: u8 = 5;
+
+/// Maximum input delay, in ticks
+pub const MAX_INPUT_DELAY: u8 = 10;
+
+/// Idle player timeout is configured in steps of that many seconds, up to the maximum
+pub const IDLE_TIMEOUT_STEP: u8 = 15;
+pub const MAX_IDLE_TIMEOUT: u8 = 180;
+
+/// Live monsters cap is configured in steps of that many monsters, up to the maximum
+pub const MONSTER_CAP_STEP: u8 = 10;
+pub const MAX_MONSTER_CAP: u8 = 200;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum WinCondition {
   ByWins,
@@ -26,6 +144,93 @@ pub struct Options {
   pub win: WinCondition,
   pub bomb_damage: u8,
   pub campaign_mode: bool,
+  /// Maximum amount of clones each player can have at the same time; 0 means no limit.
+  pub clone_cap: u8,
+  /// Clones carry collected gold until they touch their owner (instead of crediting it instantly)
+  pub clone_carry_gold: bool,
+  /// Monsters drop loot when killed
+  pub monster_loot: bool,
+  /// Neutral trader occasionally wanders the map, offering equipment at a discount
+  pub traders: bool,
+  /// Skip transitions between levels in single player
+  pub fast_forward: bool,
+  /// Save options as soon as they are changed (instead of when leaving the options menu)
+  pub autosave: bool,
+  /// Speed of the screen fades between menus and rounds
+  pub transitions: TransitionSpeed,
+  /// Go straight to the main menu, without showing the title screen
+  pub skip_intro: bool,
+  /// Explosion damage decreases with the distance from the center
+  pub realistic_damage: bool,
+  /// How armor bought in the shop protects players
+  pub armor_model: ArmorModel,
+  /// Players slowly regenerate health when not hurt for a while (multiplayer only)
+  pub regeneration: bool,
+  /// Widescreen (16:9) mode: game screen is centered, side panels show extended HUD
+  pub widescreen: bool,
+  /// Show ticker with kills and notable finds during multiplayer rounds
+  pub kill_feed: bool,
+  /// Show terrain silhouette in the shop preview in darkness games
+  pub satellite_preview: bool,
+  /// Process player commands every other tick, like the original game does
+  pub classic_input: bool,
+  /// Two boulders in a row can be pushed together, boulders crush monsters
+  pub push_chains: bool,
+  /// Boulders fall down into passages opened beneath them
+  pub rockfall: bool,
+  /// Delay (in ticks) before player inputs are applied in network games, gives peers time to
+  /// exchange them. Local games are never delayed.
+  pub input_delay: u8,
+  /// What to do when remote player inputs are late
+  pub stall_policy: StallPolicy,
+  /// Sounds coming from unrevealed cells in darkness have imprecise stereo position and pitch
+  pub muffled_fog_sounds: bool,
+  /// Rotate through all levels of the game directory instead of playing random maps
+  pub level_rotation: LevelRotation,
+  /// Lives player starts the single player game with
+  pub lives: u8,
+  /// How many times single player game could be continued after losing all lives
+  pub continues: u8,
+  /// Save heatmap of explosions, digs and deaths of each multiplayer round as a PNG image
+  pub heatmaps: bool,
+  /// Skip the first shop, players start with the loadout bought for them with the starting cash
+  pub loadout: StartingLoadout,
+  /// Items of the custom loadout, read from the loadout file of the game directory
+  pub custom_loadout: Vec<(Equipment, u16)>,
+  /// Pause multiplayer round if any of the alive players gives no input for that many seconds
+  /// (0 to never pause)
+  pub idle_timeout: u8,
+  /// Render actors half way between simulation steps for smoother movement
+  pub smooth_movement: bool,
+  /// Record every round into an animated PNG in the `captures` directory
+  pub capture_rounds: bool,
+  /// Maximum amount of live monsters on the map, nests stop spawning once it is reached (0 means no
+  /// limit)
+  pub monster_cap: u8,
+  /// Limit flashes, fades and screen shake for players sensitive to them
+  pub photosensitive: bool,
+  /// Placed biomass grows from a heart, destroying it kills the whole patch
+  pub biomass_hearts: bool,
+  /// Radio bombs are pinged on the map for a moment before the remote detonation
+  pub radio_ping: bool,
+}
+
+/// Options as they were last written to the disk
+#[derive(Default)]
+pub struct SavedOptions {
+  original: Vec<u8>,
+  extended: Vec<u8>,
+}
+
+impl SavedOptions {
+  /// Options currently written in the game directory. Missing files (like on the first run) are
+  /// written on the next save, even if options were not changed.
+  pub fn load(game_dir: &Path) -> Self {
+    SavedOptions {
+      original: std::fs::read(game_dir.join("OPTIONS.CFG")).unwrap_or_default(),
+      extended: std::fs::read(game_dir.join("optsrel.cfg")).unwrap_or_default(),
+    }
+  }
 }
 
 impl Default for Options {
@@ -43,6 +248,39 @@ impl Default for Options {
       win: WinCondition::ByMoney,
       bomb_damage: 100,
       campaign_mode: false,
+      clone_cap: 0,
+      clone_carry_gold: false,
+      monster_loot: false,
+      traders: false,
+      fast_forward: false,
+      autosave: false,
+      transitions: TransitionSpeed::Classic,
+      skip_intro: false,
+      realistic_damage: false,
+      armor_model: ArmorModel::Health,
+      regeneration: false,
+      widescreen: false,
+      kill_feed: false,
+      satellite_preview: false,
+      classic_input: false,
+      push_chains: false,
+      rockfall: false,
+      input_delay: 0,
+      stall_policy: StallPolicy::Wait,
+      muffled_fog_sounds: false,
+      level_rotation: LevelRotation::Off,
+      lives: 3,
+      continues: 0,
+      heatmaps: false,
+      loadout: StartingLoadout::Shop,
+      custom_loadout: Vec::new(),
+      idle_timeout: 0,
+      smooth_movement: false,
+      capture_rounds: false,
+      monster_cap: 0,
+      photosensitive: false,
+      biomass_hearts: false,
+      radio_ping: false,
     }
   }
 }
@@ -71,7 +309,7 @@ impl Options {
         WinCondition::ByMoney
       },
       bomb_damage: it.read_u8().unwrap(),
-      campaign_mode: false,
+      ..Default::default()
     };
     if opts.players > 4 {
       opts.players = 2;
@@ -95,24 +333,231 @@ impl Options {
   }
 
   /// Load options from a configuration file. This function uses the same format as the original game.
+  /// Options that original game doesn't have are loaded from a separate file.
   pub fn load(game_dir: &Path) -> Self {
     let path = game_dir.join("OPTIONS.CFG");
     let mut buf: [u8; 17] = [0; 17];
-    std::fs::File::open(path)
+    let mut options: Options = std::fs::File::open(path)
       .and_then(|mut file| file.read_exact(&mut buf))
       .map(|()| Options::from_binary(&buf))
-      .unwrap_or_default()
+      .unwrap_or_default();
+    if let Ok(data) = std::fs::read(game_dir.join("optsrel.cfg")) {
+      options.load_extended(&data);
+    }
+    if let Ok(data) = std::fs::read(game_dir.join(LOADOUT_FILE)) {
+      options.custom_loadout = parse_loadout(&String::from_utf8_lossy(&data));
+    }
+    options
+  }
+
+  /// Items (and their amounts) of the starting loadout, `None` if players visit the shop instead.
+  /// Players visit the shop if custom loadout is selected, but there are no items in it.
+  pub fn loadout_items(&self) -> Option<&[(Equipment, u16)]> {
+    match self.loadout {
+      StartingLoadout::Custom => Some(self.custom_loadout.as_slice()).filter(|items| !items.is_empty()),
+      loadout => loadout.preset(),
+    }
+  }
+
+  /// Load options which are not supported by the original game. Each option is stored sequentially,
+  /// so missing values (file written by an older version) keep their defaults.
+  fn load_extended(&mut self, mut it: &[u8]) {
+    if let Ok(clone_cap) = it.read_u8() {
+      self.clone_cap = clone_cap.min(MAX_CLONE_CAP);
+    }
+    if let Ok(clone_carry_gold) = it.read_u8() {
+      self.clone_carry_gold = clone_carry_gold != 0;
+    }
+    if let Ok(monster_loot) = it.read_u8() {
+      self.monster_loot = monster_loot != 0;
+    }
+    if let Ok(traders) = it.read_u8() {
+      self.traders = traders != 0;
+    }
+    if let Ok(fast_forward) = it.read_u8() {
+      self.fast_forward = fast_forward != 0;
+    }
+    if let Ok(autosave) = it.read_u8() {
+      self.autosave = autosave != 0;
+    }
+    if let Ok(transitions) = it.read_u8() {
+      self.transitions = TransitionSpeed::try_from(transitions).unwrap_or(TransitionSpeed::Classic);
+    }
+    if let Ok(skip_intro) = it.read_u8() {
+      self.skip_intro = skip_intro != 0;
+    }
+    if let Ok(realistic_damage) = it.read_u8() {
+      self.realistic_damage = realistic_damage != 0;
+    }
+    if let Ok(armor_model) = it.read_u8() {
+      self.armor_model = ArmorModel::try_from(armor_model).unwrap_or(ArmorModel::Health);
+    }
+    if let Ok(regeneration) = it.read_u8() {
+      self.regeneration = regeneration != 0;
+    }
+    if let Ok(widescreen) = it.read_u8() {
+      self.widescreen = widescreen != 0;
+    }
+    if let Ok(kill_feed) = it.read_u8() {
+      self.kill_feed = kill_feed != 0;
+    }
+    if let Ok(satellite_preview) = it.read_u8() {
+      self.satellite_preview = satellite_preview != 0;
+    }
+    if let Ok(classic_input) = it.read_u8() {
+      self.classic_input = classic_input != 0;
+    }
+    if let Ok(push_chains) = it.read_u8() {
+      self.push_chains = push_chains != 0;
+    }
+    if let Ok(rockfall) = it.read_u8() {
+      self.rockfall = rockfall != 0;
+    }
+    if let Ok(input_delay) = it.read_u8() {
+      self.input_delay = input_delay.min(MAX_INPUT_DELAY);
+    }
+    if let Ok(stall_policy) = it.read_u8() {
+      self.stall_policy = StallPolicy::try_from(stall_policy).unwrap_or(StallPolicy::Wait);
+    }
+    if let Ok(muffled_fog_sounds) = it.read_u8() {
+      self.muffled_fog_sounds = muffled_fog_sounds != 0;
+    }
+    if let Ok(level_rotation) = it.read_u8() {
+      self.level_rotation = LevelRotation::try_from(level_rotation).unwrap_or(LevelRotation::Off);
+    }
+    if let Ok(lives) = it.read_u8() {
+      self.lives = lives.clamp(MIN_LIVES, MAX_LIVES);
+    }
+    if let Ok(continues) = it.read_u8() {
+      self.continues = continues.min(MAX_CONTINUES);
+    }
+    if let Ok(heatmaps) = it.read_u8() {
+      self.heatmaps = heatmaps != 0;
+    }
+    if let Ok(loadout) = it.read_u8() {
+      self.loadout = StartingLoadout::try_from(loadout).unwrap_or(StartingLoadout::Shop);
+    }
+    if let Ok(idle_timeout) = it.read_u8() {
+      self.idle_timeout = idle_timeout.min(MAX_IDLE_TIMEOUT) / IDLE_TIMEOUT_STEP * IDLE_TIMEOUT_STEP;
+    }
+    if let Ok(smooth_movement) = it.read_u8() {
+      self.smooth_movement = smooth_movement != 0;
+    }
+    if let Ok(capture_rounds) = it.read_u8() {
+      self.capture_rounds = capture_rounds != 0;
+    }
+    if let Ok(monster_cap) = it.read_u8() {
+      self.monster_cap = monster_cap.min(MAX_MONSTER_CAP) / MONSTER_CAP_STEP * MONSTER_CAP_STEP;
+    }
+    if let Ok(photosensitive) = it.read_u8() {
+      self.photosensitive = photosensitive != 0;
+    }
+    if let Ok(biomass_hearts) = it.read_u8() {
+      self.biomass_hearts = biomass_hearts != 0;
+    }
+    if let Ok(radio_ping) = it.read_u8() {
+      self.radio_ping = radio_ping != 0;
+    }
+    // Custom loadout is stored, so network peers get the one of the host
+    if let Ok(count) = it.read_u8() {
+      let mut items = Vec::with_capacity(usize::from(count));
+      for _ in 0..count {
+        match (it.read_u8(), it.read_u16::<LittleEndian>()) {
+          (Ok(item), Ok(amount)) => items.extend(Equipment::try_from(item).ok().map(|item| (item, amount))),
+          _ => break,
+        }
+      }
+      self.custom_loadout = items;
+    }
+  }
+
+  /// Encode all options (both original and extended ones), for sending them over the network
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut buf = self.save_inner();
+    buf.extend(self.save_extended());
+    buf
+  }
+
+  /// Decode options encoded with `to_bytes`
+  pub fn from_bytes(buf: &[u8]) -> Self {
+    if buf.len() < 17 {
+      return Default::default();
+    }
+    let mut options = Options::from_binary(&buf[..17]);
+    options.load_extended(&buf[17..]);
+    options
   }
 
   /// Save options into a binary slice
   pub fn save(&self, game_dir: &Path) -> Result<(), anyhow::Error> {
-    let data = self.save_inner();
-    let path = game_dir.join("OPTIONS.CFG");
-    // FIXME: either proper errors or logging
-    std::fs::write(path, data)?;
+    self.save_changed(game_dir, &mut SavedOptions::default())?;
     Ok(())
   }
 
+  /// Save options, but only write files which content differs from the previously saved one.
+  /// Returns `true` if anything was written.
+  pub fn save_changed(&self, game_dir: &Path, saved: &mut SavedOptions) -> Result<bool, anyhow::Error> {
+    let mut written = false;
+    let data = self.save_inner();
+    if data != saved.original {
+      // FIXME: either proper errors or logging
+      std::fs::write(game_dir.join("OPTIONS.CFG"), &data)?;
+      saved.original = data;
+      written = true;
+    }
+    let data = self.save_extended();
+    if data != saved.extended {
+      std::fs::write(game_dir.join("optsrel.cfg"), &data)?;
+      saved.extended = data;
+      written = true;
+    }
+    Ok(written)
+  }
+
+  /// Save options which are not supported by the original game
+  fn save_extended(&self) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.write_u8(self.clone_cap).unwrap();
+    buf.write_u8(self.clone_carry_gold as u8).unwrap();
+    buf.write_u8(self.monster_loot as u8).unwrap();
+    buf.write_u8(self.traders as u8).unwrap();
+    buf.write_u8(self.fast_forward as u8).unwrap();
+    buf.write_u8(self.autosave as u8).unwrap();
+    buf.write_u8(self.transitions.into()).unwrap();
+    buf.write_u8(self.skip_intro as u8).unwrap();
+    buf.write_u8(self.realistic_damage as u8).unwrap();
+    buf.write_u8(self.armor_model.into()).unwrap();
+    buf.write_u8(self.regeneration as u8).unwrap();
+    buf.write_u8(self.widescreen as u8).unwrap();
+    buf.write_u8(self.kill_feed as u8).unwrap();
+    buf.write_u8(self.satellite_preview as u8).unwrap();
+    buf.write_u8(self.classic_input as u8).unwrap();
+    buf.write_u8(self.push_chains as u8).unwrap();
+    buf.write_u8(self.rockfall as u8).unwrap();
+    buf.write_u8(self.input_delay).unwrap();
+    buf.write_u8(self.stall_policy.into()).unwrap();
+    buf.write_u8(self.muffled_fog_sounds as u8).unwrap();
+    buf.write_u8(self.level_rotation.into()).unwrap();
+    buf.write_u8(self.lives).unwrap();
+    buf.write_u8(self.continues).unwrap();
+    buf.write_u8(self.heatmaps as u8).unwrap();
+    buf.write_u8(self.loadout.into()).unwrap();
+    buf.write_u8(self.idle_timeout).unwrap();
+    buf.write_u8(self.smooth_movement as u8).unwrap();
+    buf.write_u8(self.capture_rounds as u8).unwrap();
+    buf.write_u8(self.monster_cap).unwrap();
+    buf.write_u8(self.photosensitive as u8).unwrap();
+    buf.write_u8(self.biomass_hearts as u8).unwrap();
+    buf.write_u8(self.radio_ping as u8).unwrap();
+    let items = &self.custom_loadout[..self.custom_loadout.len().min(usize::from(u8::MAX))];
+    buf.write_u8(items.len() as u8).unwrap();
+    for &(item, amount) in items {
+      buf.write_u8(item as u8).unwrap();
+      buf.write_u16::<LittleEndian>(amount).unwrap();
+    }
+    buf
+  }
+
   /// Save options into a binary slice
   fn save_inner(&self) -> Vec<u8> {
     let mut buf = Vec::with_capacity(17);
@@ -148,3 +593,21 @@ fn to_duration(value: u32) -> Duration {
 fn from_duration(value: Duration) -> u32 {
   (value.as_secs() * 182 / 10) as u32
 }
+
+/// Parse items of the custom loadout file
+fn parse_loadout(text: &str) -> Vec<(Equipment, u16)> {
+  let mut items = Vec::new();
+  for line in text.lines() {
+    let (name, amount) = match line.split_once('=') {
+      Some((name, amount)) => (name.trim(), amount.trim()),
+      None => continue,
+    };
+    let item = Equipment::all_equipment().find(|item| item.name().eq_ignore_ascii_case(name));
+    if let (Some(item), Ok(amount)) = (item, amount.parse::<u16>()) {
+      // Item listed again replaces the previous amount
+      items.retain(|(other, _)| *other != item);
+      items.push((item, amount));
+    }
+  }
+  items
+}
@@ -0,0 +1,138 @@
+//! Named roles of the palette colors. Render code asks for a role (`TextPrimary`, `Cash`) instead of
+//! indexing the palette with magic numbers. Roles are resolved against the palette of each texture
+//! when it is loaded, so any of them could be overridden (for themes or better contrast) in one place:
+//! `COLORS.CFG` file in the game data (or asset pack) with `<role>=<RRGGBB>` lines, where role is
+//! the name of the role (like `TextPrimary`, case is ignored).
+use sdl2::pixels::Color;
+
+/// File with the palette role overrides
+pub const OVERRIDES_FILE: &str = "COLORS.CFG";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteRole {
+  /// Regular text (white in the original palettes)
+  TextPrimary,
+  /// Secondary details next to the regular text (red in the original palettes)
+  TextSecondary,
+  /// Hints and inactive items
+  Muted,
+  /// Warnings, errors and negative values
+  Danger,
+  /// Selected or otherwise emphasized items
+  Highlight,
+  /// Amounts of money
+  Cash,
+  /// Good results (best places on the history graph)
+  Success,
+  /// Poor results, not as bad as the `Danger` ones
+  Warning,
+  /// Round conditions (like darkness) and the time left
+  Notice,
+  /// Background under the text and bars
+  Background,
+  /// Light edge of the embossed text
+  Bevel,
+  /// Open areas of the satellite map preview
+  Passage,
+  Player1,
+  Player2,
+  Player3,
+  Player4,
+}
+
+impl PaletteRole {
+  pub const ALL: [PaletteRole; 16] = [
+    PaletteRole::TextPrimary,
+    PaletteRole::TextSecondary,
+    PaletteRole::Muted,
+    PaletteRole::Danger,
+    PaletteRole::Highlight,
+    PaletteRole::Cash,
+    PaletteRole::Success,
+    PaletteRole::Warning,
+    PaletteRole::Notice,
+    PaletteRole::Background,
+    PaletteRole::Bevel,
+    PaletteRole::Passage,
+    PaletteRole::Player1,
+    PaletteRole::Player2,
+    PaletteRole::Player3,
+    PaletteRole::Player4,
+  ];
+
+  /// Role of the color of the given player
+  pub fn player(player: usize) -> PaletteRole {
+    [
+      PaletteRole::Player1,
+      PaletteRole::Player2,
+      PaletteRole::Player3,
+      PaletteRole::Player4,
+    ][player % 4]
+  }
+
+  /// Index of the palette color used for the role by the original game
+  fn default_index(self) -> usize {
+    match self {
+      PaletteRole::Background => 0,
+      PaletteRole::TextPrimary => 1,
+      PaletteRole::Success => 4,
+      PaletteRole::Notice => 6,
+      PaletteRole::Warning => 7,
+      PaletteRole::Bevel => 10,
+      PaletteRole::Passage => 14,
+      PaletteRole::Muted => 8,
+      PaletteRole::TextSecondary | PaletteRole::Danger => 3,
+      PaletteRole::Highlight | PaletteRole::Cash => 5,
+      PaletteRole::Player1 => 2,
+      PaletteRole::Player2 => 3,
+      PaletteRole::Player3 => 4,
+      PaletteRole::Player4 => 6,
+    }
+  }
+}
+
+/// Colors of all roles, resolved for a particular texture palette
+#[derive(Clone, Copy)]
+pub struct PaletteRoles {
+  colors: [Color; PaletteRole::ALL.len()],
+}
+
+impl PaletteRoles {
+  /// Resolve roles to the colors of the palette, using the same indices as the original game
+  pub fn resolve(palette: &[Color; 16]) -> Self {
+    PaletteRoles {
+      colors: PaletteRole::ALL.map(|role| palette[role.default_index()]),
+    }
+  }
+
+  /// Override color of the role
+  pub fn set(&mut self, role: PaletteRole, color: Color) {
+    self.colors[role as usize] = color;
+  }
+
+  /// Apply overrides given as `<role>=<RRGGBB>` lines. Unknown roles and invalid colors are ignored.
+  pub fn apply_overrides(&mut self, text: &str) {
+    for line in text.lines() {
+      let (name, color) = match line.split_once('=') {
+        Some((name, color)) => (name.trim(), u32::from_str_radix(color.trim(), 16)),
+        None => continue,
+      };
+      let role = PaletteRole::ALL
+        .iter()
+        .copied()
+        .find(|role| format!("{:?}", role).eq_ignore_ascii_case(name));
+      if let (Some(role), Ok(color @ 0..=0xff_ffff)) = (role, color) {
+        let [_, r, g, b] = color.to_be_bytes();
+        self.set(role, Color::RGB(r, g, b));
+      }
+    }
+  }
+}
+
+impl std::ops::Index<PaletteRole> for PaletteRoles {
+  type Output = Color;
+
+  fn index(&self, role: PaletteRole) -> &Color {
+    &self.colors[role as usize]
+  }
+}
@@ -0,0 +1,63 @@
+//! Name the game is registered to, shown in the main menu. Stored in `register.dat` as a length
+//! byte followed by the name characters.
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const REGISTER_FILE: &str = "register.dat";
+
+/// Maximum length of the registered name
+pub const MAX_REGISTERED_LEN: usize = 25;
+
+#[derive(Debug, Error)]
+pub enum InvalidName {
+  #[error("Name cannot be empty")]
+  Empty,
+  #[error("Name cannot be longer than {} characters", MAX_REGISTERED_LEN)]
+  TooLong,
+  #[error("Name can only contain printable ASCII characters")]
+  InvalidCharacters,
+}
+
+#[derive(Debug, Error)]
+#[error("Failed to save registration to '{path}'")]
+pub struct RegisterSaveError {
+  #[source]
+  source: std::io::Error,
+  path: PathBuf,
+}
+
+pub fn load_registered(path: &Path) -> Option<String> {
+  let register = std::fs::read(path.join(REGISTER_FILE)).ok()?;
+  if register.is_empty() {
+    return None;
+  }
+  let len = register[0] as usize;
+  if len < 26 && len < register.len() {
+    Some(String::from_utf8_lossy(&register[1..1 + len]).into_owned())
+  } else {
+    None
+  }
+}
+
+/// Check that name could be stored in the registration file and rendered with the game font
+pub fn validate_name(name: &str) -> Result<(), InvalidName> {
+  if name.is_empty() {
+    Err(InvalidName::Empty)
+  } else if name.len() > MAX_REGISTERED_LEN {
+    Err(InvalidName::TooLong)
+  } else if !name.chars().all(|ch| ch.is_ascii_graphic() || ch == ' ') {
+    Err(InvalidName::InvalidCharacters)
+  } else {
+    Ok(())
+  }
+}
+
+/// Write registration file. Name must be validated first.
+pub fn save_registered(path: &Path, name: &str) -> Result<(), RegisterSaveError> {
+  // Original file is a fixed-size record: length byte followed by the space for the longest name
+  let mut data = vec![0; MAX_REGISTERED_LEN + 1];
+  data[0] = name.len() as u8;
+  data[1..=name.len()].copy_from_slice(name.as_bytes());
+  let path = path.join(REGISTER_FILE);
+  std::fs::write(&path, data).map_err(|source| RegisterSaveError { source, path })
+}
@@ -3,6 +3,9 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+/// File with player statistics which are not supported by the original game
+const EXTENDED_FILE: &str = "playrel.dat";
+
 #[derive(Debug, Error)]
 #[error("Failed to load player statistics from '{path}'")]
 pub struct PlayersLoadError {
@@ -33,6 +36,8 @@ pub struct RosterInfo {
   pub deaths: u32,
   pub meters_ran: u32,
   pub history: Vec<u8>,
+  /// How many times player finished a tournament at each place (1st to 4th)
+  pub ranks: [u32; 4],
 }
 
 impl Default for RosterInfo {
@@ -50,6 +55,7 @@ impl Default for RosterInfo {
       deaths: 0,
       meters_ran: 0,
       history: vec![0; 34],
+      ranks: [0; 4],
     }
   }
 }
@@ -76,6 +82,9 @@ impl RosterInfo {
     self.deaths += other.deaths;
     self.meters_ran += other.meters_ran;
     self.history[history_idx] = history_value;
+    for (rank, other) in self.ranks.iter_mut().zip(other.ranks.iter()) {
+      *rank += *other;
+    }
   }
 }
 
@@ -85,13 +94,31 @@ pub struct PlayersRoster {
 }
 
 impl PlayersRoster {
-  /// Load player statistics from `PLAYERS.DAT` file.
+  /// Load player statistics from `PLAYERS.DAT` file. Statistics that original game doesn't track
+  /// are loaded from a separate file.
   pub fn load(game_dir: &Path) -> Result<PlayersRoster, PlayersLoadError> {
     let path = game_dir.join("PLAYERS.DAT");
-    if path.is_file() {
-      PlayersRoster::load_players_internal(&path).map_err(|source| PlayersLoadError { path, source })
+    let mut roster = if path.is_file() {
+      PlayersRoster::load_players_internal(&path).map_err(|source| PlayersLoadError { path, source })?
     } else {
-      Ok(Default::default())
+      Default::default()
+    };
+    if let Ok(data) = std::fs::read(game_dir.join(EXTENDED_FILE)) {
+      roster.load_extended(&data);
+    }
+    Ok(roster)
+  }
+
+  /// Load statistics which are not supported by the original game. Each record is a sequence of
+  /// place counters, so missing data (file written by an older version) is treated as zeroes.
+  fn load_extended(&mut self, mut it: &[u8]) {
+    for record in self.players.iter_mut() {
+      for rank in 0..4 {
+        let value = it.read_u32::<LittleEndian>().unwrap_or(0);
+        if let Some(record) = record {
+          record.ranks[rank] = value;
+        }
+      }
     }
   }
 
@@ -174,6 +201,16 @@ impl PlayersRoster {
 
     let path = game_dir.join("PLAYERS.DAT");
     std::fs::write(&path, &out).map_err(|source| PlayersSaveError { path, source })?;
+
+    let mut out: Vec<u8> = Vec::with_capacity(32 * 16);
+    for player in self.players.iter() {
+      let ranks = player.as_ref().map_or([0; 4], |record| record.ranks);
+      for value in &ranks {
+        out.extend_from_slice(&value.to_le_bytes());
+      }
+    }
+    let path = game_dir.join(EXTENDED_FILE);
+    std::fs::write(&path, &out).map_err(|source| PlayersSaveError { path, source })?;
     Ok(())
   }
 }
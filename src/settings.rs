@@ -1,13 +1,17 @@
 use crate::keys::KeysConfig;
 use crate::options::Options;
 use crate::world::map::LevelInfo;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 pub struct GameSettings {
   pub keys: KeysConfig,
   pub levels: Vec<Rc<LevelInfo>>,
   pub options: Options,
+  /// Game is started from the command line: no menus, shop or final screens are shown
+  pub scripted: bool,
+  /// Tuning file for the practice mode, re-read at the beginning of every round
+  pub tuning: Option<PathBuf>,
 }
 
 impl GameSettings {
@@ -17,6 +21,8 @@ impl GameSettings {
       keys: KeysConfig::load(game_dir),
       levels: Vec::new(),
       options: Options::load(game_dir),
+      scripted: false,
+      tuning: None,
     }
   }
 }
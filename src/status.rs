@@ -0,0 +1,86 @@
+//! Game status reporting for external tools (streaming overlays, presence bridges, etc).
+//! Status is written as a small JSON document into a file given on the command line.
+use crate::matchlog::json_string;
+use std::path::PathBuf;
+
+/// What the game is currently doing
+pub enum GameStatus<'a> {
+  Menu,
+  Shop {
+    round: u16,
+    total_rounds: u16,
+  },
+  Round {
+    round: u16,
+    total_rounds: u16,
+    level: &'a str,
+    players: usize,
+    alive: usize,
+  },
+  Results,
+}
+
+impl GameStatus<'_> {
+  fn to_json(&self) -> String {
+    match *self {
+      GameStatus::Menu => "{\"state\":\"menu\"}".to_owned(),
+      GameStatus::Shop { round, total_rounds } => format!(
+        "{{\"state\":\"shop\",\"round\":{},\"total_rounds\":{}}}",
+        round + 1,
+        total_rounds
+      ),
+      GameStatus::Round {
+        round,
+        total_rounds,
+        level,
+        players,
+        alive,
+      } => format!(
+        "{{\"state\":\"round\",\"round\":{},\"total_rounds\":{},\"level\":{},\"players\":{},\"alive\":{}}}",
+        round + 1,
+        total_rounds,
+        json_string(level),
+        players,
+        alive
+      ),
+      GameStatus::Results => "{\"state\":\"results\"}".to_owned(),
+    }
+  }
+}
+
+/// Writes game status into a file, if enabled. File is only touched when status changes.
+#[derive(Default)]
+pub struct StatusReporter {
+  path: Option<PathBuf>,
+  last: String,
+}
+
+impl StatusReporter {
+  pub fn new(path: Option<PathBuf>) -> Self {
+    StatusReporter {
+      path,
+      last: String::new(),
+    }
+  }
+
+  pub fn report(&mut self, status: GameStatus) {
+    let path = match self.path {
+      Some(ref path) => path,
+      None => return,
+    };
+    let json = status.to_json();
+    if json == self.last {
+      return;
+    }
+
+    // Write into a temporary file first, so readers never see partially written status
+    let tmp = path.with_extension("tmp");
+    let result = std::fs::write(&tmp, &json).and_then(|()| std::fs::rename(&tmp, path));
+    if let Err(err) = result {
+      // Status is optional, so don't fail the game; disable reporting instead
+      eprintln!("Failed to write status to '{}': {}", path.display(), err);
+      self.path = None;
+    }
+    self.last = json;
+  }
+}
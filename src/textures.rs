@@ -0,0 +1,118 @@
+//! Cache of the textures created at runtime (level previews). Cache owns the textures, menus only
+//! keep handles to them, so textures could be shared between screens without tying their lifetimes
+//! to the menu code. Handles of evicted textures are detected and resolve to nothing.
+use sdl2::render::{Texture, TextureCreator};
+use sdl2::video::WindowContext;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+/// Maximum amount of cached textures; oldest textures are evicted first
+const MAX_CACHED: usize = 256;
+
+/// Identity of the cached texture, derived from everything the texture is generated from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureKey(u64);
+
+impl TextureKey {
+  pub fn of(source: impl Hash) -> Self {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    TextureKey(hasher.finish())
+  }
+}
+
+/// Handle to the cached texture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureHandle {
+  slot: usize,
+  generation: u32,
+}
+
+struct Slot<'t> {
+  generation: u32,
+  texture: Option<(TextureKey, Texture<'t>)>,
+}
+
+pub struct TextureCache<'t> {
+  texture_creator: &'t TextureCreator<WindowContext>,
+  slots: Vec<Slot<'t>>,
+  index: HashMap<TextureKey, TextureHandle>,
+  /// Handles in the order textures were created
+  order: VecDeque<TextureHandle>,
+}
+
+impl<'t> TextureCache<'t> {
+  pub fn new(texture_creator: &'t TextureCreator<WindowContext>) -> Self {
+    TextureCache {
+      texture_creator,
+      slots: Vec::new(),
+      index: HashMap::new(),
+      order: VecDeque::new(),
+    }
+  }
+
+  /// Get handle of the texture with the given key, creating the texture if it is not cached yet
+  pub fn get_or_create(
+    &mut self,
+    key: TextureKey,
+    create: impl FnOnce(&'t TextureCreator<WindowContext>) -> Result<Texture<'t>, anyhow::Error>,
+  ) -> Result<TextureHandle, anyhow::Error> {
+    if let Some(handle) = self.index.get(&key) {
+      return Ok(*handle);
+    }
+    let texture = create(self.texture_creator)?;
+    if self.order.len() >= MAX_CACHED {
+      if let Some(oldest) = self.order.pop_front() {
+        self.evict(oldest);
+      }
+    }
+    let slot = match self.slots.iter().position(|slot| slot.texture.is_none()) {
+      Some(slot) => slot,
+      None => {
+        self.slots.push(Slot {
+          generation: 0,
+          texture: None,
+        });
+        self.slots.len() - 1
+      }
+    };
+    let entry = &mut self.slots[slot];
+    entry.texture = Some((key, texture));
+    let handle = TextureHandle {
+      slot,
+      generation: entry.generation,
+    };
+    self.index.insert(key, handle);
+    self.order.push_back(handle);
+    Ok(handle)
+  }
+
+  /// Get cached texture; `None` if texture was evicted
+  pub fn get(&self, handle: TextureHandle) -> Option<&Texture<'t>> {
+    let slot = self.slots.get(handle.slot)?;
+    if slot.generation != handle.generation {
+      return None;
+    }
+    slot.texture.as_ref().map(|(_, texture)| texture)
+  }
+
+  /// Evict all cached textures
+  pub fn clear(&mut self) {
+    while let Some(handle) = self.order.pop_front() {
+      self.evict(handle);
+    }
+  }
+
+  fn evict(&mut self, handle: TextureHandle) {
+    let slot = &mut self.slots[handle.slot];
+    if slot.generation != handle.generation {
+      return;
+    }
+    // Texture is destroyed once it is dropped
+    if let Some((key, _)) = slot.texture.take() {
+      self.index.remove(&key);
+    }
+    slot.generation = slot.generation.wrapping_add(1);
+  }
+}
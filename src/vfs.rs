@@ -0,0 +1,148 @@
+//! Virtual file system: game files are looked up in the enabled asset packs first (see `mods`),
+//! then in the game data itself. Both game data and asset packs could be either directories or ZIP
+//! archives (files are expected at the top level of the archive).
+use crate::archive::{ArchiveReadError, ZipArchive};
+use crate::error::ApplicationError;
+use crate::mods::ModList;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// File read from the virtual file system
+pub struct VfsFile {
+  /// Path to the file (or archive path followed by the file name), for the error reporting
+  pub path: PathBuf,
+  pub data: Vec<u8>,
+}
+
+#[derive(Clone)]
+enum Root {
+  Dir(PathBuf),
+  Archive(Rc<ZipArchive>),
+}
+
+#[derive(Clone)]
+pub struct Vfs {
+  /// Places to look files up in, from the highest priority to the lowest (game data)
+  roots: Vec<Root>,
+}
+
+/// Check if path refers to a ZIP archive rather than a directory
+pub fn is_archive(path: &Path) -> bool {
+  path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Directory where game keeps its settings and asset packs. Same as the game data path, unless
+/// game data is an archive, in which case it is the directory the archive is in.
+pub fn game_dir(data: &Path) -> PathBuf {
+  match data.parent() {
+    Some(parent) if is_archive(data) => parent.to_owned(),
+    _ => data.to_owned(),
+  }
+}
+
+impl Vfs {
+  /// Create file system from the game data (directory or ZIP archive) and enabled asset packs
+  pub fn new(data: &Path, mods: &ModList) -> Result<Self, ArchiveReadError> {
+    let root = open_root(data)?;
+    let vfs = Vfs { roots: vec![root] };
+    Ok(vfs.with_mods(&game_dir(data), mods))
+  }
+
+  /// Same game data, but with a different set of asset packs. Broken packs are reported and
+  /// skipped.
+  pub fn with_mods(&self, game_dir: &Path, mods: &ModList) -> Self {
+    let mut roots = Vec::new();
+    for path in mods.enabled_paths(game_dir).rev() {
+      match open_root(&path) {
+        Ok(root) => roots.push(root),
+        Err(err) => eprintln!("Failed to load asset pack: {:#}", anyhow::Error::from(err)),
+      }
+    }
+    roots.push(self.roots.last().unwrap().clone());
+    Vfs { roots }
+  }
+
+  /// Read file with the given name. File is taken from the highest priority pack that has it;
+  /// names are matched ignoring the case, as original game files are all upper case.
+  pub fn read(&self, name: &str) -> Result<VfsFile, ApplicationError> {
+    for root in &self.roots {
+      let (path, data) = match root {
+        Root::Dir(dir) => match find_file(dir, name) {
+          Some(path) => {
+            let data = std::fs::read(&path).map_err(anyhow::Error::from);
+            (path, data)
+          }
+          None => continue,
+        },
+        Root::Archive(archive) => match archive.read(name) {
+          Some(data) => (archive.path().join(name), data),
+          None => continue,
+        },
+      };
+      return match data {
+        Ok(data) => Ok(VfsFile { path, data }),
+        Err(source) => Err(ApplicationError::AssetRead { path, source }),
+      };
+    }
+    let path = match self.roots.last() {
+      Some(Root::Dir(dir)) => dir.join(name),
+      Some(Root::Archive(archive)) => archive.path().join(name),
+      None => PathBuf::from(name),
+    };
+    Err(ApplicationError::AssetMissing { path })
+  }
+
+  /// Upper case names of all files with one of the given extensions (in upper case)
+  pub fn list(&self, extensions: &[&str]) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    for root in &self.roots {
+      match root {
+        Root::Dir(dir) => {
+          for entry in dir.read_dir().into_iter().flatten().flatten() {
+            if entry.path().is_file() {
+              names.insert(entry.file_name().to_string_lossy().to_ascii_uppercase());
+            }
+          }
+        }
+        Root::Archive(archive) => names.extend(archive.names().map(str::to_owned)),
+      }
+    }
+    names.retain(|name| name.rsplit_once('.').is_some_and(|(_, ext)| extensions.contains(&ext)));
+    names.into_iter().collect()
+  }
+
+  /// Directories files are looked up in (archives are not included)
+  pub fn dirs(&self) -> Vec<PathBuf> {
+    self
+      .roots
+      .iter()
+      .filter_map(|root| match root {
+        Root::Dir(dir) => Some(dir.clone()),
+        Root::Archive(_) => None,
+      })
+      .collect()
+  }
+}
+
+fn open_root(path: &Path) -> Result<Root, ArchiveReadError> {
+  if is_archive(path) {
+    Ok(Root::Archive(Rc::new(ZipArchive::open(path)?)))
+  } else {
+    Ok(Root::Dir(path.to_owned()))
+  }
+}
+
+fn find_file(root: &Path, name: &str) -> Option<PathBuf> {
+  let path = root.join(name);
+  if path.is_file() {
+    return Some(path);
+  }
+  root
+    .read_dir()
+    .ok()?
+    .flatten()
+    .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(name))
+    .map(|entry| entry.path())
+    .filter(|path| path.is_file())
+}
@@ -4,6 +4,9 @@ use crate::world::position::{Cursor, Direction, Position};
 use rand::prelude::*;
 use std::cmp::Ordering;
 
+/// Damage reduction (in percents) of the intact armor
+const ARMOR_REDUCTION: u32 = 60;
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(usize)]
 pub enum Player {
@@ -20,6 +23,8 @@ pub enum ActorKind {
   Grenadier,
   Slime,
   Alien,
+  /// Neutral trader, wanders around and sells equipment
+  Trader,
   Player(Player),
   Clone(Player),
 }
@@ -31,6 +36,7 @@ impl ActorKind {
       ActorKind::Grenadier => 12,
       ActorKind::Slime => 12,
       ActorKind::Alien => 52,
+      ActorKind::Trader => 5,
       ActorKind::Clone(_) => 52,
       _ => unimplemented!(),
     }
@@ -42,6 +48,7 @@ impl ActorKind {
       ActorKind::Grenadier => 29,
       ActorKind::Slime => 10,
       ActorKind::Alien => 66,
+      ActorKind::Trader => 40,
       ActorKind::Clone(_) => 100,
       _ => unimplemented!(),
     }
@@ -54,8 +61,8 @@ impl ActorKind {
       ActorKind::Slime => 1,
       ActorKind::Alien => 5,
       ActorKind::Clone(_) => 1,
-      // Players don't do damage by hands!
-      ActorKind::Player(_) => 0,
+      // Players and traders don't do damage by hands!
+      ActorKind::Player(_) | ActorKind::Trader => 0,
     }
   }
 
@@ -65,6 +72,7 @@ impl ActorKind {
       ActorKind::Grenadier => 3,
       ActorKind::Slime => 2,
       ActorKind::Alien => 100,
+      ActorKind::Trader => 4,
       ActorKind::Clone(_) => 100,
       _ => unimplemented!(),
     }
@@ -77,17 +85,67 @@ impl ActorKind {
     }
   }
 
-  pub fn death_animation_value(self) -> MapValue {
+  pub fn death_sound_effect(self) -> SoundEffect {
     match self {
-      ActorKind::Slime => MapValue::SlimeDying,
-      _ => MapValue::MonsterDying,
+      ActorKind::Slime => SoundEffect::Urethan,
+      _ => SoundEffect::Aargh,
     }
   }
 
-  pub fn death_sound_effect(self) -> SoundEffect {
+  /// Items (together with their weights) monster can drop once its death animation is over.
+  /// `None` means that nothing is dropped.
+  pub fn loot_table(self) -> &'static [(u32, Option<MapValue>)] {
     match self {
-      ActorKind::Slime => SoundEffect::Urethan,
-      _ => SoundEffect::Aargh,
+      ActorKind::Furry => &[
+        (12, None),
+        (5, Some(MapValue::GoldBracelet)),
+        (2, Some(MapValue::GoldPileCoins)),
+        (1, Some(MapValue::WeaponsCrate)),
+      ],
+      ActorKind::Grenadier => &[
+        (10, None),
+        (4, Some(MapValue::GoldShield)),
+        (3, Some(MapValue::WeaponsCrate)),
+      ],
+      ActorKind::Slime => &[
+        (15, None),
+        (4, Some(MapValue::GoldBracelet)),
+        (1, Some(MapValue::GoldEgg)),
+      ],
+      ActorKind::Alien => &[
+        (6, None),
+        (4, Some(MapValue::GoldBar)),
+        (2, Some(MapValue::GoldScepter)),
+        (3, Some(MapValue::WeaponsCrate)),
+        (1, Some(MapValue::Medikit)),
+      ],
+      ActorKind::Trader => &[(3, Some(MapValue::GoldPileCoins)), (1, Some(MapValue::WeaponsCrate))],
+      ActorKind::Player(_) | ActorKind::Clone(_) => &[],
+    }
+  }
+
+  /// Roll a dice to select loot dropped by the killed monster
+  pub fn roll_loot(self, rng: &mut impl Rng) -> Option<MapValue> {
+    self
+      .loot_table()
+      .choose_weighted(rng, |(weight, _)| *weight)
+      .ok()
+      .and_then(|(_, loot)| *loot)
+  }
+
+  /// If actor is a hostile monster
+  pub fn is_monster(self) -> bool {
+    matches!(
+      self,
+      ActorKind::Furry | ActorKind::Grenadier | ActorKind::Slime | ActorKind::Alien
+    )
+  }
+
+  /// Get index of the player who owns this clone. Returns `None` if this actor is not a clone.
+  pub fn clone_owner(self) -> Option<usize> {
+    match self {
+      ActorKind::Clone(player) => Some(player as usize),
+      _ => None,
     }
   }
 }
@@ -103,6 +161,10 @@ pub struct ActorComponent {
   pub max_health: u16,
   /// Current health
   pub health: u16,
+  /// Armor left (only used with damage reduction armor model)
+  pub armor: u16,
+  /// Armor at the start of the round
+  pub max_armor: u16,
   pub pos: Position,
   pub drilling: u16,
   pub animation: u8,
@@ -113,6 +175,12 @@ pub struct ActorComponent {
   pub accumulated_cash: u32,
   /// Countdown of player activated acceleration bonus
   pub super_drill_count: u32,
+  /// Location of the nest which spawned this monster
+  pub spawner: Option<Cursor>,
+  /// Countdown till actor leaves the map (only used by traders)
+  pub leave_countdown: u32,
+  /// Cells actor went through during the round
+  pub path: PathRecorder,
 }
 
 impl Default for ActorComponent {
@@ -123,6 +191,8 @@ impl Default for ActorComponent {
       moving: false,
       max_health: 0,
       health: 0,
+      armor: 0,
+      max_armor: 0,
       pos: Position { x: 0, y: 0 },
       drilling: 0,
       animation: 0,
@@ -130,11 +200,47 @@ impl Default for ActorComponent {
       is_active: false,
       accumulated_cash: 0,
       super_drill_count: 0,
+      spawner: None,
+      leave_countdown: 0,
+      path: PathRecorder::default(),
     }
   }
 }
 
+/// Breadcrumb trail of the actor, for the post-round review. Actor position is recorded every tick,
+/// but only cell changes are stored.
+#[derive(Clone, Default)]
+pub struct PathRecorder {
+  cells: Vec<Cursor>,
+}
+
+impl PathRecorder {
+  pub fn record(&mut self, cursor: Cursor) {
+    if self.cells.last() != Some(&cursor) {
+      self.cells.push(cursor);
+    }
+  }
+
+  /// All recorded cells, from the oldest to the newest
+  pub fn cells(&self) -> &[Cursor] {
+    &self.cells
+  }
+}
+
 impl ActorComponent {
+  /// Absorb part of the damage by the armor, wearing it out. Returns amount of absorbed damage.
+  /// Armor absorbs less damage as it wears out.
+  pub fn absorb_damage(&mut self, dmg: u16) -> u16 {
+    if self.armor == 0 {
+      return 0;
+    }
+    let percent = ARMOR_REDUCTION * u32::from(self.armor) / u32::from(self.max_armor);
+    let absorbed = (u32::from(dmg) * percent / 100) as u16;
+    let absorbed = absorbed.min(self.armor);
+    self.armor -= absorbed;
+    absorbed
+  }
+
   /// Check if we can continue moving in the current direction
   pub fn can_move(&self, level: &LevelMap) -> bool {
     let next = self.pos.cursor().to(self.facing);
@@ -143,9 +249,8 @@ impl ActorComponent {
   }
 
   /// Actively avoid given location
-  pub fn avoid_position(&mut self, bomb: Cursor, level: &LevelMap) {
+  pub fn avoid_position(&mut self, bomb: Cursor, level: &LevelMap, rng: &mut impl Rng) {
     let cursor = self.pos.cursor();
-    let mut rng = rand::thread_rng();
     let (delta_row, delta_col) = cursor.distance(bomb);
 
     if delta_col > delta_row || rng.gen_range(0..100) < 3 {
@@ -179,7 +284,7 @@ impl ActorComponent {
   }
 
   /// Actively avoid given location
-  pub fn head_to_target(&mut self, target: Cursor, level: &LevelMap) {
+  pub fn head_to_target(&mut self, target: Cursor, level: &LevelMap, rng: &mut impl Rng) {
     let cursor = self.pos.cursor();
     let (delta_row, delta_col) = cursor.distance(target);
 
@@ -219,8 +324,6 @@ impl ActorComponent {
 
     // If blocked, choose random direction!
     if !self.can_move(level) {
-      let mut rng = rand::thread_rng();
-
       // Note that this is a bit different than other place we go in random direction
       // Here it is possible to choose "stop" randomly
       let dir = *[
@@ -230,7 +333,7 @@ impl ActorComponent {
         Some(Direction::Up),
         Some(Direction::Down),
       ]
-      .choose(&mut rng)
+      .choose(rng)
       .unwrap();
       if let Some(dir) = dir {
         self.facing = dir;
@@ -0,0 +1,176 @@
+//! Biomass growth. Every patch of biomass grows from a single origin; patch can only seed a limited
+//! amount of cells and dies completely once its heart is destroyed.
+use crate::world::map::MapValue;
+use crate::world::position::{Cursor, Direction};
+use crate::world::World;
+use rand::prelude::*;
+use std::collections::VecDeque;
+
+/// Initial "health" of biomass cells
+pub const BIOMASS_HITS: i32 = 400;
+
+/// Biomass growth parameters. Could be tuned per level via `BIOMASS_*` keys of the level metadata.
+#[derive(Clone, Copy, Debug)]
+pub struct BiomassParams {
+  /// Maximum amount of ticks between biomass cell seeding new cells
+  pub growth_interval: u16,
+  /// Maximum amount of cells seeded per origin (0 is unlimited)
+  pub max_cells: u16,
+  /// Chance (in percents) for the fire to spread from burning biomass cell into its neighbours
+  pub flammability: u8,
+}
+
+impl Default for BiomassParams {
+  fn default() -> Self {
+    BiomassParams {
+      growth_interval: 140,
+      max_cells: 0,
+      flammability: 0,
+    }
+  }
+}
+
+/// Patch of biomass growing from a single origin
+pub struct BiomassPatch {
+  /// Location of the patch heart, if patch has one
+  heart: Option<Cursor>,
+  /// How many cells were seeded by this patch so far
+  seeded: u16,
+}
+
+impl World<'_> {
+  /// Split initial biomass into patches: each connected area of biomass is a separate patch. Only one
+  /// heart is allowed per patch, extra ones (or all of them, if hearts are disabled) become regular
+  /// biomass.
+  pub(super) fn init_biomass_patches(&mut self) {
+    for start in Cursor::all() {
+      if !is_biomass(self.maps.level[start]) || self.maps.patch[start].is_some() {
+        continue;
+      }
+
+      let patch = self.biomass_patches.len() as u16;
+      let mut heart = None;
+      let mut queue = VecDeque::new();
+      self.maps.patch[start] = Some(patch);
+      queue.push_back(start);
+      while let Some(cursor) = queue.pop_front() {
+        if self.maps.level[cursor] == MapValue::BiomassHeart {
+          if heart.is_none() && self.biomass_hearts {
+            heart = Some(cursor);
+          } else {
+            self.maps.level[cursor] = MapValue::Biomass;
+          }
+        }
+        for dir in Direction::all() {
+          let next = cursor.to(dir);
+          if is_biomass(self.maps.level[next]) && self.maps.patch[next].is_none() {
+            self.maps.patch[next] = Some(patch);
+            queue.push_back(next);
+          }
+        }
+      }
+      self.biomass_patches.push(BiomassPatch { heart, seeded: 0 });
+    }
+  }
+
+  /// Start a new biomass patch at the given location; placed biomass is the heart of the patch if
+  /// hearts are enabled
+  pub(super) fn seed_biomass_patch(&mut self, cursor: Cursor) {
+    let heart = if self.biomass_hearts {
+      self.maps.level[cursor] = MapValue::BiomassHeart;
+      Some(cursor)
+    } else {
+      None
+    };
+    self.maps.patch[cursor] = Some(self.biomass_patches.len() as u16);
+    self.biomass_patches.push(BiomassPatch { heart, seeded: 0 });
+  }
+
+  /// Biomass timer went off: seed a new cell in a random direction
+  pub(super) fn grow_biomass(&mut self, cursor: Cursor) {
+    let patch = self.maps.patch[cursor];
+    if let Some(patch) = patch {
+      let max_cells = self.biomass.max_cells;
+      if max_cells != 0 && self.biomass_patches[usize::from(patch)].seeded >= max_cells {
+        // Patch is fully grown
+        return;
+      }
+    }
+
+    let mut rng = self.rng.clone();
+    let clock = rng.gen_range(1..=self.biomass.growth_interval);
+    self.maps.timer[cursor] = clock;
+
+    let dir = *[Direction::Left, Direction::Right, Direction::Up, Direction::Down]
+      .choose(&mut rng)
+      .unwrap();
+    let cursor = cursor.to(dir);
+    if self.maps.level[cursor].is_passable() {
+      self.maps.level[cursor] = MapValue::Biomass;
+      self.maps.timer[cursor] = clock;
+      self.maps.hits[cursor] = BIOMASS_HITS;
+      self.maps.patch[cursor] = patch;
+      if let Some(patch) = patch {
+        self.biomass_patches[usize::from(patch)].seeded += 1;
+      }
+      self.update.update_cell(cursor);
+    }
+  }
+
+  /// Spread the fire from the burning biomass cell through the rest of the patch
+  pub(super) fn burn_biomass(&mut self, start: Cursor, damage: u16) {
+    let flammability = self.biomass.flammability;
+    if flammability == 0 {
+      return;
+    }
+
+    let mut rng = self.rng.clone();
+    let mut queue = VecDeque::new();
+    queue.push_back((start, 0));
+    while let Some((cursor, depth)) = queue.pop_front() {
+      for dir in Direction::all() {
+        let next = cursor.to(dir);
+        let burns = is_biomass(self.maps.level[next]) && !self.maps.protected[next];
+        if burns && rng.gen_range(0..100) < flammability {
+          // Cells further away burn a bit later
+          self.blast_cell(next, 3 + depth);
+          self.apply_damage_in_cell(next, damage);
+          self.update.update_cell(next);
+          self.update.update_burned_border(next);
+          queue.push_back((next, depth + 1));
+        }
+      }
+    }
+  }
+
+  /// Kill patches whose heart was destroyed
+  pub(super) fn check_biomass_hearts(&mut self) {
+    for patch in 0..self.biomass_patches.len() {
+      let heart = match self.biomass_patches[patch].heart {
+        Some(heart) => heart,
+        None => continue,
+      };
+      if self.maps.level[heart] == MapValue::BiomassHeart && self.maps.patch[heart] == Some(patch as u16) {
+        continue;
+      }
+
+      self.biomass_patches[patch].heart = None;
+      for cursor in Cursor::all() {
+        if self.maps.level[cursor] == MapValue::Biomass && self.maps.patch[cursor] == Some(patch as u16) {
+          self.maps.level[cursor] = MapValue::Passage;
+          self.maps.timer[cursor] = 0;
+          self.maps.hits[cursor] = 0;
+          self.maps.patch[cursor] = None;
+          if !self.maps.darkness || !self.maps.fog[cursor].dark {
+            self.show_smoke(cursor);
+          }
+        }
+      }
+    }
+  }
+}
+
+/// If map value is a biomass cell (regular or heart)
+pub fn is_biomass(value: MapValue) -> bool {
+  value == MapValue::Biomass || value == MapValue::BiomassHeart
+}
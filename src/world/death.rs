@@ -0,0 +1,128 @@
+//! Death animations. Animation is an overlay drawn over the map cell: level map keeps whatever is in
+//! the cell (explosion, items lying around), so nothing gets lost while animation is playing.
+use crate::world::actor::ActorKind;
+use crate::world::explode::can_splatter_blood;
+use crate::world::map::{MapValue, MAP_COLS, MAP_ROWS};
+use crate::world::position::{Cursor, Direction};
+use crate::world::{SplatterKind, World};
+
+/// For how many ticks each frame of the animation is shown
+const FRAME_TICKS: u16 = 3;
+
+/// How far from the cell item could be dropped if the cell itself is taken once animation is over
+const DROP_RADIUS: u16 = 3;
+
+const MONSTER_FRAMES: [MapValue; 3] = [MapValue::MonsterDying, MapValue::MonsterSmoke1, MapValue::MonsterSmoke2];
+const SLIME_FRAMES: [MapValue; 3] = [MapValue::SlimeDying, MapValue::SlimeSmoke1, MapValue::SlimeSmoke2];
+
+pub struct DeathAnimation {
+  cursor: Cursor,
+  frames: &'static [MapValue; 3],
+  frame: usize,
+  timer: u16,
+  /// Corpse left in the cell once animation is over
+  corpse: MapValue,
+  splatter: SplatterKind,
+}
+
+impl World<'_> {
+  /// Start death animation of an actor of the given kind
+  pub(super) fn start_death_animation(&mut self, cursor: Cursor, kind: ActorKind) {
+    let (frames, splatter) = match kind {
+      ActorKind::Slime => (&SLIME_FRAMES, SplatterKind::Slime),
+      _ => (&MONSTER_FRAMES, SplatterKind::Blood),
+    };
+    // Only one animation per cell, the latest one wins
+    self.death_animations.retain(|animation| animation.cursor != cursor);
+    self.death_animations.push(DeathAnimation {
+      cursor,
+      frames,
+      frame: 0,
+      timer: FRAME_TICKS,
+      corpse: kind.blood_value(),
+      splatter,
+    });
+    self.update.update_cell(cursor);
+  }
+
+  /// Value to render in the cell instead of its map value, if death animation is playing there
+  pub fn death_overlay(&self, cursor: Cursor) -> Option<MapValue> {
+    self
+      .death_animations
+      .iter()
+      .find(|animation| animation.cursor == cursor)
+      .map(|animation| animation.frames[animation.frame])
+  }
+
+  pub(super) fn animate_deaths(&mut self) {
+    let mut idx = 0;
+    while idx < self.death_animations.len() {
+      let animation = &mut self.death_animations[idx];
+      let cursor = animation.cursor;
+      animation.timer -= 1;
+      if animation.timer > 0 {
+        idx += 1;
+        continue;
+      }
+
+      animation.timer = FRAME_TICKS;
+      if animation.frame + 1 < animation.frames.len() {
+        animation.frame += 1;
+        self.update.update_cell(cursor);
+        idx += 1;
+        continue;
+      }
+
+      // Wait for the explosion in the cell to clear, so corpse is not overwritten by the smoke
+      if self.maps.visual[cursor].is_some() {
+        animation.timer = 1;
+        idx += 1;
+        continue;
+      }
+
+      let animation = self.death_animations.swap_remove(idx);
+      let drop = self.take_drop(cursor);
+      if self.maps.level[cursor] == MapValue::Passage {
+        self.maps.level[cursor] = drop.unwrap_or(animation.corpse);
+        // Dropped items belong to nobody
+        self.maps.owner[cursor] = None;
+      } else if let Some(item) = drop {
+        // Something was placed into the cell while animation was playing, item goes next to it
+        match self.nearest_passage(cursor) {
+          Some(target) => {
+            self.maps.level[target] = item;
+            self.maps.owner[target] = None;
+            self.update.update_cell(target);
+          }
+          None => {
+            self.place_at_random(item);
+          }
+        }
+      }
+      self.update.update_cell(cursor);
+      for dir in Direction::all() {
+        if can_splatter_blood(self.maps.level[cursor.to(dir)]) {
+          self.update.update_splatter(cursor, dir, animation.splatter);
+        }
+      }
+    }
+  }
+
+  /// Empty cell closest to the given one
+  fn nearest_passage(&self, cursor: Cursor) -> Option<Cursor> {
+    let top_left = Cursor::new(
+      cursor.row.saturating_sub(DROP_RADIUS).max(1),
+      cursor.col.saturating_sub(DROP_RADIUS).max(1),
+    );
+    let bottom_right = Cursor::new(
+      (cursor.row + DROP_RADIUS).min(MAP_ROWS - 2),
+      (cursor.col + DROP_RADIUS).min(MAP_COLS - 2),
+    );
+    Cursor::rect(top_left, bottom_right)
+      .filter(|target| self.maps.level[*target] == MapValue::Passage)
+      .min_by_key(|target| {
+        let (rows, cols) = cursor.distance(*target);
+        rows + cols
+      })
+  }
+}
@@ -34,14 +34,164 @@ pub enum Equipment {
   Armor,
   JumpingBomb,
   SuperDrill,
+  Bandage,
+}
+
+/// Broad kind of the equipment, used for grouping items in the user interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EquipmentCategory {
+  /// Placed bombs exploding on a timer
+  Bomb,
+  /// Bombs detonated remotely or triggered by something else
+  Trigger,
+  /// Items used directly against other players
+  Weapon,
+  /// Items changing the terrain
+  Terrain,
+  /// Passive items improving the player
+  Upgrade,
+  /// Everything else
+  Utility,
+}
+
+impl EquipmentCategory {
+  pub fn name(self) -> &'static str {
+    match self {
+      EquipmentCategory::Bomb => "Bomb",
+      EquipmentCategory::Trigger => "Triggered bomb",
+      EquipmentCategory::Weapon => "Weapon",
+      EquipmentCategory::Terrain => "Terrain",
+      EquipmentCategory::Upgrade => "Upgrade",
+      EquipmentCategory::Utility => "Utility",
+    }
+  }
+}
+
+/// Descriptive information about the equipment
+#[derive(Debug, Clone, Copy)]
+pub struct EquipmentInfo {
+  pub name: &'static str,
+  pub description: &'static str,
+  pub category: EquipmentCategory,
 }
 
 impl Equipment {
-  pub const TOTAL: usize = 27;
+  pub const TOTAL: usize = 28;
+
+  const INFO: [EquipmentInfo; Equipment::TOTAL] = [
+    info("Small bomb", "Cheap bomb with a small blast", EquipmentCategory::Bomb),
+    info("Big bomb", "Bomb with a larger blast", EquipmentCategory::Bomb),
+    info(
+      "Dynamite",
+      "Powerful blast, destroys most of the rock",
+      EquipmentCategory::Bomb,
+    ),
+    info(
+      "Atomic bomb",
+      "Huge blast, run as far as you can",
+      EquipmentCategory::Bomb,
+    ),
+    info(
+      "Small radio",
+      "Small bomb detonated with the remote key",
+      EquipmentCategory::Trigger,
+    ),
+    info(
+      "Large radio",
+      "Big bomb detonated with the remote key",
+      EquipmentCategory::Trigger,
+    ),
+    info(
+      "Grenade",
+      "Thrown forward, explodes on impact",
+      EquipmentCategory::Weapon,
+    ),
+    info(
+      "Mine",
+      "Hidden bomb, explodes when stepped on",
+      EquipmentCategory::Trigger,
+    ),
+    info(
+      "Flamethrower",
+      "Burns everything in front of you",
+      EquipmentCategory::Weapon,
+    ),
+    info("Napalm", "Floods the tunnels around with fire", EquipmentCategory::Bomb),
+    info(
+      "Barrel",
+      "Explodes when hit by another blast",
+      EquipmentCategory::Trigger,
+    ),
+    info(
+      "Small crucifix",
+      "Blast spreads in a short cross",
+      EquipmentCategory::Bomb,
+    ),
+    info(
+      "Large crucifix",
+      "Blast spreads in a long cross",
+      EquipmentCategory::Bomb,
+    ),
+    info(
+      "Plastic",
+      "Fills the tunnels around with plastic",
+      EquipmentCategory::Terrain,
+    ),
+    info(
+      "Explosive plastic",
+      "Plastic that explodes once it spreads",
+      EquipmentCategory::Bomb,
+    ),
+    info("Digger", "Digs out the tunnels around", EquipmentCategory::Terrain),
+    info(
+      "Metal wall",
+      "Places an indestructible wall",
+      EquipmentCategory::Terrain,
+    ),
+    info(
+      "Small pickaxe",
+      "Adds 1 to the drilling power",
+      EquipmentCategory::Upgrade,
+    ),
+    info(
+      "Large pickaxe",
+      "Adds 3 to the drilling power",
+      EquipmentCategory::Upgrade,
+    ),
+    info("Drill", "Adds 5 to the drilling power", EquipmentCategory::Upgrade),
+    info(
+      "Teleport",
+      "Step on it to warp to another teleport",
+      EquipmentCategory::Utility,
+    ),
+    info("Clone", "Summons a clone fighting for you", EquipmentCategory::Weapon),
+    info(
+      "Biomass",
+      "Living rock slowly growing from its heart",
+      EquipmentCategory::Terrain,
+    ),
+    info(
+      "Extinguisher",
+      "Puts out fire in front of you",
+      EquipmentCategory::Utility,
+    ),
+    info("Armor", "Protects you from the damage", EquipmentCategory::Upgrade),
+    info(
+      "Jumping bomb",
+      "Bomb hopping around before exploding",
+      EquipmentCategory::Bomb,
+    ),
+    info(
+      "Super drill",
+      "Digs through anything for a while",
+      EquipmentCategory::Utility,
+    ),
+    info("Bandage", "Restores some of your health", EquipmentCategory::Utility),
+  ];
 
   const PRICES: [u32; Equipment::TOTAL] = [
     1, 3, 10, 650, 15, 65, 300, 25, 500, 80, 90, 35, 145, 15, 80, 120, 50, 400, 1100, 1600, 70, 400, 50, 80, 800, 95,
-    575,
+    575, 40,
   ];
 
   pub fn all_equipment() -> impl Iterator<Item = Equipment> {
@@ -52,6 +202,24 @@ impl Equipment {
     Self::PRICES[self as usize]
   }
 
+  pub fn info(self) -> &'static EquipmentInfo {
+    &Self::INFO[self as usize]
+  }
+
+  /// Name to show to the player
+  pub fn name(self) -> &'static str {
+    self.info().name
+  }
+
+  /// Short description of what item does
+  pub fn description(self) -> &'static str {
+    self.info().description
+  }
+
+  pub fn category(self) -> EquipmentCategory {
+    self.info().category
+  }
+
   /// Create an iterator that loops over all inventory items starting from the given one
   pub fn selection_iter(self) -> impl Iterator<Item = Equipment> {
     SelectionIter {
@@ -61,6 +229,14 @@ impl Equipment {
   }
 }
 
+const fn info(name: &'static str, description: &'static str, category: EquipmentCategory) -> EquipmentInfo {
+  EquipmentInfo {
+    name,
+    description,
+    category,
+  }
+}
+
 struct SelectionIter {
   start: Equipment,
   current: Equipment,
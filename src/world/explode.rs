@@ -1,9 +1,11 @@
 use crate::bitmap;
 use crate::bitmap::MapValueSet;
 use crate::effects::SoundEffect;
-use crate::world::map::{MapValue, MAP_ROWS};
+use crate::world::biomass::is_biomass;
+use crate::world::map::MapValue;
 use crate::world::position::{Cursor, Direction};
-use crate::world::{grenade_direction, SplatterKind, World};
+use crate::world::profile::Subsystem;
+use crate::world::{grenade_direction, radio_owner, GameEvent, JumpTarget, World};
 use rand::prelude::*;
 
 impl World<'_> {
@@ -13,8 +15,23 @@ impl World<'_> {
     if total > 200 {
       return;
     }
+    self.events.push(GameEvent::Exploded { cursor });
+
+    // Entities without an owner (placed on the map by a level designer, for example) are attributed
+    // to the player who triggered them.
+    let attacker = self.attacker;
+    self.attacker = self.maps.owner[cursor].take().or(attacker);
+    self.explode_entity_owned(cursor, total);
+    self.attacker = attacker;
+  }
 
+  /// Activate entity in the cell once the player responsible for it is known
+  fn explode_entity_owned(&mut self, cursor: Cursor, total: u32) {
     let value = self.maps.level[cursor];
+    if let Some(owner) = radio_owner(value) {
+      // Amount of armed radio bombs is shown next to the selection
+      self.update.update_player_selection(owner);
+    }
     match value {
       MapValue::MetalWall | MapValue::Door => {
         self.apply_damage_in_cell(cursor, 50);
@@ -50,7 +67,10 @@ impl World<'_> {
           let cathet = f64::ceil(f64::sqrt(144.0 - (delta_col * delta_col) as f64)) as i16;
           for delta_row in -cathet..=cathet {
             if let Some(cursor) = cursor.offset(delta_row, delta_col) {
-              self.explode_cell(cursor, 255, true, total);
+              // Damage goes down to 50% at the edge of the blast
+              let distance = f64::sqrt(f64::from(delta_row * delta_row + delta_col * delta_col));
+              let percent = 100 - (distance * 50.0 / 12.0).min(50.0) as u16;
+              self.explode_cell(cursor, self.damage_falloff(255, percent), true, total);
             }
           }
         }
@@ -59,7 +79,7 @@ impl World<'_> {
         self.effects.play(SoundEffect::Explos3, 9900, cursor);
         self.effects.play(SoundEffect::Explos3, 10000, cursor);
         self.flash = true;
-        self.shake = (self.shake + 10).min(MAP_ROWS);
+        self.shake_from(10, cursor);
       }
 
       MapValue::SmallBomb1
@@ -94,6 +114,7 @@ impl World<'_> {
               MapValue::MetalWall | MapValue::Exit | MapValue::Door | MapValue::ButtonOff | MapValue::ButtonOn => {
                 break;
               }
+              value if value.locked_door_color().is_some() => break,
               _ => {}
             }
 
@@ -156,78 +177,15 @@ impl World<'_> {
         self.expand_algo(&PlasticExpansion, cursor, total);
         self.effects.play(SoundEffect::Urethan, 11000, cursor);
       }
-      MapValue::Explosion => {
-        self.maps.level[cursor] = MapValue::Smoke1;
-        self.maps.timer[cursor] = 3;
-        self.update.update_cell(cursor);
-      }
-      MapValue::Smoke1 => {
-        self.maps.level[cursor] = MapValue::Smoke2;
-        self.maps.timer[cursor] = 3;
-        self.update.update_cell(cursor);
-      }
-      MapValue::Smoke2 => {
-        self.maps.level[cursor] = MapValue::Passage;
-        self.maps.timer[cursor] = 0;
-        self.update.update_cell(cursor);
-      }
-      MapValue::MonsterDying => {
-        self.maps.level[cursor] = MapValue::MonsterSmoke1;
-        self.maps.timer[cursor] = 3;
-        self.update.update_cell(cursor);
-      }
-      MapValue::MonsterSmoke1 => {
-        self.maps.level[cursor] = MapValue::MonsterSmoke2;
-        self.maps.timer[cursor] = 3;
-        self.update.update_cell(cursor);
-      }
-      MapValue::MonsterSmoke2 => {
-        self.maps.level[cursor] = MapValue::Blood;
-        self.maps.timer[cursor] = 0;
-        self.update.update_cell(cursor);
-
-        for dir in Direction::all() {
-          if can_splatter_blood(self.maps.level[cursor.to(dir)]) {
-            self.update.update_splatter(cursor, dir, SplatterKind::Blood);
-          }
-        }
-      }
-      MapValue::SlimeDying => {
-        self.maps.level[cursor] = MapValue::SlimeSmoke1;
-        self.maps.timer[cursor] = 3;
-        self.update.update_cell(cursor);
-      }
-      MapValue::SlimeSmoke1 => {
-        self.maps.level[cursor] = MapValue::SlimeSmoke2;
-        self.maps.timer[cursor] = 3;
-        self.update.update_cell(cursor);
+      MapValue::Fire => {
+        self.burn_out(cursor);
       }
-      MapValue::SlimeSmoke2 => {
-        self.maps.level[cursor] = MapValue::SlimeCorpse;
-        self.maps.timer[cursor] = 0;
-        self.update.update_cell(cursor);
-
-        for dir in Direction::all() {
-          if can_splatter_blood(self.maps.level[cursor.to(dir)]) {
-            self.update.update_splatter(cursor, dir, SplatterKind::Slime);
-          }
-        }
+      MapValue::NestFurry | MapValue::NestGrenadier | MapValue::NestSlime | MapValue::NestAlien => {
+        self.nest_spawn_monster(cursor);
+        self.maps.timer[cursor] = self.tuning.nest_interval;
       }
-      MapValue::Biomass => {
-        let mut rng = thread_rng();
-        let clock = rng.gen_range(1..141);
-        self.maps.timer[cursor] = clock;
-
-        let dir = *[Direction::Left, Direction::Right, Direction::Up, Direction::Down]
-          .choose(&mut rng)
-          .unwrap();
-        let cursor = cursor.to(dir);
-        if self.maps.level[cursor].is_passable() {
-          self.maps.level[cursor] = MapValue::Biomass;
-          self.maps.timer[cursor] = clock;
-          self.maps.hits[cursor] = 400;
-          self.update.update_cell(cursor);
-        }
+      MapValue::Biomass | MapValue::BiomassHeart => {
+        self.grow_biomass(cursor);
       }
 
       _ => {
@@ -237,7 +195,7 @@ impl World<'_> {
   }
 
   fn explode_jumping_bomb(&mut self, cursor: Cursor, total: u32) {
-    let mut rng = thread_rng();
+    let mut rng = self.rng.clone();
     let bomb = *[MapValue::SmallBomb1, MapValue::BigBomb1, MapValue::Dynamite1]
       .choose(&mut rng)
       .unwrap();
@@ -247,42 +205,74 @@ impl World<'_> {
     //self.update.update_cell(cursor);
     self.explode_entity(cursor, total + 1);
 
+    // Landing spot could have been selected on the previous jump
+    let planned = self.take_jump_target(cursor);
     let jumps = self.maps.hits[cursor];
     if jumps > 1 {
-      let mut next = None;
-      for _ in 0..6 {
-        // Note that ranges are not symmetric as per original game!
-        let delta_row = rng.gen_range(-4..4);
-        let delta_col = rng.gen_range(-4..4);
-        if let Some(cur) = cursor.offset(delta_row, delta_col) {
-          let v = self.maps.level[cur];
-          // FIXME: verify: cannot jump on blood; cannot jump on brick; cannot jump on cracked stone
-          if v == MapValue::Passage
-            || v.is_sand()
-            || v.is_stone_corner()
-            || v.is_stone()
-            || v == MapValue::Boulder
-            || v == MapValue::Explosion
-          {
-            next = Some(cur);
-          }
-        }
-      }
-      let next = next.unwrap_or(cursor);
+      let next = planned
+        .filter(|cur| can_jump_on(self.cell_value(*cur)) && !self.maps.protected[*cur])
+        .unwrap_or_else(|| self.pick_jump_target(cursor));
       self.maps.level[next] = MapValue::JumpingBomb;
+      self.maps.owner[next] = self.attacker;
       self.maps.hits[cursor] = 0;
       self.maps.hits[next] = jumps - 1;
       self.update.update_cell(next);
       self.maps.timer[next] = rng.gen_range(1..181);
+
+      if jumps > 2 {
+        // Select the next landing spot right away, so the owner could see it
+        let target = self.pick_jump_target(next);
+        let owner = self.attacker.filter(|owner| *owner < self.players.len());
+        self.jump_targets.push(JumpTarget {
+          bomb: next,
+          target,
+          owner,
+        });
+        if let Some(owner) = owner {
+          self.update.update_jump_target(owner, target);
+        }
+      }
+    }
+
+    // Erase the marker of the previous landing spot
+    if let Some(planned) = planned {
+      self.update.update_cell(planned);
+    }
+  }
+
+  /// Select random landing spot for the jumping bomb. Bomb stays in place if no good spot was found.
+  fn pick_jump_target(&self, cursor: Cursor) -> Cursor {
+    let mut rng = self.rng.clone();
+    let mut next = None;
+    for _ in 0..6 {
+      // Note that ranges are not symmetric as per original game!
+      let delta_row = rng.gen_range(-4..4);
+      let delta_col = rng.gen_range(-4..4);
+      if let Some(cur) = cursor.offset(delta_row, delta_col) {
+        if can_jump_on(self.cell_value(cur)) && !self.maps.protected[cur] {
+          next = Some(cur);
+        }
+      }
     }
+    next.unwrap_or(cursor)
+  }
+
+  /// Take landing spot selected by the jumping bomb in the given cell. Also forgets landing spots of
+  /// bombs which are gone.
+  fn take_jump_target(&mut self, bomb: Cursor) -> Option<Cursor> {
+    let pos = self.jump_targets.iter().position(|target| target.bomb == bomb);
+    let planned = pos.map(|pos| self.jump_targets.swap_remove(pos).target);
+    let level = &self.maps.level;
+    self
+      .jump_targets
+      .retain(|target| level[target.bomb] == MapValue::JumpingBomb);
+    planned
   }
 
   fn explode_barrel(&mut self, cursor: Cursor, total: u32) {
-    let mut rng = thread_rng();
+    let mut rng = self.rng.clone();
 
-    self.maps.level[cursor] = MapValue::Explosion;
-    self.maps.timer[cursor] = 3;
-    self.update.update_cell(cursor);
+    self.blast_cell(cursor, 3);
 
     self.effects.play(SoundEffect::Explos1, 11000, cursor);
 
@@ -314,6 +304,7 @@ impl World<'_> {
       self.update.update_cell(cursor);
 
       self.maps.level[next] = value;
+      self.maps.owner[next] = self.attacker;
       self.update.update_cell(next);
       self.maps.timer[next] = 2;
     } else {
@@ -327,12 +318,15 @@ impl World<'_> {
     let value = self.maps.level[cursor];
     if EXPLODABLE_ENTITY[value] {
       self.explode_entity(cursor, total);
+    } else if self.maps.protected[cursor] || value.locked_door_color().is_some() {
+      // Protected cells (and locked doors, which only open with a key) are never destroyed, only
+      // actors in them get hurt
+      self.apply_damage_in_cell(cursor, damage);
     } else if value.is_stone() || value.is_stone_corner() || value == MapValue::Boulder {
       if heavy_explosion {
-        self.maps.level[cursor] = MapValue::Explosion;
-        self.maps.timer[cursor] = 3;
+        self.blast_cell(cursor, 3);
       } else {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.clone();
         if rng.gen::<bool>() {
           self.maps.level[cursor] = MapValue::StoneHeavyCracked;
           self.maps.hits[cursor] = 500;
@@ -341,10 +335,15 @@ impl World<'_> {
           self.maps.hits[cursor] = 1000;
         }
       }
+    } else if value.nest().is_some() {
+      // Nests are pretty sturdy, but will eventually crumble
+      self.maps.hits[cursor] -= i32::from(damage) * 10;
+      if heavy_explosion || self.maps.hits[cursor] <= 0 {
+        self.blast_cell(cursor, 3);
+      }
     } else if value.is_brick_like() {
       if heavy_explosion {
-        self.maps.level[cursor] = MapValue::Explosion;
-        self.maps.timer[cursor] = 3;
+        self.blast_cell(cursor, 3);
       } else if value == MapValue::Brick {
         self.maps.hits[cursor] = 4000;
         self.maps.level[cursor] = MapValue::BrickLightCracked;
@@ -352,75 +351,106 @@ impl World<'_> {
         self.maps.hits[cursor] = 2000;
         self.maps.level[cursor] = MapValue::BrickHeavyCracked;
       } else if value == MapValue::BrickHeavyCracked {
-        self.maps.level[cursor] = MapValue::Explosion;
-        self.maps.timer[cursor] = 3;
+        self.blast_cell(cursor, 3);
       }
     } else {
-      self.maps.level[cursor] = MapValue::Explosion;
-      self.maps.timer[cursor] = 3;
-
+      self.blast_cell(cursor, 3);
       self.apply_damage_in_cell(cursor, damage);
+      if heavy_explosion && is_biomass(value) {
+        self.burn_biomass(cursor, damage);
+      }
     }
 
     self.update.update_cell(cursor);
     self.update.update_burned_border(cursor);
   }
 
-  /// Generate an explosion given the pattern (list of row and collumn offsets). Note that pattern
-  /// should not include the central square.
-  fn explode_pattern(&mut self, center: Cursor, dmg: u16, pattern: &[(i16, i16)], total: u32) {
+  /// Generate an explosion given the pattern (list of row and collumn offsets, plus damage
+  /// percentage used in realistic damage mode). Note that pattern should not include the central
+  /// square.
+  fn explode_pattern(&mut self, center: Cursor, dmg: u16, pattern: &[(i16, i16, u16)], total: u32) {
     self.explode_cell(center, dmg, false, total);
-    for (delta_row, delta_col) in pattern {
+    for (delta_row, delta_col, percent) in pattern {
       if let Some(cur) = center.offset(*delta_row, *delta_col) {
-        self.explode_cell(cur, dmg, false, total);
+        self.explode_cell(cur, self.damage_falloff(dmg, *percent), false, total);
       }
     }
   }
 
-  /// Generic expansion algorithm used by plastic and digger
-  fn expand_algo<E: Expansion>(&mut self, expansion: &E, cursor: Cursor, total: u32) {
-    self.maps.level[cursor] = E::MARKER1;
+  /// Scale the damage by the given percentage, if realistic damage is enabled
+  fn damage_falloff(&self, dmg: u16, percent: u16) -> u16 {
+    if self.realistic_damage {
+      dmg * percent / 100
+    } else {
+      dmg
+    }
+  }
+
+  /// Generic expansion algorithm used by plastic and digger. Expansion spreads in waves: on every
+  /// wave, cells claimed on the previous one (the frontier) try to spread into their neighbours.
+  fn expand_algo<E: Expansion>(&mut self, expansion: &E, start: Cursor, total: u32) {
+    let started = self.profile.start();
+    // Bomb itself is consumed by the expansion
+    self.maps.level[start] = MapValue::Passage;
+    self.claimed[start] = true;
+    // Nested expansions (explosion triggering another bomb) take their own buffers from the pool
+    let mut covered = self.cursors.take();
+    let mut frontier = self.cursors.take();
+    let mut next = self.cursors.take();
+    covered.push(start);
+    frontier.push(start);
 
     let mut expanded_count = 0;
     while expanded_count < E::MAX_EXPANSION {
       let mut spread = false;
-      for cursor in Cursor::all_without_borders() {
-        if self.maps.level[cursor] != E::MARKER1 {
+      for cursor in frontier.drain(..) {
+        if cursor.is_on_border() {
           continue;
         }
 
+        let mut retry = false;
         for dir in Direction::all() {
-          let cursor = cursor.to(dir);
-          let value = self.maps.level[cursor];
+          let target = cursor.to(dir);
+          if self.claimed[target] {
+            continue;
+          }
+
+          let value = self.cell_value(target);
           if E::EXPLODE_ENTITIES && EXPLODABLE_ENTITY[value] {
-            self.explode_entity(cursor, total);
-          } else if expansion.can_expand(value, cursor, dir) {
-            self.maps.level[cursor] = E::MARKER2;
-            self.update.update_cell(cursor);
+            self.explode_entity(target, total);
+            // Blast might have cleared the way, so try again on the next wave
+            retry |= self.cell_value(target) != value;
+          } else if !self.maps.protected[target] && expansion.can_expand(value, target, dir) {
+            self.claimed[target] = true;
             expanded_count += 1;
             spread = true;
-            expansion.expand(self, cursor);
+            expansion.expand(self, target);
+            covered.push(target);
+            next.push(target);
           }
         }
+        if retry {
+          next.push(cursor);
+        }
       }
 
       // Haven't expanded even a single bit
       if !spread {
         break;
       }
-
-      for cursor in Cursor::all() {
-        if self.maps.level[cursor] == E::MARKER2 {
-          self.maps.level[cursor] = E::MARKER1;
-        }
-      }
+      std::mem::swap(&mut frontier, &mut next);
     }
 
-    for cursor in Cursor::all() {
-      if self.maps.level[cursor] == E::MARKER1 {
-        expansion.finalize(self, cursor, total);
-      }
+    // Finalize row by row, same order as original game did
+    covered.sort_unstable_by_key(|cursor| (cursor.row, cursor.col));
+    for cursor in covered.drain(..) {
+      self.claimed[cursor] = false;
+      expansion.finalize(self, cursor, total);
     }
+    self.cursors.give(covered);
+    self.cursors.give(frontier);
+    self.cursors.give(next);
+    self.profile.record(Subsystem::Expansion, started);
   }
 
   /// Fire a flamethrower
@@ -430,7 +460,7 @@ impl World<'_> {
     // If next cell is passable, start flame there (otherwise, start in current spot)
     // Note that if flame starts in current spot, it will destroy everything in that cell,
     // including metal walls (original behavior)!
-    let value = self.maps.level[cursor.to(direction)];
+    let value = self.cell_value(cursor.to(direction));
     if is_flame_passable(value) {
       cursor = cursor.to(direction);
     }
@@ -479,70 +509,68 @@ pub const EXPLODABLE_ENTITY: MapValueSet = bitmap!([
   0b0000_0000,
 ]);
 
-/// Cross pattern of barrel explosion (these are offsets to row and column).
-const BIG_BOMB_PATTERN: [(i16, i16); 12] = [
-  (-1, 0),
-  (1, 0),
-  (0, -1),
-  (0, 1),
-  (-2, 0),
-  (-1, 1),
-  (0, 2),
-  (1, 1),
-  (2, 0),
-  (1, -1),
-  (0, -2),
-  (-1, -1),
+/// Cross pattern of barrel explosion (these are offsets to row and column, and damage percentage).
+const BIG_BOMB_PATTERN: [(i16, i16, u16); 12] = [
+  (-1, 0, 80),
+  (1, 0, 80),
+  (0, -1, 80),
+  (0, 1, 80),
+  (-2, 0, 60),
+  (-1, 1, 60),
+  (0, 2, 60),
+  (1, 1, 60),
+  (2, 0, 60),
+  (1, -1, 60),
+  (0, -2, 60),
+  (-1, -1, 60),
 ];
 
-/// Cross pattern of small bomb explosion (these are offsets to row and column).
-const SMALL_BOMB_PATTERN: [(i16, i16); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
-
-/// Cross pattern of barrel explosion (these are offsets to row and column).
-const DYNAMITE_PATTERN: [(i16, i16); 36] = [
-  (-1, 0),
-  (1, 0),
-  (0, -1),
-  (0, 1),
-  (-2, 0),
-  (-1, 1),
-  (0, 2),
-  (1, 1),
-  (2, 0),
-  (1, -1),
-  (0, -2),
-  (-1, -1),
+/// Cross pattern of small bomb explosion (these are offsets to row and column, and damage percentage).
+const SMALL_BOMB_PATTERN: [(i16, i16, u16); 4] = [(-1, 0, 100), (1, 0, 100), (0, -1, 100), (0, 1, 100)];
+
+/// Cross pattern of barrel explosion (these are offsets to row and column, and damage percentage).
+const DYNAMITE_PATTERN: [(i16, i16, u16); 36] = [
+  (-1, 0, 80),
+  (1, 0, 80),
+  (0, -1, 80),
+  (0, 1, 80),
+  (-2, 0, 60),
+  (-1, 1, 60),
+  (0, 2, 60),
+  (1, 1, 60),
+  (2, 0, 60),
+  (1, -1, 60),
+  (0, -2, 60),
+  (-1, -1, 60),
   // 13
-  (-3, 0),
-  (-3, 1),
-  (-2, 1),
-  (-2, 2),
-  (-1, 2),
-  (-1, 3),
-  (0, 3),
-  (1, 3),
-  (1, 2),
-  (2, 2),
-  (2, 1),
-  (3, 1),
-  (3, 0),
-  (3, -1),
-  (2, -1),
-  (2, -2),
-  (1, -2),
-  (1, -3),
-  (0, -3),
-  (-1, -3),
-  (-1, -2),
-  (-2, -2),
-  (-2, -1),
-  (-3, -1),
+  (-3, 0, 40),
+  (-3, 1, 40),
+  (-2, 1, 40),
+  (-2, 2, 40),
+  (-1, 2, 40),
+  (-1, 3, 40),
+  (0, 3, 40),
+  (1, 3, 40),
+  (1, 2, 40),
+  (2, 2, 40),
+  (2, 1, 40),
+  (3, 1, 40),
+  (3, 0, 40),
+  (3, -1, 40),
+  (2, -1, 40),
+  (2, -2, 40),
+  (1, -2, 40),
+  (1, -3, 40),
+  (0, -3, 40),
+  (-1, -3, 40),
+  (-1, -2, 40),
+  (-2, -2, 40),
+  (-2, -1, 40),
+  (-3, -1, 40),
 ];
 
 /// Common trait for all expandable bombs (plastic, digger, napalm)
 trait Expansion {
-  const MARKER1: MapValue;
-  const MARKER2: MapValue;
   const MAX_EXPANSION: u16;
   const EXPLODE_ENTITIES: bool;
 
@@ -562,8 +590,6 @@ trait Expansion {
 struct ExplodingPlasticExpansion;
 
 impl Expansion for ExplodingPlasticExpansion {
-  const MARKER1: MapValue = MapValue::TempMarker1;
-  const MARKER2: MapValue = MapValue::TempMarker2;
   const MAX_EXPANSION: u16 = 50;
   const EXPLODE_ENTITIES: bool = false;
 
@@ -579,8 +605,6 @@ impl Expansion for ExplodingPlasticExpansion {
 struct PlasticExpansion;
 
 impl Expansion for PlasticExpansion {
-  const MARKER1: MapValue = MapValue::TempMarker1;
-  const MARKER2: MapValue = MapValue::TempMarker2;
   const MAX_EXPANSION: u16 = 45;
   const EXPLODE_ENTITIES: bool = false;
 
@@ -596,8 +620,6 @@ impl Expansion for PlasticExpansion {
 struct DiggerExpansion;
 
 impl Expansion for DiggerExpansion {
-  const MARKER1: MapValue = MapValue::TempMarker1;
-  const MARKER2: MapValue = MapValue::TempMarker2;
   const MAX_EXPANSION: u16 = 75;
   const EXPLODE_ENTITIES: bool = true;
 
@@ -613,8 +635,6 @@ impl Expansion for DiggerExpansion {
 struct NapalmExpansion;
 
 impl Expansion for NapalmExpansion {
-  const MARKER1: MapValue = MapValue::NapalmTempMarker1;
-  const MARKER2: MapValue = MapValue::NapalmTempMarker2;
   const MAX_EXPANSION: u16 = 75;
   const EXPLODE_ENTITIES: bool = true;
 
@@ -626,6 +646,8 @@ impl Expansion for NapalmExpansion {
         | MapValue::Smoke2
         | MapValue::Blood
         | MapValue::Biomass
+        | MapValue::BiomassHeart
+        | MapValue::Fire
         | MapValue::Explosion
         | MapValue::MonsterDying
         | MapValue::MonsterSmoke1
@@ -643,6 +665,10 @@ impl Expansion for NapalmExpansion {
   fn finalize(&self, world: &mut World, cursor: Cursor, total: u32) {
     world.maps.level[cursor] = MapValue::Passage;
     world.explode_cell(cursor, 220, true, total);
+    if world.is_exploding(cursor) && world.maps.level[cursor] == MapValue::Passage {
+      // Napalm keeps burning for a while
+      world.start_fire(cursor);
+    }
   }
 }
 
@@ -654,8 +680,6 @@ struct FlamethrowerExpansion {
 }
 
 impl Expansion for FlamethrowerExpansion {
-  const MARKER1: MapValue = MapValue::NapalmTempMarker1;
-  const MARKER2: MapValue = MapValue::NapalmTempMarker2;
   const MAX_EXPANSION: u16 = 30;
   const EXPLODE_ENTITIES: bool = true;
 
@@ -692,10 +716,7 @@ fn place_plastic(world: &mut World, cursor: Cursor, explosive: bool) {
   // However, the way check is written, it does not work for player 1 (it immediately
   // overrides square with `ExplosivePlastic`). Also, players 3 and 4 are not checked at all.
   // We fix that and make it work for every player
-  if world.actors[..world.players.len()]
-    .iter()
-    .any(|actor| actor.pos.cursor() == cursor)
-  {
+  if world.actors_at(cursor).any(|idx| idx < world.players.len()) {
     // Player is in this square: don't drop plastic here
     world.maps.level[cursor] = MapValue::Passage;
     world.maps.timer[cursor] = 0;
@@ -703,6 +724,7 @@ fn place_plastic(world: &mut World, cursor: Cursor, explosive: bool) {
     world.maps.level[cursor] = MapValue::ExplosivePlastic;
     world.maps.hits[cursor] = 400;
     world.maps.timer[cursor] = 250;
+    world.maps.owner[cursor] = world.attacker;
   } else {
     world.maps.level[cursor] = MapValue::Plastic;
     world.maps.hits[cursor] = 400;
@@ -711,13 +733,24 @@ fn place_plastic(world: &mut World, cursor: Cursor, explosive: bool) {
   world.update.update_cell(cursor);
 }
 
-fn can_splatter_blood(value: MapValue) -> bool {
+/// Check if jumping bomb can land on the given square
+fn can_jump_on(value: MapValue) -> bool {
+  // FIXME: verify: cannot jump on blood; cannot jump on brick; cannot jump on cracked stone
+  value == MapValue::Passage
+    || value.is_sand()
+    || value.is_stone_corner()
+    || value.is_stone()
+    || value == MapValue::Boulder
+    || value == MapValue::Explosion
+}
+
+pub(super) fn can_splatter_blood(value: MapValue) -> bool {
   value == MapValue::MetalWall
     || value.is_sand()
     || value == MapValue::LightGravel
     || value == MapValue::HeavyGravel
     || value.is_stone_like()
-    || value == MapValue::Biomass
+    || is_biomass(value)
     || value == MapValue::Plastic
     || value == MapValue::ExplosivePlastic
     || value.is_brick_like()
@@ -727,7 +760,8 @@ fn can_splatter_blood(value: MapValue) -> bool {
 fn is_flame_passable(value: MapValue) -> bool {
   value.is_passable()
     || (value >= MapValue::Smoke1 && value <= MapValue::Smoke2)
-    || value == MapValue::Biomass
+    || is_biomass(value)
+    || value == MapValue::Fire
     || (value >= MapValue::Explosion && value <= MapValue::MonsterSmoke2)
     || value == MapValue::Plastic
 }
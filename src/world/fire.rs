@@ -0,0 +1,79 @@
+//! Lingering fire left by napalm. Fire burns for a while, hurting anyone standing in it and igniting
+//! bombs nearby, and then burns out into passage.
+use crate::world::map::MapValue;
+use crate::world::position::{Cursor, Direction};
+use crate::world::World;
+
+/// For how many ticks fire keeps burning
+const FIRE_DURATION: u16 = 150;
+
+/// How often (in ticks) fire damages actors and ignites bombs around
+const FIRE_INTERVAL: u16 = 10;
+
+/// Damage dealt by the fire to the actors in the cell every `FIRE_INTERVAL` ticks
+const FIRE_DAMAGE: u16 = 6;
+
+/// Fuse of the bombs ignited by the fire
+const FIRE_FUSE: u16 = 20;
+
+impl World<'_> {
+  /// Set the cell on fire. Fire is attributed to the current attacker.
+  pub(super) fn start_fire(&mut self, cursor: Cursor) {
+    self.maps.level[cursor] = MapValue::Fire;
+    self.maps.timer[cursor] = FIRE_DURATION;
+    self.maps.owner[cursor] = self.attacker;
+    self.update.update_cell(cursor);
+  }
+
+  /// Fire timer ticks: hurt actors in the cell and ignite bombs around
+  pub(super) fn tick_fire(&mut self, cursor: Cursor, clock: u16) {
+    if clock % FIRE_INTERVAL != 0 {
+      return;
+    }
+
+    let attacker = self.attacker;
+    self.attacker = self.maps.owner[cursor];
+    self.apply_damage_in_cell(cursor, FIRE_DAMAGE);
+    self.attacker = attacker;
+
+    for dir in Direction::all() {
+      self.ignite(cursor.to(dir));
+    }
+  }
+
+  /// Fire burned out
+  pub(super) fn burn_out(&mut self, cursor: Cursor) {
+    self.maps.level[cursor] = MapValue::Passage;
+    self.maps.timer[cursor] = 0;
+    self.update.update_cell(cursor);
+  }
+
+  /// Light up extinguished bomb or shorten the fuse of the burning one
+  fn ignite(&mut self, cursor: Cursor) {
+    let lit = match self.maps.level[cursor] {
+      MapValue::SmallBombExtinguished => MapValue::SmallBomb3,
+      MapValue::BigBombExtinguished => MapValue::BigBomb3,
+      MapValue::DynamiteExtinguished => MapValue::Dynamite3,
+      MapValue::NapalmExtinguished => MapValue::Napalm1,
+      value @ MapValue::SmallBomb1
+      | value @ MapValue::SmallBomb2
+      | value @ MapValue::SmallBomb3
+      | value @ MapValue::BigBomb1
+      | value @ MapValue::BigBomb2
+      | value @ MapValue::BigBomb3
+      | value @ MapValue::Dynamite1
+      | value @ MapValue::Dynamite2
+      | value @ MapValue::Dynamite3
+      | value @ MapValue::Napalm1
+      | value @ MapValue::Napalm2 => value,
+      _ => return,
+    };
+
+    let timer = self.maps.timer[cursor];
+    self.maps.timer[cursor] = if timer == 0 { FIRE_FUSE } else { timer.min(FIRE_FUSE) };
+    if self.maps.level[cursor] != lit {
+      self.maps.level[cursor] = lit;
+      self.update.update_cell(cursor);
+    }
+  }
+}
@@ -0,0 +1,192 @@
+//! World state consistency checks. Only compiled into debug builds: after each tick, we verify that
+//! maps and actors are consistent with each other, so desync bugs are caught close to their origin.
+use crate::world::actor::ActorKind;
+use crate::world::map::{MapValue, MAP_COLS, MAP_ROWS};
+use crate::world::position::Cursor;
+use crate::world::World;
+
+/// Stop logging after that many violations, to avoid flooding the output
+const MAX_REPORTED: usize = 20;
+
+/// Environment variable to panic on the first violation instead of logging it
+const PANIC_VAR: &str = "MB_PANIC_ON_INVARIANTS";
+
+/// State carried between checks
+#[derive(Default)]
+pub struct InvariantsState {
+  /// Cells which were revealed in the darkness mode; these should never get dark again
+  revealed: Vec<bool>,
+  reported: usize,
+}
+
+impl World<'_> {
+  /// Verify world invariants, logging (or panicking) with a state dump if any are violated
+  pub(super) fn check_invariants(&mut self) {
+    let mut violations = Vec::new();
+
+    for cursor in Cursor::all() {
+      let value = self.maps.level[cursor];
+      let timer = self.maps.timer[cursor];
+      let hits = self.maps.hits[cursor];
+      if timer != 0 && !is_timed(value) {
+        violations.push((cursor, format!("timer {} is set on inactive cell", timer)));
+      }
+      // Explosions and smoke count down in the visual layer
+      if self.maps.visual[cursor].is_some_and(|visual| visual.timer == 0) {
+        violations.push((cursor, "visual effect has no time left".to_owned()));
+      }
+      if value == MapValue::MetalWall && hits != 30_000 {
+        violations.push((cursor, format!("metal wall has {} hits instead of 30000", hits)));
+      }
+    }
+
+    for (idx, actor) in self.actors.iter().enumerate() {
+      let cursor = actor.pos.cursor();
+      if cursor.row >= MAP_ROWS || cursor.col >= MAP_COLS {
+        violations.push((cursor, format!("actor {} is out of map bounds", idx)));
+      }
+      if actor.health > actor.max_health {
+        violations.push((cursor, format!("actor {} health is above maximum", idx)));
+      }
+      let is_player = matches!(actor.kind, ActorKind::Player(_));
+      if is_player != (idx < self.players.len()) {
+        violations.push((
+          cursor,
+          format!("actor {} is in the wrong place in the actors list", idx),
+        ));
+      }
+    }
+
+    if !self.occupancy.is_consistent(&self.actors) {
+      violations.push((Cursor::new(0, 0), "actors occupancy index is out of date".to_owned()));
+    }
+
+    if self.maps.darkness {
+      let revealed = &mut self.invariants.revealed;
+      revealed.resize(usize::from(MAP_ROWS * MAP_COLS), false);
+      for cursor in Cursor::all() {
+        let idx = usize::from(cursor.row * MAP_COLS + cursor.col);
+        if !self.maps.fog[cursor].dark {
+          revealed[idx] = true;
+        } else if revealed[idx] {
+          violations.push((cursor, "revealed cell got dark again".to_owned()));
+        }
+      }
+    }
+
+    for (cursor, message) in violations {
+      self.report_violation(cursor, &message);
+    }
+  }
+
+  fn report_violation(&mut self, cursor: Cursor, message: &str) {
+    let dump = self.dump_cell(cursor);
+    if std::env::var_os(PANIC_VAR).is_some() {
+      panic!(
+        "World invariant violated on tick {}: {}\n{}",
+        self.round_counter, message, dump
+      );
+    }
+    if self.invariants.reported < MAX_REPORTED {
+      eprintln!(
+        "World invariant violated on tick {}: {}\n{}",
+        self.round_counter, message, dump
+      );
+      self.invariants.reported += 1;
+      if self.invariants.reported == MAX_REPORTED {
+        eprintln!("Too many invariant violations, not reporting any more of them");
+      }
+    }
+  }
+
+  /// Dump the state of the cell and the actors standing in it
+  fn dump_cell(&self, cursor: Cursor) -> String {
+    let mut dump = format!("  cell ({}, {})", cursor.row, cursor.col);
+    if cursor.row < MAP_ROWS && cursor.col < MAP_COLS {
+      let fog = self.maps.fog[cursor];
+      dump += &format!(
+        ": value {:?}, timer {}, hits {}, owner {:?}, dark {}, open door {}",
+        self.maps.level[cursor],
+        self.maps.timer[cursor],
+        self.maps.hits[cursor],
+        self.maps.owner[cursor],
+        fog.dark,
+        fog.open_door
+      );
+    }
+    for (idx, actor) in self.actors.iter().enumerate() {
+      if actor.pos.cursor() == cursor {
+        dump += &format!(
+          "\n  actor {}: pos ({}, {}), health {}/{}, dead {}, active {}",
+          idx, actor.pos.x, actor.pos.y, actor.health, actor.max_health, actor.is_dead, actor.is_active
+        );
+      }
+    }
+    dump
+  }
+}
+
+/// Values which can have timer set (bombs, animations, etc)
+fn is_timed(value: MapValue) -> bool {
+  matches!(
+    value,
+    MapValue::MetalWall
+      | MapValue::Door
+      | MapValue::ButtonOff
+      | MapValue::ButtonOn
+      | MapValue::MetalWallPlaced
+      | MapValue::JumpingBomb
+      | MapValue::Barrel
+      | MapValue::GrenadeFlyingRight
+      | MapValue::GrenadeFlyingLeft
+      | MapValue::GrenadeFlyingDown
+      | MapValue::GrenadeFlyingUp
+      | MapValue::Atomic1
+      | MapValue::Atomic2
+      | MapValue::Atomic3
+      | MapValue::SmallBomb1
+      | MapValue::SmallBomb2
+      | MapValue::SmallBomb3
+      | MapValue::Mine
+      | MapValue::SmallBombExtinguished
+      | MapValue::SmallCrucifixBomb
+      | MapValue::LargeCrucifixBomb
+      | MapValue::BigBomb1
+      | MapValue::BigBomb2
+      | MapValue::BigBomb3
+      | MapValue::SmallRadioBlue
+      | MapValue::SmallRadioRed
+      | MapValue::SmallRadioGreen
+      | MapValue::SmallRadioYellow
+      | MapValue::ExplosivePlastic
+      | MapValue::BigBombExtinguished
+      | MapValue::Dynamite1
+      | MapValue::Dynamite2
+      | MapValue::Dynamite3
+      | MapValue::BigRadioBlue
+      | MapValue::BigRadioRed
+      | MapValue::BigRadioGreen
+      | MapValue::BigRadioYellow
+      | MapValue::Teleport
+      | MapValue::DynamiteExtinguished
+      | MapValue::ExplosivePlasticBomb
+      | MapValue::DiggerBomb
+      | MapValue::Napalm1
+      | MapValue::Napalm2
+      | MapValue::NapalmExtinguished
+      | MapValue::PlasticBomb
+      | MapValue::MonsterDying
+      | MapValue::MonsterSmoke1
+      | MapValue::MonsterSmoke2
+      | MapValue::SlimeDying
+      | MapValue::SlimeSmoke1
+      | MapValue::SlimeSmoke2
+      | MapValue::NestFurry
+      | MapValue::NestGrenadier
+      | MapValue::NestSlime
+      | MapValue::NestAlien
+      | MapValue::Biomass
+      | MapValue::BiomassHeart
+      | MapValue::Fire
+  )
+}
@@ -0,0 +1,58 @@
+//! Colored keys and locked doors. Locked door only opens for a player carrying the key of the same
+//! color; the key is used up. Buttons don't affect locked doors.
+use crate::effects::SoundEffect;
+use crate::world::map::MapValue;
+use crate::world::position::Cursor;
+use crate::world::{EntityIndex, World};
+use sdl2::pixels::Color;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyColor {
+  Red,
+  Green,
+  Blue,
+  Yellow,
+}
+
+impl KeyColor {
+  /// Color to tint key and door images with
+  pub fn color(self) -> Color {
+    match self {
+      KeyColor::Red => Color::RGB(255, 64, 64),
+      KeyColor::Green => Color::RGB(64, 255, 64),
+      KeyColor::Blue => Color::RGB(96, 96, 255),
+      KeyColor::Yellow => Color::RGB(255, 255, 64),
+    }
+  }
+}
+
+impl World<'_> {
+  /// Pick up the key. Only players can carry keys.
+  pub(super) fn pick_up_key(&mut self, entity: EntityIndex, cursor: Cursor, color: KeyColor) {
+    let player = match self.players.get_mut(entity) {
+      Some(player) => player,
+      None => return,
+    };
+    let keys = &mut player.door_keys[color as usize];
+    *keys = keys.saturating_add(1);
+    self.maps.level[cursor] = MapValue::Passage;
+    self.update.update_cell(cursor);
+    self.play_sound_of(entity, SoundEffect::Picaxe, 11000, cursor);
+  }
+
+  /// Open locked door if the player has the matching key
+  pub(super) fn unlock_door(&mut self, entity: EntityIndex, cursor: Cursor, color: KeyColor) {
+    let keys = match self.players.get_mut(entity) {
+      Some(player) => &mut player.door_keys[color as usize],
+      None => return,
+    };
+    if *keys == 0 {
+      return;
+    }
+    *keys -= 1;
+    self.maps.level[cursor] = MapValue::Passage;
+    self.update.update_cell(cursor);
+    self.update.update_cell_border(cursor);
+    self.play_sound_of(entity, SoundEffect::Kili, 8000, cursor);
+  }
+}
@@ -1,5 +1,6 @@
 use crate::bitmap;
 use crate::bitmap::MapValueSet;
+use crate::world::map::MapValue;
 
 /// Bitmap of which map values are exposing border of surrounding dirt and stones
 pub const DIRT_BORDER_BITMAP: MapValueSet = bitmap!([
@@ -247,3 +248,21 @@ pub const SEE_THROUGH: MapValueSet = bitmap!([
   0b0000_0000,
   0b0000_0000,
 ]);
+
+/// Bombs with a burning fuse (hissing and sparkling until they go off)
+pub const LIT_FUSE: MapValueSet = MapValueSet::from_values(&[
+  MapValue::SmallBomb1,
+  MapValue::SmallBomb2,
+  MapValue::SmallBomb3,
+  MapValue::BigBomb1,
+  MapValue::BigBomb2,
+  MapValue::BigBomb3,
+  MapValue::Dynamite1,
+  MapValue::Dynamite2,
+  MapValue::Dynamite3,
+  MapValue::Napalm1,
+  MapValue::Napalm2,
+  MapValue::Atomic1,
+  MapValue::Atomic2,
+  MapValue::Atomic3,
+]);
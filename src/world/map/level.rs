@@ -1,5 +1,8 @@
-use super::{Map, MAP_COLS, MAP_ROWS};
+use super::{LevelMeta, Map, MAP_COLS, MAP_ROWS};
+use crate::error::ApplicationError;
+use crate::vfs::Vfs;
 use crate::world::actor::ActorKind;
+use crate::world::locks::KeyColor;
 use crate::world::position::{Cursor, Direction};
 use num_enum::TryFromPrimitive;
 use rand::distributions::WeightedIndex;
@@ -9,8 +12,11 @@ use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
-#[error("Invalid map format")]
-pub struct InvalidMap;
+#[error("Invalid map format at byte {offset}")]
+pub struct InvalidMap {
+  /// Offset of the first invalid byte (or the length of the data, if it is too short)
+  pub offset: usize,
+}
 
 #[derive(Debug, Error)]
 #[error("Single player map '{path}' cannot be loaded")]
@@ -41,7 +47,43 @@ const RANDOM_TREASURES_WEIGHTS: [usize; 13] = [18, 12, 8, 200, 200, 200, 200, 20
 
 pub enum LevelInfo {
   Random,
-  File { name: String, map: LevelMap },
+  File {
+    name: String,
+    map: LevelMap,
+    meta: LevelMeta,
+  },
+}
+
+impl LevelInfo {
+  /// Load level from the map file
+  pub fn load_file(path: &Path) -> Result<LevelInfo, anyhow::Error> {
+    let data = std::fs::read(path)?;
+    let meta = LevelMeta::from_file_map(&data);
+    let map = LevelMap::from_file_map(data).map_err(|err| ApplicationError::MapParse {
+      path: path.to_owned(),
+      offset: err.offset,
+    })?;
+    let name = path
+      .file_stem()
+      .map_or_else(String::new, |name| name.to_string_lossy().to_uppercase());
+    Ok(LevelInfo::File { name, map, meta })
+  }
+
+  /// Metadata of the level
+  pub fn meta(&self) -> LevelMeta {
+    match self {
+      LevelInfo::Random => LevelMeta::default(),
+      LevelInfo::File { meta, .. } => meta.clone(),
+    }
+  }
+
+  /// Name of the level to show to the players
+  pub fn name(&self) -> &str {
+    match self {
+      LevelInfo::Random => "Random",
+      LevelInfo::File { name, .. } => name,
+    }
+  }
 }
 
 impl LevelMap {
@@ -56,16 +98,20 @@ impl LevelMap {
   pub fn from_file_map(external_map: Vec<u8>) -> Result<LevelMap, InvalidMap> {
     // Each map is 45 lines 66 bytes each (64 columns plus "\r\n" at the end of each row)
     if external_map.len() < 2970 {
-      return Err(InvalidMap);
+      return Err(InvalidMap {
+        offset: external_map.len(),
+      });
     }
 
     let mut data = Vec::with_capacity(usize::from(MAP_ROWS * MAP_COLS));
     for row in 0..MAP_ROWS {
       // Two last bytes of the row are 0xd 0xa (newline), so 64 + 2 = 66
-      let row = &external_map[usize::from(row * (MAP_COLS + 2))..][..usize::from(MAP_COLS)];
-      for value in row {
+      let start = usize::from(row * (MAP_COLS + 2));
+      let row = &external_map[start..][..usize::from(MAP_COLS)];
+      for (col, value) in row.iter().enumerate() {
         // We could transmute here, but let's avoid all unsafe; amount of data is pretty small.
-        data.push(MapValue::try_from(*value).unwrap());
+        let value = MapValue::try_from(*value).map_err(|_| InvalidMap { offset: start + col })?;
+        data.push(value);
       }
     }
 
@@ -89,58 +135,88 @@ impl LevelMap {
   }
 
   /// Generate randomized map
-  pub fn random_map(treasures: u8) -> Self {
+  pub fn random_map(treasures: u8, rng: &mut impl Rng) -> Self {
     let mut map = LevelMap::empty();
-    map.generate_random_stone();
-    map.finalize_map();
-    map.generate_treasures(treasures);
-    map.generate_random_items();
+    map.generate_random_stone(rng);
+    map.finalize_map(rng);
+    map.generate_treasures(treasures, rng);
+    map.generate_random_items(rng);
     map.generate_borders();
     map
   }
 
   /// Load a campaign level for a given round
-  pub fn prepare_campaign_level(game_dir: &Path, round: u16) -> Result<LevelInfo, CannotLoadSinglePlayer> {
-    let filename = format!("LEVEL{}.MNL", round);
-    let path = game_dir.join(filename);
-    let mut map = std::fs::read(&path)
-      .map_err(anyhow::Error::from)
-      .and_then(|data| LevelMap::from_file_map(data).map_err(anyhow::Error::from))
-      .map_err(|source| CannotLoadSinglePlayer {
-        path: path.to_owned(),
-        source,
-      })?;
-
-    let exit_count = Cursor::all().filter(|cur| map[*cur] == MapValue::Exit).count();
-    let mut rng = rand::thread_rng();
-    let selected = rng.gen_range(0..exit_count);
-    let mut idx = 0;
-    for cur in Cursor::all() {
-      if map[cur] == MapValue::Exit {
-        if idx != selected {
+  pub fn prepare_campaign_level(vfs: &Vfs, round: u16) -> Result<LevelInfo, CannotLoadSinglePlayer> {
+    let (mut map, meta) = LevelMap::load_single_player(vfs, &format!("LEVEL{}.MNL", round))?;
+    // Tags which don't point to any exit of the map are ignored, so level could still be finished
+    let tagged = meta.exits.iter().any(|exit| map[exit.cursor] == MapValue::Exit);
+    if !tagged {
+      // Only one random exit is kept
+      let exit_count = Cursor::all().filter(|cur| map[*cur] == MapValue::Exit).count();
+      let mut rng = rand::thread_rng();
+      let selected = rng.gen_range(0..exit_count);
+      let mut idx = 0;
+      for cur in Cursor::all() {
+        if map[cur] == MapValue::Exit {
+          if idx != selected {
+            map[cur] = MapValue::Passage;
+          }
+          idx += 1;
+        }
+      }
+    } else {
+      // Keep all tagged exits, each leads to its own outcome
+      for cur in Cursor::all() {
+        if map[cur] == MapValue::Exit && meta.exits.iter().all(|exit| exit.cursor != cur) {
           map[cur] = MapValue::Passage;
         }
-        idx += 1;
       }
     }
     Ok(LevelInfo::File {
       name: format!("LEVEL{}", round),
       map,
+      meta,
+    })
+  }
+
+  /// Load a bonus level of the campaign. All exits of the bonus level are kept.
+  pub fn prepare_bonus_level(vfs: &Vfs, bonus: usize) -> Result<LevelInfo, CannotLoadSinglePlayer> {
+    let (map, meta) = LevelMap::load_single_player(vfs, &format!("BONUS{}.MNL", bonus))?;
+    Ok(LevelInfo::File {
+      name: format!("BONUS{}", bonus),
+      map,
+      meta,
     })
   }
 
+  fn load_single_player(vfs: &Vfs, filename: &str) -> Result<(LevelMap, LevelMeta), CannotLoadSinglePlayer> {
+    let file = vfs.read(filename).map_err(|source| CannotLoadSinglePlayer {
+      path: PathBuf::from(filename),
+      source: source.into(),
+    })?;
+    let (path, data) = (file.path, file.data);
+    let meta = LevelMeta::from_file_map(&data);
+    let map = LevelMap::from_file_map(data).map_err(|err| CannotLoadSinglePlayer {
+      path: path.to_owned(),
+      source: ApplicationError::MapParse {
+        path: path.to_owned(),
+        offset: err.offset,
+      }
+      .into(),
+    })?;
+    Ok((map, meta))
+  }
+
   /// Generate random stones on the map. This algorithm is close to the one used in the original
   /// game, but not exactly the same.
-  fn generate_random_stone(&mut self) {
-    let mut rng = rand::thread_rng();
+  fn generate_random_stone(&mut self, rng: &mut impl Rng) {
     for _ in 0..rng.gen_range(29..40) {
-      self.generate_stone_chunk();
+      self.generate_stone_chunk(rng);
     }
   }
 
   /// Generate one single stone chunk
-  fn generate_stone_chunk(&mut self) {
-    let mut rng = rand::thread_rng();
+  fn generate_stone_chunk(&mut self, rng: &mut impl Rng) {
     let mut col = rng.gen_range(1..(MAP_COLS - 1));
     let mut row = rng.gen_range(1..(MAP_ROWS - 1));
     loop {
@@ -216,8 +292,8 @@ impl LevelMap {
         break;
       }
 
-      row = random_offset(row, MAP_ROWS);
-      col = random_offset(col, MAP_COLS);
+      row = random_offset(row, MAP_ROWS, rng);
+      col = random_offset(col, MAP_COLS, rng);
     }
   }
 
@@ -225,9 +301,7 @@ impl LevelMap {
   ///
   /// This function in particular was rewritten a bit compared to the original one (minor changes
   /// to make code more readable, result looks similar).
-  fn finalize_map(&mut self) {
-    let mut rng = rand::thread_rng();
-
+  fn finalize_map(&mut self, rng: &mut impl Rng) {
     // Step 1: replace lonely stones with boulders
     for cursor in Cursor::all_without_borders() {
       if self[cursor].is_stone_like()
@@ -306,25 +380,22 @@ impl LevelMap {
     for cursor in Cursor::all() {
       if self[cursor] == MapValue::Stone1 {
         self[cursor] = *[MapValue::Stone1, MapValue::Stone2, MapValue::Stone3, MapValue::Stone4]
-          .choose(&mut rng)
+          .choose(rng)
           .unwrap();
       } else if self[cursor] == MapValue::Passage {
-        self[cursor] = *[MapValue::Sand1, MapValue::Sand2, MapValue::Sand3]
-          .choose(&mut rng)
-          .unwrap();
+        self[cursor] = *[MapValue::Sand1, MapValue::Sand2, MapValue::Sand3].choose(rng).unwrap();
       }
     }
 
     // Step 5: place gravel
     for _ in 0..300 {
-      let cursor = self.pick_random_coord(MapValue::is_sand);
-      self[cursor] = *[MapValue::LightGravel, MapValue::HeavyGravel].choose(&mut rng).unwrap();
+      let cursor = self.pick_random_coord(MapValue::is_sand, rng);
+      self[cursor] = *[MapValue::LightGravel, MapValue::HeavyGravel].choose(rng).unwrap();
     }
   }
 
   /// Place treasures on the map
-  fn generate_treasures(&mut self, treasures: u8) {
-    let mut rng = rand::thread_rng();
+  fn generate_treasures(&mut self, treasures: u8, rng: &mut impl Rng) {
     // Original game would randomize treasures, but "min treasures" is always the same as
     // "max treasures", so we don't bother calling random.
 
@@ -332,7 +403,7 @@ impl LevelMap {
 
     let mut treasures_in_stone = 0;
     for _ in 0..treasures {
-      let item = RANDOM_TREASURES[distribution.sample(&mut rng)];
+      let item = RANDOM_TREASURES[distribution.sample(rng)];
 
       // Once we placed 20 treasures into stone, we place remaining ones randomly
       if treasures_in_stone > 20 {
@@ -340,7 +411,7 @@ impl LevelMap {
         let row = rng.gen_range(0..MAP_ROWS);
         self[Cursor::new(row, col)] = item;
       } else {
-        let cursor = self.pick_random_coord(MapValue::is_stone);
+        let cursor = self.pick_random_coord(MapValue::is_stone, rng);
         self[cursor] = item;
         treasures_in_stone += 1;
       }
@@ -349,23 +420,22 @@ impl LevelMap {
 
   /// Generate various random items
   /// Note that original game would also place items on borders, but we don't.
-  fn generate_random_items(&mut self) {
-    let mut rng = rand::thread_rng();
+  fn generate_random_items(&mut self, rng: &mut impl Rng) {
     while rng.gen_range(0..100) > 70 {
-      self[random_coord()] = MapValue::Boulder;
+      self[random_coord(rng)] = MapValue::Boulder;
     }
 
     while rng.gen_range(0..100) > 70 {
-      self[random_coord()] = MapValue::WeaponsCrate;
+      self[random_coord(rng)] = MapValue::WeaponsCrate;
     }
 
     while rng.gen_range(0..100) > 65 {
-      self[random_coord()] = MapValue::Medikit;
+      self[random_coord(rng)] = MapValue::Medikit;
     }
 
     while rng.gen_range(0..100) > 70 {
-      self[random_coord()] = MapValue::Teleport;
-      self[random_coord()] = MapValue::Teleport;
+      self[random_coord(rng)] = MapValue::Teleport;
+      self[random_coord(rng)] = MapValue::Teleport;
     }
   }
 
@@ -383,8 +453,8 @@ impl LevelMap {
   }
 
   /// Pick random coordinate such that its map value matches the predicate. Returns row and column.
-  fn pick_random_coord(&self, predicate: impl Fn(MapValue) -> bool) -> Cursor {
-    let mut cursor = random_coord();
+  fn pick_random_coord(&self, predicate: impl Fn(MapValue) -> bool, rng: &mut impl Rng) -> Cursor {
+    let mut cursor = random_coord(rng);
     for _ in 0..MAP_ROWS * MAP_COLS {
       if predicate(self[cursor]) {
         break;
@@ -397,15 +467,13 @@ impl LevelMap {
         cursor.row += 1;
       }
       if cursor.row > MAP_ROWS - 1 {
-        cursor = random_coord();
+        cursor = random_coord(rng);
       }
     }
     cursor
   }
 
-  pub fn generate_entrances(&mut self, players: u8) {
-    let mut rng = rand::thread_rng();
-
+  pub fn generate_entrances(&mut self, players: u8, rng: &mut impl Rng) {
     // Top left
     let rnd = rng.gen_range(4..10);
     for col in 1..=rnd {
@@ -450,8 +518,7 @@ impl LevelMap {
   }
 }
 
-fn random_coord() -> Cursor {
-  let mut rng = rand::thread_rng();
+fn random_coord(rng: &mut impl Rng) -> Cursor {
   let col = rng.gen_range(1..(MAP_COLS - 1));
   let row = rng.gen_range(1..(MAP_ROWS - 1));
   Cursor::new(row, col)
@@ -572,11 +639,13 @@ pub enum MapValue {
   Exit = 0x6B,
   Door = 0x6C,
   Medikit = 0x6D,
-  Map6E = 0x6E,
+  /// Origin of a biomass patch; destroying it kills the whole patch
+  BiomassHeart = 0x6E,
   Biomass = 0x6F,
   StoneLightCracked = 0x70,
   StoneHeavyCracked = 0x71,
-  Map72 = 0x72,
+  /// Lingering fire left by napalm
+  Fire = 0x72,
   Diamond = 0x73,
   Map74 = 0x74,
   Map75 = 0x75,
@@ -599,9 +668,9 @@ pub enum MapValue {
   MonsterDying = 0x85,
   MonsterSmoke1 = 0x86,
   MonsterSmoke2 = 0x87,
-  /// Temporary value used in plastic and digger spreading algorithm
+  /// Temporary value used in plastic and digger spreading algorithm of the original game
   TempMarker1 = 0x88,
-  /// Temporary value used in plastic and digger spreading algorithm
+  /// Temporary value used in plastic and digger spreading algorithm of the original game
   TempMarker2 = 0x89,
   SmallCrucifixBomb = 0x8A,
   BigBomb2 = 0x8B,
@@ -648,18 +717,18 @@ pub enum MapValue {
   ButtonOff = 0xB4,
   ButtonOn = 0xB5,
   Item182 = 0xB6,
-  MapB7 = 0xB7,
-  MapB8 = 0xB8,
-  MapB9 = 0xB9,
-  MapBA = 0xBA,
-  MapBB = 0xBB,
-  MapBC = 0xBC,
-  MapBD = 0xBD,
-  MapBE = 0xBE,
-  MapBF = 0xBF,
-  MapC0 = 0xC0,
-  MapC1 = 0xC1,
-  MapC2 = 0xC2,
+  NestFurry = 0xB7,
+  NestGrenadier = 0xB8,
+  NestSlime = 0xB9,
+  NestAlien = 0xBA,
+  KeyRed = 0xBB,
+  KeyGreen = 0xBC,
+  KeyBlue = 0xBD,
+  KeyYellow = 0xBE,
+  LockedDoorRed = 0xBF,
+  LockedDoorGreen = 0xC0,
+  LockedDoorBlue = 0xC1,
+  LockedDoorYellow = 0xC2,
   MapC3 = 0xC3,
   MapC4 = 0xC4,
   MapC5 = 0xC5,
@@ -774,6 +843,39 @@ impl MapValue {
     matches!(self, MapValue::Passage | MapValue::Blood | MapValue::SlimeCorpse)
   }
 
+  /// If map value is a door key, return its color
+  pub fn key_color(self) -> Option<KeyColor> {
+    match self {
+      MapValue::KeyRed => Some(KeyColor::Red),
+      MapValue::KeyGreen => Some(KeyColor::Green),
+      MapValue::KeyBlue => Some(KeyColor::Blue),
+      MapValue::KeyYellow => Some(KeyColor::Yellow),
+      _ => None,
+    }
+  }
+
+  /// If map value is a locked door, return color of the key opening it
+  pub fn locked_door_color(self) -> Option<KeyColor> {
+    match self {
+      MapValue::LockedDoorRed => Some(KeyColor::Red),
+      MapValue::LockedDoorGreen => Some(KeyColor::Green),
+      MapValue::LockedDoorBlue => Some(KeyColor::Blue),
+      MapValue::LockedDoorYellow => Some(KeyColor::Yellow),
+      _ => None,
+    }
+  }
+
+  /// If map value is a monster nest, return kind of monsters it spawns.
+  pub fn nest(self) -> Option<ActorKind> {
+    match self {
+      MapValue::NestFurry => Some(ActorKind::Furry),
+      MapValue::NestGrenadier => Some(ActorKind::Grenadier),
+      MapValue::NestSlime => Some(ActorKind::Slime),
+      MapValue::NestAlien => Some(ActorKind::Alien),
+      _ => None,
+    }
+  }
+
   /// If map value is a monster, return its actor kind and direction.
   pub fn monster(self) -> Option<(ActorKind, Direction)> {
     Some(match self {
@@ -817,6 +919,24 @@ impl MapValue {
     }
   }
 
+  /// Find the most valuable treasure which is worth no more than the given amount of cash
+  pub fn treasure_worth(cash: u32) -> Option<MapValue> {
+    [
+      MapValue::Diamond,
+      MapValue::GoldCrown,
+      MapValue::GoldRubin,
+      MapValue::GoldScepter,
+      MapValue::GoldCross,
+      MapValue::GoldBar,
+      MapValue::GoldEgg,
+      MapValue::GoldShield,
+      MapValue::GoldBracelet,
+    ]
+    .iter()
+    .copied()
+    .find(|value| value.gold_value() <= cash)
+  }
+
   /// Check if map value is treasure item
   pub fn is_treasure(self) -> bool {
     self.gold_value() > 0
@@ -857,9 +977,7 @@ impl MapValue {
 }
 
 /// Apply random offset to the coordinate
-fn random_offset(mut coord: u16, max: u16) -> u16 {
-  let mut rng = rand::thread_rng();
-
+fn random_offset(mut coord: u16, max: u16, rng: &mut impl Rng) -> u16 {
   // Note: original game uses condition `x < 1` here (for both rows and columns). We use `x < 2` so
   // we never get too close to the border that one of the offsets above go outside of the map.
   if coord < 2 {
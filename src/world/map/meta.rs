@@ -0,0 +1,144 @@
+//! Level metadata ("v2" maps). Metadata is given as `KEY=VALUE` text lines after the map data; the
+//! original game ignores everything after the map, so such maps are still compatible with it.
+use super::{Map, MAP_COLS, MAP_ROWS};
+use crate::world::biomass::BiomassParams;
+use crate::world::position::Cursor;
+use sdl2::pixels::Color;
+
+/// Offset in the map file where metadata starts
+const METADATA_OFFSET: usize = 2970;
+
+/// Cells which cannot be destroyed by explosions, diggers, napalm and so on
+pub type ProtectedMap = Map<bool>;
+
+#[derive(Clone, Default)]
+pub struct LevelMeta {
+  /// Biomass growth parameters
+  pub biomass: BiomassParams,
+  /// Protected cells, given as `PROTECTED=<row> <mask>` lines, where every `#` character in the
+  /// mask marks protected cell in the corresponding column.
+  pub protected: ProtectedMap,
+  /// Overrides rockfall option for this level, given as `ROCKFALL=0` or `ROCKFALL=1`
+  pub rockfall: Option<bool>,
+  /// Gold player needs to collect to unlock the exit in campaign mode, given as `EXIT_GOLD=<amount>`
+  pub exit_gold: u32,
+  /// Tagged exits, given as `EXIT=<row> <col> <id> [<bonus cash>]` lines. If level has tagged exits,
+  /// all of them are kept in campaign mode; campaign manifest tells which level each exit leads to.
+  pub exits: Vec<ExitTag>,
+  /// Alternate glyph sheet used to render the level (for example, an ice theme), given as
+  /// `TILES=<file name>`. Sheet must have the same layout as `SIKA.SPY` and be in the game directory
+  /// (names with paths are ignored).
+  pub tiles: Option<String>,
+  /// Palette overrides for the glyph sheet, given as `PALETTE=<index> <RRGGBB>` lines, where index
+  /// is the palette color (0 to 15) to replace.
+  pub palette: Vec<(u8, Color)>,
+}
+
+#[derive(Clone, Copy)]
+pub struct ExitTag {
+  pub cursor: Cursor,
+  /// Exit identifier, used by the campaign manifest to select the next level
+  pub id: u8,
+  /// Cash given to the player leaving the level through this exit
+  pub bonus: u32,
+}
+
+impl LevelMeta {
+  /// Parse metadata from the map file
+  pub fn from_file_map(data: &[u8]) -> Self {
+    let mut meta = LevelMeta::default();
+    let text = match data.get(METADATA_OFFSET..) {
+      Some(text) => String::from_utf8_lossy(text),
+      None => return meta,
+    };
+    for line in text.lines() {
+      let (key, value) = match line.split_once('=') {
+        Some((key, value)) => (key.trim().to_ascii_uppercase(), value.trim()),
+        None => continue,
+      };
+      match key.as_str() {
+        "BIOMASS_GROWTH" => {
+          if let Ok(value) = value.parse::<u16>() {
+            meta.biomass.growth_interval = value.max(1);
+          }
+        }
+        "BIOMASS_CELLS" => {
+          if let Ok(value) = value.parse() {
+            meta.biomass.max_cells = value;
+          }
+        }
+        "BIOMASS_FLAMMABILITY" => {
+          if let Ok(value) = value.parse::<u8>() {
+            meta.biomass.flammability = value.min(100);
+          }
+        }
+        "PROTECTED" => meta.parse_protected(value),
+        "EXIT" => meta.parse_exit(value),
+        "EXIT_GOLD" => {
+          if let Ok(value) = value.parse() {
+            meta.exit_gold = value;
+          }
+        }
+        "TILES" if is_plain_file_name(value) => meta.tiles = Some(value.to_owned()),
+        "PALETTE" => meta.parse_palette(value),
+        "ROCKFALL" => {
+          if let Ok(value) = value.parse::<u8>() {
+            meta.rockfall = Some(value != 0);
+          }
+        }
+        _ => {}
+      }
+    }
+    meta
+  }
+
+  fn parse_exit(&mut self, value: &str) {
+    let mut it = value.split_whitespace().map(str::parse::<u32>);
+    let (row, col, id) = match (it.next(), it.next(), it.next()) {
+      (Some(Ok(row)), Some(Ok(col)), Some(Ok(id))) if row < u32::from(MAP_ROWS) && col < u32::from(MAP_COLS) => {
+        (row as u16, col as u16, id)
+      }
+      _ => return,
+    };
+    let bonus = it.next().and_then(Result::ok).unwrap_or(0);
+    self.exits.push(ExitTag {
+      cursor: Cursor::new(row, col),
+      id: id.min(u32::from(u8::MAX)) as u8,
+      bonus,
+    });
+  }
+
+  fn parse_palette(&mut self, value: &str) {
+    let (index, color) = match value.split_once(' ') {
+      Some((index, color)) => (index.parse::<u8>(), u32::from_str_radix(color.trim(), 16)),
+      None => return,
+    };
+    if let (Ok(index @ 0..=15), Ok(color @ 0..=0xff_ffff)) = (index, color) {
+      let [_, r, g, b] = color.to_be_bytes();
+      self.palette.push((index, Color::RGB(r, g, b)));
+    }
+  }
+
+  /// Check if level is rendered differently from the regular levels
+  pub fn has_skin(&self) -> bool {
+    self.tiles.is_some() || !self.palette.is_empty()
+  }
+
+  fn parse_protected(&mut self, value: &str) {
+    let (row, mask) = value.split_once(' ').unwrap_or((value, ""));
+    let row = match row.parse::<u16>() {
+      Ok(row) if row < MAP_ROWS => row,
+      _ => return,
+    };
+    for (col, ch) in mask.trim().chars().take(usize::from(MAP_COLS)).enumerate() {
+      if ch == '#' {
+        self.protected[Cursor::new(row, col as u16)] = true;
+      }
+    }
+  }
+}
+
+/// Check if the file name refers to a file in the game directory itself
+fn is_plain_file_name(name: &str) -> bool {
+  !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', ':'])
+}
@@ -1,15 +1,23 @@
 mod bitmaps;
 mod level;
+mod meta;
 
 pub const MAP_ROWS: u16 = 45;
 pub const MAP_COLS: u16 = 64;
 
+/// Initial "health" of the monster nest
+pub const NEST_HITS: i32 = 6000;
+
+/// Amount of ticks between monster nest spawning new monsters
+pub const NEST_SPAWN_INTERVAL: u16 = 400;
+
 use crate::world::position::Cursor;
 pub use bitmaps::{
-  CANNOT_PLACE_BOMB, CAN_EXTINGUISH, DIRT_BORDER_BITMAP, DOOR_EXPLODES_ENTITY, EXTINGUISHER_PASSABLE, PUSHABLE_BITMAP,
-  SEE_THROUGH,
+  CANNOT_PLACE_BOMB, CAN_EXTINGUISH, DIRT_BORDER_BITMAP, DOOR_EXPLODES_ENTITY, EXTINGUISHER_PASSABLE, LIT_FUSE,
+  PUSHABLE_BITMAP, SEE_THROUGH,
 };
 pub use level::{InvalidMap, LevelInfo, LevelMap, MapValue};
+pub use meta::{ExitTag, LevelMeta, ProtectedMap};
 use rand::prelude::*;
 use ref_cast::RefCast;
 
@@ -104,16 +112,32 @@ fn hits(value: MapValue) -> i32 {
     MapValue::Stone2 => 2150,
     MapValue::Stone3 => 2200,
     MapValue::Stone4 => 2100,
-    MapValue::Plastic | MapValue::Biomass => 400,
+    MapValue::Plastic | MapValue::Biomass | MapValue::BiomassHeart => 400,
     MapValue::StoneLightCracked => 1000,
     MapValue::StoneHeavyCracked => 500,
     MapValue::Brick => 8000,
     MapValue::BrickLightCracked => 4000,
     MapValue::BrickHeavyCracked => 2000,
+    MapValue::NestFurry | MapValue::NestGrenadier | MapValue::NestSlime | MapValue::NestAlien => NEST_HITS,
     _ => 0,
   }
 }
 
+// Owner map
+
+/// Tracks which player placed an active entity (bomb, mine, etc) in the cell
+pub type OwnerMap = Map<Option<usize>>;
+
+// Patch map
+
+/// Tracks which biomass patch the cell belongs to
+pub type PatchMap = Map<Option<u16>>;
+
+// Corpse map
+
+/// Corpses of the despawned actors, restored when something moves out of the cell
+pub type CorpseMap = Map<Option<MapValue>>;
+
 // Fog map
 
 pub type FogMap = Map<FogValue>;
@@ -143,19 +167,34 @@ impl Default for FogValue {
   }
 }
 
+// Visual map
+
+/// Transient visual effects (explosions, smoke) played over the level map. These are purely visual
+/// and never change what is in the cell.
+pub type VisualMap = Map<Option<Visual>>;
+
+#[derive(Clone, Copy)]
+pub struct Visual {
+  /// Map value to render instead of the cell content
+  pub frame: MapValue,
+  /// Ticks until the next frame
+  pub timer: u16,
+}
+
 // Timer map
 
 pub type TimerMap = Map<u16>;
 
 impl Map<MapValue> {
-  pub fn generate_timer_map(&self) -> TimerMap {
-    let mut rng = rand::thread_rng();
+  pub fn generate_timer_map(&self, rng: &mut impl Rng) -> TimerMap {
     let mut map = Map {
       data: vec![0; usize::from(MAP_COLS * MAP_ROWS)],
     };
     for cursor in Cursor::all() {
-      if self[cursor] == MapValue::Biomass {
+      if self[cursor] == MapValue::Biomass || self[cursor] == MapValue::BiomassHeart {
         map[cursor] = rng.gen_range(0..30);
+      } else if self[cursor].nest().is_some() {
+        map[cursor] = rng.gen_range(1..=NEST_SPAWN_INTERVAL);
       }
     }
     map
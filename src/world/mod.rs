@@ -1,30 +1,64 @@
 use crate::effects::SoundEffect;
 use crate::glyphs::Digging;
-use crate::keys::Key;
-use crate::world::actor::{ActorComponent, ActorKind, Player};
+use crate::keys::{ControlStyle, Key};
+use crate::options::Options;
+use crate::world::actor::{ActorComponent, ActorKind, PathRecorder, Player};
+use crate::world::biomass::{is_biomass, BiomassParams, BiomassPatch, BIOMASS_HITS};
+use crate::world::death::DeathAnimation;
 use crate::world::equipment::Equipment;
 use crate::world::map::{
-  FogMap, HitsMap, LevelMap, MapValue, TimerMap, CANNOT_PLACE_BOMB, CAN_EXTINGUISH, DOOR_EXPLODES_ENTITY,
-  EXTINGUISHER_PASSABLE, PUSHABLE_BITMAP, SEE_THROUGH,
+  CorpseMap, ExitTag, FogMap, HitsMap, LevelMap, LevelMeta, Map, MapValue, OwnerMap, PatchMap, ProtectedMap, TimerMap,
+  VisualMap, CANNOT_PLACE_BOMB, CAN_EXTINGUISH, DOOR_EXPLODES_ENTITY, EXTINGUISHER_PASSABLE, LIT_FUSE, MAP_COLS,
+  MAP_ROWS, PUSHABLE_BITMAP, SEE_THROUGH,
 };
+use crate::world::occupancy::Occupancy;
 use crate::world::player::PlayerComponent;
 use crate::world::position::{Cursor, Direction, Position};
+use crate::world::profile::{Subsystem, TickProfile};
+use crate::world::rng::WorldRng;
+use crate::world::scratch::{clear_bounded, VecPool};
+use crate::world::trader::TradeOffer;
+use crate::world::tuning::Tuning;
 use rand::prelude::*;
 
 pub mod actor;
+pub mod biomass;
+mod death;
 pub mod equipment;
 mod explode;
+mod fire;
+#[cfg(debug_assertions)]
+mod invariants;
+pub mod locks;
 pub mod map;
 mod monster;
+mod occupancy;
 pub mod player;
 pub mod position;
+pub mod profile;
+mod push;
+pub mod rng;
+mod rockfall;
+pub mod scratch;
+pub mod settlement;
+pub mod snapshot;
+mod trader;
+pub mod tuning;
+mod visual;
 
 pub struct Maps {
   pub darkness: bool,
+  /// Exit doesn't work until enough gold is collected
+  pub exit_locked: bool,
   pub timer: TimerMap,
   pub level: LevelMap,
   pub hits: HitsMap,
   pub fog: FogMap,
+  pub owner: OwnerMap,
+  pub patch: PatchMap,
+  pub corpse: CorpseMap,
+  pub protected: ProtectedMap,
+  pub visual: VisualMap,
 }
 
 pub struct World<'p> {
@@ -36,7 +70,10 @@ pub struct World<'p> {
   pub actors: Vec<ActorComponent>,
   /// If atomic flash should be displayed
   pub flash: bool,
+  /// For how many more ticks screen is shaking
   pub shake: u16,
+  /// Intensity (in percents) of the current shake, depends on how close players are to the blast
+  shake_intensity: u16,
   /// Frame counter. Incremented by 1 each tick. Not every process is invoked on every tick.
   pub round_counter: usize,
   /// Counter for the "end of round" condition
@@ -45,18 +82,110 @@ pub struct World<'p> {
   pub update: UpdateQueue,
   /// Sound effects to play
   pub effects: SoundEffectsQueue,
+  /// Notable gameplay events (kills, finds)
+  pub events: GameEventQueue,
   /// Damage percentage (0..100)
   pub bomb_damage: u8,
-  /// If exit was triggered (single player mode)
-  pub exited: bool,
+  /// Explosion damage decreases with the distance from the center
+  pub realistic_damage: bool,
+  /// Identifier of the exit player left the level through (single player mode). Untagged exits
+  /// have identifier 0.
+  pub exited: Option<u8>,
+  /// Tagged exits of the level
+  exits: Vec<ExitTag>,
+  /// Bonus round: no monsters, exit is always open and round ends once time is up (single player
+  /// mode)
+  pub bonus_round: bool,
+  /// Ticks left until the end of the bonus round
+  bonus_ticks_left: usize,
+  /// Ticks left until the level time limit runs out (campaign mode)
+  time_limit_ticks_left: Option<usize>,
+  /// Time limit of the level ran out: monsters move at full speed and always chase players
+  pub enraged: bool,
+  /// Game constants tweaked in practice mode
+  pub tuning: Tuning,
+  /// Source of all random decisions of the simulation
+  rng: WorldRng,
+  /// Movement accumulated by each player, in percents of a single step
+  move_budget: [u16; 4],
+  /// Gold player needs to collect before exit unlocks (single player mode)
+  pub exit_gold: u32,
+  /// Maximum amount of alive clones per player (0 is unlimited)
+  pub clone_cap: u8,
+  /// Maximum amount of live monsters (0 is unlimited)
+  pub monster_cap: u8,
+  /// Clones carry collected gold until they touch their owner
+  pub clone_carry_gold: bool,
+  /// Items to drop once death animation in the cell is over
+  drops: Vec<(Cursor, MapValue)>,
+  /// Death animations currently playing over the map
+  death_animations: Vec<DeathAnimation>,
+  /// Monsters drop loot when killed
+  pub monster_loot: bool,
+  /// Monsters are less aggressive: they only look for players to chase half as often
+  pub calm_monsters: bool,
+  /// Player responsible for the explosion currently being processed
+  attacker: Option<EntityIndex>,
+  /// For how many input ticks each player is holding the remote key
+  remote_held: [usize; 4],
+  /// For how many input ticks each player is holding the key of the direction they face (tap-to-turn
+  /// controls)
+  move_held: [usize; 4],
+  /// Area covering remote bombs of each player; remote key only scans that area
+  remote_area: [Option<(Cursor, Cursor)>; 4],
+  /// Area covering all doors and buttons of the level
+  door_area: Option<(Cursor, Cursor)>,
+  /// Bombs with a burning fuse, collected on every tick
+  fuses: Vec<Cursor>,
+  /// Scratch map of cells claimed by the expansions currently in progress (plastic, napalm, etc)
+  claimed: Map<bool>,
+  /// Players slowly regenerate health when not hurt for a while
+  pub regeneration: bool,
+  /// Tick on which each player was hurt last time
+  last_hurt: [usize; 4],
+  /// Actors to remove from the world at the beginning of the next tick
+  despawned: Vec<EntityIndex>,
+  /// If wandering traders should spawn
+  pub traders: bool,
+  /// Two boulders in a row can be pushed together and pushed boulders crush monsters
+  pub push_chains: bool,
+  /// Boulders fall down into passages opened beneath them
+  pub rockfall: bool,
+  /// Boulders which were falling during the last rockfall pass
+  falling: Vec<Cursor>,
+  /// Offer of the trader currently wandering the map
+  trade_offer: Option<TradeOffer>,
+  /// Which players are standing next to the trader
+  trading: [bool; 4],
+  /// Landing spots jumping bombs selected for their next jump
+  jump_targets: Vec<JumpTarget>,
+  /// Biomass growth parameters of the current level
+  pub biomass: BiomassParams,
+  /// Biomass patches grow from a heart, destroying it kills the whole patch
+  pub biomass_hearts: bool,
+  /// Triggered radio bombs are pinged on the map before going off
+  radio_ping: bool,
+  /// Biomass patches, indexed by the values of the patch map
+  biomass_patches: Vec<BiomassPatch>,
+  /// Time spent in the world subsystems (only measured when enabled)
+  pub profile: TickProfile,
+  /// Reusable buffers of the map expansion (explosions, digging and so on)
+  cursors: VecPool<Cursor>,
+  /// Actors by the cell they are in
+  occupancy: Occupancy,
+  #[cfg(debug_assertions)]
+  invariants: invariants::InvariantsState,
 }
 
 /// Request to play sound effect at a given frequency and location
+#[derive(Clone, Copy)]
 pub struct SoundRequest {
   pub effect: SoundEffect,
   pub frequency: i32,
   /// Position to play the effect in the world
   pub location: Cursor,
+  /// Player whose own action caused the sound
+  pub source: Option<EntityIndex>,
 }
 
 #[derive(Default)]
@@ -64,45 +193,168 @@ pub struct SoundEffectsQueue {
   pub queue: Vec<SoundRequest>,
 }
 
+impl SoundEffectsQueue {
+  /// Clear the queue once all sounds are played, keeping its storage for the next tick
+  pub fn clear(&mut self) {
+    clear_bounded(&mut self.queue);
+  }
+}
+
+impl SoundRequest {
+  /// Sound as heard in the darkness: if it comes from the unrevealed cell, stereo position is only
+  /// known up to one of the three zones of the map and the pitch is slightly off. Sounds of the own
+  /// actions of the listening players are always precise.
+  pub fn muffled(&self, maps: &Maps, viewer: Viewer) -> SoundRequest {
+    let own = match viewer {
+      Viewer::Shared => self.source.is_some(),
+      Viewer::Player(player) => self.source == Some(player),
+    };
+    if own || !maps.darkness || !maps.fog[self.location].dark {
+      return *self;
+    }
+    let zone = MAP_COLS / 3;
+    let col = ((self.location.col / zone) * zone + zone / 2).min(MAP_COLS - 1);
+    let frequency = self.frequency * rand::thread_rng().gen_range(90..=110) / 100;
+    SoundRequest {
+      effect: self.effect,
+      frequency,
+      location: Cursor::new(self.location.row, col),
+      source: self.source,
+    }
+  }
+}
+
 impl SoundEffectsQueue {
   fn play(&mut self, effect: SoundEffect, frequency: i32, location: Cursor) {
+    self.play_by(effect, frequency, location, None);
+  }
+
+  fn play_by(&mut self, effect: SoundEffect, frequency: i32, location: Cursor, source: Option<EntityIndex>) {
     self.queue.push(SoundRequest {
       effect,
       frequency,
       location,
+      source,
     });
   }
 }
 
+/// Notable gameplay event, reported to the observers outside of the world (like kill feed)
+#[derive(Clone, Copy, Debug)]
+pub enum GameEvent {
+  /// Player was killed; killer is the player who caused the death (could be the victim themselves)
+  PlayerKilled {
+    victim: EntityIndex,
+    killer: Option<EntityIndex>,
+  },
+  /// Player picked up a treasure
+  TreasureFound { player: EntityIndex, value: MapValue },
+  /// Entity (bomb, barrel, etc) was activated in the cell
+  Exploded { cursor: Cursor },
+  /// Cell was dug through
+  Dug { cursor: Cursor },
+}
+
+#[derive(Default)]
+pub struct GameEventQueue {
+  pub queue: Vec<GameEvent>,
+}
+
+impl GameEventQueue {
+  fn push(&mut self, event: GameEvent) {
+    self.queue.push(event);
+  }
+
+  /// Clear the queue once all events are processed, keeping its storage for the next tick
+  pub fn clear(&mut self) {
+    clear_bounded(&mut self.queue);
+  }
+}
+
 pub type EntityIndex = usize;
 
+/// Amount of ticks radio bombs are pinged on the map between pressing the remote key and detonation
+const REMOTE_PING_TICKS: u16 = 6;
+
+/// Sparks of lit bombs are redrawn every that many ticks
+const SPARK_INTERVAL: usize = 3;
+
+/// Fuses further than that many cells from every player are not heard
+const FUSE_HEARING_DISTANCE: u32 = 20;
+
+/// Amount of input ticks player needs to hold remote key to recall all clones
+const REMOTE_HOLD_RECALL: usize = 25;
+
+/// Amount of input ticks player needs to hold direction key to start moving (tap-to-turn controls)
+const MOVE_HOLD_TICKS: usize = 3;
+
+/// Amount of health restored by a bandage
+const BANDAGE_HEALING: u16 = 25;
+
+/// Players start regenerating health if not hurt for that many ticks (10 seconds)
+const REGEN_DELAY: usize = 500;
+
+/// How often (in ticks) regenerating players get their health back
+const REGEN_INTERVAL: usize = 50;
+
+/// Amount of health regenerated every `REGEN_INTERVAL` ticks
+const REGEN_AMOUNT: u16 = 2;
+
+/// Game runs at 50 ticks per second
+pub const TICKS_PER_SECOND: usize = 50;
+
+/// Cash given for each second left when leaving the bonus level through the exit
+const BONUS_SECOND_CASH: u32 = 10;
+
+/// How often (in ticks) falling boulders move one cell down
+const ROCKFALL_INTERVAL: usize = 4;
+
 impl<'p> World<'p> {
   pub fn create(
     mut level: LevelMap,
     players: &'p mut [PlayerComponent],
     darkness: bool,
-    bomb_damage: u8,
+    options: &Options,
     campaign_mode: bool,
+    meta: LevelMeta,
+    mut rng: WorldRng,
   ) -> Self {
-    let mut actors = spawn_actors(&mut level, players.len(), campaign_mode);
+    let mut actors = spawn_actors(&mut level, players.len(), campaign_mode, &mut rng);
 
     // Initialize players health and drilling power
     for (player_idx, player) in players.iter_mut().enumerate() {
       let actor = &mut actors[player_idx];
-      actor.max_health = player.initial_health();
+      actor.max_health = player.initial_health(options.armor_model);
       actor.health = actor.max_health;
+      actor.max_armor = player.initial_armor(options.armor_model);
+      actor.armor = actor.max_armor;
       actor.drilling = 1 + player.initial_drilling_power();
 
       // Reset player armor count
       player.inventory[Equipment::Armor] = 0;
+      player.door_keys = [0; 4];
     }
 
-    World {
+    let remote_area =
+      [0, 1, 2, 3].map(|player| Cursor::bounds(Cursor::all().filter(|c| is_remote_for(level[*c], player))));
+    let door_area = Cursor::bounds(Cursor::all().filter(|cursor| {
+      matches!(
+        level[*cursor],
+        MapValue::Door | MapValue::ButtonOff | MapValue::ButtonOn
+      )
+    }));
+    let mut world = World {
       maps: Maps {
         darkness,
-        timer: level.generate_timer_map(),
+        exit_locked: campaign_mode && meta.exit_gold > 0,
+        timer: level.generate_timer_map(&mut rng),
         hits: level.generate_hits_map(),
         fog: FogMap::default(),
+        owner: OwnerMap::default(),
+        patch: PatchMap::default(),
+        corpse: CorpseMap::default(),
+        protected: meta.protected,
+        visual: VisualMap::default(),
         level,
       },
       campaign_mode,
@@ -110,13 +362,69 @@ impl<'p> World<'p> {
       actors,
       flash: false,
       shake: 0,
+      shake_intensity: 0,
       round_counter: 0,
       end_round_counter: 0,
       update: Default::default(),
       effects: Default::default(),
-      bomb_damage,
-      exited: false,
+      events: Default::default(),
+      bomb_damage: options.bomb_damage,
+      realistic_damage: options.realistic_damage,
+      exited: None,
+      exits: meta.exits,
+      bonus_round: false,
+      bonus_ticks_left: 0,
+      time_limit_ticks_left: None,
+      enraged: false,
+      tuning: Tuning::default(),
+      move_budget: [0; 4],
+      exit_gold: if campaign_mode { meta.exit_gold } else { 0 },
+      clone_cap: options.clone_cap,
+      monster_cap: options.monster_cap,
+      clone_carry_gold: options.clone_carry_gold,
+      drops: Vec::new(),
+      death_animations: Vec::new(),
+      monster_loot: options.monster_loot,
+      calm_monsters: false,
+      attacker: None,
+      remote_held: [0; 4],
+      move_held: [0; 4],
+      remote_area,
+      door_area,
+      fuses: Vec::new(),
+      claimed: Map::default(),
+      regeneration: options.regeneration && !campaign_mode,
+      last_hurt: [0; 4],
+      despawned: Vec::new(),
+      traders: options.traders,
+      push_chains: options.push_chains,
+      rockfall: meta.rockfall.unwrap_or(options.rockfall),
+      falling: Vec::new(),
+      trade_offer: None,
+      trading: [false; 4],
+      jump_targets: Vec::new(),
+      biomass: meta.biomass,
+      biomass_hearts: options.biomass_hearts,
+      radio_ping: options.radio_ping,
+      biomass_patches: Vec::new(),
+      profile: TickProfile::default(),
+      cursors: VecPool::default(),
+      occupancy: Occupancy::default(),
+      rng,
+      #[cfg(debug_assertions)]
+      invariants: Default::default(),
+    };
+    world.occupancy.rebuild(&world.actors);
+    world.init_biomass_patches();
+    if world.maps.exit_locked {
+      world.redraw_exits();
     }
+    world
+  }
+
+  /// Actors in the given cell (including dead ones), in the order of their indices
+  pub fn actors_at(&self, cursor: Cursor) -> impl Iterator<Item = EntityIndex> + '_ {
+    self.occupancy.at(cursor).iter().copied()
   }
 
   /// Get player component if given entity is a player
@@ -132,31 +440,126 @@ impl<'p> World<'p> {
       .count()
   }
 
+  /// Iterate through alive clones owned by the given player
+  pub fn player_clones(&self, player: EntityIndex) -> impl Iterator<Item = EntityIndex> + '_ {
+    (self.players.len()..self.actors.len()).filter(move |idx| {
+      let actor = &self.actors[*idx];
+      !actor.is_dead && actor.kind.clone_owner() == Some(player) && !self.despawned.contains(idx)
+    })
+  }
+
+  /// Track keys which player is holding down. Invoked on every input tick.
+  pub fn player_key_held(&mut self, player: usize, key: Key, held: bool) {
+    match key_direction(key) {
+      Some(direction) => self.direction_key_held(player, direction, held),
+      None if key == Key::Remote => self.remote_key_held(player, held),
+      None => {}
+    }
+  }
+
+  /// With tap-to-turn controls, player only moves while direction key is held down
+  fn direction_key_held(&mut self, player: usize, direction: Direction, held: bool) {
+    let actor = &mut self.actors[player];
+    if self.players[player].keys.style != ControlStyle::TurnThenMove || actor.is_dead || actor.facing != direction {
+      return;
+    }
+    if !held {
+      if self.move_held[player] > 0 {
+        // Key was released
+        actor.moving = false;
+      }
+      self.move_held[player] = 0;
+      return;
+    }
+    self.move_held[player] += 1;
+    if self.move_held[player] >= MOVE_HOLD_TICKS {
+      actor.moving = true;
+    }
+  }
+
+  fn remote_key_held(&mut self, player: usize, held: bool) {
+    if !held || self.actors[player].is_dead {
+      self.remote_held[player] = 0;
+      return;
+    }
+    self.remote_held[player] += 1;
+    if self.remote_held[player] == REMOTE_HOLD_RECALL {
+      self.recall_clones(player);
+    }
+  }
+
+  /// Remove all clones of the player, refunding half of the clone price for each one of them
+  fn recall_clones(&mut self, player: EntityIndex) {
+    let clones: Vec<EntityIndex> = self.player_clones(player).collect();
+    if clones.is_empty() {
+      return;
+    }
+    let cash_player = if self.campaign_mode { 0 } else { player };
+    self.players[cash_player].cash += (clones.len() as u32) * Equipment::Clone.base_price() / 2;
+    for clone in clones {
+      // Recalled clones bring all the gold they carry
+      self.actors[cash_player].accumulated_cash += self.actors[clone].accumulated_cash;
+      self.actors[clone].accumulated_cash = 0;
+      self.despawn_actor(clone);
+    }
+    self
+      .effects
+      .play(SoundEffect::Picaxe, 8000, self.actors[player].pos.cursor());
+    self.update.update_player_stats(player);
+  }
+
+  /// Schedule actor to be removed from the world. Removal is delayed till the next tick, so queued
+  /// view updates still refer to the correct actors.
+  fn despawn_actor(&mut self, actor: EntityIndex) {
+    if !self.despawned.contains(&actor) {
+      self.despawned.push(actor);
+    }
+  }
+
+  /// Despawn dead monsters and clones once their death animation is over, so the list of actors
+  /// doesn't grow during the long rounds. Their corpses are kept in the corpse map.
+  fn despawn_dead(&mut self) {
+    for idx in self.players.len()..self.actors.len() {
+      let actor = &self.actors[idx];
+      let cursor = actor.pos.cursor();
+      if actor.is_dead && self.death_overlay(cursor).is_none() {
+        self.maps.corpse[cursor] = Some(actor.kind.blood_value());
+        self.despawn_actor(idx);
+      }
+    }
+  }
+
+  /// Remove actors scheduled for despawn
+  fn remove_despawned(&mut self) {
+    if self.despawned.is_empty() {
+      return;
+    }
+    self.despawned.sort_unstable();
+    while let Some(idx) = self.despawned.pop() {
+      let cursor = self.actors.remove(idx).pos.cursor();
+      for cur in std::iter::once(cursor).chain(Direction::all().map(|dir| cursor.to(dir))) {
+        self.update.update_cell(cur);
+      }
+    }
+    // Indices of the following actors have shifted
+    self.occupancy.rebuild(&self.actors);
+  }
+
   pub fn player_action(&mut self, player: usize, key: Key) {
     if self.actors[player].is_dead {
       // Dead players cannot do any actions
       return;
     }
-    let mut direction = None;
     let selection = self.players[player].selection;
     match key {
-      Key::Up => {
-        direction = Some(Direction::Up);
-      }
-      Key::Down => {
-        direction = Some(Direction::Down);
-      }
-      Key::Left => {
-        direction = Some(Direction::Left);
-      }
-      Key::Right => {
-        direction = Some(Direction::Right);
-      }
+      Key::Up | Key::Down | Key::Left | Key::Right => {}
       Key::Stop => {
         self.actors[player].moving = false;
       }
       Key::Bomb => {
-        self.activate_item(player);
+        if !self.trade(player) {
+          self.activate_item(player);
+        }
       }
       Key::Choose => {
         let inventory = &self.players[player].inventory;
@@ -170,38 +573,77 @@ impl<'p> World<'p> {
         self.update.update_player_selection(player);
       }
       Key::Remote => {
-        for cursor in Cursor::all() {
-          // Activate remote bombs for the player
-          if is_remote_for(self.maps.level[cursor], player) {
-            self.maps.timer[cursor] = 1;
+        // All remote bombs of the player go off, so we can forget about their area
+        if let Some((top_left, bottom_right)) = self.remote_area[player].take() {
+          for cursor in Cursor::rect(top_left, bottom_right) {
+            // Activate remote bombs for the player; if enabled, they are pinged on the map for a
+            // moment before going off.
+            if is_remote_for(self.maps.level[cursor], player) {
+              self.maps.timer[cursor] = if self.radio_ping { REMOTE_PING_TICKS } else { 1 };
+              self.update.update_cell_for(player, cursor);
+            }
           }
         }
+        self.update.update_player_selection(player);
       }
     }
-    if let Some(direction) = direction {
+    if let Some(direction) = key_direction(key) {
       let actor = &mut self.actors[player];
+      match self.players[player].keys.style {
+        ControlStyle::Classic => actor.moving = true,
+        // Tap only turns the player (unless it is already moving that way), holding the key moves
+        ControlStyle::TurnThenMove if actor.moving && actor.facing == direction => {}
+        ControlStyle::TurnThenMove => {
+          actor.moving = false;
+          self.move_held[player] = 0;
+        }
+      }
       actor.facing = direction;
-      actor.moving = true;
     }
   }
 
   /// Run on tick of update for the world state
   pub fn tick(&mut self) {
     self.flash = false;
+    self.remove_despawned();
 
     if self.round_counter % 18 == 0 {
       self.update_super_drill();
     }
 
+    let started = self.profile.start();
     self.tick_bombs();
+    self.profile.record(Subsystem::Bombs, started);
+    if self.bonus_round {
+      self.tick_bonus_time();
+    } else if self.time_limit_ticks_left.is_some() {
+      self.tick_time_limit();
+    }
+    if self.maps.exit_locked && self.collected_gold() >= self.exit_gold {
+      self.unlock_exit();
+    }
+    if self.rockfall && self.round_counter % ROCKFALL_INTERVAL == 0 {
+      self.settle_boulders();
+    }
+    self.animate_visuals();
+    self.animate_deaths();
+    self.despawn_dead();
+    self.check_biomass_hearts();
     if self.shake > 0 {
       self.shake -= 1;
+    } else {
+      self.shake_intensity = 0;
+    }
+
+    if self.regeneration && self.round_counter % REGEN_INTERVAL == 0 {
+      self.regenerate_players();
     }
 
     if self.round_counter % 5 == 0 {
       if self.campaign_mode {
         if self.alive_players() == 0 {
-          if self.end_round_counter == 0 {
+          // Dying in the bonus round doesn't cost a life
+          if self.end_round_counter == 0 && !self.bonus_round {
             self.players[0].lives -= 1;
             self.update.update_player_lives();
           }
@@ -213,7 +655,9 @@ impl<'p> World<'p> {
     }
 
     // Animate players
+    let started = self.profile.start();
     self.animate_players();
+    self.profile.record(Subsystem::Players, started);
 
     if self.round_counter % 2 == 0 {
       self.check_dead_players();
@@ -225,77 +669,37 @@ impl<'p> World<'p> {
       self.monsters_detect_players();
     }
 
+    let started = self.profile.start();
     self.animate_monsters();
+    self.profile.record(Subsystem::Monsters, started);
+    self.maybe_spawn_trader();
+    self.update_trade_range();
+    self.record_paths();
 
     if self.round_counter % 20 == 0 && !self.campaign_mode && self.gold_remaining() == 0 {
       self.end_round_counter += 20;
     }
     self.round_counter += 1;
-  }
-
-  /// Apply end of round rules (apply interest, commit collected cash, etc)
-  pub fn end_of_round(&mut self) {
-    // Apply interest on all existing cash
-    for player in self.players.iter_mut() {
-      // add 7% of cash
-      player.cash = (107 * player.cash + 50) / 100;
-    }
+    self.profile.end_tick();
 
-    if self.campaign_mode {
-      // In single player, we never lose money, even if we die
-      self.players[0].cash += self.actors[0].accumulated_cash;
-    } else {
-      self.distribute_money();
-    }
-
-    for (idx, player) in self.players.iter_mut().enumerate() {
-      player.stats.total_money += self.actors[idx].accumulated_cash;
-      self.actors[idx].accumulated_cash = 0;
-      player.stats.rounds += 1;
-    }
+    #[cfg(debug_assertions)]
+    self.check_invariants();
   }
 
-  /// Distribute money in a multiplayer mode
-  fn distribute_money(&mut self) {
-    let mut lost_money: u32 = self.actors[0..self.players.len()]
-      .iter()
-      .filter(|actor| actor.is_dead)
-      .map(|actor| actor.accumulated_cash)
-      .sum();
-    let alive_players = self.actors[0..self.players.len()]
-      .iter()
-      .filter(|actor| !actor.is_dead)
-      .count();
-    if alive_players == 1 {
-      // If only one player is alive, take 40% of the remaining money on the level
-      lost_money += self.gold_remaining() * 2 / 5;
-    }
-
-    let total_players = self.players.len();
-    for (idx, player) in self.players.iter_mut().enumerate() {
-      let actor = &mut self.actors[idx];
-      if !actor.is_dead {
-        player.cash += lost_money / (alive_players as u32) + actor.accumulated_cash;
-
-        if alive_players != total_players {
-          player.rounds_win += 1;
-          player.stats.rounds_wins += 1;
-        }
-      }
-
-      if player.cash < 100 {
-        player.cash += 150;
-      }
+  /// Record current positions of all living actors for the post-round review
+  fn record_paths(&mut self) {
+    for actor in self.actors.iter_mut().filter(|actor| !actor.is_dead) {
+      actor.path.record(actor.pos.cursor());
     }
   }
 
   /// Check end-of-round condition
   pub fn is_end_of_round(&self) -> bool {
-    self.exited || self.end_round_counter > 100
+    self.exited.is_some() || self.end_round_counter > 100
   }
 
   /// Check if still has gold remaining in the level
-  fn gold_remaining(&self) -> u32 {
+  pub(super) fn gold_remaining(&self) -> u32 {
     let mut total = 0;
     for cursor in Cursor::all() {
       total += self.maps.level[cursor].gold_value();
@@ -307,9 +711,14 @@ impl<'p> World<'p> {
   fn animate_players(&mut self) {
     for monster in 0..self.players.len() {
       if !self.actors[monster].is_dead {
-        self.animate_actor(monster);
-        if self.actors[monster].super_drill_count > 0 {
+        // With normal speed, players make exactly one step every tick
+        self.move_budget[monster] += self.tuning.player_speed;
+        while self.move_budget[monster] >= 100 {
+          self.move_budget[monster] -= 100;
           self.animate_actor(monster);
+          if self.actors[monster].super_drill_count > 0 {
+            self.animate_actor(monster);
+          }
         }
       }
     }
@@ -321,6 +730,10 @@ impl<'p> World<'p> {
       if !actor.is_dead && actor.health < 1 {
         self.players[player].stats.deaths += 1;
         actor.is_dead = true;
+        self.events.push(GameEvent::PlayerKilled {
+          victim: player,
+          killer: None,
+        });
         self.effects.play(SoundEffect::Aargh, 11000, actor.pos.cursor());
         let cursor = actor.pos.cursor();
         self.maps.level[cursor] = MapValue::Blood;
@@ -342,6 +755,7 @@ impl<'p> World<'p> {
 
   /// Update bombs state
   fn tick_bombs(&mut self) {
+    self.fuses.clear();
     for cursor in Cursor::all() {
       match self.maps.timer[cursor] {
         0 => {
@@ -350,16 +764,28 @@ impl<'p> World<'p> {
         1 => {
           self.maps.timer[cursor] = 0;
           // Some bombs might extinguish themselves
-          if let Some(extinguished) = check_fuse_went_out(self.maps.level[cursor]) {
+          if let Some(extinguished) = check_fuse_went_out(self.maps.level[cursor], &mut self.rng) {
             self.maps.level[cursor] = extinguished;
             self.update.update_cell(cursor);
           } else {
             self.explode_entity(cursor, 0);
           }
         }
+        clock if self.maps.level[cursor] == MapValue::Fire => {
+          self.maps.timer[cursor] = clock - 1;
+          self.tick_fire(cursor, clock);
+        }
         clock => {
           // Countdown and update animation if needed
           self.maps.timer[cursor] = clock - 1;
+          if LIT_FUSE[self.maps.level[cursor]] {
+            self.fuses.push(cursor);
+            if self.round_counter % SPARK_INTERVAL == 0 {
+              // Redraw the cell to erase the previous spark
+              self.update.update_cell(cursor);
+              self.update.update_spark(cursor);
+            }
+          }
           let replacement = match self.maps.level[cursor] {
             MapValue::SmallBomb1 if clock <= 60 => MapValue::SmallBomb2,
             MapValue::SmallBomb2 if clock <= 30 => MapValue::SmallBomb3,
@@ -381,6 +807,32 @@ impl<'p> World<'p> {
     }
   }
 
+  /// Lit bombs closest to the alive players (nearest first), at most `limit` of them. Fuses are
+  /// written into the given buffer, so it could be reused between ticks.
+  pub fn audible_fuses(&self, limit: usize, fuses: &mut Vec<Cursor>) {
+    let distance = |fuse: Cursor| {
+      self.actors[..self.players.len()]
+        .iter()
+        .filter(|actor| !actor.is_dead)
+        .map(|listener| {
+          let (delta_row, delta_col) = fuse.distance(listener.pos.cursor());
+          u32::from(delta_row).pow(2) + u32::from(delta_col).pow(2)
+        })
+        .min()
+        .unwrap_or(u32::MAX)
+    };
+    fuses.clear();
+    fuses.extend(
+      self
+        .fuses
+        .iter()
+        .copied()
+        .filter(|fuse| distance(*fuse) <= FUSE_HEARING_DISTANCE.pow(2)),
+    );
+    fuses.sort_by_key(|fuse| distance(*fuse));
+    fuses.truncate(limit);
+  }
+
   /// Activate currently selected item for the given player
   fn activate_item(&mut self, player: usize) {
     let item = self.players[player].selection;
@@ -393,7 +845,13 @@ impl<'p> World<'p> {
     let cursor = self.actors[player].pos.cursor();
     match item {
       Equipment::Flamethrower => {
+        self.attacker = Some(player);
         self.activate_flamethrower(cursor, self.actors[player].facing);
+        self.attacker = None;
+      }
+      Equipment::Clone if self.clone_cap > 0 && self.player_clones(player).count() >= usize::from(self.clone_cap) => {
+        // Too many clones already
+        return;
       }
       Equipment::Clone => {
         self.activate_clone(player);
@@ -413,14 +871,30 @@ impl<'p> World<'p> {
         self.actors[player].super_drill_count = 10;
         self.actors[player].drilling += 300;
       }
+      Equipment::Bandage if self.actors[player].health >= self.actors[player].max_health => {
+        // Healthy already
+        return;
+      }
+      Equipment::Bandage => {
+        let actor = &mut self.actors[player];
+        actor.health = (actor.health + BANDAGE_HEALING).min(actor.max_health);
+        self.update.update_player_health(player);
+      }
       _other if CANNOT_PLACE_BOMB[self.maps.level[cursor]] => {
         // Cannot place bomb here!
         return;
       }
       item => {
         self.maps.level[cursor] = item_placement_level(item, self.actors[player].facing, player);
-        self.maps.timer[cursor] = item_placement_timer(item);
-        self.maps.hits[cursor] = item_placement_hits(item);
+        self.maps.timer[cursor] = self.tuning.fuse_time(item_placement_timer(item, &mut self.rng));
+        self.maps.hits[cursor] = item_placement_hits(item, &mut self.rng);
+        self.maps.owner[cursor] = Some(player);
+        if matches!(item, Equipment::SmallRadio | Equipment::LargeRadio) {
+          self.remote_area[player] = Some(Cursor::enclose(self.remote_area[player], cursor));
+        }
+        if item == Equipment::Biomass {
+          self.seed_biomass_patch(cursor);
+        }
       }
     }
 
@@ -468,10 +942,12 @@ impl<'p> World<'p> {
       }
       self.update.update_cell(cursor);
       true
-    } else if value.is_passable() {
-      self.maps.level[cursor] = MapValue::Smoke1;
-      self.maps.timer[cursor] = 3;
-      self.update.update_cell(cursor);
+    } else if value.is_passable() || value == MapValue::Fire {
+      if value == MapValue::Fire {
+        self.maps.level[cursor] = MapValue::Passage;
+        self.maps.timer[cursor] = 0;
+      }
+      self.show_smoke(cursor);
       true
     } else {
       false
@@ -522,11 +998,12 @@ impl<'p> World<'p> {
       || value.is_sand()
       || value.is_stone_like()
       || value.is_brick_like()
-      || value == MapValue::Biomass
+      || is_biomass(value)
       || value == MapValue::Plastic
       || value == MapValue::ExplosivePlastic
       || value == MapValue::LightGravel
       || value == MapValue::HeavyGravel
+      || value.nest().is_some()
     {
       let actor = &self.actors[entity];
       // Diggable squares
@@ -561,9 +1038,11 @@ impl<'p> World<'p> {
         }
       } else {
         self.maps.hits[cursor] = 0;
+        self.maps.timer[cursor] = 0;
         self.maps.level[cursor] = MapValue::Passage;
         self.update.update_cell(cursor);
         self.update.update_cell_border(cursor);
+        self.events.push(GameEvent::Dug { cursor });
       }
     } else if value == MapValue::Diamond
       || (value >= MapValue::GoldShield && value <= MapValue::GoldCrown)
@@ -582,7 +1061,12 @@ impl<'p> World<'p> {
         let player = player as usize;
         let cash_player = if self.campaign_mode { 0 } else { player };
         self.actors[player].drilling += drill_value;
-        self.actors[cash_player].accumulated_cash += gold_value;
+        if self.clone_carry_gold {
+          // Clone needs to bring gold to its owner
+          self.actors[entity].accumulated_cash += gold_value;
+        } else {
+          self.actors[cash_player].accumulated_cash += gold_value;
+        }
       }
 
       self.actors[entity].drilling += drill_value;
@@ -593,14 +1077,17 @@ impl<'p> World<'p> {
       }
 
       if value >= MapValue::SmallPickaxe && value <= MapValue::Drill {
-        self.effects.play(SoundEffect::Picaxe, 11000, cursor);
+        self.play_sound_of(entity, SoundEffect::Picaxe, 11000, cursor);
       } else {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.clone();
         let frequency = *[10000, 12599, 14983].choose(&mut rng).unwrap();
-        self.effects.play(SoundEffect::Kili, frequency, cursor);
+        self.play_sound_of(entity, SoundEffect::Kili, frequency, cursor);
         if let Some(player) = self.player_mut(entity) {
           player.stats.treasures_collected += 1;
         }
+        if entity < self.players.len() && value.is_treasure() {
+          self.events.push(GameEvent::TreasureFound { player: entity, value });
+        }
       }
 
       self.maps.hits[cursor] = 0;
@@ -608,38 +1095,33 @@ impl<'p> World<'p> {
 
       self.update.update_player_stats(entity);
       self.update.update_cell(cursor);
+    } else if let Some(color) = value.key_color() {
+      self.pick_up_key(entity, cursor, color);
+    } else if let Some(color) = value.locked_door_color() {
+      self.unlock_door(entity, cursor, color);
     } else if value == MapValue::Mine {
       // Activate the mine
       self.maps.timer[cursor] = 1;
     } else if PUSHABLE_BITMAP[value] {
       let actor = &self.actors[entity];
-      // Go to the target position
-      let target = cursor.to(actor.facing);
+      let facing = actor.facing;
       if self.maps.hits[cursor] == 30_000 {
         // FIXME: wall shouldn't be pushable anyways?
       } else if self.maps.hits[cursor] > 1 {
         // Still need to push a little
         self.maps.hits[cursor] -= i32::from(actor.drilling);
-      } else if self.maps.level[target].is_passable() {
-        // Check if no actors are blocking the path
-        if self.actors.iter().all(|p| p.is_dead || p.pos.cursor() != target) {
-          // Push to `target` location
-          self.maps.level[target] = self.maps.level[cursor];
-          self.maps.timer[target] = self.maps.timer[cursor];
-          self.maps.hits[target] = 24;
-
-          // Clear old position
-          self.maps.level[cursor] = MapValue::Passage;
-          self.maps.timer[cursor] = 0;
-
-          self.reapply_blood(cursor);
-
-          self.update.update_cell(cursor);
-          self.update.update_cell(target);
+      } else if self.push_chains && value == MapValue::Boulder && self.maps.level[cursor.to(facing)] == value {
+        // Push both boulders, the far one first to make room for the near one
+        let next = cursor.to(facing);
+        if self.push_target_clear(entity, next, facing) {
+          self.push_cell(next, facing);
+          self.push_cell(cursor, facing);
         }
+      } else if self.push_target_clear(entity, cursor, facing) {
+        self.push_cell(cursor, facing);
       }
     } else if value == MapValue::WeaponsCrate {
-      let mut rng = rand::thread_rng();
+      let mut rng = self.rng.clone();
       match rng.gen_range(0..5) {
         0 => {
           let cnt = rng.gen_range(1..3);
@@ -701,7 +1183,7 @@ impl<'p> World<'p> {
 
       self.update.update_player_selection(entity);
       self.update.update_cell(cursor);
-      self.effects.play(SoundEffect::Picaxe, 11000, cursor);
+      self.play_sound_of(entity, SoundEffect::Picaxe, 11000, cursor);
     } else if value == MapValue::LifeItem {
       if let ActorKind::Player(_) = self.actors[entity].kind {
         self.players[0].lives += 1;
@@ -732,7 +1214,7 @@ impl<'p> World<'p> {
         }
       }
 
-      let mut rng = rand::thread_rng();
+      let mut rng = self.rng.clone();
       let mut exit = if teleport_count == 1 {
         0
       } else {
@@ -752,15 +1234,25 @@ impl<'p> World<'p> {
 
             // Move to the exit point
             actor.pos = cur.into();
-            self.update.update_cell(actor.pos.cursor());
+            self.occupancy.moved(entity, cur);
+            self.update.update_cell(cur);
             break;
           }
           exit -= 1;
         }
       }
     } else if value == MapValue::Exit {
-      if self.campaign_mode && entity < self.players.len() {
-        self.exited = true;
+      if self.campaign_mode && entity < self.players.len() && !self.maps.exit_locked && self.exited.is_none() {
+        let tag = self.exits.iter().find(|exit| exit.cursor == cursor).copied();
+        if let Some(tag) = tag.filter(|tag| tag.bonus > 0) {
+          self.actors[0].accumulated_cash += tag.bonus;
+          self.update.update_player_stats(0);
+        }
+        if self.bonus_round {
+          self.actors[0].accumulated_cash += self.bonus_seconds_left() * BONUS_SECOND_CASH;
+          self.update.update_player_stats(0);
+        }
+        self.exited = Some(tag.map_or(0, |tag| tag.id));
       }
     } else if value == MapValue::Medikit {
       if entity < self.players.len() {
@@ -770,25 +1262,38 @@ impl<'p> World<'p> {
       self.maps.level[cursor] = MapValue::Passage;
       self.update.update_player_health(entity);
       self.update.update_cell(cursor);
-      self.effects.play(SoundEffect::Picaxe, 11000, cursor);
+      self.play_sound_of(entity, SoundEffect::Picaxe, 11000, cursor);
     }
   }
 
   /// Re-apply blood / slime corpse to the map cell. Iterates through all of the actors and places
-  /// blood / slime corpse at the cell if dead actors are found.
+  /// blood / slime corpse at the cell if dead actors are found (or were found before they were
+  /// despawned).
   fn reapply_blood(&mut self, cursor: Cursor) {
+    if let Some(corpse) = self.maps.corpse[cursor] {
+      self.maps.level[cursor] = corpse;
+    }
     self.apply_damage_in_cell(cursor, 0);
   }
 
+  /// Restore some health of the players who were not hurt for a while
+  fn regenerate_players(&mut self) {
+    for player in 0..self.players.len() {
+      let actor = &mut self.actors[player];
+      let resting = self.round_counter - self.last_hurt[player] >= REGEN_DELAY;
+      if resting && !actor.is_dead && actor.health < actor.max_health {
+        actor.health = (actor.health + REGEN_AMOUNT).min(actor.max_health);
+        self.update.update_player_health(player);
+      }
+    }
+  }
+
   /// Apply damage to all actors in the cell. Returns `true` if found live actor in that cell.
   fn apply_damage_in_cell(&mut self, cursor: Cursor, dmg: u16) -> bool {
     let mut found_alive = false;
-    for idx in 0..self.actors.len() {
+    let occupants: Vec<EntityIndex> = self.actors_at(cursor).collect();
+    for idx in occupants {
       let actor = &self.actors[idx];
-      if actor.pos.cursor() != cursor {
-        continue;
-      }
-
       let effective_dmg = match actor.kind {
         // In single player, damage is always 100%
         ActorKind::Player(_) if self.campaign_mode => dmg,
@@ -797,7 +1302,11 @@ impl<'p> World<'p> {
       };
       // Get mutable
       let actor = &mut self.actors[idx];
+      let effective_dmg = effective_dmg - actor.absorb_damage(effective_dmg);
       actor.health = actor.health.saturating_sub(effective_dmg);
+      if effective_dmg > 0 && idx < self.players.len() {
+        self.last_hurt[idx] = self.round_counter;
+      }
 
       if idx < self.players.len() {
         self.update.update_player_health(idx);
@@ -805,27 +1314,255 @@ impl<'p> World<'p> {
 
       found_alive |= !actor.is_dead;
       if actor.health == 0 {
-        if dmg > 0 {
-          self.maps.level[cursor] = actor.kind.death_animation_value();
-          self.maps.timer[cursor] = 3;
-        } else {
+        if dmg == 0 {
           self.maps.level[cursor] = actor.kind.blood_value();
+        } else if !actor.is_dead {
+          let kind = actor.kind;
+          self.start_death_animation(cursor, kind);
         }
+        let actor = &mut self.actors[idx];
         if !actor.is_dead {
           if idx < self.players.len() {
             self.players[idx].stats.deaths += 1;
+            self.events.push(GameEvent::PlayerKilled {
+              victim: idx,
+              killer: self.attacker,
+            });
           }
+          let kind = actor.kind;
+          let carried_cash = actor.accumulated_cash;
           actor.is_dead = true;
-          self.effects.play(actor.kind.death_sound_effect(), 11000, cursor);
+          self.effects.play(kind.death_sound_effect(), 11000, cursor);
+
+          if let Some(owner) = kind.clone_owner() {
+            self.update.update_player_stats(owner);
+            // Gold carried by the clone is dropped as a treasure; only one item fits into the cell,
+            // so the rest of it goes to the owner
+            self.actors[idx].accumulated_cash = 0;
+            let dropped = match MapValue::treasure_worth(carried_cash) {
+              Some(treasure) if self.drop_item(cursor, treasure) => treasure.gold_value(),
+              _ => 0,
+            };
+            let cash_player = if self.campaign_mode { 0 } else { owner };
+            self.actors[cash_player].accumulated_cash += carried_cash - dropped;
+          } else if idx >= self.players.len() && self.monster_loot {
+            if let Some(loot) = kind.roll_loot(&mut self.rng) {
+              self.drop_item(cursor, loot);
+            }
+          }
+
+          if let Some(killer) = self.attacker.filter(|killer| *killer != idx) {
+            self.players[killer].kills += 1;
+          }
         }
       }
     }
     found_alive
   }
 
+  /// Schedule item to be dropped in the cell once death animation is over. Only one item can be
+  /// dropped in a single cell; returns if item was scheduled.
+  fn drop_item(&mut self, cursor: Cursor, item: MapValue) -> bool {
+    let free = self.drops.iter().all(|(cur, _)| *cur != cursor);
+    if free {
+      self.drops.push((cursor, item));
+    }
+    free
+  }
+
+  /// Amount of armed (not yet triggered) radio bombs of the given player
+  pub fn armed_radios(&self, player: EntityIndex) -> usize {
+    match self.remote_area[player] {
+      Some((top_left, bottom_right)) => Cursor::rect(top_left, bottom_right)
+        .filter(|cursor| is_remote_for(self.maps.level[*cursor], player) && self.maps.timer[*cursor] == 0)
+        .count(),
+      None => 0,
+    }
+  }
+
+  /// Player whose radio bomb in the cell was triggered and is about to go off (pinged on the map)
+  pub fn radio_ping_owner(&self, cursor: Cursor) -> Option<EntityIndex> {
+    radio_owner(self.maps.level[cursor]).filter(|_| self.radio_ping && self.maps.timer[cursor] > 0)
+  }
+
+  /// If given cell is a landing spot of a jumping bomb placed by a player, return that player
+  pub fn jump_target_owner(&self, cursor: Cursor) -> Option<EntityIndex> {
+    self
+      .jump_targets
+      .iter()
+      .filter(|target| target.target == cursor && self.maps.level[target.bomb] == MapValue::JumpingBomb)
+      .find_map(|target| target.owner)
+  }
+
+  /// Take item to drop in the cell once death animation is over
+  fn take_drop(&mut self, cursor: Cursor) -> Option<MapValue> {
+    let pos = self.drops.iter().position(|(cur, _)| *cur == cursor)?;
+    Some(self.drops.swap_remove(pos).1)
+  }
+
+  /// Place item into a random empty cell on the map. Returns location of the item, if free cell
+  /// was found.
+  pub fn place_at_random(&mut self, item: MapValue) -> Option<Cursor> {
+    let mut rng = self.rng.clone();
+    for _ in 0..100 {
+      let cursor = Cursor::new(rng.gen_range(1..MAP_ROWS - 1), rng.gen_range(1..MAP_COLS - 1));
+      if self.maps.level[cursor].is_passable() && self.actors_at(cursor).next().is_none() {
+        self.maps.level[cursor] = item;
+        self.maps.owner[cursor] = None;
+        self.update.update_cell(cursor);
+        return Some(cursor);
+      }
+    }
+    None
+  }
+
+  /// Shake the screen because of the blast at the given location. The closer the nearest alive
+  /// player is to the blast, the stronger the screen shakes.
+  fn shake_from(&mut self, amount: u16, location: Cursor) {
+    let distance = self.actors[..self.players.len()]
+      .iter()
+      .filter(|actor| !actor.is_dead)
+      .map(|actor| {
+        let (delta_row, delta_col) = actor.pos.cursor().distance(location);
+        delta_row.max(delta_col)
+      })
+      .min()
+      .unwrap_or(MAP_COLS);
+    // Full shake right next to the blast, down to 20% across the map
+    let intensity = 100 - (distance * 80 / MAP_COLS).min(80);
+    self.shake = (self.shake + amount).min(MAP_ROWS);
+    self.shake_intensity = self.shake_intensity.max(intensity);
+  }
+
+  /// Amount of screen shake to render this frame
+  pub fn shake_offset(&self) -> u16 {
+    if self.shake % 2 == 0 {
+      // Only every second frame is shifted
+      0
+    } else {
+      (self.shake * self.shake_intensity / 100).max(1)
+    }
+  }
+
+  /// Shake the whole level, as if a huge explosion happened somewhere nearby
+  pub fn earthquake(&mut self) {
+    self.shake = MAP_ROWS;
+    self.shake_intensity = 100;
+    let location = Cursor::new(MAP_ROWS / 2, MAP_COLS / 2);
+    self.effects.play(SoundEffect::Explos2, 5000, location);
+  }
+
+  /// Turn this round into a bonus round with the given time limit: monsters and traders are
+  /// removed and exit is unlocked.
+  pub fn start_bonus_round(&mut self, seconds: u32) {
+    self.bonus_round = true;
+    self.bonus_ticks_left = seconds as usize * TICKS_PER_SECOND;
+    self.traders = false;
+    self.remove_monsters();
+    if self.maps.exit_locked {
+      self.maps.exit_locked = false;
+      self.redraw_exits();
+    }
+    self.exit_gold = 0;
+  }
+
+  /// Use tuned game constants (practice mode)
+  pub fn apply_tuning(&mut self, tuning: Tuning) {
+    if !tuning.monsters {
+      self.remove_monsters();
+    }
+    self.tuning = tuning;
+  }
+
+  /// Remove all monsters from the level
+  fn remove_monsters(&mut self) {
+    self.despawned.extend(self.players.len()..self.actors.len());
+  }
+
+  /// Seconds left until the end of the bonus round
+  pub fn bonus_seconds_left(&self) -> u32 {
+    self.bonus_ticks_left.div_ceil(TICKS_PER_SECOND) as u32
+  }
+
+  fn tick_bonus_time(&mut self) {
+    if self.bonus_ticks_left == 0 {
+      // Time is up
+      self.end_round_counter = self.end_round_counter.max(101);
+      return;
+    }
+    self.bonus_ticks_left -= 1;
+    if self.bonus_ticks_left % TICKS_PER_SECOND == 0 {
+      self.update.update_player_stats(0);
+    }
+  }
+
+  /// Limit the time of this round. Once time is up, round continues, but monsters get enraged.
+  pub fn set_time_limit(&mut self, seconds: u32) {
+    self.time_limit_ticks_left = Some(seconds as usize * TICKS_PER_SECOND);
+  }
+
+  /// Seconds left until monsters get enraged, if round has a time limit
+  pub fn time_limit_seconds_left(&self) -> Option<u32> {
+    self
+      .time_limit_ticks_left
+      .map(|ticks| ticks.div_ceil(TICKS_PER_SECOND) as u32)
+  }
+
+  fn tick_time_limit(&mut self) {
+    match self.time_limit_ticks_left {
+      Some(0) if !self.enraged => {
+        // Time is up
+        self.enraged = true;
+        let location = self.actors[0].pos.cursor();
+        self.effects.play(SoundEffect::Karjaisu, 11000, location);
+        self.update.update_player_stats(0);
+      }
+      Some(0) if self.round_counter % (TICKS_PER_SECOND / 2) == 0 => {
+        // Keep flashing the warning
+        self.update.update_player_stats(0);
+      }
+      Some(0) => {}
+      Some(ref mut ticks) => {
+        *ticks -= 1;
+        if *ticks % TICKS_PER_SECOND == 0 {
+          self.update.update_player_stats(0);
+        }
+      }
+      None => {}
+    }
+  }
+
+  /// Gold collected by the player during this round (single player mode)
+  pub fn collected_gold(&self) -> u32 {
+    self.actors[0].accumulated_cash
+  }
+
+  /// Enough gold was collected, exit can be used now
+  fn unlock_exit(&mut self) {
+    self.maps.exit_locked = false;
+    self.redraw_exits();
+    self.update.update_player_stats(0);
+    if let Some(exit) = Cursor::all().find(|cursor| self.maps.level[*cursor] == MapValue::Exit) {
+      self.effects.play(SoundEffect::Applause, 11000, exit);
+    }
+  }
+
+  /// Redraw exits once they get locked or unlocked
+  fn redraw_exits(&mut self) {
+    for cursor in Cursor::all() {
+      if self.maps.level[cursor] == MapValue::Exit {
+        self.update.update_cell(cursor);
+      }
+    }
+  }
+
   /// Open all doors on the map
   fn open_doors(&mut self) {
-    for cursor in Cursor::all() {
+    let (top_left, bottom_right) = match self.door_area {
+      Some(area) => area,
+      None => return,
+    };
+    for cursor in Cursor::rect(top_left, bottom_right) {
       match self.maps.level[cursor] {
         MapValue::ButtonOff => {
           self.maps.timer[cursor] = 40;
@@ -838,15 +1575,17 @@ impl<'p> World<'p> {
         _ => continue,
       }
 
-      if !self.maps.darkness || !self.maps.fog[cursor].dark {
-        self.update.update_cell(cursor);
-      }
+      self.update.update_cell(cursor);
     }
   }
 
   /// Close all doors on the map; explodes entities placed in an open door.
   fn close_doors(&mut self) {
-    for cursor in Cursor::all() {
+    let (top_left, bottom_right) = match self.door_area {
+      Some(area) => area,
+      None => return,
+    };
+    for cursor in Cursor::rect(top_left, bottom_right) {
       if self.maps.level[cursor] == MapValue::ButtonOn {
         self.maps.timer[cursor] = 40;
         self.maps.level[cursor] = MapValue::ButtonOff;
@@ -858,14 +1597,14 @@ impl<'p> World<'p> {
       } else {
         continue;
       }
-      if !self.maps.darkness || !self.maps.fog[cursor].dark {
-        self.update.update_cell(cursor);
-      }
+      self.update.update_cell(cursor);
     }
   }
 
   /// Animate actor under a given index. Updates coordinates, animation phase.
   fn animate_actor(&mut self, entity: EntityIndex) {
+    let ahead = self.actors[entity].pos.cursor().to(self.actors[entity].facing);
+    let map_value = self.cell_value(ahead);
     let actor = &mut self.actors[entity];
     if !actor.moving {
       self.update.update_actor(entity, Digging::Hands);
@@ -886,10 +1625,10 @@ impl<'p> World<'p> {
 
     // Vertically centered enough to be moving in the current direction
     let is_moving = can_move && delta_orthogonal > 3 && delta_orthogonal < 6;
-    let map_value = self.maps.level[cursor.to(direction)];
-    // Either finishing move into the cell or cell to the left is passable
-    if is_moving && (finishing_move || map_value.is_passable()) {
+    // Either finishing move into the cell or cell to the left is passable (fire does not stop anyone)
+    if is_moving && (finishing_move || map_value.is_passable() || map_value == MapValue::Fire) {
       actor.pos.step(direction);
+      self.occupancy.moved(entity, actor.pos.cursor());
     }
 
     if delta_orthogonal != 5 {
@@ -929,12 +1668,25 @@ impl<'p> World<'p> {
 
     let actor = &mut self.actors[entity];
     actor.animation %= 30;
-    if digging == Digging::Pickaxe && actor.animation == 16 {
-      let mut rng = rand::thread_rng();
+    let swing = digging == Digging::Pickaxe && actor.animation == 16;
+    actor.animation += 1;
+    if swing {
+      let mut rng = self.rng.clone();
       let frequency = rng.gen_range(11000..11100);
-      self.effects.play(SoundEffect::Picaxe, frequency, cursor);
+      self.play_sound_of(entity, SoundEffect::Picaxe, frequency, cursor);
     }
-    actor.animation += 1;
+  }
+
+  /// Play sound effect caused by the given actor; sounds of players' own actions are never muffled
+  fn play_sound_of(&mut self, entity: EntityIndex, effect: SoundEffect, frequency: i32, location: Cursor) {
+    let source = Some(entity).filter(|entity| *entity < self.players.len());
+    self.effects.play_by(effect, frequency, location, source);
+  }
+
+  /// Reveal the cell in the darkness mode: it becomes visible to everyone
+  fn reveal_cell(&mut self, cursor: Cursor) {
+    self.maps.fog[cursor].reveal();
+    self.update.queue.push(Update::Map(cursor, Visibility::Everyone));
   }
 
   /// Reveal map based on player vision
@@ -951,7 +1703,7 @@ impl<'p> World<'p> {
       for dir in Direction::all() {
         let tgt = cursor.to(dir);
         if self.maps.fog[tgt].dark {
-          self.update.update_cell(tgt);
+          self.reveal_cell(tgt);
         }
       }
 
@@ -972,7 +1724,7 @@ impl<'p> World<'p> {
     let mut current = cursor;
     for _ in 0..=len {
       if self.maps.fog[current].dark {
-        self.update.update_cell(current);
+        self.reveal_cell(current);
       }
       if !SEE_THROUGH[self.maps.level[current]] {
         break;
@@ -1004,6 +1756,8 @@ impl<'p> World<'p> {
       moving: true,
       max_health: 100,
       health: 100,
+      armor: 0,
+      max_armor: 0,
       pos: player.pos.cursor().position(),
       drilling: player.drilling,
       animation: 1,
@@ -1011,6 +1765,9 @@ impl<'p> World<'p> {
       is_active: true,
       accumulated_cash: 0,
       super_drill_count: 0,
+      spawner: None,
+      leave_countdown: 0,
+      path: PathRecorder::default(),
     };
 
     // Don't inherit super drill
@@ -1019,7 +1776,10 @@ impl<'p> World<'p> {
     }
 
     // Original game places in front of the list, but it's easier to push back for us
+    let cursor = clone.pos.cursor();
     self.actors.push(clone);
+    self.occupancy.moved(self.actors.len() - 1, cursor);
+    self.update.update_player_stats(player_idx);
   }
 }
 
@@ -1063,23 +1823,29 @@ fn item_placement_level(item: Equipment, direction: Direction, player: usize) ->
     | Equipment::Extinguisher
     | Equipment::Armor
     | Equipment::SuperDrill
-    | Equipment::Clone => {
+    | Equipment::Clone
+    | Equipment::Bandage => {
       unreachable!();
     }
   }
 }
 
 fn is_remote_for(value: MapValue, player: EntityIndex) -> bool {
+  radio_owner(value) == Some(player)
+}
+
+/// Player who detonates given radio bomb with their remote key
+fn radio_owner(value: MapValue) -> Option<EntityIndex> {
   match value {
-    MapValue::SmallRadioBlue | MapValue::BigRadioBlue if player == 0 => true,
-    MapValue::SmallRadioRed | MapValue::BigRadioRed if player == 1 => true,
-    MapValue::SmallRadioGreen | MapValue::BigRadioGreen if player == 2 => true,
-    MapValue::SmallRadioYellow | MapValue::BigRadioYellow if player == 3 => true,
-    _ => false,
+    MapValue::SmallRadioBlue | MapValue::BigRadioBlue => Some(0),
+    MapValue::SmallRadioRed | MapValue::BigRadioRed => Some(1),
+    MapValue::SmallRadioGreen | MapValue::BigRadioGreen => Some(2),
+    MapValue::SmallRadioYellow | MapValue::BigRadioYellow => Some(3),
+    _ => None,
   }
 }
 
-fn item_placement_timer(item: Equipment) -> u16 {
+fn item_placement_timer(item: Equipment, rng: &mut WorldRng) -> u16 {
   match item {
     Equipment::Mine | Equipment::SmallRadio | Equipment::LargeRadio | Equipment::Barrel | Equipment::Teleport => 0,
     Equipment::Napalm => 260,
@@ -1087,23 +1853,17 @@ fn item_placement_timer(item: Equipment) -> u16 {
     Equipment::MetalWall => 1,
     Equipment::ExplosivePlastic => 90,
     Equipment::Dynamite => 80,
-    Equipment::JumpingBomb => {
-      let mut rng = rand::thread_rng();
-      rng.gen_range(80..160)
-    }
-    Equipment::Biomass => {
-      let mut rng = rand::thread_rng();
-      rng.gen_range(0..80)
-    }
+    Equipment::JumpingBomb => rng.gen_range(80..160),
+    Equipment::Biomass => rng.gen_range(0..80),
     Equipment::Grenade => 1,
     _ => 100,
   }
 }
 
-fn item_placement_hits(item: Equipment) -> i32 {
+fn item_placement_hits(item: Equipment, rng: &mut WorldRng) -> i32 {
   match item {
-    Equipment::JumpingBomb => rand::thread_rng().gen_range(7..27),
-    Equipment::Biomass => 400,
+    Equipment::JumpingBomb => rng.gen_range(7..27),
+    Equipment::Biomass => BIOMASS_HITS,
     Equipment::Grenade => 0,
     // Note that this is also "push" difficulty and in `interact_map` we actually set it to 24
     // for pushed items (so it's easier to push for the first time). This seems to be the behavior
@@ -1112,6 +1872,17 @@ fn item_placement_hits(item: Equipment) -> i32 {
   }
 }
 
+/// Direction player turns to when the given key is pressed
+fn key_direction(key: Key) -> Option<Direction> {
+  match key {
+    Key::Up => Some(Direction::Up),
+    Key::Down => Some(Direction::Down),
+    Key::Left => Some(Direction::Left),
+    Key::Right => Some(Direction::Right),
+    _ => None,
+  }
+}
+
 fn is_selectable(item: Equipment) -> bool {
   !matches!(
     item,
@@ -1119,7 +1890,12 @@ fn is_selectable(item: Equipment) -> bool {
   )
 }
 
-fn spawn_actors(map: &mut LevelMap, players_count: usize, campaign_mode: bool) -> Vec<ActorComponent> {
+fn spawn_actors(
+  map: &mut LevelMap,
+  players_count: usize,
+  campaign_mode: bool,
+  rng: &mut WorldRng,
+) -> Vec<ActorComponent> {
   let mut actors = Vec::new();
 
   // Initialize players
@@ -1136,7 +1912,7 @@ fn spawn_actors(map: &mut LevelMap, players_count: usize, campaign_mode: bool) -
       ..Default::default()
     });
   }
-  init_players_positions(&mut actors, campaign_mode);
+  init_players_positions(&mut actors, campaign_mode, rng);
 
   // Take all the monsters from the map and add them to the actors list
   for cursor in Cursor::all() {
@@ -1145,6 +1921,7 @@ fn spawn_actors(map: &mut LevelMap, players_count: usize, campaign_mode: bool) -
       actors.push(ActorComponent {
         kind,
         pos: cursor.into(),
+        max_health: kind.initial_health(),
         health: kind.initial_health(),
         drilling: kind.drilling_power(),
         facing,
@@ -1157,14 +1934,12 @@ fn spawn_actors(map: &mut LevelMap, players_count: usize, campaign_mode: bool) -
   }
   actors
 }
-fn init_players_positions(players: &mut [ActorComponent], campaign_mode: bool) {
+fn init_players_positions(players: &mut [ActorComponent], campaign_mode: bool, rng: &mut WorldRng) {
   if campaign_mode {
     players.iter_mut().for_each(|p| p.pos = Position::new(15, 45));
     return;
   }
 
-  let mut rng = thread_rng();
-
   if rng.gen::<bool>() {
     players[0].pos = Position::new(15, 45);
     players[1].pos = Position::new(625, 465);
@@ -1199,10 +1974,54 @@ pub enum SplatterKind {
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Update {
   Actor(EntityIndex, Digging),
-  Map(Cursor),
+  Map(Cursor, Visibility),
   Border(Cursor),
   BurnedBorder(Cursor),
   Splatter(Cursor, Direction, SplatterKind),
+  /// Landing spot of the jumping bomb, only shown to the player who placed the bomb
+  JumpTarget(EntityIndex, Cursor),
+  /// Spark flying off the fuse of the lit bomb
+  Spark(Cursor),
+}
+
+/// Who is allowed to see the update of the map cell
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visibility {
+  /// Cell was just revealed by the player vision, everyone sees it
+  Everyone,
+  /// Visible to the viewers who have the cell revealed (everyone if there is no darkness)
+  Revealed,
+  /// Visible to the given player only, if they have the cell revealed
+  Player(EntityIndex),
+}
+
+/// Who is looking at the world
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Viewer {
+  /// Single screen shared by all players (and observers)
+  Shared,
+  /// Viewport of a single player
+  Player(EntityIndex),
+}
+
+impl Visibility {
+  /// Check if the update of the given cell could be shown to the viewer
+  pub fn is_visible(self, viewer: Viewer, cursor: Cursor, maps: &Maps) -> bool {
+    let revealed = !maps.darkness || !maps.fog[cursor].dark;
+    match self {
+      Visibility::Everyone => true,
+      Visibility::Revealed => revealed,
+      Visibility::Player(player) => revealed && (viewer == Viewer::Shared || viewer == Viewer::Player(player)),
+    }
+  }
+}
+
+/// Landing spot selected by a jumping bomb for its next jump
+struct JumpTarget {
+  bomb: Cursor,
+  target: Cursor,
+  /// Player who placed the bomb
+  owner: Option<EntityIndex>,
 }
 
 /// List of UI areas to update
@@ -1214,6 +2033,11 @@ pub struct UpdateQueue {
 }
 
 impl UpdateQueue {
+  /// Clear the queue once all updates are rendered, keeping its storage for the next tick
+  pub fn clear(&mut self) {
+    clear_bounded(&mut self.queue);
+  }
+
   /// Need to re-render player lives
   pub fn update_player_lives(&mut self) {
     self.players_info = true;
@@ -1238,8 +2062,14 @@ impl UpdateQueue {
     self.queue.push(Update::Actor(actor, digging));
   }
 
+  /// Need to re-render the cell; only shown to the viewers who have it revealed
   pub fn update_cell(&mut self, cursor: Cursor) {
-    self.queue.push(Update::Map(cursor));
+    self.queue.push(Update::Map(cursor, Visibility::Revealed));
+  }
+
+  /// Need to re-render the cell for the given player only (if they have it revealed)
+  pub fn update_cell_for(&mut self, player: EntityIndex, cursor: Cursor) {
+    self.queue.push(Update::Map(cursor, Visibility::Player(player)));
   }
 
   pub fn update_cell_border(&mut self, cursor: Cursor) {
@@ -1253,6 +2083,14 @@ impl UpdateQueue {
   pub fn update_splatter(&mut self, cursor: Cursor, direction: Direction, splatter: SplatterKind) {
     self.queue.push(Update::Splatter(cursor, direction, splatter));
   }
+
+  pub fn update_jump_target(&mut self, player: EntityIndex, cursor: Cursor) {
+    self.queue.push(Update::JumpTarget(player, cursor));
+  }
+
+  pub fn update_spark(&mut self, cursor: Cursor) {
+    self.queue.push(Update::Spark(cursor));
+  }
 }
 
 /// Check if two coordinates are in proximity to each other (less than 20 pixels in both direction)
@@ -1297,7 +2135,7 @@ fn in_fov_sight(first: Cursor, second: Cursor, facing: Direction) -> bool {
 }
 
 /// Make a dice roll to check if fuse went out for the given bomb
-fn check_fuse_went_out(value: MapValue) -> Option<MapValue> {
+fn check_fuse_went_out(value: MapValue, rng: &mut WorldRng) -> Option<MapValue> {
   let replacement = match value {
     MapValue::SmallBomb3 => MapValue::SmallBombExtinguished,
     MapValue::BigBomb3 => MapValue::BigBombExtinguished,
@@ -1305,8 +2143,7 @@ fn check_fuse_went_out(value: MapValue) -> Option<MapValue> {
     MapValue::Napalm1 | MapValue::Napalm2 => MapValue::NapalmExtinguished,
     _ => return None,
   };
-  let mut rnd = rand::thread_rng();
-  if rnd.gen_range(0..1000) <= 10 {
+  if rng.gen_range(0..1000) <= 10 {
     Some(replacement)
   } else {
     None
@@ -1,3 +1,5 @@
+use crate::effects::SoundEffect;
+use crate::glyphs::Digging;
 use crate::world::actor::{ActorComponent, ActorKind, Player};
 use crate::world::map::{LevelMap, MapValue};
 use crate::world::position::{Cursor, Direction};
@@ -17,9 +19,17 @@ impl World<'_> {
         continue;
       }
 
+      if monster_kind == ActorKind::Trader {
+        // Traders are neutral: never chase nor hurt anybody
+        self.animate_trader(actor_idx);
+        continue;
+      }
+
       self.damage_players(actor_idx);
+      self.clone_deposit_gold(actor_idx);
 
-      if self.round_counter % monster_kind.speed() != 0 {
+      let enraged = self.enraged && monster_kind.is_monster();
+      if enraged || self.round_counter % monster_kind.speed() != 0 {
         self.animate_actor(actor_idx);
       }
 
@@ -31,12 +41,14 @@ impl World<'_> {
 
       if self.round_counter % 26 == 0 {
         if let Some(bomb_cursor) = look_for_bombs(monster_cursor, &self.maps.level) {
-          self.actors[actor_idx].avoid_position(bomb_cursor, &self.maps.level);
+          self.actors[actor_idx].avoid_position(bomb_cursor, &self.maps.level, &mut self.rng);
         } else {
-          match look_for_players(monster_cursor, &self.actors[0..self.players.len()]) {
+          let chase = enraged || !self.calm_monsters || self.round_counter % 52 == 0;
+          let target = look_for_players(monster_cursor, &self.actors[0..self.players.len()]).filter(|_| chase);
+          match target {
             // Clones shouldn't chase their player!
             Some((player_cursor, player_idx)) if self.clone_can_chase(monster_kind, player_idx) => {
-              self.actors[actor_idx].head_to_target(player_cursor, &self.maps.level);
+              self.actors[actor_idx].head_to_target(player_cursor, &self.maps.level, &mut self.rng);
 
               if let ActorKind::Clone(_) = monster_kind {
                 // Clones throw grenades only when actually locked on somebody
@@ -47,10 +59,17 @@ impl World<'_> {
               if let ActorKind::Clone(_) = monster_kind {
                 // Clones look for gold!
                 if let Some(gold_cursor) = look_for_gold(monster_cursor, &self.maps.level) {
-                  self.actors[actor_idx].head_to_target(gold_cursor, &self.maps.level);
+                  self.actors[actor_idx].head_to_target(gold_cursor, &self.maps.level, &mut self.rng);
                 }
               }
             }
+            _ if self.actors[actor_idx].accumulated_cash > 0 => {
+              // Nothing else to collect, bring the gold to the owner
+              if let Some(owner) = monster_kind.clone_owner() {
+                let owner_cursor = self.actors[owner].pos.cursor();
+                self.actors[actor_idx].head_to_target(owner_cursor, &self.maps.level, &mut self.rng);
+              }
+            }
             _ => {}
           }
 
@@ -63,7 +82,7 @@ impl World<'_> {
 
       let actor = &self.actors[actor_idx];
       if (self.round_counter % 33 == 0 && !actor.can_move(&self.maps.level)) || self.round_counter % 121 == 0 {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.clone();
         let dir = *[Direction::Left, Direction::Right, Direction::Up, Direction::Down]
           .choose(&mut rng)
           .unwrap();
@@ -73,6 +92,54 @@ impl World<'_> {
     }
   }
 
+  /// Spawn a new monster next to the nest unless nest has too many monsters alive already
+  pub(super) fn nest_spawn_monster(&mut self, nest: Cursor) {
+    let kind = match self.maps.level[nest].nest() {
+      Some(kind) if !self.bonus_round => kind,
+      _ => return,
+    };
+    let alive = self
+      .actors
+      .iter()
+      .filter(|actor| !actor.is_dead && actor.spawner == Some(nest))
+      .count();
+    if alive >= self.tuning.nest_cap {
+      return;
+    }
+    if self.monster_cap > 0 {
+      let monsters = self.actors[self.players.len()..]
+        .iter()
+        .filter(|actor| !actor.is_dead && actor.kind.is_monster())
+        .count();
+      if monsters >= usize::from(self.monster_cap) {
+        return;
+      }
+    }
+
+    let mut rng = self.rng.clone();
+    let mut directions = [Direction::Left, Direction::Right, Direction::Up, Direction::Down];
+    directions.shuffle(&mut rng);
+    let free = directions.iter().copied().find(|dir| {
+      let cursor = nest.to(*dir);
+      self.maps.level[cursor].is_passable() && self.actors_at(cursor).next().is_none()
+    });
+    if let Some(dir) = free {
+      self.actors.push(ActorComponent {
+        kind,
+        pos: nest.to(dir).into(),
+        max_health: kind.initial_health(),
+        health: kind.initial_health(),
+        drilling: kind.drilling_power(),
+        facing: dir,
+        moving: true,
+        spawner: Some(nest),
+        ..Default::default()
+      });
+      self.occupancy.moved(self.actors.len() - 1, nest.to(dir));
+      self.update.update_actor(self.actors.len() - 1, Digging::Hands);
+    }
+  }
+
   fn clone_can_chase(&self, monster_kind: ActorKind, target_player: Player) -> bool {
     match monster_kind {
       ActorKind::Clone(clone_player) if clone_player != target_player && !self.campaign_mode => true,
@@ -101,6 +168,25 @@ impl World<'_> {
     }
   }
 
+  /// Clones carrying gold hand it over to their owner when they touch
+  fn clone_deposit_gold(&mut self, actor: EntityIndex) {
+    let clone = &self.actors[actor];
+    let owner = match clone.kind.clone_owner() {
+      Some(owner) if clone.accumulated_cash > 0 => owner,
+      _ => return,
+    };
+    let cursor = clone.pos.cursor();
+    if self.actors[owner].is_dead || self.actors[owner].pos.cursor() != cursor {
+      return;
+    }
+
+    let cash_player = if self.campaign_mode { 0 } else { owner };
+    self.actors[cash_player].accumulated_cash += self.actors[actor].accumulated_cash;
+    self.actors[actor].accumulated_cash = 0;
+    self.update.update_player_stats(owner);
+    self.effects.play(SoundEffect::Kili, 12599, cursor);
+  }
+
   /// Throw grenades if not blocked by map or by other monster
   fn grenadier_maybe_toss_grenade(&mut self, actor: EntityIndex) {
     // Minimum distance to obstacle when grenadier still wants to throw a grenade
@@ -116,6 +202,7 @@ impl World<'_> {
         if same_row != same_col {
           self.maps.level[cursor] = grenade_value(actor.facing);
           self.maps.timer[cursor] = 1;
+          self.maps.owner[cursor] = actor.kind.clone_owner();
         }
       }
     }
@@ -135,10 +222,7 @@ impl World<'_> {
       }
 
       // Some monster is blocking grenade throw
-      if self.actors[self.players.len()..]
-        .iter()
-        .any(|actor| actor.pos.cursor() == cursor)
-      {
+      if self.actors_at(cursor).any(|idx| idx >= self.players.len()) {
         return distance;
       }
 
@@ -154,7 +238,7 @@ impl World<'_> {
 }
 
 /// Look around for bombs
-fn look_for_bombs(cursor: Cursor, level: &LevelMap) -> Option<Cursor> {
+pub(super) fn look_for_bombs(cursor: Cursor, level: &LevelMap) -> Option<Cursor> {
   look_around(cursor, 5, |offset| {
     let value = level[offset];
     if value.is_bomb() {
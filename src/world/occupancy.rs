@@ -0,0 +1,74 @@
+//! Index of actors by the cell they are in. Pushing, grenade flight, plastic placement and damage
+//! all need actors of a particular cell, so index saves them from scanning all actors. Index is
+//! updated every time actor changes the cell; removing actors shifts indices of the following
+//! actors, so index is rebuilt from scratch in that case.
+use crate::world::actor::ActorComponent;
+use crate::world::map::{MAP_COLS, MAP_ROWS};
+use crate::world::position::Cursor;
+use crate::world::EntityIndex;
+
+pub struct Occupancy {
+  /// Actors in each cell, sorted by their indices
+  cells: Vec<Vec<EntityIndex>>,
+  /// Cell each actor is indexed in
+  actors: Vec<Cursor>,
+}
+
+impl Default for Occupancy {
+  fn default() -> Self {
+    Occupancy {
+      cells: vec![Vec::new(); usize::from(MAP_ROWS * MAP_COLS)],
+      actors: Vec::new(),
+    }
+  }
+}
+
+impl Occupancy {
+  /// Index all actors from scratch
+  pub fn rebuild(&mut self, actors: &[ActorComponent]) {
+    for cursor in self.actors.drain(..) {
+      self.cells[cell_idx(cursor)].clear();
+    }
+    for (idx, actor) in actors.iter().enumerate() {
+      let cursor = actor.pos.cursor();
+      self.cells[cell_idx(cursor)].push(idx);
+      self.actors.push(cursor);
+    }
+  }
+
+  /// Update cell of the actor after it moved. Actor which is not indexed yet must be the next one
+  /// after the indexed actors (actors are only added to the end of the list).
+  pub fn moved(&mut self, actor: EntityIndex, cursor: Cursor) {
+    if actor == self.actors.len() {
+      self.actors.push(cursor);
+    } else {
+      let previous = std::mem::replace(&mut self.actors[actor], cursor);
+      if previous == cursor {
+        return;
+      }
+      self.cells[cell_idx(previous)].retain(|&idx| idx != actor);
+    }
+    let cell = &mut self.cells[cell_idx(cursor)];
+    let pos = cell.partition_point(|&idx| idx < actor);
+    cell.insert(pos, actor);
+  }
+
+  /// Actors in the given cell, in the order of their indices
+  pub fn at(&self, cursor: Cursor) -> &[EntityIndex] {
+    &self.cells[cell_idx(cursor)]
+  }
+
+  /// Check if index agrees with the actual positions of the actors
+  #[cfg(debug_assertions)]
+  pub fn is_consistent(&self, actors: &[ActorComponent]) -> bool {
+    self.actors.len() == actors.len()
+      && actors.iter().enumerate().all(|(idx, actor)| {
+        let cursor = actor.pos.cursor();
+        self.actors[idx] == cursor && self.at(cursor).contains(&idx)
+      })
+  }
+}
+
+fn cell_idx(cursor: Cursor) -> usize {
+  usize::from(cursor.row) * usize::from(MAP_COLS) + usize::from(cursor.col)
+}
@@ -1,8 +1,11 @@
 use crate::keys::KeyBindings;
-use crate::options::Options;
+use crate::options::{ArmorModel, Options};
 use crate::roster::RosterInfo;
 use crate::world::equipment::Equipment;
 
+/// Armor points given by each armor item (with damage reduction armor model)
+const ARMOR_POINTS: u16 = 100;
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum GlyphCheat {
   /// Render player as a slime
@@ -12,7 +15,7 @@ pub enum GlyphCheat {
 }
 
 /// Component corresponding to the active player
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct PlayerComponent {
   /// Player name and statistics
   pub stats: RosterInfo,
@@ -32,6 +35,10 @@ pub struct PlayerComponent {
   /// For multi-player mode, amount of won rounds (separate from stats, which tracks rounds won
   /// across all games).
   pub rounds_win: u32,
+  /// Amount of monsters, clones and other players killed during the game
+  pub kills: u32,
+  /// Door keys of each color player is carrying. Keys are only valid for the current round.
+  pub door_keys: [u8; 4],
 }
 
 impl PlayerComponent {
@@ -72,18 +79,40 @@ impl PlayerComponent {
     player
   }
 
+  /// Buy starting loadout, at the base prices. Items player cannot afford are skipped.
+  pub fn buy_loadout(&mut self, items: &[(Equipment, u16)]) {
+    for &(item, count) in items {
+      let price = item.base_price();
+      let count = count.min((self.cash / price).min(u32::from(u16::MAX)) as u16);
+      self.cash -= price * u32::from(count);
+      self.inventory[item] += count;
+      self.stats.bombs_bought += u32::from(count);
+    }
+  }
+
   pub fn initial_drilling_power(&self) -> u16 {
     self.inventory[Equipment::SmallPickaxe]
       + 3 * self.inventory[Equipment::LargePickaxe]
       + 5 * self.inventory[Equipment::Drill]
   }
 
-  pub fn initial_health(&self) -> u16 {
+  /// Initial health of the player. Armor adds to it unless it is used for damage reduction.
+  pub fn initial_health(&self, armor_model: ArmorModel) -> u16 {
     // Cheat code -- almost invulnerable
     if self.stats.name == "Rambo" {
       32000
-    } else {
+    } else if armor_model == ArmorModel::Health {
       100 + 100 * self.inventory[Equipment::Armor]
+    } else {
+      100
+    }
+  }
+
+  /// Initial armor of the player, when armor is used for damage reduction
+  pub fn initial_armor(&self, armor_model: ArmorModel) -> u16 {
+    match armor_model {
+      ArmorModel::Health => 0,
+      ArmorModel::Reduction => ARMOR_POINTS * self.inventory[Equipment::Armor],
     }
   }
 
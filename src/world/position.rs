@@ -1,7 +1,8 @@
 use crate::world::map::{MAP_COLS, MAP_ROWS};
+use num_enum::TryFromPrimitive;
 
 /// Facing direction
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
 #[repr(u8)]
 pub enum Direction {
   Right,
@@ -40,7 +41,7 @@ impl Direction {
 }
 
 /// Position on the map. Center of the row 0, column 0 is (x = 5; y = 35)
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Position {
   pub x: u16,
   pub y: u16,
@@ -170,6 +171,42 @@ impl Cursor {
       .map(|(row, col)| Cursor::new(row, col))
   }
 
+  /// Iterate through map cells of the rectangle between two corners (inclusive), row by row
+  pub fn rect(top_left: Cursor, bottom_right: Cursor) -> impl Iterator<Item = Cursor> {
+    let cols = top_left.col..=bottom_right.col.min(MAP_COLS - 1);
+    (top_left.row..=bottom_right.row.min(MAP_ROWS - 1))
+      .flat_map(move |row| cols.clone().map(move |col| (row, col)))
+      .map(|(row, col)| Cursor::new(row, col))
+  }
+
+  /// Iterate through map cells which are not further than `radius` cells from the center, row by row
+  pub fn disc(center: Cursor, radius: u16) -> impl Iterator<Item = Cursor> {
+    let radius = radius.min(MAP_COLS) as i16;
+    let top_left = center.offset_clamp(-radius, -radius);
+    let bottom_right = center.offset_clamp(radius, radius);
+    let max_distance = u32::from(radius as u16).pow(2);
+    Cursor::rect(top_left, bottom_right).filter(move |cursor| {
+      let (delta_row, delta_col) = cursor.distance(center);
+      u32::from(delta_row).pow(2) + u32::from(delta_col).pow(2) <= max_distance
+    })
+  }
+
+  /// Smallest rectangle (top left and bottom right corners) covering both the given area and the cursor
+  pub fn enclose(area: Option<(Cursor, Cursor)>, cursor: Cursor) -> (Cursor, Cursor) {
+    match area {
+      None => (cursor, cursor),
+      Some((top_left, bottom_right)) => (
+        Cursor::new(top_left.row.min(cursor.row), top_left.col.min(cursor.col)),
+        Cursor::new(bottom_right.row.max(cursor.row), bottom_right.col.max(cursor.col)),
+      ),
+    }
+  }
+
+  /// Smallest rectangle covering all given cells; `None` if there are no cells
+  pub fn bounds(cursors: impl Iterator<Item = Cursor>) -> Option<(Cursor, Cursor)> {
+    cursors.fold(None, |area, cursor| Some(Cursor::enclose(area, cursor)))
+  }
+
   /// Check if this cursor is pointing at a border cell
   pub fn is_on_border(self) -> bool {
     self.row == 0 || self.row == (MAP_ROWS - 1) || self.col == 0 || self.col == (MAP_COLS - 1)
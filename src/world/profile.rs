@@ -0,0 +1,136 @@
+//! Tick profiling: time spent in the major world subsystems, aggregated per second of the game time.
+//! Used by the practice mode overlay and the benchmark report saved after each round to attribute
+//! performance regressions to specific systems. Profiling is off unless enabled, as measuring time is
+//! not free.
+use crate::world::TICKS_PER_SECOND;
+use anyhow::Context;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+pub enum Subsystem {
+  Bombs,
+  Players,
+  Monsters,
+  /// Plastic, digger and napalm expansion. This is a part of bombs time, too; expansions triggered
+  /// by another expansion are counted within the outer one as well.
+  Expansion,
+}
+
+impl Subsystem {
+  pub const ALL: [Subsystem; 4] = [
+    Subsystem::Bombs,
+    Subsystem::Players,
+    Subsystem::Monsters,
+    Subsystem::Expansion,
+  ];
+
+  pub fn label(self) -> &'static str {
+    match self {
+      Subsystem::Bombs => "Bombs",
+      Subsystem::Players => "Players",
+      Subsystem::Monsters => "Monsters",
+      Subsystem::Expansion => "Expansion",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SubsystemStats {
+  pub calls: u32,
+  pub time: Duration,
+}
+
+impl SubsystemStats {
+  fn add(&mut self, other: SubsystemStats) {
+    self.calls += other.calls;
+    self.time += other.time;
+  }
+}
+
+#[derive(Default)]
+pub struct TickProfile {
+  enabled: bool,
+  /// Stats of the second in progress
+  current: [SubsystemStats; 4],
+  /// Stats of the last complete second
+  last: [SubsystemStats; 4],
+  /// Stats of the whole round
+  total: [SubsystemStats; 4],
+  ticks: usize,
+}
+
+impl TickProfile {
+  pub fn enable(&mut self) {
+    self.enabled = true;
+  }
+
+  /// Start measuring; `None` if profiling is disabled
+  pub fn start(&self) -> Option<Instant> {
+    if self.enabled {
+      Some(Instant::now())
+    } else {
+      None
+    }
+  }
+
+  /// Record time spent in the subsystem since the measurement was started
+  pub fn record(&mut self, subsystem: Subsystem, started: Option<Instant>) {
+    if let Some(started) = started {
+      let stats = &mut self.current[subsystem as usize];
+      stats.calls += 1;
+      stats.time += started.elapsed();
+    }
+  }
+
+  /// Finish the tick; stats are aggregated once a second of game time passes
+  pub fn end_tick(&mut self) {
+    if !self.enabled {
+      return;
+    }
+    self.ticks += 1;
+    if self.ticks % TICKS_PER_SECOND == 0 {
+      for (total, current) in self.total.iter_mut().zip(self.current.iter()) {
+        total.add(*current);
+      }
+      self.last = std::mem::take(&mut self.current);
+    }
+  }
+
+  /// Stats of the last complete second of game time
+  pub fn last_second(&self, subsystem: Subsystem) -> SubsystemStats {
+    self.last[subsystem as usize]
+  }
+
+  /// Report of the round: total and average per second time of each subsystem. Second in progress
+  /// is included, too, so short rounds are reported as well.
+  pub fn report(&self) -> String {
+    let seconds = self.ticks.max(1) as f64 / TICKS_PER_SECOND as f64;
+    let mut report = String::new();
+    let _ = writeln!(report, "{} ticks ({:.2}s of game time)", self.ticks, seconds);
+    for subsystem in Subsystem::ALL {
+      let mut stats = self.total[subsystem as usize];
+      stats.add(self.current[subsystem as usize]);
+      let _ = writeln!(
+        report,
+        "{:<10} {:>9.2?} total, {:>9.2?}/s, {:>6} calls",
+        subsystem.label(),
+        stats.time,
+        stats.time.div_f64(seconds),
+        stats.calls
+      );
+    }
+    report
+  }
+
+  /// Save benchmark report of the round into the game directory (next to the match log)
+  pub fn save(&self, game_dir: &Path, round: u16, level: &str) -> Result<(), anyhow::Error> {
+    let timestamp = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |duration| duration.as_secs());
+    let path = game_dir.join(format!("profile-{}-round{:02}-{}.txt", timestamp, round + 1, level));
+    std::fs::write(&path, self.report()).with_context(|| format!("cannot write '{}'", path.display()))
+  }
+}
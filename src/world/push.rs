@@ -0,0 +1,75 @@
+//! Pushing boulders and other pushable items around.
+use crate::world::actor::ActorKind;
+use crate::world::map::MapValue;
+use crate::world::position::{Cursor, Direction};
+use crate::world::{radio_owner, EntityIndex, World};
+
+/// Damage dealt to monsters standing in the way of a pushed boulder, on every push attempt
+const CRUSH_DAMAGE: u16 = 15;
+
+impl World<'_> {
+  /// Check if item in the `cursor` cell could be pushed in the given direction. With push chains
+  /// enabled, boulder pushed into monsters crushes them; the boulder moves once no monster is left
+  /// alive in the cell.
+  pub(super) fn push_target_clear(&mut self, entity: EntityIndex, cursor: Cursor, dir: Direction) -> bool {
+    let target = cursor.to(dir);
+    if !self.maps.level[target].is_passable() {
+      return false;
+    }
+
+    let mut blocked = false;
+    let mut monsters = false;
+    for idx in self.actors_at(target) {
+      let actor = &self.actors[idx];
+      if actor.is_dead {
+        continue;
+      }
+      if actor.kind.is_monster() {
+        monsters = true;
+      } else {
+        blocked = true;
+      }
+    }
+    if blocked {
+      return false;
+    }
+
+    if monsters {
+      if !self.push_chains || self.maps.level[cursor] != MapValue::Boulder {
+        return false;
+      }
+      // Crushed monsters are credited to the player pushing the boulder
+      let attacker = match self.actors[entity].kind {
+        ActorKind::Player(_) => Some(entity),
+        kind => kind.clone_owner(),
+      };
+      let previous = std::mem::replace(&mut self.attacker, attacker);
+      let alive = self.apply_damage_in_cell(target, CRUSH_DAMAGE);
+      self.attacker = previous;
+      return !alive;
+    }
+    true
+  }
+
+  /// Move item from the `cursor` cell to the adjacent cell in the given direction
+  pub(super) fn push_cell(&mut self, cursor: Cursor, dir: Direction) {
+    let target = cursor.to(dir);
+    self.maps.level[target] = self.maps.level[cursor];
+    self.maps.timer[target] = self.maps.timer[cursor];
+    self.maps.owner[target] = self.maps.owner[cursor].take();
+    self.maps.hits[target] = 24;
+    if let Some(player) = radio_owner(self.maps.level[target]) {
+      // Remote key of the owner only scans the area radio bombs were placed in
+      self.remote_area[player] = Some(Cursor::enclose(self.remote_area[player], target));
+    }
+
+    // Clear old position
+    self.maps.level[cursor] = MapValue::Passage;
+    self.maps.timer[cursor] = 0;
+
+    self.reapply_blood(cursor);
+
+    self.update.update_cell(cursor);
+    self.update.update_cell(target);
+  }
+}
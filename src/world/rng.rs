@@ -0,0 +1,42 @@
+//! Random numbers of the simulation. Peers of the network game simulate the world independently, so
+//! every random decision of the world (including the random level it is played on) comes from the
+//! generator seeded with the same seed. Presentation (like sound pitch heard by the listener) keeps
+//! using the thread generator.
+use rand::rngs::StdRng;
+use rand::{Error, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Handle to the random generator of the world. Clones share the same generator, so code could take
+/// a handle and keep using it while the world is borrowed mutably.
+#[derive(Clone)]
+pub struct WorldRng(Rc<RefCell<StdRng>>);
+
+impl WorldRng {
+  pub fn seeded(seed: u64) -> Self {
+    WorldRng(Rc::new(RefCell::new(StdRng::seed_from_u64(seed))))
+  }
+
+  /// Generator for the local game, which nobody needs to reproduce
+  pub fn random() -> Self {
+    WorldRng::seeded(rand::random())
+  }
+}
+
+impl RngCore for WorldRng {
+  fn next_u32(&mut self) -> u32 {
+    self.0.borrow_mut().next_u32()
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    self.0.borrow_mut().next_u64()
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    self.0.borrow_mut().fill_bytes(dest)
+  }
+
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+    self.0.borrow_mut().try_fill_bytes(dest)
+  }
+}
@@ -0,0 +1,51 @@
+//! Rockfall: boulders fall down into passages opened beneath them, crushing everyone below.
+use crate::world::map::{MapValue, MAP_COLS, MAP_ROWS};
+use crate::world::position::{Cursor, Direction};
+use crate::world::World;
+
+/// Damage dealt to actors hit by a falling boulder
+const ROCKFALL_DAMAGE: u16 = 100;
+
+impl World<'_> {
+  /// Move every unsupported boulder one cell down. Boulder resting on an actor stays in place, but
+  /// boulder which is already falling crushes actors below it.
+  pub(super) fn settle_boulders(&mut self) {
+    let was_falling = std::mem::take(&mut self.falling);
+    // Go from the bottom, so each boulder moves at most one cell per pass
+    for row in (1..MAP_ROWS - 2).rev() {
+      for col in 1..MAP_COLS - 1 {
+        let cursor = Cursor::new(row, col);
+        if self.maps.level[cursor] != MapValue::Boulder || self.maps.protected[cursor] {
+          continue;
+        }
+        let below = cursor.to(Direction::Down);
+        if !self.maps.level[below].is_passable() {
+          continue;
+        }
+
+        let occupied = self.actors_at(below).any(|idx| !self.actors[idx].is_dead);
+        if occupied {
+          if !was_falling.contains(&cursor) {
+            continue;
+          }
+          // Nobody is credited for rockfall kills
+          self.attacker = None;
+          if self.apply_damage_in_cell(below, ROCKFALL_DAMAGE) {
+            // Boulder stops on top of the crushed actors, falls further once the cell is clear
+            continue;
+          }
+        }
+
+        self.maps.level[below] = MapValue::Boulder;
+        self.maps.hits[below] = self.maps.hits[cursor];
+        self.maps.level[cursor] = MapValue::Passage;
+        self.maps.timer[cursor] = 0;
+        self.maps.hits[cursor] = 0;
+        self.reapply_blood(cursor);
+        self.update.update_cell(cursor);
+        self.update.update_cell(below);
+        self.falling.push(below);
+      }
+    }
+  }
+}
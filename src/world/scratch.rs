@@ -0,0 +1,42 @@
+//! Reusable buffers for the hot paths of the world tick. Buffers are cleared rather than freed, so
+//! steady state ticks don't allocate; retained capacity is bounded, so a single huge explosion
+//! doesn't keep its memory for the rest of the round.
+
+/// Capacity retained by the scratch buffers and the queues after being cleared
+pub const RETAINED_CAPACITY: usize = 256;
+
+/// Maximum amount of vectors kept in the pool (nested expansions take few of them at once)
+const MAX_POOLED: usize = 8;
+
+/// Clear the vector, releasing its memory only if it grew over the retained capacity
+pub fn clear_bounded<T>(vec: &mut Vec<T>) {
+  vec.clear();
+  vec.shrink_to(RETAINED_CAPACITY);
+}
+
+/// Pool of the empty vectors. Vectors are taken from the pool and given back once the caller is done
+/// with them, which also works for the recursive callers (nested call simply takes another vector).
+pub struct VecPool<T> {
+  free: Vec<Vec<T>>,
+}
+
+impl<T> Default for VecPool<T> {
+  fn default() -> Self {
+    VecPool { free: Vec::new() }
+  }
+}
+
+impl<T> VecPool<T> {
+  /// Take an empty vector from the pool (allocated on first use)
+  pub fn take(&mut self) -> Vec<T> {
+    self.free.pop().unwrap_or_else(|| Vec::with_capacity(RETAINED_CAPACITY))
+  }
+
+  /// Return vector back into the pool
+  pub fn give(&mut self, mut vec: Vec<T>) {
+    if self.free.len() < MAX_POOLED {
+      clear_bounded(&mut vec);
+      self.free.push(vec);
+    }
+  }
+}
@@ -0,0 +1,125 @@
+//! End of round economy: interest, collected gold, redistribution of the gold lost by the dead
+//! players and the bonus for the broke ones. Settlement is computed from the state of the world
+//! first and then applied to the players, so it could be shown to the players and logged.
+use crate::world::World;
+
+/// Interest on the cash players had during the round, in percents
+const INTEREST_PERCENT: u32 = 7;
+/// Players having less cash than that after the round get a bonus
+const BROKE_THRESHOLD: u32 = 100;
+const BROKE_BONUS: u32 = 150;
+/// Share of the gold left on the level taken by the only surviving player, in percents
+const SURVIVOR_SHARE_PERCENT: u32 = 40;
+
+/// Cash changes of a single player at the end of the round
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlayerSettlement {
+  /// Cash player had during the round
+  pub before: u32,
+  /// Gold collected during the round
+  pub collected: u32,
+  /// Collected gold was lost (player died in the multiplayer round)
+  pub lost: bool,
+  /// Interest on the cash player had
+  pub interest: u32,
+  /// Share of the gold lost by the dead players (and of the gold left on the level, if player is the
+  /// only one alive)
+  pub inherited: u32,
+  /// Bonus given to the players who are almost broke
+  pub bonus: u32,
+  /// Player has won the round (survived while some other players didn't)
+  pub won: bool,
+}
+
+impl PlayerSettlement {
+  /// Cash player has after the settlement
+  pub fn after(&self) -> u32 {
+    let collected = if self.lost { 0 } else { self.collected };
+    self.before + collected + self.interest + self.inherited + self.bonus
+  }
+}
+
+/// Outcome of the round for each of the players
+#[derive(Clone, Debug, Default)]
+pub struct RoundSettlement {
+  pub players: Vec<PlayerSettlement>,
+}
+
+impl RoundSettlement {
+  /// Players who won the round
+  pub fn winners(&self) -> Vec<usize> {
+    (0..self.players.len()).filter(|idx| self.players[*idx].won).collect()
+  }
+
+  /// Cash of each player after the round
+  pub fn cash(&self) -> Vec<u32> {
+    self.players.iter().map(PlayerSettlement::after).collect()
+  }
+}
+
+impl World<'_> {
+  /// Compute end of round settlement without changing the world
+  pub fn settle(&self) -> RoundSettlement {
+    let mut players: Vec<PlayerSettlement> = (0..self.players.len())
+      .map(|idx| PlayerSettlement {
+        before: self.players[idx].cash,
+        collected: self.actors[idx].accumulated_cash,
+        ..Default::default()
+      })
+      .collect();
+
+    // Apply interest on all existing cash; bonus rounds don't count
+    if !self.bonus_round {
+      for player in &mut players {
+        player.interest = ((100 + INTEREST_PERCENT) * player.before + 50) / 100 - player.before;
+      }
+    }
+
+    // In single player, we never lose money, even if we die
+    if self.campaign_mode {
+      return RoundSettlement { players };
+    }
+
+    // Gold collected by the dead players is shared by the survivors
+    let dead = |idx: usize| self.actors[idx].is_dead;
+    let mut lost_money: u32 = (0..players.len())
+      .filter(|idx| dead(*idx))
+      .map(|idx| players[idx].collected)
+      .sum();
+    let alive_players = (0..players.len()).filter(|idx| !dead(*idx)).count();
+    if alive_players == 1 {
+      // If only one player is alive, take 40% of the remaining money on the level
+      lost_money += self.gold_remaining() * SURVIVOR_SHARE_PERCENT / 100;
+    }
+
+    for (idx, player) in players.iter_mut().enumerate() {
+      if dead(idx) {
+        player.lost = true;
+      } else {
+        player.inherited = lost_money / (alive_players as u32);
+        player.won = alive_players != self.players.len();
+      }
+      if player.after() < BROKE_THRESHOLD {
+        player.bonus = BROKE_BONUS;
+      }
+    }
+    RoundSettlement { players }
+  }
+
+  /// Apply end of round rules (apply interest, commit collected cash, etc). Returns the applied
+  /// settlement.
+  pub fn end_of_round(&mut self) -> RoundSettlement {
+    let settlement = self.settle();
+    for (idx, (player, outcome)) in self.players.iter_mut().zip(&settlement.players).enumerate() {
+      player.cash = outcome.after();
+      if outcome.won {
+        player.rounds_win += 1;
+        player.stats.rounds_wins += 1;
+      }
+      player.stats.total_money += self.actors[idx].accumulated_cash;
+      self.actors[idx].accumulated_cash = 0;
+      player.stats.rounds += 1;
+    }
+    settlement
+  }
+}
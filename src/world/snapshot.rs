@@ -0,0 +1,503 @@
+//! Snapshots of the world state and deltas between them. Used to stream the game to observers and
+//! to resynchronize peers which went out of sync.
+use crate::glyphs::Digging;
+use crate::world::actor::{ActorComponent, ActorKind, Player};
+use crate::world::map::{HitsMap, LevelMap, MapValue, TimerMap, MAP_COLS, MAP_ROWS};
+use crate::world::position::{Cursor, Direction, Position};
+use crate::world::{EntityIndex, World};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("invalid world snapshot data")]
+pub struct InvalidSnapshot;
+
+/// State of the world at a given tick
+#[derive(Clone)]
+pub struct WorldSnapshot {
+  pub tick: usize,
+  level: LevelMap,
+  timer: TimerMap,
+  hits: HitsMap,
+  actors: Vec<ActorState>,
+  players: Vec<PlayerState>,
+}
+
+/// Part of the actor state visible to other peers
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ActorState {
+  pub kind: ActorKind,
+  pub pos: Position,
+  pub facing: Direction,
+  pub moving: bool,
+  pub health: u16,
+  pub max_health: u16,
+  pub drilling: u16,
+  pub accumulated_cash: u32,
+  pub is_dead: bool,
+}
+
+impl ActorState {
+  fn of(actor: &ActorComponent) -> Self {
+    ActorState {
+      kind: actor.kind,
+      pos: actor.pos,
+      facing: actor.facing,
+      moving: actor.moving,
+      health: actor.health,
+      max_health: actor.max_health,
+      drilling: actor.drilling,
+      accumulated_cash: actor.accumulated_cash,
+      is_dead: actor.is_dead,
+    }
+  }
+}
+
+/// Player information shown in the stat panels
+#[derive(Clone, PartialEq, Eq)]
+pub struct PlayerState {
+  pub name: String,
+  pub cash: u32,
+}
+
+/// Single changed map cell
+#[derive(Clone, Copy)]
+pub struct CellDelta {
+  pub cursor: Cursor,
+  pub value: MapValue,
+  pub timer: u16,
+  pub hits: i32,
+}
+
+/// Changes between two snapshots
+pub struct WorldDelta {
+  /// Tick of the snapshot delta was taken against
+  pub base_tick: usize,
+  pub tick: usize,
+  pub cells: Vec<CellDelta>,
+  pub actors: Vec<(EntityIndex, ActorState)>,
+  /// Total amount of actors; actors past that count were removed
+  pub actor_count: usize,
+  pub players: Vec<(usize, PlayerState)>,
+  /// Checksum of the state after applying the delta (only sent periodically)
+  pub checksum: Option<u64>,
+}
+
+impl WorldDelta {
+  /// Check if the delta could be applied to the world with the given amount of players
+  pub fn fits(&self, players: usize) -> bool {
+    self.players.iter().all(|(idx, _)| *idx < players) && self.actors.iter().all(|(_, actor)| fits(actor, players))
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.cells.is_empty() && self.actors.is_empty() && self.players.is_empty()
+  }
+
+  pub fn write_to(&self, w: &mut impl Write) -> Result<(), std::io::Error> {
+    w.write_u32::<LittleEndian>(self.base_tick as u32)?;
+    w.write_u32::<LittleEndian>(self.tick as u32)?;
+    w.write_u16::<LittleEndian>(self.cells.len() as u16)?;
+    for cell in &self.cells {
+      w.write_u8(cell.cursor.row as u8)?;
+      w.write_u8(cell.cursor.col as u8)?;
+      w.write_u8(cell.value as u8)?;
+      w.write_u16::<LittleEndian>(cell.timer)?;
+      w.write_i32::<LittleEndian>(cell.hits)?;
+    }
+    w.write_u16::<LittleEndian>(self.actor_count as u16)?;
+    w.write_u16::<LittleEndian>(self.actors.len() as u16)?;
+    for (idx, actor) in &self.actors {
+      w.write_u16::<LittleEndian>(*idx as u16)?;
+      write_actor(w, actor)?;
+    }
+    w.write_u8(self.players.len() as u8)?;
+    for (idx, player) in &self.players {
+      w.write_u8(*idx as u8)?;
+      write_player(w, player)?;
+    }
+    match self.checksum {
+      Some(checksum) => {
+        w.write_u8(1)?;
+        w.write_u64::<LittleEndian>(checksum)?;
+      }
+      None => w.write_u8(0)?,
+    }
+    Ok(())
+  }
+
+  pub fn read_from(r: &mut impl Read) -> Result<Self, anyhow::Error> {
+    let base_tick = r.read_u32::<LittleEndian>()? as usize;
+    let tick = r.read_u32::<LittleEndian>()? as usize;
+    let cells_len = r.read_u16::<LittleEndian>()?;
+    let mut cells = Vec::with_capacity(usize::from(cells_len));
+    for _ in 0..cells_len {
+      let cursor = Cursor::new(u16::from(r.read_u8()?), u16::from(r.read_u8()?));
+      if cursor.row >= MAP_ROWS || cursor.col >= MAP_COLS {
+        return Err(InvalidSnapshot.into());
+      }
+      cells.push(CellDelta {
+        cursor,
+        value: MapValue::try_from(r.read_u8()?).map_err(|_| InvalidSnapshot)?,
+        timer: r.read_u16::<LittleEndian>()?,
+        hits: r.read_i32::<LittleEndian>()?,
+      });
+    }
+    let actor_count = usize::from(r.read_u16::<LittleEndian>()?);
+    let actors_len = r.read_u16::<LittleEndian>()?;
+    let mut actors = Vec::with_capacity(usize::from(actors_len));
+    for _ in 0..actors_len {
+      let idx = usize::from(r.read_u16::<LittleEndian>()?);
+      if idx >= actor_count {
+        return Err(InvalidSnapshot.into());
+      }
+      actors.push((idx, read_actor(r)?));
+    }
+    let players_len = r.read_u8()?;
+    let mut players = Vec::with_capacity(usize::from(players_len));
+    for _ in 0..players_len {
+      let idx = usize::from(r.read_u8()?);
+      players.push((idx, read_player(r)?));
+    }
+    let checksum = match r.read_u8()? {
+      0 => None,
+      _ => Some(r.read_u64::<LittleEndian>()?),
+    };
+    Ok(WorldDelta {
+      base_tick,
+      tick,
+      cells,
+      actors,
+      actor_count,
+      players,
+      checksum,
+    })
+  }
+}
+
+impl WorldSnapshot {
+  /// Changes to apply to this snapshot to get the `next` one
+  pub fn diff(&self, next: &WorldSnapshot) -> WorldDelta {
+    WorldDelta {
+      base_tick: self.tick,
+      tick: next.tick,
+      cells: self.changed_cells(&next.level, &next.timer, &next.hits),
+      actors: changed(&self.actors, &next.actors),
+      actor_count: next.actors.len(),
+      players: changed(&self.players, &next.players),
+      checksum: None,
+    }
+  }
+
+  /// Changes of the world since this snapshot; snapshot is brought up to date with the world. Unlike
+  /// taking a new snapshot, this doesn't copy the whole map.
+  pub fn advance(&mut self, world: &World) -> WorldDelta {
+    let maps = &world.maps;
+    let cells = self.changed_cells(&maps.level, &maps.timer, &maps.hits);
+    for cell in &cells {
+      self.level[cell.cursor] = cell.value;
+      self.timer[cell.cursor] = cell.timer;
+      self.hits[cell.cursor] = cell.hits;
+    }
+    let actors: Vec<ActorState> = world.actors.iter().map(ActorState::of).collect();
+    let players = player_states(world);
+    let delta = WorldDelta {
+      base_tick: self.tick,
+      tick: world.round_counter,
+      cells,
+      actors: changed(&self.actors, &actors),
+      actor_count: actors.len(),
+      players: changed(&self.players, &players),
+      checksum: None,
+    };
+    self.tick = world.round_counter;
+    self.actors = actors;
+    self.players = players;
+    delta
+  }
+
+  fn changed_cells(&self, level: &LevelMap, timer: &TimerMap, hits: &HitsMap) -> Vec<CellDelta> {
+    Cursor::all()
+      .filter(|&cursor| {
+        self.level[cursor] != level[cursor] || self.timer[cursor] != timer[cursor] || self.hits[cursor] != hits[cursor]
+      })
+      .map(|cursor| CellDelta {
+        cursor,
+        value: level[cursor],
+        timer: timer[cursor],
+        hits: hits[cursor],
+      })
+      .collect()
+  }
+
+  /// Canonical hash of the map and actors state. Peers simulating (or replicating) the same game
+  /// must get the same checksum for the same tick.
+  pub fn checksum(&self) -> u64 {
+    let mut hasher = Fnv1a::default();
+    for cursor in Cursor::all() {
+      hasher.write_u8(self.level[cursor] as u8).unwrap();
+      hasher.write_u16::<LittleEndian>(self.timer[cursor]).unwrap();
+      hasher.write_i32::<LittleEndian>(self.hits[cursor]).unwrap();
+    }
+    for actor in &self.actors {
+      write_actor(&mut hasher, actor).unwrap();
+    }
+    hasher.0
+  }
+
+  /// Amount of players in the snapshot
+  pub fn players(&self) -> usize {
+    self.players.len()
+  }
+
+  pub fn write_to(&self, w: &mut impl Write) -> Result<(), std::io::Error> {
+    w.write_u32::<LittleEndian>(self.tick as u32)?;
+    for cursor in Cursor::all() {
+      w.write_u8(self.level[cursor] as u8)?;
+      w.write_u16::<LittleEndian>(self.timer[cursor])?;
+      w.write_i32::<LittleEndian>(self.hits[cursor])?;
+    }
+    w.write_u16::<LittleEndian>(self.actors.len() as u16)?;
+    for actor in &self.actors {
+      write_actor(w, actor)?;
+    }
+    w.write_u8(self.players.len() as u8)?;
+    for player in &self.players {
+      write_player(w, player)?;
+    }
+    Ok(())
+  }
+
+  pub fn read_from(r: &mut impl Read) -> Result<Self, anyhow::Error> {
+    let tick = r.read_u32::<LittleEndian>()? as usize;
+    let mut level = LevelMap::empty();
+    let mut timer = TimerMap::default();
+    let mut hits = HitsMap::default();
+    for cursor in Cursor::all() {
+      level[cursor] = MapValue::try_from(r.read_u8()?).map_err(|_| InvalidSnapshot)?;
+      timer[cursor] = r.read_u16::<LittleEndian>()?;
+      hits[cursor] = r.read_i32::<LittleEndian>()?;
+    }
+    let actors_len = r.read_u16::<LittleEndian>()?;
+    let mut actors = Vec::with_capacity(usize::from(actors_len));
+    for _ in 0..actors_len {
+      actors.push(read_actor(r)?);
+    }
+    let players_len = r.read_u8()?;
+    let mut players = Vec::with_capacity(usize::from(players_len));
+    for _ in 0..players_len {
+      players.push(read_player(r)?);
+    }
+    if players.len() > 4 || actors.len() < players.len() || !actors.iter().all(|actor| fits(actor, players.len())) {
+      return Err(InvalidSnapshot.into());
+    }
+    Ok(WorldSnapshot {
+      tick,
+      level,
+      timer,
+      hits,
+      actors,
+      players,
+    })
+  }
+}
+
+/// Elements of `next` which are different from the ones in `prev`, with their indices
+fn changed<T: PartialEq + Clone>(prev: &[T], next: &[T]) -> Vec<(usize, T)> {
+  next
+    .iter()
+    .enumerate()
+    .filter(|&(idx, state)| prev.get(idx) != Some(state))
+    .map(|(idx, state)| (idx, state.clone()))
+    .collect()
+}
+
+/// Check if the actor could exist in the world with the given amount of players: players and their
+/// clones are indexed by the player number
+fn fits(actor: &ActorState, players: usize) -> bool {
+  match actor.kind {
+    ActorKind::Player(player) | ActorKind::Clone(player) => (player as usize) < players,
+    _ => true,
+  }
+}
+
+fn player_states(world: &World) -> Vec<PlayerState> {
+  world
+    .players
+    .iter()
+    .map(|player| PlayerState {
+      name: player.stats.name.clone(),
+      cash: player.cash,
+    })
+    .collect()
+}
+
+/// FNV-1a hash; unlike `DefaultHasher`, guaranteed to be the same on every peer
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+  fn default() -> Self {
+    Fnv1a(0xcbf2_9ce4_8422_2325)
+  }
+}
+
+impl Write for Fnv1a {
+  fn write(&mut self, buf: &[u8]) -> Result<usize, std::io::Error> {
+    for byte in buf {
+      self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> Result<(), std::io::Error> {
+    Ok(())
+  }
+}
+
+fn write_actor(w: &mut impl Write, actor: &ActorState) -> Result<(), std::io::Error> {
+  w.write_u8(kind_to_byte(actor.kind))?;
+  w.write_u16::<LittleEndian>(actor.pos.x)?;
+  w.write_u16::<LittleEndian>(actor.pos.y)?;
+  w.write_u8(actor.facing as u8)?;
+  w.write_u8(u8::from(actor.moving) | (u8::from(actor.is_dead) << 1))?;
+  w.write_u16::<LittleEndian>(actor.health)?;
+  w.write_u16::<LittleEndian>(actor.max_health)?;
+  w.write_u16::<LittleEndian>(actor.drilling)?;
+  w.write_u32::<LittleEndian>(actor.accumulated_cash)?;
+  Ok(())
+}
+
+fn read_actor(r: &mut impl Read) -> Result<ActorState, anyhow::Error> {
+  let kind = kind_from_byte(r.read_u8()?).ok_or(InvalidSnapshot)?;
+  let pos = Position::new(r.read_u16::<LittleEndian>()?, r.read_u16::<LittleEndian>()?);
+  let facing = Direction::try_from(r.read_u8()?).map_err(|_| InvalidSnapshot)?;
+  let flags = r.read_u8()?;
+  Ok(ActorState {
+    kind,
+    pos,
+    facing,
+    moving: flags & 1 != 0,
+    is_dead: flags & 2 != 0,
+    health: r.read_u16::<LittleEndian>()?,
+    max_health: r.read_u16::<LittleEndian>()?,
+    drilling: r.read_u16::<LittleEndian>()?,
+    accumulated_cash: r.read_u32::<LittleEndian>()?,
+  })
+}
+
+fn write_player(w: &mut impl Write, player: &PlayerState) -> Result<(), std::io::Error> {
+  let name = player.name.as_bytes();
+  w.write_u8(name.len().min(255) as u8)?;
+  w.write_all(&name[..name.len().min(255)])?;
+  w.write_u32::<LittleEndian>(player.cash)?;
+  Ok(())
+}
+
+fn read_player(r: &mut impl Read) -> Result<PlayerState, anyhow::Error> {
+  let mut name = vec![0; usize::from(r.read_u8()?)];
+  r.read_exact(&mut name)?;
+  Ok(PlayerState {
+    name: String::from_utf8_lossy(&name).into_owned(),
+    cash: r.read_u32::<LittleEndian>()?,
+  })
+}
+
+fn kind_to_byte(kind: ActorKind) -> u8 {
+  match kind {
+    ActorKind::Furry => 0,
+    ActorKind::Grenadier => 1,
+    ActorKind::Slime => 2,
+    ActorKind::Alien => 3,
+    ActorKind::Trader => 4,
+    ActorKind::Player(player) => 0x10 + player as u8,
+    ActorKind::Clone(player) => 0x20 + player as u8,
+  }
+}
+
+fn kind_from_byte(value: u8) -> Option<ActorKind> {
+  const PLAYERS: [Player; 4] = [Player::Player1, Player::Player2, Player::Player3, Player::Player4];
+  Some(match value {
+    0 => ActorKind::Furry,
+    1 => ActorKind::Grenadier,
+    2 => ActorKind::Slime,
+    3 => ActorKind::Alien,
+    4 => ActorKind::Trader,
+    0x10..=0x13 => ActorKind::Player(PLAYERS[usize::from(value - 0x10)]),
+    0x20..=0x23 => ActorKind::Clone(PLAYERS[usize::from(value - 0x20)]),
+    _ => return None,
+  })
+}
+
+impl World<'_> {
+  /// Capture current state of the world
+  pub fn snapshot(&self) -> WorldSnapshot {
+    WorldSnapshot {
+      tick: self.round_counter,
+      level: self.maps.level.clone(),
+      timer: self.maps.timer.clone(),
+      hits: self.maps.hits.clone(),
+      actors: self.actors.iter().map(ActorState::of).collect(),
+      players: player_states(self),
+    }
+  }
+
+  /// Apply changes received from the peer. Changed cells and actors are queued for re-rendering.
+  pub fn apply_delta(&mut self, delta: &WorldDelta) {
+    for cell in &delta.cells {
+      self.maps.level[cell.cursor] = cell.value;
+      self.maps.timer[cell.cursor] = cell.timer;
+      self.maps.hits[cell.cursor] = cell.hits;
+      self.update.update_cell(cell.cursor);
+    }
+
+    // Erase actors which are gone
+    for idx in delta.actor_count..self.actors.len() {
+      let cursor = self.actors[idx].pos.cursor();
+      self.update.update_cell(cursor);
+    }
+    self.actors.truncate(delta.actor_count);
+    for &(idx, state) in &delta.actors {
+      if idx >= self.actors.len() {
+        self.actors.resize_with(idx + 1, ActorComponent::default);
+      }
+      let actor = &mut self.actors[idx];
+      let previous = actor.pos.cursor();
+      actor.kind = state.kind;
+      actor.pos = state.pos;
+      actor.facing = state.facing;
+      actor.moving = state.moving;
+      actor.health = state.health;
+      actor.max_health = state.max_health;
+      actor.drilling = state.drilling;
+      actor.accumulated_cash = state.accumulated_cash;
+      actor.is_dead = state.is_dead;
+      actor.is_active = true;
+      self.update.update_cell(previous);
+      if !state.is_dead {
+        self.update.update_actor(idx, Digging::Hands);
+      }
+    }
+
+    for (idx, state) in &delta.players {
+      if let Some(player) = self.players.get_mut(*idx) {
+        player.stats.name = state.name.clone();
+        player.cash = state.cash;
+      }
+    }
+    self.occupancy.rebuild(&self.actors);
+    self.update.players_info = true;
+    self.round_counter = delta.tick;
+  }
+
+  /// Canonical hash of the world state, see `WorldSnapshot::checksum`
+  pub fn checksum(&self) -> u64 {
+    self.snapshot().checksum()
+  }
+
+  /// Replace the whole state of the world with the given snapshot (safety resync after desync)
+  pub fn apply_snapshot(&mut self, snapshot: &WorldSnapshot) {
+    let current = self.snapshot();
+    self.apply_delta(&current.diff(snapshot));
+  }
+}
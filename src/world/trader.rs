@@ -0,0 +1,192 @@
+//! Neutral trader wandering around the map and offering equipment at a discount
+use crate::effects::SoundEffect;
+use crate::world::actor::{ActorComponent, ActorKind};
+use crate::world::equipment::Equipment;
+use crate::world::map::{MAP_COLS, MAP_ROWS};
+use crate::world::position::{Cursor, Direction};
+use crate::world::{EntityIndex, World};
+use rand::prelude::*;
+
+/// How often (in ticks) we roll a dice to spawn a trader
+const TRADER_SPAWN_INTERVAL: usize = 500;
+
+/// For how many ticks trader stays on the map
+const TRADER_LIFETIME: u32 = 1500;
+
+/// Items trader can offer
+const TRADER_ITEMS: [Equipment; 12] = [
+  Equipment::BigBomb,
+  Equipment::Dynamite,
+  Equipment::AtomicBomb,
+  Equipment::LargeRadio,
+  Equipment::Grenade,
+  Equipment::Flamethrower,
+  Equipment::Napalm,
+  Equipment::LargeCrucifix,
+  Equipment::Digger,
+  Equipment::Teleport,
+  Equipment::Clone,
+  Equipment::SuperDrill,
+];
+
+/// Offer made by the trader
+#[derive(Clone, Copy)]
+pub struct TradeOffer {
+  pub item: Equipment,
+  pub count: u16,
+  pub price: u32,
+}
+
+impl TradeOffer {
+  /// Generate a random offer; prices are half of the base price
+  fn random(rng: &mut impl Rng) -> TradeOffer {
+    let item = *TRADER_ITEMS.choose(rng).unwrap();
+    let count = rng.gen_range(1..=3);
+    TradeOffer {
+      item,
+      count,
+      price: (item.base_price() * u32::from(count) / 2).max(1),
+    }
+  }
+}
+
+impl World<'_> {
+  /// Roll a dice to spawn a trader somewhere on the map, far enough from the players
+  pub(super) fn maybe_spawn_trader(&mut self) {
+    if !self.traders || self.round_counter == 0 || self.round_counter % TRADER_SPAWN_INTERVAL != 0 {
+      return;
+    }
+    let mut rng = self.rng.clone();
+    if self.trader().is_some() || rng.gen_range(0..4) != 0 {
+      return;
+    }
+
+    for _ in 0..50 {
+      let cursor = Cursor::new(rng.gen_range(1..MAP_ROWS - 1), rng.gen_range(1..MAP_COLS - 1));
+      let is_free = self.maps.level[cursor].is_passable()
+        && self.actors_at(cursor).next().is_none()
+        && self.actors[..self.players.len()].iter().all(|player| {
+          let (delta_row, delta_col) = player.pos.cursor().distance(cursor);
+          delta_row + delta_col >= 10
+        });
+      if is_free {
+        let kind = ActorKind::Trader;
+        self.actors.push(ActorComponent {
+          kind,
+          facing: *[Direction::Left, Direction::Right, Direction::Up, Direction::Down]
+            .choose(&mut rng)
+            .unwrap(),
+          moving: true,
+          max_health: kind.initial_health(),
+          health: kind.initial_health(),
+          pos: cursor.into(),
+          drilling: kind.drilling_power(),
+          is_active: true,
+          leave_countdown: TRADER_LIFETIME,
+          ..Default::default()
+        });
+        self.occupancy.moved(self.actors.len() - 1, cursor);
+        self.trade_offer = Some(TradeOffer::random(&mut self.rng));
+        self.effects.play(SoundEffect::Kili, 14983, cursor);
+        return;
+      }
+    }
+  }
+
+  /// Find trader wandering on the map
+  pub fn trader(&self) -> Option<EntityIndex> {
+    (self.players.len()..self.actors.len()).find(|idx| {
+      let actor = &self.actors[*idx];
+      actor.kind == ActorKind::Trader && !actor.is_dead && !self.despawned.contains(idx)
+    })
+  }
+
+  /// Get trade offer available to the player (player should be standing next to the trader)
+  pub fn trade_offer(&self, player: EntityIndex) -> Option<TradeOffer> {
+    if self.trading[player] {
+      self.trade_offer
+    } else {
+      None
+    }
+  }
+
+  /// Trader wanders around randomly, runs away from bombs and leaves the map after a while
+  pub(super) fn animate_trader(&mut self, actor_idx: EntityIndex) {
+    let actor = &mut self.actors[actor_idx];
+    actor.leave_countdown = actor.leave_countdown.saturating_sub(1);
+    if actor.leave_countdown == 0 {
+      self.trade_offer = None;
+      self.despawn_actor(actor_idx);
+      return;
+    }
+
+    if self.round_counter % ActorKind::Trader.speed() != 0 {
+      self.animate_actor(actor_idx);
+    }
+
+    let actor = &mut self.actors[actor_idx];
+    let cursor = actor.pos.cursor();
+    if self.round_counter % 13 == 0 {
+      if let Some(bomb) = super::monster::look_for_bombs(cursor, &self.maps.level) {
+        actor.avoid_position(bomb, &self.maps.level, &mut self.rng);
+        return;
+      }
+    }
+
+    if (self.round_counter % 33 == 0 && !actor.can_move(&self.maps.level)) || self.round_counter % 97 == 0 {
+      let mut rng = self.rng.clone();
+      actor.facing = *[Direction::Left, Direction::Right, Direction::Up, Direction::Down]
+        .choose(&mut rng)
+        .unwrap();
+      actor.moving = true;
+    }
+  }
+
+  /// Track which players are close enough to the trader to trade
+  pub(super) fn update_trade_range(&mut self) {
+    let trader = self.trader().map(|idx| self.actors[idx].pos.cursor());
+    for player in 0..self.players.len() {
+      let actor = &self.actors[player];
+      let in_range = match trader {
+        Some(trader) if !actor.is_dead && self.trade_offer.is_some() => {
+          let (delta_row, delta_col) = actor.pos.cursor().distance(trader);
+          delta_row + delta_col <= 1
+        }
+        _ => false,
+      };
+      if self.trading[player] != in_range {
+        self.trading[player] = in_range;
+        self.update.update_player_selection(player);
+      }
+    }
+  }
+
+  /// Buy items offered by the trader. Returns `false` if player is not next to the trader.
+  pub(super) fn trade(&mut self, player: EntityIndex) -> bool {
+    let offer = match self.trade_offer(player) {
+      Some(offer) => offer,
+      None => return false,
+    };
+
+    let cash_player = if self.campaign_mode { 0 } else { player };
+    if self.players[cash_player].cash >= offer.price {
+      self.players[cash_player].cash -= offer.price;
+      let component = &mut self.players[player];
+      component.inventory[offer.item] += offer.count;
+      component.stats.bombs_bought += u32::from(offer.count);
+      component.selection = offer.item;
+
+      // Trader only makes one offer; it will leave shortly after
+      self.trade_offer = None;
+      if let Some(trader) = self.trader() {
+        let trader = &mut self.actors[trader];
+        trader.leave_countdown = trader.leave_countdown.min(100);
+      }
+      self
+        .effects
+        .play(SoundEffect::Kili, 10000, self.actors[player].pos.cursor());
+      self.update.update_player_stats(player);
+    }
+    true
+  }
+}
@@ -0,0 +1,105 @@
+//! Practice mode tuning: game constants which could be tweaked without rebuilding the game. Tuning
+//! file is given with `--practice <file>` and is re-read at the beginning of every round, so values
+//! could be changed between rounds. File consists of `KEY=VALUE` lines:
+//!
+//! * `PLAYER_SPEED=<percent>`, movement speed of players (100 is normal speed)
+//! * `FUSE=<percent>`, fuse time of bombs (100 is normal fuse)
+//! * `NEST_INTERVAL=<ticks>`, how often nests spawn monsters
+//! * `NEST_CAP=<count>`, maximum amount of alive monsters spawned by a single nest
+//! * `MONSTERS=0`, remove monsters placed on the map
+use crate::vfs::Vfs;
+use crate::world::map::NEST_SPAWN_INTERVAL;
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+pub struct Tuning {
+  /// Movement speed of players, in percents of the normal speed
+  pub player_speed: u16,
+  /// Fuse time of bombs, in percents of the normal fuse time
+  pub fuse: u16,
+  /// How often (in ticks) nests spawn monsters
+  pub nest_interval: u16,
+  /// Maximum amount of alive monsters spawned by a single nest
+  pub nest_cap: usize,
+  /// If monsters placed on the map are kept
+  pub monsters: bool,
+}
+
+impl Default for Tuning {
+  fn default() -> Self {
+    Tuning {
+      player_speed: 100,
+      fuse: 100,
+      nest_interval: NEST_SPAWN_INTERVAL,
+      nest_cap: 3,
+      monsters: true,
+    }
+  }
+}
+
+impl Tuning {
+  /// Load tuning file. File which doesn't exist on the disk is looked up by its name in the asset
+  /// packs and the game data. Missing or invalid values keep their defaults.
+  pub fn load(vfs: &Vfs, path: &Path) -> Self {
+    let mut tuning = Tuning::default();
+    let data = if path.is_file() {
+      std::fs::read(path).map_err(anyhow::Error::from)
+    } else {
+      vfs
+        .read(&path.to_string_lossy())
+        .map(|file| file.data)
+        .map_err(anyhow::Error::from)
+    };
+    let text = match data {
+      Ok(data) => String::from_utf8_lossy(&data).into_owned(),
+      Err(err) => {
+        eprintln!("Failed to read tuning file '{}': {:#}", path.display(), err);
+        return tuning;
+      }
+    };
+    for line in text.lines() {
+      let (key, value) = match line.split_once('=') {
+        Some((key, value)) => (key.trim().to_ascii_uppercase(), value.trim()),
+        None => continue,
+      };
+      match key.as_str() {
+        "PLAYER_SPEED" => {
+          if let Ok(value) = value.parse::<u16>() {
+            tuning.player_speed = value.clamp(10, 400);
+          }
+        }
+        "FUSE" => {
+          if let Ok(value) = value.parse::<u16>() {
+            tuning.fuse = value.clamp(10, 1000);
+          }
+        }
+        "NEST_INTERVAL" => {
+          if let Ok(value) = value.parse::<u16>() {
+            tuning.nest_interval = value.max(1);
+          }
+        }
+        "NEST_CAP" => {
+          if let Ok(value) = value.parse() {
+            tuning.nest_cap = value;
+          }
+        }
+        "MONSTERS" => {
+          if let Ok(value) = value.parse::<u8>() {
+            tuning.monsters = value != 0;
+          }
+        }
+        _ => {}
+      }
+    }
+    tuning
+  }
+
+  /// Adjust fuse time of the placed bomb
+  pub fn fuse_time(&self, timer: u16) -> u16 {
+    if timer <= 1 {
+      // Items which are not triggered by timer or which activate right away
+      return timer;
+    }
+    (u32::from(timer) * u32::from(self.fuse) / 100).clamp(2, u32::from(u16::MAX)) as u16
+  }
+}
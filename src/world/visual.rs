@@ -0,0 +1,87 @@
+//! Transient visual effects: explosions and smoke. These are played in the visual layer over the
+//! level map, so they never destroy anything by themselves: whatever survived the blast stays in the
+//! cell.
+use crate::world::map::{MapValue, Visual};
+use crate::world::position::Cursor;
+use crate::world::World;
+
+/// For how many ticks each frame of the smoke is shown
+const SMOKE_TICKS: u16 = 3;
+
+impl World<'_> {
+  /// Cell is hit by the blast: everything except treasures is destroyed. Explosion is shown for
+  /// `delay` ticks, then it turns into smoke.
+  pub(super) fn blast_cell(&mut self, cursor: Cursor, delay: u16) {
+    if !self.maps.level[cursor].is_treasure() {
+      self.maps.level[cursor] = MapValue::Passage;
+    }
+    self.maps.timer[cursor] = 0;
+    self.show_visual(cursor, MapValue::Explosion, delay);
+  }
+
+  /// Show a puff of smoke in the cell
+  pub(super) fn show_smoke(&mut self, cursor: Cursor) {
+    self.show_visual(cursor, MapValue::Smoke1, SMOKE_TICKS);
+  }
+
+  fn show_visual(&mut self, cursor: Cursor, frame: MapValue, timer: u16) {
+    self.maps.visual[cursor] = Some(Visual { frame, timer });
+    self.update.update_cell(cursor);
+  }
+
+  /// If explosion is shown in the cell
+  pub(super) fn is_exploding(&self, cursor: Cursor) -> bool {
+    self.maps.visual[cursor].is_some_and(|visual| visual.frame == MapValue::Explosion)
+  }
+
+  /// Value of the cell as seen by the game logic: explosion and smoke over the cleared cell occupy it
+  /// until they are gone, same as in the original game where they were stored in the level itself
+  pub(super) fn cell_value(&self, cursor: Cursor) -> MapValue {
+    match self.maps.visual[cursor] {
+      Some(visual) if self.maps.level[cursor] == MapValue::Passage => visual.frame,
+      _ => self.maps.level[cursor],
+    }
+  }
+
+  /// Value to render in the cell instead of its map value, if visual effect is playing there
+  pub fn visual_overlay(&self, cursor: Cursor) -> Option<MapValue> {
+    self.maps.visual[cursor].map(|visual| visual.frame)
+  }
+
+  pub(super) fn animate_visuals(&mut self) {
+    for cursor in Cursor::all() {
+      let visual = match &mut self.maps.visual[cursor] {
+        Some(visual) => visual,
+        None => continue,
+      };
+      visual.timer = visual.timer.saturating_sub(1);
+      if visual.timer > 0 {
+        continue;
+      }
+
+      let next = match visual.frame {
+        MapValue::Explosion => Some(MapValue::Smoke1),
+        MapValue::Smoke1 => Some(MapValue::Smoke2),
+        _ => None,
+      };
+      match next {
+        Some(frame) => {
+          *visual = Visual {
+            frame,
+            timer: SMOKE_TICKS,
+          };
+        }
+        None => {
+          self.maps.visual[cursor] = None;
+          // Items dropped by monsters killed in the blast appear once smoke clears
+          if self.maps.level[cursor] == MapValue::Passage {
+            if let Some(item) = self.take_drop(cursor) {
+              self.maps.level[cursor] = item;
+            }
+          }
+        }
+      }
+      self.update.update_cell(cursor);
+    }
+  }
+}
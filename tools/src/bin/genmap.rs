@@ -26,7 +26,7 @@ enum ToolError {
 fn main() -> Result<(), anyhow::Error> {
   let args: Args = Args::parse();
 
-  let map = mb_reloaded::world::map::LevelMap::random_map(args.treasures);
+  let map = mb_reloaded::world::map::LevelMap::random_map(args.treasures, &mut rand::thread_rng());
   let data = map.to_file_map();
 
   write_map(&args.output, &data).map_err(|source| ToolError::OutputWriteError {